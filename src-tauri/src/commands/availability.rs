@@ -0,0 +1,41 @@
+use crate::db::DatabaseExt;
+use crate::error::AppResult;
+use crate::models::Product;
+use crate::services::availability;
+use tauri::{AppHandle, Emitter};
+
+/// Sent with the product whenever it is 86'd or comes back, so every open
+/// window can refresh.
+pub const AVAILABILITY_CHANGED: &str = "product-availability-changed";
+
+pub(crate) fn notify(app: &AppHandle, product: &Product) {
+    if let Err(e) = app.emit(AVAILABILITY_CHANGED, product.clone()) {
+        println!("[availability] Warning: could not send update: {}", e);
+    }
+}
+
+/// 86 a product, optionally until a local "YYYY-MM-DD HH:MM"
+#[tauri::command]
+pub fn mark_product_unavailable(
+    app: AppHandle,
+    product_id: i64,
+    reason: Option<String>,
+    until: Option<String>,
+) -> AppResult<Product> {
+    let product = {
+        let conn = app.db().conn.lock()?;
+        availability::mark_product_unavailable(&conn, product_id, reason, until)?
+    };
+    notify(&app, &product);
+    Ok(product)
+}
+
+#[tauri::command]
+pub fn mark_product_available(app: AppHandle, product_id: i64) -> AppResult<Product> {
+    let product = {
+        let conn = app.db().conn.lock()?;
+        availability::mark_product_available(&conn, product_id)?
+    };
+    notify(&app, &product);
+    Ok(product)
+}
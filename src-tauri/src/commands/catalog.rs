@@ -0,0 +1,25 @@
+use crate::db::DatabaseExt;
+use crate::error::AppResult;
+use crate::models::ImportReport;
+use crate::services::catalog;
+use std::path::Path;
+use tauri::AppHandle;
+
+/// Import products from a .csv or .json file; a dry run only reports
+#[tauri::command]
+pub fn import_products(
+    app: AppHandle,
+    path: String,
+    dry_run: Option<bool>,
+    staff_id: Option<i64>,
+) -> AppResult<ImportReport> {
+    let mut conn = app.db().conn.lock()?;
+    catalog::import_products(&mut conn, Path::new(&path), dry_run.unwrap_or(false), staff_id)
+}
+
+/// Export products to a .csv or .json file; returns how many were written
+#[tauri::command]
+pub fn export_products(app: AppHandle, path: String) -> AppResult<usize> {
+    let conn = app.db().conn.lock()?;
+    catalog::export_products(&conn, Path::new(&path))
+}
@@ -1,57 +1,52 @@
 use crate::db::DatabaseExt;
-use crate::models::Category;
+use crate::error::AppResult;
+use crate::models::{Category, UpdateCategory};
+use crate::services::categories;
 use tauri::AppHandle;
 
 #[tauri::command]
-pub fn get_categories(app: AppHandle) -> Result<Vec<Category>, String> {
-    let db = app.db();
-    let conn = db.conn.lock().map_err(|e| e.to_string())?;
-
-    let mut stmt = conn
-        .prepare("SELECT id, name FROM categories ORDER BY name")
-        .map_err(|e| e.to_string())?;
-
-    let categories = stmt
-        .query_map([], |row| {
-            Ok(Category {
-                id: row.get(0)?,
-                name: row.get(1)?,
-            })
-        })
-        .map_err(|e| e.to_string())?
-        .collect::<Result<Vec<_>, _>>()
-        .map_err(|e| e.to_string())?;
-
-    Ok(categories)
+pub fn get_categories(app: AppHandle, include_archived: Option<bool>) -> AppResult<Vec<Category>> {
+    let conn = app.db().conn.lock()?;
+    categories::get_categories(&conn, include_archived.unwrap_or(false))
 }
 
 #[tauri::command]
-pub fn create_category(app: AppHandle, name: String) -> Result<Category, String> {
-    let db = app.db();
-    let conn = db.conn.lock().map_err(|e| e.to_string())?;
-
-    conn.execute("INSERT INTO categories (name) VALUES (?1)", [&name])
-        .map_err(|e| e.to_string())?;
-
-    let id = conn.last_insert_rowid();
+pub fn create_category(app: AppHandle, name: String) -> AppResult<Category> {
+    let conn = app.db().conn.lock()?;
+    categories::create_category(&conn, name)
+}
 
-    Ok(Category { id, name })
+#[tauri::command]
+pub fn update_category(app: AppHandle, category: UpdateCategory) -> AppResult<Category> {
+    let mut conn = app.db().conn.lock()?;
+    categories::update_category(&mut conn, category)
 }
 
+/// Order the children of `parent_id`, or the top level; returns every category
 #[tauri::command]
-pub fn delete_category(app: AppHandle, id: i64) -> Result<(), String> {
-    let db = app.db();
-    let conn = db.conn.lock().map_err(|e| e.to_string())?;
+pub fn reorder_categories(
+    app: AppHandle,
+    parent_id: Option<i64>,
+    category_ids: Vec<i64>,
+) -> AppResult<Vec<Category>> {
+    let mut conn = app.db().conn.lock()?;
+    categories::reorder_categories(&mut conn, parent_id, category_ids)
+}
 
-    // Set category_id to NULL for products in this category
-    conn.execute(
-        "UPDATE products SET category_id = NULL WHERE category_id = ?1",
-        [id],
-    )
-    .map_err(|e| e.to_string())?;
+#[tauri::command]
+pub fn archive_category(app: AppHandle, id: i64) -> AppResult<Category> {
+    let conn = app.db().conn.lock()?;
+    categories::archive_category(&conn, id)
+}
 
-    conn.execute("DELETE FROM categories WHERE id = ?1", [id])
-        .map_err(|e| e.to_string())?;
+#[tauri::command]
+pub fn restore_category(app: AppHandle, id: i64) -> AppResult<Category> {
+    let conn = app.db().conn.lock()?;
+    categories::restore_category(&conn, id)
+}
 
-    Ok(())
+#[tauri::command]
+pub fn purge_category(app: AppHandle, id: i64) -> AppResult<()> {
+    let mut conn = app.db().conn.lock()?;
+    categories::purge_category(&mut conn, id)
 }
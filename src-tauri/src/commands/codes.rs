@@ -0,0 +1,36 @@
+use crate::db::DatabaseExt;
+use crate::error::AppResult;
+use crate::models::{CodeMatch, ProductCode};
+use crate::services::codes;
+use tauri::AppHandle;
+
+/// Look up a scanned SKU or EAN. Unknown codes fail with `UNKNOWN_CODE`.
+#[tauri::command]
+pub fn find_product_by_code(app: AppHandle, code: String) -> AppResult<CodeMatch> {
+    let conn = app.db().conn.lock()?;
+    codes::find_product_by_code(&conn, code)
+}
+
+#[tauri::command]
+pub fn get_product_codes(app: AppHandle, product_id: i64) -> AppResult<Vec<ProductCode>> {
+    let conn = app.db().conn.lock()?;
+    codes::get_product_codes(&conn, product_id)
+}
+
+#[tauri::command]
+pub fn add_product_code(
+    app: AppHandle,
+    product_id: i64,
+    variant_id: Option<i64>,
+    code: String,
+    pack_quantity: Option<i32>,
+) -> AppResult<ProductCode> {
+    let conn = app.db().conn.lock()?;
+    codes::add_product_code(&conn, product_id, variant_id, code, pack_quantity)
+}
+
+#[tauri::command]
+pub fn remove_product_code(app: AppHandle, code: String) -> AppResult<()> {
+    let conn = app.db().conn.lock()?;
+    codes::remove_product_code(&conn, code)
+}
@@ -0,0 +1,46 @@
+use crate::db::DatabaseExt;
+use crate::error::AppResult;
+use crate::models::{LocationLowStock, LocationQuantity, StockLocation, TransferStock};
+use crate::services::locations;
+use tauri::AppHandle;
+
+#[tauri::command]
+pub fn get_stock_locations(app: AppHandle) -> AppResult<Vec<StockLocation>> {
+    let conn = app.db().conn.lock()?;
+    locations::get_stock_locations(&conn)
+}
+
+#[tauri::command]
+pub fn create_stock_location(app: AppHandle, name: String) -> AppResult<StockLocation> {
+    let conn = app.db().conn.lock()?;
+    locations::create_stock_location(&conn, name)
+}
+
+#[tauri::command]
+pub fn get_stock_by_location(app: AppHandle, product_id: i64) -> AppResult<Vec<LocationQuantity>> {
+    let conn = app.db().conn.lock()?;
+    locations::get_stock_by_location(&conn, product_id)
+}
+
+#[tauri::command]
+pub fn transfer_stock(app: AppHandle, transfer: TransferStock) -> AppResult<Vec<LocationQuantity>> {
+    let mut conn = app.db().conn.lock()?;
+    locations::transfer_stock(&mut conn, transfer)
+}
+
+#[tauri::command]
+pub fn set_location_threshold(
+    app: AppHandle,
+    location_id: i64,
+    product_id: i64,
+    threshold: Option<i32>,
+) -> AppResult<()> {
+    let conn = app.db().conn.lock()?;
+    locations::set_location_threshold(&conn, location_id, product_id, threshold)
+}
+
+#[tauri::command]
+pub fn get_location_low_stock(app: AppHandle, location_id: i64) -> AppResult<Vec<LocationLowStock>> {
+    let conn = app.db().conn.lock()?;
+    locations::get_location_low_stock(&conn, location_id)
+}
@@ -0,0 +1,42 @@
+use crate::db::DatabaseExt;
+use crate::error::AppResult;
+use crate::models::{CreateStockLot, KegYield, StockLot};
+use crate::services::lots;
+use tauri::AppHandle;
+
+#[tauri::command]
+pub fn get_stock_lots(app: AppHandle, product_id: i64) -> AppResult<Vec<StockLot>> {
+    let conn = app.db().conn.lock()?;
+    lots::get_stock_lots(&conn, product_id)
+}
+
+/// Lots expiring within `days` (7 when omitted), already expired ones included
+#[tauri::command]
+pub fn get_expiring_lots(app: AppHandle, days: Option<i32>) -> AppResult<Vec<StockLot>> {
+    let conn = app.db().conn.lock()?;
+    lots::get_expiring_lots(&conn, days.unwrap_or(7))
+}
+
+#[tauri::command]
+pub fn receive_stock_lot(app: AppHandle, lot: CreateStockLot) -> AppResult<StockLot> {
+    let mut conn = app.db().conn.lock()?;
+    lots::receive_stock_lot(&mut conn, lot)
+}
+
+#[tauri::command]
+pub fn tap_keg(app: AppHandle, lot_id: i64) -> AppResult<StockLot> {
+    let conn = app.db().conn.lock()?;
+    lots::tap_keg(&conn, lot_id)
+}
+
+#[tauri::command]
+pub fn retire_keg(app: AppHandle, lot_id: i64, staff_id: Option<i64>) -> AppResult<KegYield> {
+    let mut conn = app.db().conn.lock()?;
+    lots::retire_keg(&mut conn, lot_id, staff_id)
+}
+
+#[tauri::command]
+pub fn get_keg_yield(app: AppHandle, lot_id: i64) -> AppResult<KegYield> {
+    let conn = app.db().conn.lock()?;
+    lots::get_keg_yield(&conn, lot_id)
+}
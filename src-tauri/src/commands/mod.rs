@@ -1,5 +1,20 @@
+pub mod availability;
+pub mod catalog;
 pub mod categories;
+pub mod codes;
+pub mod locations;
+pub mod lots;
+pub mod modifiers;
 pub mod orders;
+pub mod prices;
+pub mod pricing;
 pub mod products;
+pub mod purchasing;
+pub mod recipes;
+pub mod reorder;
 pub mod reports;
 pub mod staff;
+pub mod stock;
+pub mod stock_takes;
+pub mod taxes;
+pub mod variants;
@@ -0,0 +1,60 @@
+use crate::db::DatabaseExt;
+use crate::error::AppResult;
+use crate::models::{CreateModifier, CreateModifierGroup, ModifierGroup, UpdateModifierGroup};
+use crate::services::modifiers;
+use tauri::AppHandle;
+
+#[tauri::command]
+pub fn get_modifier_groups(app: AppHandle) -> AppResult<Vec<ModifierGroup>> {
+    let conn = app.db().conn.lock()?;
+    modifiers::get_modifier_groups(&conn)
+}
+
+/// Groups offered with a product, directly or through its category
+#[tauri::command]
+pub fn get_product_modifier_groups(app: AppHandle, product_id: i64) -> AppResult<Vec<ModifierGroup>> {
+    let conn = app.db().conn.lock()?;
+    modifiers::get_product_modifier_groups(&conn, product_id)
+}
+
+#[tauri::command]
+pub fn create_modifier_group(app: AppHandle, group: CreateModifierGroup) -> AppResult<ModifierGroup> {
+    let mut conn = app.db().conn.lock()?;
+    modifiers::create_modifier_group(&mut conn, group)
+}
+
+#[tauri::command]
+pub fn update_modifier_group(app: AppHandle, group: UpdateModifierGroup) -> AppResult<ModifierGroup> {
+    let conn = app.db().conn.lock()?;
+    modifiers::update_modifier_group(&conn, group)
+}
+
+#[tauri::command]
+pub fn delete_modifier_group(app: AppHandle, id: i64) -> AppResult<()> {
+    let mut conn = app.db().conn.lock()?;
+    modifiers::delete_modifier_group(&mut conn, id)
+}
+
+#[tauri::command]
+pub fn add_modifier(app: AppHandle, group_id: i64, modifier: CreateModifier) -> AppResult<ModifierGroup> {
+    let mut conn = app.db().conn.lock()?;
+    modifiers::add_modifier(&mut conn, group_id, modifier)
+}
+
+#[tauri::command]
+pub fn delete_modifier(app: AppHandle, id: i64) -> AppResult<()> {
+    let conn = app.db().conn.lock()?;
+    modifiers::delete_modifier(&conn, id)
+}
+
+/// Replace the products and categories the group is offered with
+#[tauri::command]
+pub fn set_modifier_group_links(
+    app: AppHandle,
+    group_id: i64,
+    product_ids: Vec<i64>,
+    category_ids: Vec<i64>,
+) -> AppResult<ModifierGroup> {
+    let mut conn = app.db().conn.lock()?;
+    modifiers::set_modifier_group_links(&mut conn, group_id, product_ids, category_ids)
+}
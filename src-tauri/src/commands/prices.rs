@@ -0,0 +1,25 @@
+use crate::db::DatabaseExt;
+use crate::error::AppResult;
+use crate::models::{PriceChange, SchedulePriceChange};
+use crate::services::prices;
+use tauri::AppHandle;
+
+/// A product's price timeline, including scheduled changes
+#[tauri::command]
+pub fn get_price_history(app: AppHandle, product_id: i64) -> AppResult<Vec<PriceChange>> {
+    let conn = app.db().conn.lock()?;
+    prices::get_price_history(&conn, product_id)
+}
+
+/// Change a price now, or at a later local time
+#[tauri::command]
+pub fn schedule_price_change(app: AppHandle, change: SchedulePriceChange) -> AppResult<PriceChange> {
+    let mut conn = app.db().conn.lock()?;
+    prices::schedule_price_change(&mut conn, change)
+}
+
+#[tauri::command]
+pub fn cancel_price_change(app: AppHandle, id: i64) -> AppResult<()> {
+    let conn = app.db().conn.lock()?;
+    prices::cancel_price_change(&conn, id)
+}
@@ -0,0 +1,36 @@
+use crate::db::DatabaseExt;
+use crate::error::AppResult;
+use crate::models::{CreatePricingRule, PricingRule, UpdatePricingRule};
+use crate::services::pricing;
+use tauri::AppHandle;
+
+#[tauri::command]
+pub fn get_pricing_rules(app: AppHandle) -> AppResult<Vec<PricingRule>> {
+    let conn = app.db().conn.lock()?;
+    pricing::get_pricing_rules(&conn)
+}
+
+/// Rules in effect right now, e.g. to show that happy hour is on
+#[tauri::command]
+pub fn get_active_pricing_rules(app: AppHandle) -> AppResult<Vec<PricingRule>> {
+    let conn = app.db().conn.lock()?;
+    pricing::get_active_pricing_rules(&conn, chrono::Local::now().naive_local())
+}
+
+#[tauri::command]
+pub fn create_pricing_rule(app: AppHandle, rule: CreatePricingRule) -> AppResult<PricingRule> {
+    let conn = app.db().conn.lock()?;
+    pricing::create_pricing_rule(&conn, rule)
+}
+
+#[tauri::command]
+pub fn update_pricing_rule(app: AppHandle, rule: UpdatePricingRule) -> AppResult<PricingRule> {
+    let conn = app.db().conn.lock()?;
+    pricing::update_pricing_rule(&conn, rule)
+}
+
+#[tauri::command]
+pub fn delete_pricing_rule(app: AppHandle, id: i64) -> AppResult<()> {
+    let conn = app.db().conn.lock()?;
+    pricing::delete_pricing_rule(&conn, id)
+}
@@ -1,163 +1,61 @@
 use crate::db::DatabaseExt;
+use crate::error::AppResult;
 use crate::models::{CreateProduct, Product, UpdateProduct};
+use crate::services::products;
 use tauri::AppHandle;
 
+/// Products on sale, or every product when `include_archived` is set
 #[tauri::command]
-pub fn get_products(app: AppHandle) -> Result<Vec<Product>, String> {
-    let db = app.db();
-    let conn = db.conn.lock().map_err(|e| e.to_string())?;
-
-    let mut stmt = conn
-        .prepare(
-            "SELECT p.id, p.name, p.price, p.quantity, p.category_id, c.name, p.low_stock_threshold, p.created_at
-             FROM products p
-             LEFT JOIN categories c ON p.category_id = c.id
-             ORDER BY p.name",
-        )
-        .map_err(|e| e.to_string())?;
-
-    let products = stmt
-        .query_map([], |row| {
-            Ok(Product {
-                id: row.get(0)?,
-                name: row.get(1)?,
-                price: row.get(2)?,
-                quantity: row.get(3)?,
-                category_id: row.get(4)?,
-                category_name: row.get(5)?,
-                low_stock_threshold: row.get(6)?,
-                created_at: row.get(7)?,
-            })
-        })
-        .map_err(|e| e.to_string())?
-        .collect::<Result<Vec<_>, _>>()
-        .map_err(|e| e.to_string())?;
-
-    Ok(products)
+pub fn get_products(app: AppHandle, include_archived: Option<bool>) -> AppResult<Vec<Product>> {
+    let conn = app.db().conn.lock()?;
+    products::get_products(&conn, include_archived.unwrap_or(false))
 }
 
+/// Products on sale matching a search, best matches and best sellers first
 #[tauri::command]
-pub fn create_product(app: AppHandle, product: CreateProduct) -> Result<Product, String> {
-    let db = app.db();
-    let conn = db.conn.lock().map_err(|e| e.to_string())?;
-
-    let threshold = product.low_stock_threshold.unwrap_or(5);
-
-    conn.execute(
-        "INSERT INTO products (name, price, quantity, category_id, low_stock_threshold) VALUES (?1, ?2, ?3, ?4, ?5)",
-        rusqlite::params![product.name, product.price, product.quantity, product.category_id, threshold],
-    )
-    .map_err(|e| e.to_string())?;
-
-    let id = conn.last_insert_rowid();
-
-    let mut stmt = conn
-        .prepare(
-            "SELECT p.id, p.name, p.price, p.quantity, p.category_id, c.name, p.low_stock_threshold, p.created_at
-             FROM products p
-             LEFT JOIN categories c ON p.category_id = c.id
-             WHERE p.id = ?1",
-        )
-        .map_err(|e| e.to_string())?;
-
-    let product = stmt
-        .query_row([id], |row| {
-            Ok(Product {
-                id: row.get(0)?,
-                name: row.get(1)?,
-                price: row.get(2)?,
-                quantity: row.get(3)?,
-                category_id: row.get(4)?,
-                category_name: row.get(5)?,
-                low_stock_threshold: row.get(6)?,
-                created_at: row.get(7)?,
-            })
-        })
-        .map_err(|e| e.to_string())?;
-
-    Ok(product)
+pub fn search_products(
+    app: AppHandle,
+    query: String,
+    category_id: Option<i64>,
+    limit: Option<usize>,
+) -> AppResult<Vec<Product>> {
+    let conn = app.db().conn.lock()?;
+    products::search_products(&conn, &query, category_id, limit.unwrap_or(50))
 }
 
 #[tauri::command]
-pub fn update_product(app: AppHandle, product: UpdateProduct) -> Result<Product, String> {
-    let db = app.db();
-    let conn = db.conn.lock().map_err(|e| e.to_string())?;
-
-    conn.execute(
-        "UPDATE products SET name = ?1, price = ?2, quantity = ?3, category_id = ?4, low_stock_threshold = ?5 WHERE id = ?6",
-        rusqlite::params![product.name, product.price, product.quantity, product.category_id, product.low_stock_threshold, product.id],
-    )
-    .map_err(|e| e.to_string())?;
-
-    let mut stmt = conn
-        .prepare(
-            "SELECT p.id, p.name, p.price, p.quantity, p.category_id, c.name, p.low_stock_threshold, p.created_at
-             FROM products p
-             LEFT JOIN categories c ON p.category_id = c.id
-             WHERE p.id = ?1",
-        )
-        .map_err(|e| e.to_string())?;
-
-    let product = stmt
-        .query_row([product.id], |row| {
-            Ok(Product {
-                id: row.get(0)?,
-                name: row.get(1)?,
-                price: row.get(2)?,
-                quantity: row.get(3)?,
-                category_id: row.get(4)?,
-                category_name: row.get(5)?,
-                low_stock_threshold: row.get(6)?,
-                created_at: row.get(7)?,
-            })
-        })
-        .map_err(|e| e.to_string())?;
-
-    Ok(product)
+pub fn create_product(app: AppHandle, product: CreateProduct) -> AppResult<Product> {
+    let mut conn = app.db().conn.lock()?;
+    products::create_product(&mut conn, product)
 }
 
 #[tauri::command]
-pub fn delete_product(app: AppHandle, id: i64) -> Result<(), String> {
-    let db = app.db();
-    let conn = db.conn.lock().map_err(|e| e.to_string())?;
-
-    conn.execute("DELETE FROM products WHERE id = ?1", [id])
-        .map_err(|e| e.to_string())?;
-
-    Ok(())
+pub fn update_product(app: AppHandle, product: UpdateProduct) -> AppResult<Product> {
+    let mut conn = app.db().conn.lock()?;
+    products::update_product(&mut conn, product)
 }
 
 #[tauri::command]
-pub fn get_low_stock(app: AppHandle) -> Result<Vec<Product>, String> {
-    let db = app.db();
-    let conn = db.conn.lock().map_err(|e| e.to_string())?;
+pub fn archive_product(app: AppHandle, id: i64) -> AppResult<Product> {
+    let conn = app.db().conn.lock()?;
+    products::archive_product(&conn, id)
+}
 
-    let mut stmt = conn
-        .prepare(
-            "SELECT p.id, p.name, p.price, p.quantity, p.category_id, c.name, p.low_stock_threshold, p.created_at
-             FROM products p
-             LEFT JOIN categories c ON p.category_id = c.id
-             WHERE p.quantity <= p.low_stock_threshold
-             ORDER BY p.quantity ASC",
-        )
-        .map_err(|e| e.to_string())?;
+#[tauri::command]
+pub fn restore_product(app: AppHandle, id: i64) -> AppResult<Product> {
+    let conn = app.db().conn.lock()?;
+    products::restore_product(&conn, id)
+}
 
-    let products = stmt
-        .query_map([], |row| {
-            Ok(Product {
-                id: row.get(0)?,
-                name: row.get(1)?,
-                price: row.get(2)?,
-                quantity: row.get(3)?,
-                category_id: row.get(4)?,
-                category_name: row.get(5)?,
-                low_stock_threshold: row.get(6)?,
-                created_at: row.get(7)?,
-            })
-        })
-        .map_err(|e| e.to_string())?
-        .collect::<Result<Vec<_>, _>>()
-        .map_err(|e| e.to_string())?;
+/// Permanently delete an archived product nothing refers to
+#[tauri::command]
+pub fn purge_product(app: AppHandle, id: i64) -> AppResult<()> {
+    let mut conn = app.db().conn.lock()?;
+    products::purge_product(&mut conn, id)
+}
 
-    Ok(products)
+#[tauri::command]
+pub fn get_low_stock(app: AppHandle) -> AppResult<Vec<Product>> {
+    let conn = app.db().conn.lock()?;
+    products::get_low_stock(&conn)
 }
@@ -0,0 +1,119 @@
+use crate::db::DatabaseExt;
+use crate::error::AppResult;
+use crate::models::{
+    CreatePurchaseOrder, CreatePurchaseOrderLine, CreateSupplier, PurchaseOrder, PurchaseOrderStatus,
+    PurchaseOrderWithLines, PurchaseReceipt, ReceivePurchaseOrder, Supplier, SupplierBalance, UpdateSupplier,
+};
+use crate::services::purchasing;
+use tauri::AppHandle;
+
+// ============ SUPPLIERS ============
+
+#[tauri::command]
+pub fn get_suppliers(app: AppHandle) -> AppResult<Vec<Supplier>> {
+    let conn = app.db().conn.lock()?;
+    purchasing::get_suppliers(&conn)
+}
+
+#[tauri::command]
+pub fn create_supplier(app: AppHandle, supplier: CreateSupplier) -> AppResult<Supplier> {
+    let conn = app.db().conn.lock()?;
+    purchasing::create_supplier(&conn, supplier)
+}
+
+#[tauri::command]
+pub fn update_supplier(app: AppHandle, supplier: UpdateSupplier) -> AppResult<Supplier> {
+    let conn = app.db().conn.lock()?;
+    purchasing::update_supplier(&conn, supplier)
+}
+
+// ============ PURCHASE ORDERS ============
+
+#[tauri::command]
+pub fn get_purchase_orders(
+    app: AppHandle,
+    supplier_id: Option<i64>,
+    status: Option<PurchaseOrderStatus>,
+) -> AppResult<Vec<PurchaseOrder>> {
+    let conn = app.db().conn.lock()?;
+    purchasing::get_purchase_orders(&conn, supplier_id, status)
+}
+
+#[tauri::command]
+pub fn get_purchase_order(app: AppHandle, id: i64) -> AppResult<PurchaseOrderWithLines> {
+    let conn = app.db().conn.lock()?;
+    purchasing::get_purchase_order(&conn, id)
+}
+
+/// Draft a new purchase order
+#[tauri::command]
+pub fn create_purchase_order(app: AppHandle, order: CreatePurchaseOrder) -> AppResult<PurchaseOrderWithLines> {
+    let mut conn = app.db().conn.lock()?;
+    purchasing::create_purchase_order(&mut conn, order)
+}
+
+/// Replace the lines of a draft purchase order
+#[tauri::command]
+pub fn update_purchase_order_lines(
+    app: AppHandle,
+    id: i64,
+    lines: Vec<CreatePurchaseOrderLine>,
+) -> AppResult<PurchaseOrderWithLines> {
+    let mut conn = app.db().conn.lock()?;
+    purchasing::update_purchase_order_lines(&mut conn, id, lines)
+}
+
+#[tauri::command]
+pub fn send_purchase_order(app: AppHandle, id: i64) -> AppResult<PurchaseOrderWithLines> {
+    let mut conn = app.db().conn.lock()?;
+    purchasing::send_purchase_order(&mut conn, id)
+}
+
+#[tauri::command]
+pub fn cancel_purchase_order(app: AppHandle, id: i64) -> AppResult<PurchaseOrderWithLines> {
+    let mut conn = app.db().conn.lock()?;
+    purchasing::cancel_purchase_order(&mut conn, id)
+}
+
+/// Receive a delivery, fully or partially
+#[tauri::command]
+pub fn receive_purchase_order(
+    app: AppHandle,
+    id: i64,
+    receipt: ReceivePurchaseOrder,
+) -> AppResult<PurchaseOrderWithLines> {
+    let mut conn = app.db().conn.lock()?;
+    purchasing::receive_purchase_order(&mut conn, id, receipt)
+}
+
+/// Receive a scanned delivery against the purchase order
+#[tauri::command]
+pub fn receive_by_code(
+    app: AppHandle,
+    id: i64,
+    code: String,
+    quantity: i32,
+    staff_id: Option<i64>,
+) -> AppResult<PurchaseOrderWithLines> {
+    let mut conn = app.db().conn.lock()?;
+    purchasing::receive_by_code(&mut conn, id, code, quantity, staff_id)
+}
+
+// ============ REPORTS ============
+
+#[tauri::command]
+pub fn get_supplier_balances(app: AppHandle) -> AppResult<Vec<SupplierBalance>> {
+    let conn = app.db().conn.lock()?;
+    purchasing::get_supplier_balances(&conn)
+}
+
+#[tauri::command]
+pub fn get_supplier_purchase_history(
+    app: AppHandle,
+    supplier_id: i64,
+    start_date: Option<String>,
+    end_date: Option<String>,
+) -> AppResult<Vec<PurchaseReceipt>> {
+    let conn = app.db().conn.lock()?;
+    purchasing::get_supplier_purchase_history(&conn, supplier_id, start_date.as_deref(), end_date.as_deref())
+}
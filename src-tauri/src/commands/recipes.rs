@@ -0,0 +1,22 @@
+use crate::db::DatabaseExt;
+use crate::error::AppResult;
+use crate::models::{RecipeComponent, SetRecipeComponent};
+use crate::services::recipes;
+use tauri::AppHandle;
+
+#[tauri::command]
+pub fn get_recipe(app: AppHandle, product_id: i64) -> AppResult<Vec<RecipeComponent>> {
+    let conn = app.db().conn.lock()?;
+    recipes::get_recipe(&conn, product_id)
+}
+
+/// Replace the product's ingredients; an empty list removes the recipe
+#[tauri::command]
+pub fn set_recipe(
+    app: AppHandle,
+    product_id: i64,
+    components: Vec<SetRecipeComponent>,
+) -> AppResult<Vec<RecipeComponent>> {
+    let mut conn = app.db().conn.lock()?;
+    recipes::set_recipe(&mut conn, product_id, components)
+}
@@ -0,0 +1,31 @@
+use crate::db::DatabaseExt;
+use crate::error::AppResult;
+use crate::models::{Product, SupplierReorder};
+use crate::services::reorder;
+use tauri::AppHandle;
+
+#[tauri::command]
+pub fn get_reorder_suggestions(
+    app: AppHandle,
+    lead_time_days: i32,
+    cover_days: Option<i32>,
+    sessions: Option<i32>,
+) -> AppResult<Vec<SupplierReorder>> {
+    let conn = app.db().conn.lock()?;
+    reorder::get_reorder_suggestions(
+        &conn,
+        lead_time_days,
+        cover_days.unwrap_or(reorder::DEFAULT_COVER_DAYS),
+        sessions.unwrap_or(reorder::DEFAULT_SESSIONS),
+    )
+}
+
+#[tauri::command]
+pub fn tune_low_stock_thresholds(
+    app: AppHandle,
+    lead_time_days: i32,
+    sessions: Option<i32>,
+) -> AppResult<Vec<Product>> {
+    let mut conn = app.db().conn.lock()?;
+    reorder::tune_low_stock_thresholds(&mut conn, lead_time_days, sessions.unwrap_or(reorder::DEFAULT_SESSIONS))
+}
@@ -0,0 +1,38 @@
+use crate::db::DatabaseExt;
+use crate::error::AppResult;
+use crate::models::{CreateStockMovement, StockDiscrepancy, StockMovement};
+use crate::services::stock;
+use tauri::AppHandle;
+
+/// Record a delivery, waste, comp or manual correction
+#[tauri::command]
+pub fn record_stock_movement(app: AppHandle, movement: CreateStockMovement) -> AppResult<StockMovement> {
+    let mut conn = app.db().conn.lock()?;
+    stock::record_stock_movement(&mut conn, movement)
+}
+
+/// Stock history for one product, optionally limited to a date range
+#[tauri::command]
+pub fn get_stock_movements(
+    app: AppHandle,
+    product_id: i64,
+    start_date: Option<String>,
+    end_date: Option<String>,
+) -> AppResult<Vec<StockMovement>> {
+    let conn = app.db().conn.lock()?;
+    stock::get_stock_movements(&conn, product_id, start_date.as_deref(), end_date.as_deref())
+}
+
+/// Products whose quantity no longer matches their ledger
+#[tauri::command]
+pub fn get_stock_discrepancies(app: AppHandle) -> AppResult<Vec<StockDiscrepancy>> {
+    let conn = app.db().conn.lock()?;
+    stock::get_stock_discrepancies(&conn)
+}
+
+/// Reset quantities to the ledger totals
+#[tauri::command]
+pub fn rebuild_stock_from_ledger(app: AppHandle) -> AppResult<Vec<StockDiscrepancy>> {
+    let mut conn = app.db().conn.lock()?;
+    stock::rebuild_stock_from_ledger(&mut conn)
+}
@@ -0,0 +1,59 @@
+use crate::db::DatabaseExt;
+use crate::error::AppResult;
+use crate::models::{StockTake, StockTakeWithLines, VarianceHistoryEntry};
+use crate::services::stock_takes;
+use tauri::AppHandle;
+
+#[tauri::command]
+pub fn get_stock_takes(app: AppHandle, limit: Option<i32>) -> AppResult<Vec<StockTake>> {
+    let conn = app.db().conn.lock()?;
+    stock_takes::get_stock_takes(&conn, limit.unwrap_or(30))
+}
+
+#[tauri::command]
+pub fn get_stock_take(app: AppHandle, id: i64) -> AppResult<StockTakeWithLines> {
+    let conn = app.db().conn.lock()?;
+    stock_takes::get_stock_take(&conn, id)
+}
+
+#[tauri::command]
+pub fn start_stock_take(
+    app: AppHandle,
+    staff_id: i64,
+    location_id: Option<i64>,
+    notes: Option<String>,
+) -> AppResult<StockTakeWithLines> {
+    let mut conn = app.db().conn.lock()?;
+    stock_takes::start_stock_take(&mut conn, staff_id, location_id, notes)
+}
+
+#[tauri::command]
+pub fn record_stock_count(
+    app: AppHandle,
+    stock_take_id: i64,
+    product_id: i64,
+    staff_id: i64,
+    counted_quantity: i32,
+) -> AppResult<StockTakeWithLines> {
+    let mut conn = app.db().conn.lock()?;
+    stock_takes::record_stock_count(&mut conn, stock_take_id, product_id, staff_id, counted_quantity)
+}
+
+/// Post all adjustments from the count in one transaction
+#[tauri::command]
+pub fn commit_stock_take(app: AppHandle, id: i64, staff_id: i64) -> AppResult<StockTakeWithLines> {
+    let mut conn = app.db().conn.lock()?;
+    stock_takes::commit_stock_take(&mut conn, id, staff_id)
+}
+
+#[tauri::command]
+pub fn cancel_stock_take(app: AppHandle, id: i64) -> AppResult<StockTakeWithLines> {
+    let mut conn = app.db().conn.lock()?;
+    stock_takes::cancel_stock_take(&mut conn, id)
+}
+
+#[tauri::command]
+pub fn get_variance_history(app: AppHandle, product_id: i64) -> AppResult<Vec<VarianceHistoryEntry>> {
+    let conn = app.db().conn.lock()?;
+    stock_takes::get_variance_history(&conn, product_id)
+}
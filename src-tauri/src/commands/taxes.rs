@@ -0,0 +1,41 @@
+use crate::db::DatabaseExt;
+use crate::error::AppResult;
+use crate::models::{Category, CreateTaxRate, Product, TaxRate, UpdateTaxRate};
+use crate::services::taxes;
+use tauri::AppHandle;
+
+#[tauri::command]
+pub fn get_tax_rates(app: AppHandle) -> AppResult<Vec<TaxRate>> {
+    let conn = app.db().conn.lock()?;
+    taxes::get_tax_rates(&conn)
+}
+
+#[tauri::command]
+pub fn create_tax_rate(app: AppHandle, rate: CreateTaxRate) -> AppResult<TaxRate> {
+    let conn = app.db().conn.lock()?;
+    taxes::create_tax_rate(&conn, rate)
+}
+
+#[tauri::command]
+pub fn update_tax_rate(app: AppHandle, rate: UpdateTaxRate) -> AppResult<TaxRate> {
+    let conn = app.db().conn.lock()?;
+    taxes::update_tax_rate(&conn, rate)
+}
+
+#[tauri::command]
+pub fn delete_tax_rate(app: AppHandle, id: i64) -> AppResult<()> {
+    let conn = app.db().conn.lock()?;
+    taxes::delete_tax_rate(&conn, id)
+}
+
+#[tauri::command]
+pub fn set_product_tax_rate(app: AppHandle, product_id: i64, tax_rate_id: Option<i64>) -> AppResult<Product> {
+    let conn = app.db().conn.lock()?;
+    taxes::set_product_tax_rate(&conn, product_id, tax_rate_id)
+}
+
+#[tauri::command]
+pub fn set_category_tax_rate(app: AppHandle, category_id: i64, tax_rate_id: Option<i64>) -> AppResult<Category> {
+    let conn = app.db().conn.lock()?;
+    taxes::set_category_tax_rate(&conn, category_id, tax_rate_id)
+}
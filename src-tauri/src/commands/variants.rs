@@ -0,0 +1,30 @@
+use crate::db::DatabaseExt;
+use crate::error::AppResult;
+use crate::models::{CreateProductVariant, ProductVariant, UpdateProductVariant};
+use crate::services::variants;
+use tauri::AppHandle;
+
+/// Variants of one product, or all variants when no product is given
+#[tauri::command]
+pub fn get_variants(app: AppHandle, product_id: Option<i64>) -> AppResult<Vec<ProductVariant>> {
+    let conn = app.db().conn.lock()?;
+    variants::get_variants(&conn, product_id)
+}
+
+#[tauri::command]
+pub fn create_variant(app: AppHandle, variant: CreateProductVariant) -> AppResult<ProductVariant> {
+    let mut conn = app.db().conn.lock()?;
+    variants::create_variant(&mut conn, variant)
+}
+
+#[tauri::command]
+pub fn update_variant(app: AppHandle, variant: UpdateProductVariant) -> AppResult<ProductVariant> {
+    let mut conn = app.db().conn.lock()?;
+    variants::update_variant(&mut conn, variant)
+}
+
+#[tauri::command]
+pub fn delete_variant(app: AppHandle, id: i64) -> AppResult<()> {
+    let conn = app.db().conn.lock()?;
+    variants::delete_variant(&conn, id)
+}
@@ -1,5 +1,5 @@
-use rusqlite::{Connection, Result};
-use std::path::PathBuf;
+use rusqlite::{Connection, Result, Transaction, TransactionBehavior};
+use std::path::Path;
 use std::sync::Mutex;
 use tauri::AppHandle;
 
@@ -16,203 +16,1168 @@ impl Database {
 
         std::fs::create_dir_all(&app_dir).expect("Failed to create app data directory");
 
-        let db_path: PathBuf = app_dir.join("pub_inventory.db");
-        let conn = Connection::open(db_path)?;
+        Database::open(app_dir.join("pub_inventory.db"))
+    }
+
+    /// Open (or create) a database file at an arbitrary path, without Tauri.
+    pub fn open<P: AsRef<Path>>(path: P) -> Result<Self> {
+        let conn = Connection::open(path)?;
 
         Ok(Database {
             conn: Mutex::new(conn),
         })
     }
 
-    pub fn initialize(&self) -> Result<()> {
-        let conn = self.conn.lock().unwrap();
+    /// A private in-memory database, e.g. for tests or one-off tooling.
+    pub fn open_in_memory() -> Result<Self> {
+        Ok(Database {
+            conn: Mutex::new(Connection::open_in_memory()?),
+        })
+    }
 
-        conn.execute_batch(
-            "
-            -- Product categories
-            CREATE TABLE IF NOT EXISTS categories (
-                id INTEGER PRIMARY KEY AUTOINCREMENT,
-                name TEXT NOT NULL UNIQUE
-            );
+    pub fn initialize(&self) -> std::result::Result<(), MigrationError> {
+        let mut conn = self.conn.lock().unwrap();
+        run_migrations(&mut conn)
+    }
+}
 
-            -- Products with inventory
-            CREATE TABLE IF NOT EXISTS products (
-                id INTEGER PRIMARY KEY AUTOINCREMENT,
-                name TEXT NOT NULL,
-                price REAL NOT NULL,
-                quantity INTEGER NOT NULL DEFAULT 0,
-                category_id INTEGER,
-                low_stock_threshold INTEGER DEFAULT 5,
-                created_at DATETIME DEFAULT CURRENT_TIMESTAMP,
-                FOREIGN KEY (category_id) REFERENCES categories(id)
-            );
+/// Begin a write transaction that takes the database write lock up front, so
+/// a stock check and the matching deduction cannot interleave with another writer.
+pub fn begin_write(conn: &mut Connection) -> Result<Transaction<'_>> {
+    conn.transaction_with_behavior(TransactionBehavior::Immediate)
+}
 
-            -- Staff members
-            CREATE TABLE IF NOT EXISTS staff (
-                id INTEGER PRIMARY KEY AUTOINCREMENT,
-                name TEXT NOT NULL UNIQUE,
-                pin TEXT,
-                created_at DATETIME DEFAULT CURRENT_TIMESTAMP
-            );
+/// Labels of the `(table, column, label)` references that still point at `id`,
+/// used to refuse purging a row that history depends on.
+pub fn referenced_by(
+    conn: &Connection,
+    id: i64,
+    references: &[(&str, &str, &'static str)],
+) -> Result<Vec<&'static str>> {
+    let mut found = Vec::new();
+    for (table, column, label) in references {
+        let exists: bool = conn.query_row(
+            &format!("SELECT EXISTS (SELECT 1 FROM {} WHERE {} = ?1)", table, column),
+            [id],
+            |row| row.get(0),
+        )?;
+        if exists && !found.contains(label) {
+            found.push(*label);
+        }
+    }
+    Ok(found)
+}
 
-            -- Orders
-            CREATE TABLE IF NOT EXISTS orders (
-                id INTEGER PRIMARY KEY AUTOINCREMENT,
-                staff_id INTEGER NOT NULL,
-                table_number INTEGER NOT NULL DEFAULT 1,
-                total REAL NOT NULL,
-                customer_name TEXT,
-                notes TEXT,
-                status TEXT DEFAULT 'open',
-                created_at DATETIME DEFAULT CURRENT_TIMESTAMP,
-                FOREIGN KEY (staff_id) REFERENCES staff(id)
-            );
+// ============ SCHEMA MIGRATIONS ============
 
-            -- Order items
-            CREATE TABLE IF NOT EXISTS order_items (
-                id INTEGER PRIMARY KEY AUTOINCREMENT,
-                order_id INTEGER NOT NULL,
-                product_id INTEGER NOT NULL,
-                quantity INTEGER NOT NULL,
-                price_at_sale REAL NOT NULL,
-                FOREIGN KEY (order_id) REFERENCES orders(id),
-                FOREIGN KEY (product_id) REFERENCES products(id)
-            );
+/// A single, numbered schema change. Migrations are applied in order, each
+/// inside its own transaction, and recorded in `schema_migrations`.
+pub struct Migration {
+    pub version: i64,
+    pub name: &'static str,
+    pub up: fn(&Connection) -> Result<()>,
+}
 
-            -- Day closings
-            CREATE TABLE IF NOT EXISTS day_closings (
-                id INTEGER PRIMARY KEY AUTOINCREMENT,
-                date DATE NOT NULL UNIQUE,
-                total_revenue REAL NOT NULL,
-                total_orders INTEGER NOT NULL,
-                closed_at DATETIME DEFAULT CURRENT_TIMESTAMP
-            );
+/// Every migration this binary knows about, in order. Append only - never
+/// edit or renumber a migration that has shipped.
+pub const MIGRATIONS: &[Migration] = &[
+    Migration {
+        version: 1,
+        name: "baseline_schema",
+        up: migrate_baseline_schema,
+    },
+    Migration {
+        version: 2,
+        name: "money_minor_units",
+        up: migrate_money_minor_units,
+    },
+    Migration {
+        version: 3,
+        name: "stock_movements",
+        up: migrate_stock_movements,
+    },
+    Migration {
+        version: 4,
+        name: "purchasing",
+        up: migrate_purchasing,
+    },
+    Migration {
+        version: 5,
+        name: "stock_takes",
+        up: migrate_stock_takes,
+    },
+    Migration {
+        version: 6,
+        name: "recipes",
+        up: migrate_recipes,
+    },
+    Migration {
+        version: 7,
+        name: "units_of_measure",
+        up: migrate_units_of_measure,
+    },
+    Migration {
+        version: 8,
+        name: "product_variants",
+        up: migrate_product_variants,
+    },
+    Migration {
+        version: 9,
+        name: "modifiers",
+        up: migrate_modifiers,
+    },
+    Migration {
+        version: 10,
+        name: "product_codes",
+        up: migrate_product_codes,
+    },
+    Migration {
+        version: 11,
+        name: "cost_of_goods",
+        up: migrate_cost_of_goods,
+    },
+    Migration {
+        version: 12,
+        name: "pricing_rules",
+        up: migrate_pricing_rules,
+    },
+    Migration {
+        version: 13,
+        name: "product_price_history",
+        up: migrate_product_price_history,
+    },
+    Migration {
+        version: 14,
+        name: "archiving",
+        up: migrate_archiving,
+    },
+    Migration {
+        version: 15,
+        name: "product_search",
+        up: migrate_product_search,
+    },
+    Migration {
+        version: 16,
+        name: "product_availability",
+        up: migrate_product_availability,
+    },
+    Migration {
+        version: 17,
+        name: "stock_lots",
+        up: migrate_stock_lots,
+    },
+    Migration {
+        version: 18,
+        name: "stock_locations",
+        up: migrate_stock_locations,
+    },
+    Migration {
+        version: 19,
+        name: "category_tree",
+        up: migrate_category_tree,
+    },
+    Migration {
+        version: 20,
+        name: "tax_rates",
+        up: migrate_tax_rates,
+    },
+    Migration {
+        version: 21,
+        name: "stock_take_locations",
+        up: migrate_stock_take_locations,
+    },
+    Migration {
+        version: 22,
+        name: "stock_take_base_unit_prices",
+        up: migrate_stock_take_base_unit_prices,
+    },
+    Migration {
+        version: 23,
+        name: "order_item_deals",
+        up: migrate_order_item_deals,
+    },
+    Migration {
+        version: 24,
+        name: "stock_lot_locations",
+        up: migrate_stock_lot_locations,
+    },
+    Migration {
+        version: 25,
+        name: "code_pack_quantities",
+        up: migrate_code_pack_quantities,
+    },
+];
+
+#[derive(Debug)]
+pub enum MigrationError {
+    Sqlite(rusqlite::Error),
+    /// The database was written by a newer build; refuse to touch it.
+    DatabaseTooNew { found: i64, supported: i64 },
+}
+
+impl std::fmt::Display for MigrationError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            MigrationError::Sqlite(e) => write!(f, "Database migration failed: {}", e),
+            MigrationError::DatabaseTooNew { found, supported } => write!(
+                f,
+                "Database schema version {} is newer than this app supports ({}). Please update the app.",
+                found, supported
+            ),
+        }
+    }
+}
+
+impl std::error::Error for MigrationError {}
+
+impl From<rusqlite::Error> for MigrationError {
+    fn from(e: rusqlite::Error) -> Self {
+        MigrationError::Sqlite(e)
+    }
+}
+
+/// Bring the database up to the newest version in `MIGRATIONS`.
+pub fn run_migrations(conn: &mut Connection) -> std::result::Result<(), MigrationError> {
+    apply_migrations(conn, MIGRATIONS)
+}
+
+/// Apply any migrations from `migrations` that have not run yet.
+/// The current version is tracked in `PRAGMA user_version`; each applied
+/// migration is also logged in `schema_migrations` with its timestamp.
+/// Foreign keys are off while migrating so a table can be rebuilt under the
+/// rows that refer to it; rebuilds keep their ids.
+pub fn apply_migrations(
+    conn: &mut Connection,
+    migrations: &[Migration],
+) -> std::result::Result<(), MigrationError> {
+    conn.execute_batch(
+        "CREATE TABLE IF NOT EXISTS schema_migrations (
+            version INTEGER PRIMARY KEY,
+            name TEXT NOT NULL,
+            applied_at DATETIME DEFAULT CURRENT_TIMESTAMP
+        );",
+    )?;
+
+    let current = schema_version(conn)?;
+    let supported = migrations.last().map(|m| m.version).unwrap_or(0);
+
+    if current > supported {
+        return Err(MigrationError::DatabaseTooNew {
+            found: current,
+            supported,
+        });
+    }
+
+    // Only takes effect outside a transaction
+    let foreign_keys: bool = conn.query_row("PRAGMA foreign_keys", [], |row| row.get(0))?;
+    conn.execute_batch("PRAGMA foreign_keys = OFF")?;
+    let applied = apply_pending(conn, migrations, current);
+    if foreign_keys {
+        conn.execute_batch("PRAGMA foreign_keys = ON")?;
+    }
+    applied
+}
+
+fn apply_pending(
+    conn: &mut Connection,
+    migrations: &[Migration],
+    current: i64,
+) -> std::result::Result<(), MigrationError> {
+    for migration in migrations.iter().filter(|m| m.version > current) {
+        println!(
+            "[migrate] Applying migration {} ({})",
+            migration.version, migration.name
+        );
+
+        let tx = conn.transaction()?;
+        (migration.up)(&tx)?;
+        tx.execute(
+            "INSERT INTO schema_migrations (version, name) VALUES (?1, ?2)",
+            rusqlite::params![migration.version, migration.name],
+        )?;
+        // PRAGMA does not accept bound parameters; version is a trusted constant
+        tx.execute_batch(&format!("PRAGMA user_version = {}", migration.version))?;
+        tx.commit()?;
+    }
+
+    Ok(())
+}
+
+/// Read the schema version stored in the database header.
+pub fn schema_version(conn: &Connection) -> Result<i64> {
+    conn.query_row("PRAGMA user_version", [], |row| row.get(0))
+}
+
+/// Migration 1: the schema as it stood before versioned migrations.
+/// Fresh databases get the tables created directly; databases from older
+/// builds are upgraded in place by adding whatever columns they lack.
+fn migrate_baseline_schema(conn: &Connection) -> Result<()> {
+    conn.execute_batch(
+        "
+        -- Product categories
+        CREATE TABLE IF NOT EXISTS categories (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            name TEXT NOT NULL UNIQUE
+        );
+
+        -- Products with inventory
+        CREATE TABLE IF NOT EXISTS products (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            name TEXT NOT NULL,
+            price REAL NOT NULL,
+            quantity INTEGER NOT NULL DEFAULT 0,
+            category_id INTEGER,
+            low_stock_threshold INTEGER DEFAULT 5,
+            created_at DATETIME DEFAULT CURRENT_TIMESTAMP,
+            FOREIGN KEY (category_id) REFERENCES categories(id)
+        );
+
+        -- Staff members
+        CREATE TABLE IF NOT EXISTS staff (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            name TEXT NOT NULL UNIQUE,
+            pin TEXT,
+            created_at DATETIME DEFAULT CURRENT_TIMESTAMP
+        );
+
+        -- Orders
+        CREATE TABLE IF NOT EXISTS orders (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            staff_id INTEGER NOT NULL,
+            table_number INTEGER NOT NULL DEFAULT 1,
+            total REAL NOT NULL,
+            customer_name TEXT,
+            notes TEXT,
+            status TEXT DEFAULT 'open',
+            created_at DATETIME DEFAULT CURRENT_TIMESTAMP,
+            FOREIGN KEY (staff_id) REFERENCES staff(id)
+        );
+
+        -- Order items
+        CREATE TABLE IF NOT EXISTS order_items (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            order_id INTEGER NOT NULL,
+            product_id INTEGER NOT NULL,
+            quantity INTEGER NOT NULL,
+            price_at_sale REAL NOT NULL,
+            FOREIGN KEY (order_id) REFERENCES orders(id),
+            FOREIGN KEY (product_id) REFERENCES products(id)
+        );
+
+        -- Day closings
+        CREATE TABLE IF NOT EXISTS day_closings (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            date DATE NOT NULL UNIQUE,
+            total_revenue REAL NOT NULL,
+            total_orders INTEGER NOT NULL,
+            closed_at DATETIME DEFAULT CURRENT_TIMESTAMP
+        );
+
+        -- Day sessions (tracks when day is open for business)
+        CREATE TABLE IF NOT EXISTS day_sessions (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            date DATE,
+            started_by INTEGER NOT NULL,
+            started_at DATETIME DEFAULT CURRENT_TIMESTAMP,
+            is_active INTEGER DEFAULT 1,
+            closed_at DATETIME,
+            total_revenue REAL,
+            total_orders INTEGER,
+            FOREIGN KEY (started_by) REFERENCES staff(id)
+        );
+        ",
+    )?;
+
+    // Databases created before versioning may be missing later columns
+    let columns = table_columns(conn, "orders")?;
+
+    if !columns.contains(&"customer_name".to_string()) {
+        conn.execute("ALTER TABLE orders ADD COLUMN customer_name TEXT", [])?;
+    }
+    if !columns.contains(&"notes".to_string()) {
+        conn.execute("ALTER TABLE orders ADD COLUMN notes TEXT", [])?;
+    }
+    if !columns.contains(&"status".to_string()) {
+        conn.execute("ALTER TABLE orders ADD COLUMN status TEXT DEFAULT 'paid'", [])?;
+    }
+    if !columns.contains(&"table_number".to_string()) {
+        conn.execute("ALTER TABLE orders ADD COLUMN table_number INTEGER NOT NULL DEFAULT 1", [])?;
+    }
+    if !columns.contains(&"session_id".to_string()) {
+        conn.execute("ALTER TABLE orders ADD COLUMN session_id INTEGER", [])?;
+    }
+
+    let session_columns = table_columns(conn, "day_sessions")?;
+
+    if !session_columns.contains(&"closed_at".to_string()) {
+        conn.execute("ALTER TABLE day_sessions ADD COLUMN closed_at DATETIME", [])?;
+    }
+    if !session_columns.contains(&"total_revenue".to_string()) {
+        conn.execute("ALTER TABLE day_sessions ADD COLUMN total_revenue REAL", [])?;
+    }
+    if !session_columns.contains(&"total_orders".to_string()) {
+        conn.execute("ALTER TABLE day_sessions ADD COLUMN total_orders INTEGER", [])?;
+    }
+
+    // Backfill session_id for existing orders that don't have one
+    // First try to match by timestamp
+    conn.execute(
+        "UPDATE orders SET session_id = (
+            SELECT ds.id FROM day_sessions ds
+            WHERE orders.created_at >= ds.started_at
+            ORDER BY ds.started_at DESC LIMIT 1
+        ) WHERE session_id IS NULL",
+        [],
+    )?;
+
+    // Link remaining orphan orders to active session (if any)
+    conn.execute(
+        "UPDATE orders SET session_id = (
+            SELECT id FROM day_sessions WHERE is_active = 1 LIMIT 1
+        ) WHERE session_id IS NULL",
+        [],
+    )?;
+
+    conn.execute(
+        "CREATE INDEX IF NOT EXISTS idx_orders_session_id ON orders(session_id)",
+        [],
+    )?;
+
+    // Early builds had UNIQUE(date) on day_sessions; rebuild the table without it
+    let table_sql: String = conn
+        .query_row(
+            "SELECT sql FROM sqlite_master WHERE type='table' AND name='day_sessions'",
+            [],
+            |row| row.get(0),
+        )
+        .unwrap_or_default();
 
-            -- Day sessions (tracks when day is open for business)
-            CREATE TABLE IF NOT EXISTS day_sessions (
+    if table_sql.contains("UNIQUE") {
+        conn.execute_batch(
+            "
+            CREATE TABLE day_sessions_new (
                 id INTEGER PRIMARY KEY AUTOINCREMENT,
-                date DATE NOT NULL UNIQUE,
+                date DATE,
                 started_by INTEGER NOT NULL,
                 started_at DATETIME DEFAULT CURRENT_TIMESTAMP,
                 is_active INTEGER DEFAULT 1,
+                closed_at DATETIME,
+                total_revenue REAL,
+                total_orders INTEGER,
                 FOREIGN KEY (started_by) REFERENCES staff(id)
             );
-            "
-        )?;
 
-        // Run migrations for existing databases (pass connection to avoid deadlock)
-        Self::migrate_conn(&conn)?;
+            INSERT INTO day_sessions_new (id, date, started_by, started_at, is_active, closed_at, total_revenue, total_orders)
+            SELECT id, date, started_by, started_at, is_active, closed_at, total_revenue, total_orders FROM day_sessions;
+
+            DROP TABLE day_sessions;
 
-        Ok(())
+            ALTER TABLE day_sessions_new RENAME TO day_sessions;
+            ",
+        )?;
     }
 
-    fn migrate_conn(conn: &Connection) -> Result<()> {
-        // Check if customer_name column exists, add if not
-        let columns: Vec<String> = conn
-            .prepare("PRAGMA table_info(orders)")?
-            .query_map([], |row| row.get::<_, String>(1))?
-            .filter_map(|r| r.ok())
-            .collect();
+    Ok(())
+}
 
-        if !columns.contains(&"customer_name".to_string()) {
-            conn.execute("ALTER TABLE orders ADD COLUMN customer_name TEXT", [])?;
-        }
-        if !columns.contains(&"notes".to_string()) {
-            conn.execute("ALTER TABLE orders ADD COLUMN notes TEXT", [])?;
-        }
-        if !columns.contains(&"status".to_string()) {
-            conn.execute("ALTER TABLE orders ADD COLUMN status TEXT DEFAULT 'paid'", [])?;
-        }
-        if !columns.contains(&"table_number".to_string()) {
-            conn.execute("ALTER TABLE orders ADD COLUMN table_number INTEGER NOT NULL DEFAULT 1", [])?;
-        }
+/// Migration 2: store every amount as INTEGER minor units instead of REAL.
+/// SQLite cannot change a column's type in place, so each affected table is
+/// rebuilt and its amounts converted with ROUND(x * 100).
+fn migrate_money_minor_units(conn: &Connection) -> Result<()> {
+    conn.execute_batch(
+        "
+        CREATE TABLE products_new (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            name TEXT NOT NULL,
+            price INTEGER NOT NULL,
+            quantity INTEGER NOT NULL DEFAULT 0,
+            category_id INTEGER,
+            low_stock_threshold INTEGER DEFAULT 5,
+            created_at DATETIME DEFAULT CURRENT_TIMESTAMP,
+            FOREIGN KEY (category_id) REFERENCES categories(id)
+        );
+        INSERT INTO products_new (id, name, price, quantity, category_id, low_stock_threshold, created_at)
+        SELECT id, name, CAST(ROUND(price * 100) AS INTEGER), quantity, category_id, low_stock_threshold, created_at FROM products;
+        DROP TABLE products;
+        ALTER TABLE products_new RENAME TO products;
 
-        // Session-based architecture migration
-        // Add session_id to orders table
-        if !columns.contains(&"session_id".to_string()) {
-            conn.execute("ALTER TABLE orders ADD COLUMN session_id INTEGER", [])?;
-        }
+        CREATE TABLE orders_new (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            staff_id INTEGER NOT NULL,
+            table_number INTEGER NOT NULL DEFAULT 1,
+            total INTEGER NOT NULL,
+            customer_name TEXT,
+            notes TEXT,
+            status TEXT DEFAULT 'open',
+            session_id INTEGER,
+            created_at DATETIME DEFAULT CURRENT_TIMESTAMP,
+            FOREIGN KEY (staff_id) REFERENCES staff(id)
+        );
+        INSERT INTO orders_new (id, staff_id, table_number, total, customer_name, notes, status, session_id, created_at)
+        SELECT id, staff_id, table_number, CAST(ROUND(total * 100) AS INTEGER), customer_name, notes, status, session_id, created_at FROM orders;
+        DROP TABLE orders;
+        ALTER TABLE orders_new RENAME TO orders;
+        CREATE INDEX IF NOT EXISTS idx_orders_session_id ON orders(session_id);
 
-        // Add closing fields to day_sessions
-        let session_columns: Vec<String> = conn
-            .prepare("PRAGMA table_info(day_sessions)")?
-            .query_map([], |row| row.get::<_, String>(1))?
-            .filter_map(|r| r.ok())
-            .collect();
+        CREATE TABLE order_items_new (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            order_id INTEGER NOT NULL,
+            product_id INTEGER NOT NULL,
+            quantity INTEGER NOT NULL,
+            price_at_sale INTEGER NOT NULL,
+            FOREIGN KEY (order_id) REFERENCES orders(id),
+            FOREIGN KEY (product_id) REFERENCES products(id)
+        );
+        INSERT INTO order_items_new (id, order_id, product_id, quantity, price_at_sale)
+        SELECT id, order_id, product_id, quantity, CAST(ROUND(price_at_sale * 100) AS INTEGER) FROM order_items;
+        DROP TABLE order_items;
+        ALTER TABLE order_items_new RENAME TO order_items;
 
-        if !session_columns.contains(&"closed_at".to_string()) {
-            conn.execute("ALTER TABLE day_sessions ADD COLUMN closed_at DATETIME", [])?;
-        }
-        if !session_columns.contains(&"total_revenue".to_string()) {
-            conn.execute("ALTER TABLE day_sessions ADD COLUMN total_revenue REAL", [])?;
-        }
-        if !session_columns.contains(&"total_orders".to_string()) {
-            conn.execute("ALTER TABLE day_sessions ADD COLUMN total_orders INTEGER", [])?;
-        }
+        CREATE TABLE day_sessions_new (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            date DATE,
+            started_by INTEGER NOT NULL,
+            started_at DATETIME DEFAULT CURRENT_TIMESTAMP,
+            is_active INTEGER DEFAULT 1,
+            closed_at DATETIME,
+            total_revenue INTEGER,
+            total_orders INTEGER,
+            FOREIGN KEY (started_by) REFERENCES staff(id)
+        );
+        INSERT INTO day_sessions_new (id, date, started_by, started_at, is_active, closed_at, total_revenue, total_orders)
+        SELECT id, date, started_by, started_at, is_active, closed_at, CAST(ROUND(total_revenue * 100) AS INTEGER), total_orders FROM day_sessions;
+        DROP TABLE day_sessions;
+        ALTER TABLE day_sessions_new RENAME TO day_sessions;
 
-        // Backfill session_id for existing orders that don't have one
-        // First try to match by timestamp
-        conn.execute(
-            "UPDATE orders SET session_id = (
-                SELECT ds.id FROM day_sessions ds
-                WHERE orders.created_at >= ds.started_at
-                ORDER BY ds.started_at DESC LIMIT 1
-            ) WHERE session_id IS NULL",
-            [],
-        )?;
+        CREATE TABLE day_closings_new (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            date DATE NOT NULL UNIQUE,
+            total_revenue INTEGER NOT NULL,
+            total_orders INTEGER NOT NULL,
+            closed_at DATETIME DEFAULT CURRENT_TIMESTAMP
+        );
+        INSERT INTO day_closings_new (id, date, total_revenue, total_orders, closed_at)
+        SELECT id, date, CAST(ROUND(total_revenue * 100) AS INTEGER), total_orders, closed_at FROM day_closings;
+        DROP TABLE day_closings;
+        ALTER TABLE day_closings_new RENAME TO day_closings;
+        ",
+    )
+}
 
-        // Link remaining orphan orders to active session (if any)
-        conn.execute(
-            "UPDATE orders SET session_id = (
-                SELECT id FROM day_sessions WHERE is_active = 1 LIMIT 1
-            ) WHERE session_id IS NULL",
-            [],
-        )?;
+/// Migration 3: a ledger of every change to `products.quantity`. Existing
+/// stock is carried over as an opening balance so the ledger for each product
+/// sums to its current quantity from day one. `order_id` has no foreign key
+/// because an order emptied by item decreases is deleted, while its sale and
+/// return movements stay in the ledger.
+fn migrate_stock_movements(conn: &Connection) -> Result<()> {
+    conn.execute_batch(
+        "
+        CREATE TABLE stock_movements (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            product_id INTEGER NOT NULL,
+            quantity_change INTEGER NOT NULL,
+            reason TEXT NOT NULL,
+            staff_id INTEGER,
+            order_id INTEGER,
+            reference TEXT,
+            note TEXT,
+            created_at DATETIME DEFAULT CURRENT_TIMESTAMP,
+            FOREIGN KEY (product_id) REFERENCES products(id),
+            FOREIGN KEY (staff_id) REFERENCES staff(id)
+        );
+        CREATE INDEX idx_stock_movements_product ON stock_movements(product_id, created_at);
 
-        // Create index for session_id lookups
-        conn.execute(
-            "CREATE INDEX IF NOT EXISTS idx_orders_session_id ON orders(session_id)",
-            [],
-        )?;
+        INSERT INTO stock_movements (product_id, quantity_change, reason, reference)
+        SELECT id, quantity, 'opening_balance', 'migration' FROM products WHERE quantity <> 0;
+        ",
+    )
+}
 
-        // Remove UNIQUE constraint on date in day_sessions by recreating the table
-        // Check if we need to migrate (look for UNIQUE constraint)
-        let table_sql: String = conn
-            .query_row(
-                "SELECT sql FROM sqlite_master WHERE type='table' AND name='day_sessions'",
-                [],
-                |row| row.get(0),
-            )
-            .unwrap_or_default();
-
-        if table_sql.contains("UNIQUE") {
-            // Need to recreate table without UNIQUE constraint
-            conn.execute_batch(
-                "
-                CREATE TABLE IF NOT EXISTS day_sessions_new (
-                    id INTEGER PRIMARY KEY AUTOINCREMENT,
-                    date DATE,
-                    started_by INTEGER NOT NULL,
-                    started_at DATETIME DEFAULT CURRENT_TIMESTAMP,
-                    is_active INTEGER DEFAULT 1,
-                    closed_at DATETIME,
-                    total_revenue REAL,
-                    total_orders INTEGER,
-                    FOREIGN KEY (started_by) REFERENCES staff(id)
-                );
-
-                INSERT INTO day_sessions_new (id, date, started_by, started_at, is_active, closed_at, total_revenue, total_orders)
-                SELECT id, date, started_by, started_at, is_active, closed_at, total_revenue, total_orders FROM day_sessions;
-
-                DROP TABLE day_sessions;
-
-                ALTER TABLE day_sessions_new RENAME TO day_sessions;
-                ",
-            )?;
-        }
+/// Migration 4: suppliers and purchase orders. Each delivery against a PO line
+/// is kept in `purchase_receipts` with the unit cost actually invoiced.
+fn migrate_purchasing(conn: &Connection) -> Result<()> {
+    conn.execute_batch(
+        "
+        CREATE TABLE suppliers (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            name TEXT NOT NULL UNIQUE,
+            contact_name TEXT,
+            phone TEXT,
+            email TEXT,
+            notes TEXT,
+            created_at DATETIME DEFAULT CURRENT_TIMESTAMP
+        );
 
-        Ok(())
-    }
+        CREATE TABLE purchase_orders (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            supplier_id INTEGER NOT NULL,
+            status TEXT NOT NULL DEFAULT 'draft',
+            reference TEXT,
+            notes TEXT,
+            created_by INTEGER,
+            created_at DATETIME DEFAULT CURRENT_TIMESTAMP,
+            sent_at DATETIME,
+            received_at DATETIME,
+            FOREIGN KEY (supplier_id) REFERENCES suppliers(id),
+            FOREIGN KEY (created_by) REFERENCES staff(id)
+        );
+        CREATE INDEX idx_purchase_orders_supplier ON purchase_orders(supplier_id, status);
+
+        CREATE TABLE purchase_order_lines (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            purchase_order_id INTEGER NOT NULL,
+            product_id INTEGER NOT NULL,
+            quantity_ordered INTEGER NOT NULL,
+            quantity_received INTEGER NOT NULL DEFAULT 0,
+            unit_cost INTEGER NOT NULL,
+            FOREIGN KEY (purchase_order_id) REFERENCES purchase_orders(id),
+            FOREIGN KEY (product_id) REFERENCES products(id)
+        );
+        CREATE INDEX idx_purchase_order_lines_po ON purchase_order_lines(purchase_order_id);
+
+        CREATE TABLE purchase_receipts (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            line_id INTEGER NOT NULL,
+            quantity INTEGER NOT NULL,
+            unit_cost INTEGER NOT NULL,
+            received_by INTEGER,
+            reference TEXT,
+            received_at DATETIME DEFAULT CURRENT_TIMESTAMP,
+            FOREIGN KEY (line_id) REFERENCES purchase_order_lines(id),
+            FOREIGN KEY (received_by) REFERENCES staff(id)
+        );
+        ",
+    )
+}
+
+/// Migration 5: physical stock counts. Counts are entered per staff member
+/// while a stock-take is open; on commit the system quantity, count and
+/// valuation for each product are frozen into `stock_take_lines`.
+fn migrate_stock_takes(conn: &Connection) -> Result<()> {
+    conn.execute_batch(
+        "
+        CREATE TABLE stock_takes (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            status TEXT NOT NULL DEFAULT 'open',
+            notes TEXT,
+            started_by INTEGER NOT NULL,
+            started_at DATETIME DEFAULT CURRENT_TIMESTAMP,
+            committed_by INTEGER,
+            committed_at DATETIME,
+            FOREIGN KEY (started_by) REFERENCES staff(id),
+            FOREIGN KEY (committed_by) REFERENCES staff(id)
+        );
+
+        CREATE TABLE stock_take_counts (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            stock_take_id INTEGER NOT NULL,
+            product_id INTEGER NOT NULL,
+            staff_id INTEGER NOT NULL,
+            counted_quantity INTEGER NOT NULL,
+            counted_at DATETIME DEFAULT CURRENT_TIMESTAMP,
+            UNIQUE (stock_take_id, product_id, staff_id),
+            FOREIGN KEY (stock_take_id) REFERENCES stock_takes(id),
+            FOREIGN KEY (product_id) REFERENCES products(id),
+            FOREIGN KEY (staff_id) REFERENCES staff(id)
+        );
+
+        CREATE TABLE stock_take_lines (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            stock_take_id INTEGER NOT NULL,
+            product_id INTEGER NOT NULL,
+            system_quantity INTEGER NOT NULL,
+            counted_quantity INTEGER NOT NULL,
+            unit_cost INTEGER,
+            unit_price INTEGER NOT NULL,
+            FOREIGN KEY (stock_take_id) REFERENCES stock_takes(id),
+            FOREIGN KEY (product_id) REFERENCES products(id)
+        );
+        CREATE INDEX idx_stock_take_lines_product ON stock_take_lines(product_id);
+        ",
+    )
+}
+
+/// Migration 6: recipes. A product with components has no stock of its own;
+/// selling it deducts `quantity` of each component per serving.
+fn migrate_recipes(conn: &Connection) -> Result<()> {
+    conn.execute_batch(
+        "
+        CREATE TABLE recipe_components (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            product_id INTEGER NOT NULL,
+            component_id INTEGER NOT NULL,
+            quantity INTEGER NOT NULL CHECK (quantity > 0),
+            UNIQUE (product_id, component_id),
+            FOREIGN KEY (product_id) REFERENCES products(id),
+            FOREIGN KEY (component_id) REFERENCES products(id)
+        );
+        CREATE INDEX idx_recipe_components_component ON recipe_components(component_id);
+        ",
+    )
+}
+
+/// Migration 7: units of measure. Existing products are counted in pieces
+/// and sell one at a time, so their quantities carry over unchanged.
+fn migrate_units_of_measure(conn: &Connection) -> Result<()> {
+    conn.execute_batch(
+        "
+        ALTER TABLE products ADD COLUMN unit TEXT NOT NULL DEFAULT 'piece';
+        ALTER TABLE products ADD COLUMN serving_size INTEGER NOT NULL DEFAULT 1 CHECK (serving_size > 0);
+        ",
+    )
+}
+
+/// Migration 8: product variants sharing their parent's stock. Order lines
+/// keep the parent in `product_id` so sales roll up without a join.
+fn migrate_product_variants(conn: &Connection) -> Result<()> {
+    conn.execute_batch(
+        "
+        CREATE TABLE product_variants (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            product_id INTEGER NOT NULL,
+            name TEXT NOT NULL,
+            price INTEGER NOT NULL,
+            sku TEXT UNIQUE,
+            deduction INTEGER NOT NULL CHECK (deduction > 0),
+            created_at DATETIME DEFAULT CURRENT_TIMESTAMP,
+            UNIQUE (product_id, name),
+            FOREIGN KEY (product_id) REFERENCES products(id)
+        );
+
+        ALTER TABLE order_items ADD COLUMN variant_id INTEGER REFERENCES product_variants(id);
+        ",
+    )
+}
+
+/// Migration 9: modifier groups ("extra shot", "no ice") attached to products
+/// or whole categories. Chosen modifiers are copied onto the order line with
+/// the price they sold at.
+fn migrate_modifiers(conn: &Connection) -> Result<()> {
+    conn.execute_batch(
+        "
+        CREATE TABLE modifier_groups (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            name TEXT NOT NULL,
+            min_select INTEGER NOT NULL DEFAULT 0,
+            max_select INTEGER,
+            created_at DATETIME DEFAULT CURRENT_TIMESTAMP
+        );
+
+        CREATE TABLE modifiers (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            group_id INTEGER NOT NULL,
+            name TEXT NOT NULL,
+            price_delta INTEGER NOT NULL DEFAULT 0,
+            FOREIGN KEY (group_id) REFERENCES modifier_groups(id)
+        );
+
+        CREATE TABLE modifier_group_links (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            group_id INTEGER NOT NULL,
+            product_id INTEGER,
+            category_id INTEGER,
+            CHECK ((product_id IS NULL) <> (category_id IS NULL)),
+            FOREIGN KEY (group_id) REFERENCES modifier_groups(id),
+            FOREIGN KEY (product_id) REFERENCES products(id),
+            FOREIGN KEY (category_id) REFERENCES categories(id)
+        );
+
+        CREATE TABLE order_item_modifiers (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            order_item_id INTEGER NOT NULL,
+            modifier_id INTEGER,
+            name TEXT NOT NULL,
+            price_delta INTEGER NOT NULL,
+            FOREIGN KEY (order_item_id) REFERENCES order_items(id)
+        );
+        CREATE INDEX idx_order_item_modifiers_item ON order_item_modifiers(order_item_id);
+        ",
+    )
+}
+
+/// Migration 10: scannable codes (SKUs, EANs). A product or variant can have
+/// several, and a code belongs to exactly one. Variant SKUs move here and
+/// `product_variants` is rebuilt without its `sku` column.
+fn migrate_product_codes(conn: &Connection) -> Result<()> {
+    conn.execute_batch(
+        "
+        CREATE TABLE product_codes (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            code TEXT NOT NULL UNIQUE,
+            product_id INTEGER NOT NULL,
+            variant_id INTEGER,
+            created_at DATETIME DEFAULT CURRENT_TIMESTAMP,
+            FOREIGN KEY (product_id) REFERENCES products(id),
+            FOREIGN KEY (variant_id) REFERENCES product_variants(id)
+        );
+        CREATE INDEX idx_product_codes_product ON product_codes(product_id);
+
+        INSERT INTO product_codes (code, product_id, variant_id)
+        SELECT sku, product_id, id FROM product_variants WHERE sku IS NOT NULL;
+
+        CREATE TABLE product_variants_new (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            product_id INTEGER NOT NULL,
+            name TEXT NOT NULL,
+            price INTEGER NOT NULL,
+            deduction INTEGER NOT NULL CHECK (deduction > 0),
+            created_at DATETIME DEFAULT CURRENT_TIMESTAMP,
+            UNIQUE (product_id, name),
+            FOREIGN KEY (product_id) REFERENCES products(id)
+        );
+        INSERT INTO product_variants_new (id, product_id, name, price, deduction, created_at)
+        SELECT id, product_id, name, price, deduction, created_at FROM product_variants;
+        DROP TABLE product_variants;
+        ALTER TABLE product_variants_new RENAME TO product_variants;
+        ",
+    )
+}
+
+/// Migration 11: average cost per base unit on products, seeded from the last
+/// delivery, and the cost of each order line at the time of sale. Both stay
+/// NULL where the cost is unknown.
+fn migrate_cost_of_goods(conn: &Connection) -> Result<()> {
+    conn.execute_batch(
+        "
+        ALTER TABLE products ADD COLUMN cost_price INTEGER;
+        ALTER TABLE order_items ADD COLUMN cost_at_sale INTEGER;
+
+        UPDATE products SET cost_price = (
+            SELECT r.unit_cost FROM purchase_receipts r
+            JOIN purchase_order_lines l ON r.line_id = l.id
+            WHERE l.product_id = products.id
+            ORDER BY r.received_at DESC, r.id DESC
+            LIMIT 1
+        );
+        ",
+    )
+}
+
+/// Migration 12: scheduled pricing rules such as happy hour. `weekdays` is a
+/// bitmask with Monday as bit 0, 0 meaning every day. Order lines record the
+/// rule that priced them by id and by name.
+fn migrate_pricing_rules(conn: &Connection) -> Result<()> {
+    conn.execute_batch(
+        "
+        CREATE TABLE pricing_rules (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            name TEXT NOT NULL,
+            kind TEXT NOT NULL,
+            percent INTEGER CHECK (percent IS NULL OR percent BETWEEN 1 AND 100),
+            amount INTEGER CHECK (amount IS NULL OR amount > 0),
+            buy_quantity INTEGER CHECK (buy_quantity IS NULL OR buy_quantity > 0),
+            free_quantity INTEGER CHECK (free_quantity IS NULL OR free_quantity > 0),
+            product_id INTEGER,
+            category_id INTEGER,
+            weekdays INTEGER NOT NULL DEFAULT 0,
+            start_time TEXT,
+            end_time TEXT,
+            active INTEGER NOT NULL DEFAULT 1,
+            created_at DATETIME DEFAULT CURRENT_TIMESTAMP,
+            FOREIGN KEY (product_id) REFERENCES products(id),
+            FOREIGN KEY (category_id) REFERENCES categories(id)
+        );
+
+        ALTER TABLE order_items ADD COLUMN pricing_rule_id INTEGER REFERENCES pricing_rules(id);
+        ALTER TABLE order_items ADD COLUMN pricing_rule_name TEXT;
+        ",
+    )
+}
+
+/// Migration 13: every price a product has had, or is scheduled to have.
+/// `applied_at` stays NULL until a future-dated change takes effect, and
+/// `old_price` is filled in then. Existing prices are recorded as they stand.
+fn migrate_product_price_history(conn: &Connection) -> Result<()> {
+    conn.execute_batch(
+        "
+        CREATE TABLE product_price_history (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            product_id INTEGER NOT NULL,
+            old_price INTEGER,
+            new_price INTEGER NOT NULL CHECK (new_price >= 0),
+            staff_id INTEGER,
+            effective_at DATETIME NOT NULL,
+            applied_at DATETIME,
+            created_at DATETIME DEFAULT CURRENT_TIMESTAMP,
+            FOREIGN KEY (product_id) REFERENCES products(id),
+            FOREIGN KEY (staff_id) REFERENCES staff(id)
+        );
+        CREATE INDEX idx_price_history_product ON product_price_history(product_id, effective_at);
+        CREATE INDEX idx_price_history_pending ON product_price_history(effective_at) WHERE applied_at IS NULL;
+
+        INSERT INTO product_price_history (product_id, new_price, effective_at, applied_at)
+        SELECT id, price, COALESCE(created_at, CURRENT_TIMESTAMP), COALESCE(created_at, CURRENT_TIMESTAMP)
+        FROM products;
+        ",
+    )
+}
+
+/// Migration 14: products, categories and staff are archived rather than
+/// deleted, so orders and stock history keep pointing at real rows.
+fn migrate_archiving(conn: &Connection) -> Result<()> {
+    conn.execute_batch(
+        "
+        ALTER TABLE products ADD COLUMN archived_at DATETIME;
+        ALTER TABLE categories ADD COLUMN archived_at DATETIME;
+        ALTER TABLE staff ADD COLUMN archived_at DATETIME;
+        ",
+    )
+}
+
+/// Migration 15: full-text search over products. `product_search` holds one
+/// row per product (rowid = product id) with its name, category, codes and
+/// aliases, folded so "caj" finds "Çaj". Triggers keep it in step with
+/// products, category names and codes.
+fn migrate_product_search(conn: &Connection) -> Result<()> {
+    conn.execute_batch(
+        "
+        ALTER TABLE products ADD COLUMN aliases TEXT;
+
+        CREATE VIRTUAL TABLE product_search USING fts5(
+            name, category, codes, aliases,
+            tokenize = 'unicode61 remove_diacritics 2'
+        );
+
+        CREATE VIEW product_search_source AS
+        SELECT p.id, p.name, COALESCE(c.name, ''),
+               COALESCE((SELECT GROUP_CONCAT(code, ' ') FROM product_codes WHERE product_id = p.id), ''),
+               COALESCE(p.aliases, '')
+        FROM products p
+        LEFT JOIN categories c ON p.category_id = c.id;
+
+        CREATE TRIGGER product_search_insert AFTER INSERT ON products BEGIN
+            INSERT INTO product_search (rowid, name, category, codes, aliases)
+            SELECT * FROM product_search_source WHERE id = NEW.id;
+        END;
+
+        CREATE TRIGGER product_search_update AFTER UPDATE OF name, category_id, aliases ON products BEGIN
+            DELETE FROM product_search WHERE rowid = NEW.id;
+            INSERT INTO product_search (rowid, name, category, codes, aliases)
+            SELECT * FROM product_search_source WHERE id = NEW.id;
+        END;
+
+        CREATE TRIGGER product_search_delete AFTER DELETE ON products BEGIN
+            DELETE FROM product_search WHERE rowid = OLD.id;
+        END;
+
+        CREATE TRIGGER product_search_category AFTER UPDATE OF name ON categories BEGIN
+            DELETE FROM product_search WHERE rowid IN (SELECT id FROM products WHERE category_id = NEW.id);
+            INSERT INTO product_search (rowid, name, category, codes, aliases)
+            SELECT * FROM product_search_source WHERE id IN (SELECT id FROM products WHERE category_id = NEW.id);
+        END;
+
+        CREATE TRIGGER product_search_code_insert AFTER INSERT ON product_codes BEGIN
+            DELETE FROM product_search WHERE rowid = NEW.product_id;
+            INSERT INTO product_search (rowid, name, category, codes, aliases)
+            SELECT * FROM product_search_source WHERE id = NEW.product_id;
+        END;
+
+        CREATE TRIGGER product_search_code_delete AFTER DELETE ON product_codes BEGIN
+            DELETE FROM product_search WHERE rowid = OLD.product_id;
+            INSERT INTO product_search (rowid, name, category, codes, aliases)
+            SELECT * FROM product_search_source WHERE id = OLD.product_id;
+        END;
+
+        INSERT INTO product_search (rowid, name, category, codes, aliases)
+        SELECT * FROM product_search_source;
+        ",
+    )
+}
+
+/// Migration 16: a product can be taken off sale ("86'd") whatever its
+/// stock, with an optional reason and a UTC time it comes back by itself.
+/// `unavailable_products` lists those still off right now.
+fn migrate_product_availability(conn: &Connection) -> Result<()> {
+    conn.execute_batch(
+        "
+        ALTER TABLE products ADD COLUMN unavailable_since DATETIME;
+        ALTER TABLE products ADD COLUMN unavailable_reason TEXT;
+        ALTER TABLE products ADD COLUMN unavailable_until DATETIME;
+
+        CREATE VIEW unavailable_products AS
+        SELECT id AS product_id, name, unavailable_reason, unavailable_until
+        FROM products
+        WHERE unavailable_since IS NOT NULL
+          AND (unavailable_until IS NULL OR unavailable_until > CURRENT_TIMESTAMP);
+        ",
+    )
+}
+
+/// Migration 17: stock can be held in lots with an expiry date, and draught
+/// in kegs that are tapped and retired. A product's quantity stays the total;
+/// whatever its open lots do not account for is untracked stock.
+/// `stock_lot_movements` splits ledger entries across the lots they touched.
+fn migrate_stock_lots(conn: &Connection) -> Result<()> {
+    conn.execute_batch(
+        "
+        CREATE TABLE stock_lots (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            product_id INTEGER NOT NULL,
+            keg_id TEXT,
+            received_at DATETIME DEFAULT CURRENT_TIMESTAMP,
+            expires_on DATE,
+            initial_quantity INTEGER NOT NULL CHECK (initial_quantity > 0),
+            quantity INTEGER NOT NULL CHECK (quantity >= 0),
+            tapped_at DATETIME,
+            retired_at DATETIME,
+            FOREIGN KEY (product_id) REFERENCES products(id)
+        );
+        CREATE INDEX idx_stock_lots_product ON stock_lots(product_id, expires_on);
+
+        CREATE TABLE stock_lot_movements (
+            movement_id INTEGER NOT NULL,
+            lot_id INTEGER NOT NULL,
+            quantity_change INTEGER NOT NULL,
+            PRIMARY KEY (movement_id, lot_id),
+            FOREIGN KEY (movement_id) REFERENCES stock_movements(id),
+            FOREIGN KEY (lot_id) REFERENCES stock_lots(id)
+        );
+        CREATE INDEX idx_stock_lot_movements_lot ON stock_lot_movements(lot_id);
+        ",
+    )
+}
+
+/// Migration 18: stock locations. The default location (the bar) holds
+/// whatever of a product's quantity the others do not, so all existing stock
+/// starts there. Movements and orders without a location are at the default.
+fn migrate_stock_locations(conn: &Connection) -> Result<()> {
+    conn.execute_batch(
+        "
+        CREATE TABLE stock_locations (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            name TEXT NOT NULL UNIQUE,
+            is_default INTEGER NOT NULL DEFAULT 0,
+            created_at DATETIME DEFAULT CURRENT_TIMESTAMP
+        );
+        CREATE UNIQUE INDEX idx_stock_locations_default ON stock_locations(is_default) WHERE is_default = 1;
+        INSERT INTO stock_locations (name, is_default) VALUES ('Bar', 1);
+
+        CREATE TABLE location_stock (
+            location_id INTEGER NOT NULL,
+            product_id INTEGER NOT NULL,
+            quantity INTEGER NOT NULL DEFAULT 0 CHECK (quantity >= 0),
+            low_stock_threshold INTEGER,
+            PRIMARY KEY (location_id, product_id),
+            FOREIGN KEY (location_id) REFERENCES stock_locations(id),
+            FOREIGN KEY (product_id) REFERENCES products(id)
+        );
+        CREATE INDEX idx_location_stock_product ON location_stock(product_id);
+
+        ALTER TABLE stock_movements ADD COLUMN location_id INTEGER REFERENCES stock_locations(id);
+        ALTER TABLE orders ADD COLUMN location_id INTEGER REFERENCES stock_locations(id);
+        ",
+    )
+}
+
+/// Migration 19: categories nest under a parent and are shown in
+/// `sort_order` among their siblings, with an optional color and icon for the
+/// sales grid. Existing categories keep their alphabetical order.
+fn migrate_category_tree(conn: &Connection) -> Result<()> {
+    conn.execute_batch(
+        "
+        ALTER TABLE categories ADD COLUMN parent_id INTEGER REFERENCES categories(id);
+        ALTER TABLE categories ADD COLUMN sort_order INTEGER NOT NULL DEFAULT 0;
+        ALTER TABLE categories ADD COLUMN color TEXT;
+        ALTER TABLE categories ADD COLUMN icon TEXT;
+        CREATE INDEX idx_categories_parent ON categories(parent_id, sort_order);
+
+        UPDATE categories SET sort_order = (SELECT COUNT(*) FROM categories c WHERE c.name < categories.name);
+        ",
+    )
+}
+
+/// Migration 20: tax rates in basis points, set on a category or a product,
+/// the product's own winning. Order lines snapshot the rate they sold under
+/// and the tax on the whole line; lines sold before this carry no tax.
+fn migrate_tax_rates(conn: &Connection) -> Result<()> {
+    conn.execute_batch(
+        "
+        CREATE TABLE tax_rates (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            name TEXT NOT NULL UNIQUE,
+            basis_points INTEGER NOT NULL CHECK (basis_points >= 0),
+            included INTEGER NOT NULL DEFAULT 1,
+            created_at DATETIME DEFAULT CURRENT_TIMESTAMP
+        );
+
+        ALTER TABLE categories ADD COLUMN tax_rate_id INTEGER REFERENCES tax_rates(id);
+        ALTER TABLE products ADD COLUMN tax_rate_id INTEGER REFERENCES tax_rates(id);
+
+        ALTER TABLE order_items ADD COLUMN tax_name TEXT;
+        ALTER TABLE order_items ADD COLUMN tax_basis_points INTEGER NOT NULL DEFAULT 0;
+        ALTER TABLE order_items ADD COLUMN tax_included INTEGER NOT NULL DEFAULT 1;
+        ALTER TABLE order_items ADD COLUMN tax_amount INTEGER NOT NULL DEFAULT 0;
+        ",
+    )
+}
+
+/// Migration 21: a stock take counts one location. Earlier counts were of
+/// the default location.
+fn migrate_stock_take_locations(conn: &Connection) -> Result<()> {
+    conn.execute_batch(
+        "
+        ALTER TABLE stock_takes ADD COLUMN location_id INTEGER REFERENCES stock_locations(id);
+        UPDATE stock_takes SET location_id = (SELECT id FROM stock_locations WHERE is_default = 1);
+        ",
+    )
+}
+
+/// Migration 22: stock take lines value variances per base unit, like their
+/// cost. Earlier lines froze the price of a serving.
+fn migrate_stock_take_base_unit_prices(conn: &Connection) -> Result<()> {
+    conn.execute_batch(
+        "
+        UPDATE stock_take_lines SET unit_price = (
+            SELECT (stock_take_lines.unit_price + p.serving_size / 2) / p.serving_size
+            FROM products p WHERE p.id = stock_take_lines.product_id
+        );
+        ",
+    )
+}
+
+/// Migration 23: units a buy-X-get-Y rule gives away point at the line that
+/// earned them, so the deal follows that line. Earlier free lines were rung
+/// up straight after theirs.
+fn migrate_order_item_deals(conn: &Connection) -> Result<()> {
+    conn.execute_batch(
+        "
+        ALTER TABLE order_items ADD COLUMN free_with_item_id INTEGER REFERENCES order_items(id);
+        UPDATE order_items SET free_with_item_id = id - 1
+        WHERE pricing_rule_id IN (SELECT id FROM pricing_rules WHERE kind = 'buy_x_get_y')
+          AND EXISTS (
+              SELECT 1 FROM order_items paid
+              WHERE paid.id = order_items.id - 1
+                AND paid.order_id = order_items.order_id
+                AND paid.product_id = order_items.product_id
+                AND paid.pricing_rule_id IS NULL
+          );
+        ",
+    )
+}
+
+/// Migration 24: a lot is held at one location, so sales draw from the lots
+/// where they are poured. Earlier lots were received at the default location.
+fn migrate_stock_lot_locations(conn: &Connection) -> Result<()> {
+    conn.execute_batch(
+        "
+        ALTER TABLE stock_lots ADD COLUMN location_id INTEGER REFERENCES stock_locations(id);
+        UPDATE stock_lots SET location_id = (SELECT id FROM stock_locations WHERE is_default = 1);
+        ",
+    )
+}
+
+/// Migration 25: a product code can stand for a pack, so scanning it at
+/// delivery receives the whole bottle or case.
+fn migrate_code_pack_quantities(conn: &Connection) -> Result<()> {
+    conn.execute_batch("ALTER TABLE product_codes ADD COLUMN pack_quantity INTEGER CHECK (pack_quantity > 0);")
+}
+
+fn table_columns(conn: &Connection, table: &str) -> Result<Vec<String>> {
+    let columns = conn
+        .prepare(&format!("PRAGMA table_info({})", table))?
+        .query_map([], |row| row.get::<_, String>(1))?
+        .collect::<Result<Vec<_>>>()?;
+    Ok(columns)
 }
 
 use tauri::Manager;
@@ -0,0 +1,166 @@
+use serde::ser::SerializeStruct;
+use serde::{Serialize, Serializer};
+use std::fmt;
+use std::sync::PoisonError;
+
+/// Error returned by every Tauri command.
+///
+/// Serialized as `{ "code": "INSUFFICIENT_STOCK", "message": "...", "details": {...} }`
+/// so the frontend can branch on `code` instead of matching message text.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum AppError {
+    NotFound {
+        entity: &'static str,
+        id: i64,
+    },
+    InsufficientStock {
+        product_id: i64,
+        product_name: String,
+        requested: i32,
+        available: i32,
+    },
+    DayNotStarted,
+    OrderNotOpen {
+        order_id: i64,
+    },
+    OpenTablesRemain {
+        count: i32,
+    },
+    /// A scanned code that matches no product or variant
+    UnknownCode {
+        code: String,
+    },
+    /// A product taken off ("86'd") until further notice or `until` (UTC)
+    ProductUnavailable {
+        product_id: i64,
+        product_name: String,
+        reason: Option<String>,
+        until: Option<String>,
+    },
+    Database(String),
+    Validation(String),
+}
+
+pub type AppResult<T> = Result<T, AppError>;
+
+impl AppError {
+    pub fn code(&self) -> &'static str {
+        match self {
+            AppError::NotFound { .. } => "NOT_FOUND",
+            AppError::InsufficientStock { .. } => "INSUFFICIENT_STOCK",
+            AppError::DayNotStarted => "DAY_NOT_STARTED",
+            AppError::OrderNotOpen { .. } => "ORDER_NOT_OPEN",
+            AppError::OpenTablesRemain { .. } => "OPEN_TABLES_REMAIN",
+            AppError::UnknownCode { .. } => "UNKNOWN_CODE",
+            AppError::ProductUnavailable { .. } => "PRODUCT_UNAVAILABLE",
+            AppError::Database(_) => "DATABASE",
+            AppError::Validation(_) => "VALIDATION",
+        }
+    }
+
+    fn details(&self) -> serde_json::Value {
+        match self {
+            AppError::NotFound { entity, id } => serde_json::json!({
+                "entity": entity,
+                "id": id,
+            }),
+            AppError::InsufficientStock {
+                product_id,
+                product_name,
+                requested,
+                available,
+            } => serde_json::json!({
+                "product_id": product_id,
+                "product_name": product_name,
+                "requested": requested,
+                "available": available,
+            }),
+            AppError::OrderNotOpen { order_id } => serde_json::json!({ "order_id": order_id }),
+            AppError::OpenTablesRemain { count } => serde_json::json!({ "count": count }),
+            AppError::UnknownCode { code } => serde_json::json!({ "code": code }),
+            AppError::ProductUnavailable {
+                product_id,
+                product_name,
+                reason,
+                until,
+            } => serde_json::json!({
+                "product_id": product_id,
+                "product_name": product_name,
+                "reason": reason,
+                "until": until,
+            }),
+            AppError::DayNotStarted | AppError::Database(_) | AppError::Validation(_) => {
+                serde_json::Value::Null
+            }
+        }
+    }
+}
+
+/// Map a `query_row` failure to `NotFound` when no row matched, keeping any
+/// other SQLite error as `Database`.
+pub fn not_found(entity: &'static str, id: i64) -> impl FnOnce(rusqlite::Error) -> AppError {
+    move |e| match e {
+        rusqlite::Error::QueryReturnedNoRows => AppError::NotFound { entity, id },
+        other => AppError::from(other),
+    }
+}
+
+impl fmt::Display for AppError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            AppError::NotFound { entity, id } => write!(f, "{} {} not found", entity, id),
+            AppError::InsufficientStock {
+                product_name,
+                requested,
+                available,
+                ..
+            } => write!(
+                f,
+                "Insufficient stock for {}: requested {}, available {}",
+                product_name, requested, available
+            ),
+            AppError::DayNotStarted => write!(f, "Day is not started. Please start the day first."),
+            AppError::OrderNotOpen { order_id } => {
+                write!(f, "Order {} is already paid and cannot be changed", order_id)
+            }
+            AppError::OpenTablesRemain { count } => write!(
+                f,
+                "Cannot close day: {} tables are still open. Close all tables first.",
+                count
+            ),
+            AppError::UnknownCode { code } => write!(f, "No product found for code {}", code),
+            AppError::ProductUnavailable {
+                product_name, reason, ..
+            } => match reason {
+                Some(reason) => write!(f, "{} is unavailable: {}", product_name, reason),
+                None => write!(f, "{} is unavailable", product_name),
+            },
+            AppError::Database(msg) => write!(f, "Database error: {}", msg),
+            AppError::Validation(msg) => f.write_str(msg),
+        }
+    }
+}
+
+impl std::error::Error for AppError {}
+
+impl Serialize for AppError {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut state = serializer.serialize_struct("AppError", 3)?;
+        state.serialize_field("code", self.code())?;
+        state.serialize_field("message", &self.to_string())?;
+        state.serialize_field("details", &self.details())?;
+        state.end()
+    }
+}
+
+impl From<rusqlite::Error> for AppError {
+    fn from(e: rusqlite::Error) -> Self {
+        AppError::Database(e.to_string())
+    }
+}
+
+impl<T> From<PoisonError<T>> for AppError {
+    fn from(e: PoisonError<T>) -> Self {
+        AppError::Database(format!("Database lock failed: {}", e))
+    }
+}
@@ -1,12 +1,18 @@
 mod commands;
-mod db;
-mod models;
+pub mod db;
+pub mod error;
+pub mod models;
+pub mod money;
+pub mod services;
 
 #[cfg(test)]
 mod tests;
 
-use commands::{categories, orders, products, reports, staff};
-use db::Database;
+use commands::{
+    availability, catalog, categories, codes, locations, lots, modifiers, orders, prices, pricing, products,
+    purchasing, recipes, reorder, reports, staff, stock, stock_takes, taxes, variants,
+};
+use db::{Database, DatabaseExt};
 use tauri::{
     menu::{Menu, MenuItem},
     tray::{MouseButton, MouseButtonState, TrayIconBuilder, TrayIconEvent},
@@ -25,6 +31,32 @@ pub fn run() {
             db.initialize().expect("Failed to initialize database");
             app.manage(db);
 
+            // Apply scheduled price changes and bring back 86'd products as they fall due
+            let handle = app.handle().clone();
+            std::thread::spawn(move || loop {
+                std::thread::sleep(std::time::Duration::from_secs(60));
+                let result = handle
+                    .db()
+                    .conn
+                    .lock()
+                    .map_err(error::AppError::from)
+                    .and_then(|mut conn| services::prices::apply_due_price_changes(&mut conn));
+                if let Err(e) = result {
+                    println!("[prices] Warning: scheduled price changes failed: {}", e);
+                }
+
+                let restored = handle
+                    .db()
+                    .conn
+                    .lock()
+                    .map_err(error::AppError::from)
+                    .and_then(|conn| services::availability::restore_due_availability(&conn));
+                match restored {
+                    Ok(products) => products.iter().for_each(|product| availability::notify(&handle, product)),
+                    Err(e) => println!("[availability] Warning: restoring products failed: {}", e),
+                }
+            });
+
             // Create tray menu
             let quit = MenuItem::with_id(app, "quit", "Quit", true, None::<&str>)?;
             let menu = Menu::with_items(app, &[&quit])?;
@@ -65,17 +97,115 @@ pub fn run() {
             // Categories
             categories::get_categories,
             categories::create_category,
-            categories::delete_category,
+            categories::update_category,
+            categories::reorder_categories,
+            categories::archive_category,
+            categories::restore_category,
+            categories::purge_category,
             // Products
             products::get_products,
+            products::search_products,
             products::create_product,
             products::update_product,
-            products::delete_product,
+            products::archive_product,
+            products::restore_product,
+            products::purge_product,
             products::get_low_stock,
+            // Availability
+            availability::mark_product_unavailable,
+            availability::mark_product_available,
+            // Import / Export
+            catalog::import_products,
+            catalog::export_products,
+            // Prices
+            prices::get_price_history,
+            prices::schedule_price_change,
+            prices::cancel_price_change,
+            // Variants
+            variants::get_variants,
+            variants::create_variant,
+            variants::update_variant,
+            variants::delete_variant,
+            // Codes
+            codes::find_product_by_code,
+            codes::get_product_codes,
+            codes::add_product_code,
+            codes::remove_product_code,
+            // Modifiers
+            modifiers::get_modifier_groups,
+            modifiers::get_product_modifier_groups,
+            modifiers::create_modifier_group,
+            modifiers::update_modifier_group,
+            modifiers::delete_modifier_group,
+            modifiers::add_modifier,
+            modifiers::delete_modifier,
+            modifiers::set_modifier_group_links,
+            // Pricing Rules
+            pricing::get_pricing_rules,
+            pricing::get_active_pricing_rules,
+            pricing::create_pricing_rule,
+            pricing::update_pricing_rule,
+            pricing::delete_pricing_rule,
+            // Taxes
+            taxes::get_tax_rates,
+            taxes::create_tax_rate,
+            taxes::update_tax_rate,
+            taxes::delete_tax_rate,
+            taxes::set_product_tax_rate,
+            taxes::set_category_tax_rate,
+            // Recipes
+            recipes::get_recipe,
+            recipes::set_recipe,
+            // Stock
+            stock::record_stock_movement,
+            stock::get_stock_movements,
+            stock::get_stock_discrepancies,
+            stock::rebuild_stock_from_ledger,
+            // Locations
+            locations::get_stock_locations,
+            locations::create_stock_location,
+            locations::get_stock_by_location,
+            locations::transfer_stock,
+            locations::set_location_threshold,
+            locations::get_location_low_stock,
+            // Lots and Kegs
+            lots::get_stock_lots,
+            lots::get_expiring_lots,
+            lots::receive_stock_lot,
+            lots::tap_keg,
+            lots::retire_keg,
+            lots::get_keg_yield,
+            // Purchasing
+            purchasing::get_suppliers,
+            purchasing::create_supplier,
+            purchasing::update_supplier,
+            purchasing::get_purchase_orders,
+            purchasing::get_purchase_order,
+            purchasing::create_purchase_order,
+            purchasing::update_purchase_order_lines,
+            purchasing::send_purchase_order,
+            purchasing::cancel_purchase_order,
+            purchasing::receive_purchase_order,
+            purchasing::receive_by_code,
+            purchasing::get_supplier_balances,
+            purchasing::get_supplier_purchase_history,
+            // Reorder
+            reorder::get_reorder_suggestions,
+            reorder::tune_low_stock_thresholds,
+            // Stock Takes
+            stock_takes::get_stock_takes,
+            stock_takes::get_stock_take,
+            stock_takes::start_stock_take,
+            stock_takes::record_stock_count,
+            stock_takes::commit_stock_take,
+            stock_takes::cancel_stock_take,
+            stock_takes::get_variance_history,
             // Staff
             staff::get_staff,
             staff::create_staff,
-            staff::delete_staff,
+            staff::archive_staff,
+            staff::restore_staff,
+            staff::purge_staff,
             staff::verify_staff_pin,
             // Orders
             orders::create_order,
@@ -83,6 +213,7 @@ pub fn run() {
             orders::get_open_orders,
             orders::get_order,
             orders::add_items_to_order,
+            orders::add_item_by_code,
             orders::mark_order_paid,
             orders::decrease_item_quantity,
             orders::increase_item_quantity,
@@ -91,8 +222,12 @@ pub fn run() {
             reports::close_day,
             reports::get_sales_history,
             reports::get_day_summary,
+            reports::get_category_sales,
             reports::get_orders_by_date_range,
             reports::create_day_closing_for_date,
+            reports::get_product_margins,
+            reports::get_category_margins,
+            reports::get_session_margins,
             // Day Sessions
             reports::get_active_session,
             reports::start_day,
@@ -1,40 +1,202 @@
+use crate::money::Money;
+use rusqlite::types::{FromSql, FromSqlError, FromSqlResult, ToSql, ToSqlOutput, ValueRef};
 use serde::{Deserialize, Serialize};
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct Category {
     pub id: i64,
     pub name: String,
+    pub archived_at: Option<String>,
+    pub parent_id: Option<i64>,
+    /// Position among its siblings
+    pub sort_order: i32,
+    /// `#rrggbb`
+    pub color: Option<String>,
+    pub icon: Option<String>,
+    /// Applies to its products and subcategories that set none of their own
+    pub tax_rate_id: Option<i64>,
 }
 
+/// Replaces a category's details; `sort_order` changes only by reordering.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct UpdateCategory {
+    pub id: i64,
+    pub name: String,
+    pub parent_id: Option<i64>,
+    pub color: Option<String>,
+    pub icon: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct TaxRate {
+    pub id: i64,
+    pub name: String,
+    /// Hundredths of a percent: 2000 is 20%
+    pub basis_points: i32,
+    /// Prices taxed at this rate already include it; otherwise it is added
+    pub included: bool,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct CreateTaxRate {
+    pub name: String,
+    pub basis_points: i32,
+    pub included: bool,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct UpdateTaxRate {
+    pub id: i64,
+    pub name: String,
+    pub basis_points: i32,
+    pub included: bool,
+}
+
+/// Unit a product is measured in. Stock is always held as a whole number of
+/// the unit's base (pieces, millilitres or grams), so a 70 cl bottle is 700.
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum Unit {
+    #[default]
+    Piece,
+    Ml,
+    Cl,
+    L,
+    G,
+}
+
+impl Unit {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Unit::Piece => "piece",
+            Unit::Ml => "ml",
+            Unit::Cl => "cl",
+            Unit::L => "l",
+            Unit::G => "g",
+        }
+    }
+
+    pub fn parse(s: &str) -> Option<Self> {
+        match s {
+            "piece" => Some(Unit::Piece),
+            "ml" => Some(Unit::Ml),
+            "cl" => Some(Unit::Cl),
+            "l" => Some(Unit::L),
+            "g" => Some(Unit::G),
+            _ => None,
+        }
+    }
+
+    /// The unit stock is held in.
+    pub fn base(&self) -> Unit {
+        match self {
+            Unit::Piece => Unit::Piece,
+            Unit::Ml | Unit::Cl | Unit::L => Unit::Ml,
+            Unit::G => Unit::G,
+        }
+    }
+
+    /// Base units in one of this unit, e.g. 10 ml in a centilitre.
+    pub fn base_factor(&self) -> i32 {
+        match self {
+            Unit::Piece | Unit::Ml | Unit::G => 1,
+            Unit::Cl => 10,
+            Unit::L => 1000,
+        }
+    }
+}
+
+impl ToSql for Unit {
+    fn to_sql(&self) -> rusqlite::Result<ToSqlOutput<'_>> {
+        Ok(ToSqlOutput::from(self.as_str()))
+    }
+}
+
+impl FromSql for Unit {
+    fn column_result(value: ValueRef<'_>) -> FromSqlResult<Self> {
+        let s = value.as_str()?;
+        Unit::parse(s).ok_or_else(|| FromSqlError::Other(format!("unknown unit: {}", s).into()))
+    }
+}
+
+/// `quantity`, `low_stock_threshold` and `serving_size` are in base units of
+/// `unit`; `servings` is how many can be sold from what is on hand.
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct Product {
     pub id: i64,
     pub name: String,
-    pub price: f64,
+    pub price: Money,
     pub quantity: i32,
     pub category_id: Option<i64>,
     pub category_name: Option<String>,
     pub low_stock_threshold: i32,
     pub created_at: String,
+    /// Made from other products, which hold its stock.
+    pub has_recipe: bool,
+    pub unit: Unit,
+    pub serving_size: i32,
+    pub servings: i32,
+    /// Scannable codes for the product itself; variants carry their own
+    pub codes: Vec<String>,
+    /// Other names staff search by, e.g. "Peroni" for "Nastro Azzurro"
+    pub aliases: Vec<String>,
+    /// Weighted-average cost per base unit; `None` until first costed
+    pub cost_price: Option<Money>,
+    /// Set once the product is taken off sale; history still refers to it
+    pub archived_at: Option<String>,
+    /// False while the product is 86'd, whatever its stock
+    pub available: bool,
+    pub unavailable_reason: Option<String>,
+    /// When it comes back by itself (UTC); `None` until someone restores it
+    pub unavailable_until: Option<String>,
+    /// Overrides the category's tax rate
+    pub tax_rate_id: Option<i64>,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
 pub struct CreateProduct {
     pub name: String,
-    pub price: f64,
+    pub price: Money,
     pub quantity: i32,
     pub category_id: Option<i64>,
     pub low_stock_threshold: Option<i32>,
+    #[serde(default)]
+    pub staff_id: Option<i64>,
+    #[serde(default)]
+    pub unit: Unit,
+    /// Base units one sale deducts; required unless the unit is `piece`
+    #[serde(default)]
+    pub serving_size: Option<i32>,
+    #[serde(default)]
+    pub codes: Vec<String>,
+    #[serde(default)]
+    pub aliases: Vec<String>,
+    /// Cost per base unit, until deliveries average it
+    #[serde(default)]
+    pub cost_price: Option<Money>,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
 pub struct UpdateProduct {
     pub id: i64,
     pub name: String,
-    pub price: f64,
+    pub price: Money,
     pub quantity: i32,
     pub category_id: Option<i64>,
     pub low_stock_threshold: i32,
+    /// Left unchanged when omitted
+    #[serde(default)]
+    pub unit: Option<Unit>,
+    #[serde(default)]
+    pub serving_size: Option<i32>,
+    #[serde(default)]
+    pub cost_price: Option<Money>,
+    /// Replaces the search aliases; left unchanged when omitted
+    #[serde(default)]
+    pub aliases: Option<Vec<String>>,
+    /// Who edited the product; recorded on the stock adjustment if quantity changed
+    #[serde(default)]
+    pub staff_id: Option<i64>,
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
@@ -43,6 +205,7 @@ pub struct Staff {
     pub name: String,
     pub pin: Option<String>,
     pub created_at: String,
+    pub archived_at: Option<String>,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -57,13 +220,14 @@ pub struct Order {
     pub staff_id: i64,
     pub staff_name: Option<String>,
     pub table_number: i32,
-    pub total: f64,
+    pub total: Money,
     pub customer_name: Option<String>,
     pub notes: Option<String>,
     pub status: String, // "open" or "paid"
     pub created_at: String,
 }
 
+/// `price_at_sale` is the unit price including any modifiers.
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct OrderItem {
     pub id: i64,
@@ -71,13 +235,42 @@ pub struct OrderItem {
     pub product_id: i64,
     pub product_name: Option<String>,
     pub quantity: i32,
-    pub price_at_sale: f64,
+    pub price_at_sale: Money,
+    /// Cost of one unit when sold; `None` if an ingredient had no cost yet
+    pub cost_at_sale: Option<Money>,
+    /// The pricing rule that set `price_at_sale`, if any
+    pub pricing_rule_id: Option<i64>,
+    pub pricing_rule_name: Option<String>,
+    pub variant_id: Option<i64>,
+    pub variant_name: Option<String>,
+    /// Already included in `price_at_sale`
+    pub modifiers: Vec<OrderItemModifier>,
+    /// The tax rate the line sold under; `None` if it was untaxed
+    pub tax_name: Option<String>,
+    pub tax_basis_points: i32,
+    /// Whether `price_at_sale` includes the tax or it was charged on top
+    pub tax_included: bool,
+    /// Tax on the whole line
+    pub tax_amount: Money,
+}
+
+/// A modifier as it was sold on an order line.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq)]
+pub struct OrderItemModifier {
+    pub modifier_id: Option<i64>,
+    pub name: String,
+    pub price_delta: Money,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
 pub struct CreateOrderItem {
     pub product_id: i64,
     pub quantity: i32,
+    /// Sell this variant of `product_id` instead of the product itself
+    #[serde(default)]
+    pub variant_id: Option<i64>,
+    #[serde(default)]
+    pub modifier_ids: Vec<i64>,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -87,6 +280,9 @@ pub struct CreateOrder {
     pub customer_name: Option<String>,
     pub notes: Option<String>,
     pub items: Vec<CreateOrderItem>,
+    /// Where the order sells from; the default location when omitted
+    #[serde(default)]
+    pub location_id: Option<i64>,
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
@@ -98,9 +294,81 @@ pub struct OrderWithItems {
 #[derive(Debug, Serialize, Deserialize)]
 pub struct DaySummary {
     pub date: String,
-    pub total_revenue: f64,
+    pub total_revenue: Money,
     pub total_orders: i32,
     pub orders: Vec<OrderWithItems>,
+    pub product_sales: Vec<ProductSales>,
+    pub category_sales: Vec<CategorySales>,
+    pub taxes: Vec<TaxBreakdown>,
+}
+
+/// Sales of one product with its variants rolled up into it.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct ProductSales {
+    pub product_id: i64,
+    pub product_name: Option<String>,
+    /// The product's current category
+    pub category_id: Option<i64>,
+    pub category_name: Option<String>,
+    pub quantity: i32,
+    pub revenue: Money,
+    pub variants: Vec<VariantSales>,
+}
+
+/// Sales of a category's products, or of everything under it when rolled up.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct CategorySales {
+    pub category_id: Option<i64>,
+    pub category_name: Option<String>,
+    pub quantity: i32,
+    pub revenue: Money,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct VariantSales {
+    pub variant_id: i64,
+    pub variant_name: Option<String>,
+    pub quantity: i32,
+    pub revenue: Money,
+}
+
+/// Gross margin on a product's sales. `cost` only covers costed units;
+/// `uncosted_quantity` were sold before the product had a cost, and
+/// `below_cost_quantity` were sold for less than they cost.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct ProductMargin {
+    pub product_id: i64,
+    pub product_name: Option<String>,
+    pub category_id: Option<i64>,
+    pub category_name: Option<String>,
+    pub quantity: i32,
+    pub revenue: Money,
+    pub cost: Money,
+    pub margin: Money,
+    pub below_cost_quantity: i32,
+    pub uncosted_quantity: i32,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct CategoryMargin {
+    pub category_id: Option<i64>,
+    pub category_name: Option<String>,
+    pub quantity: i32,
+    pub revenue: Money,
+    pub cost: Money,
+    pub margin: Money,
+    pub below_cost_quantity: i32,
+    pub uncosted_quantity: i32,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct SessionMargin {
+    pub session_id: i64,
+    pub date: Option<String>,
+    pub revenue: Money,
+    pub cost: Money,
+    pub margin: Money,
+    pub products: Vec<ProductMargin>,
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
@@ -112,6 +380,792 @@ pub struct DaySession {
     pub started_at: String,
     pub closed_at: Option<String>,      // When session was closed
     pub is_active: bool,
-    pub total_revenue: Option<f64>,     // Stored at close time
+    pub total_revenue: Option<Money>,   // Stored at close time
     pub total_orders: Option<i32>,      // Stored at close time
+    pub taxes: Vec<TaxBreakdown>,
+}
+
+/// Sales under one tax rate; untaxed sales have no `tax_name`.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct TaxBreakdown {
+    pub tax_name: Option<String>,
+    pub basis_points: i32,
+    pub net: Money,
+    pub tax: Money,
+    pub gross: Money,
+}
+
+/// Why a product's stock changed. Stored as text in `stock_movements.reason`.
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum StockReason {
+    OpeningBalance,
+    Sale,
+    Return,
+    Delivery,
+    Adjustment,
+    Waste,
+    Comp,
+    StockTake,
+    Transfer,
+}
+
+impl StockReason {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            StockReason::OpeningBalance => "opening_balance",
+            StockReason::Sale => "sale",
+            StockReason::Return => "return",
+            StockReason::Delivery => "delivery",
+            StockReason::Adjustment => "adjustment",
+            StockReason::Waste => "waste",
+            StockReason::Comp => "comp",
+            StockReason::StockTake => "stock_take",
+            StockReason::Transfer => "transfer",
+        }
+    }
+
+    pub fn parse(s: &str) -> Option<Self> {
+        match s {
+            "opening_balance" => Some(StockReason::OpeningBalance),
+            "sale" => Some(StockReason::Sale),
+            "return" => Some(StockReason::Return),
+            "delivery" => Some(StockReason::Delivery),
+            "adjustment" => Some(StockReason::Adjustment),
+            "waste" => Some(StockReason::Waste),
+            "comp" => Some(StockReason::Comp),
+            "stock_take" => Some(StockReason::StockTake),
+            "transfer" => Some(StockReason::Transfer),
+            _ => None,
+        }
+    }
+}
+
+impl ToSql for StockReason {
+    fn to_sql(&self) -> rusqlite::Result<ToSqlOutput<'_>> {
+        Ok(ToSqlOutput::from(self.as_str()))
+    }
+}
+
+impl FromSql for StockReason {
+    fn column_result(value: ValueRef<'_>) -> FromSqlResult<Self> {
+        let s = value.as_str()?;
+        StockReason::parse(s).ok_or_else(|| FromSqlError::Other(format!("unknown stock reason: {}", s).into()))
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct StockMovement {
+    pub id: i64,
+    pub product_id: i64,
+    pub product_name: Option<String>,
+    pub quantity_change: i32,
+    pub reason: StockReason,
+    pub staff_id: Option<i64>,
+    pub staff_name: Option<String>,
+    pub order_id: Option<i64>,
+    pub reference: Option<String>,
+    pub note: Option<String>,
+    pub created_at: String,
+    pub location_id: i64,
+    pub location_name: String,
+}
+
+/// A stock change entered by hand (delivery, waste, comp, correction).
+/// Sales and returns are recorded by the order commands themselves.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct CreateStockMovement {
+    pub product_id: i64,
+    pub quantity_change: i32,
+    pub reason: StockReason,
+    pub staff_id: Option<i64>,
+    pub reference: Option<String>,
+    pub note: Option<String>,
+    /// The default location when omitted
+    #[serde(default)]
+    pub location_id: Option<i64>,
+}
+
+/// A product whose stored quantity disagrees with the sum of its ledger.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct StockDiscrepancy {
+    pub product_id: i64,
+    pub product_name: String,
+    pub recorded_quantity: i32,
+    pub ledger_quantity: i32,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct StockLocation {
+    pub id: i64,
+    pub name: String,
+    /// Holds whatever stock no other location accounts for
+    pub is_default: bool,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct LocationQuantity {
+    pub location_id: i64,
+    pub location_name: String,
+    pub quantity: i32,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct TransferStock {
+    pub product_id: i64,
+    pub from_location_id: i64,
+    pub to_location_id: i64,
+    pub quantity: i32,
+    pub staff_id: Option<i64>,
+    pub note: Option<String>,
+}
+
+/// A product running low at one location, with where else it is in stock.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct LocationLowStock {
+    pub location_id: i64,
+    pub product_id: i64,
+    pub product_name: String,
+    pub quantity: i32,
+    pub low_stock_threshold: i32,
+    /// Other locations holding the product, fullest first
+    pub elsewhere: Vec<LocationQuantity>,
+}
+
+/// A batch of stock received together. Kegs carry a `keg_id` and only pour
+/// once tapped.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct StockLot {
+    pub id: i64,
+    pub product_id: i64,
+    pub product_name: String,
+    pub keg_id: Option<String>,
+    pub received_at: String,
+    /// `YYYY-MM-DD`
+    pub expires_on: Option<String>,
+    pub initial_quantity: i32,
+    pub quantity: i32,
+    pub tapped_at: Option<String>,
+    pub retired_at: Option<String>,
+    /// Where the lot is; its stock moves with transfers
+    pub location_id: i64,
+    pub location_name: String,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct CreateStockLot {
+    pub product_id: i64,
+    pub quantity: i32,
+    pub expires_on: Option<String>,
+    pub keg_id: Option<String>,
+    pub staff_id: Option<i64>,
+    pub reference: Option<String>,
+}
+
+/// What a retired keg gave compared with what it should have, in servings
+/// of the product.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct KegYield {
+    pub lot: StockLot,
+    /// Base units sold from the keg
+    pub poured: i32,
+    pub servings_poured: f64,
+    pub theoretical_servings: f64,
+    pub yield_percent: f64,
+    /// Left in the keg when retired, written off as waste
+    pub loss: i32,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct Supplier {
+    pub id: i64,
+    pub name: String,
+    pub contact_name: Option<String>,
+    pub phone: Option<String>,
+    pub email: Option<String>,
+    pub notes: Option<String>,
+    pub created_at: String,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct CreateSupplier {
+    pub name: String,
+    pub contact_name: Option<String>,
+    pub phone: Option<String>,
+    pub email: Option<String>,
+    pub notes: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct UpdateSupplier {
+    pub id: i64,
+    pub name: String,
+    pub contact_name: Option<String>,
+    pub phone: Option<String>,
+    pub email: Option<String>,
+    pub notes: Option<String>,
+}
+
+/// Lifecycle of a purchase order: draft -> sent -> partially_received -> received.
+/// Drafts and sent orders with nothing received yet can be cancelled.
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum PurchaseOrderStatus {
+    Draft,
+    Sent,
+    PartiallyReceived,
+    Received,
+    Cancelled,
+}
+
+impl PurchaseOrderStatus {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            PurchaseOrderStatus::Draft => "draft",
+            PurchaseOrderStatus::Sent => "sent",
+            PurchaseOrderStatus::PartiallyReceived => "partially_received",
+            PurchaseOrderStatus::Received => "received",
+            PurchaseOrderStatus::Cancelled => "cancelled",
+        }
+    }
+
+    pub fn parse(s: &str) -> Option<Self> {
+        match s {
+            "draft" => Some(PurchaseOrderStatus::Draft),
+            "sent" => Some(PurchaseOrderStatus::Sent),
+            "partially_received" => Some(PurchaseOrderStatus::PartiallyReceived),
+            "received" => Some(PurchaseOrderStatus::Received),
+            "cancelled" => Some(PurchaseOrderStatus::Cancelled),
+            _ => None,
+        }
+    }
+}
+
+impl ToSql for PurchaseOrderStatus {
+    fn to_sql(&self) -> rusqlite::Result<ToSqlOutput<'_>> {
+        Ok(ToSqlOutput::from(self.as_str()))
+    }
+}
+
+impl FromSql for PurchaseOrderStatus {
+    fn column_result(value: ValueRef<'_>) -> FromSqlResult<Self> {
+        let s = value.as_str()?;
+        PurchaseOrderStatus::parse(s)
+            .ok_or_else(|| FromSqlError::Other(format!("unknown purchase order status: {}", s).into()))
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct PurchaseOrder {
+    pub id: i64,
+    pub supplier_id: i64,
+    pub supplier_name: Option<String>,
+    pub status: PurchaseOrderStatus,
+    pub reference: Option<String>,
+    pub notes: Option<String>,
+    pub created_by: Option<i64>,
+    pub total: Money,              // Ordered quantity at ordered cost
+    pub created_at: String,
+    pub sent_at: Option<String>,
+    pub received_at: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct PurchaseOrderLine {
+    pub id: i64,
+    pub purchase_order_id: i64,
+    pub product_id: i64,
+    pub product_name: Option<String>,
+    pub quantity_ordered: i32,
+    pub quantity_received: i32,
+    pub unit_cost: Money,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct PurchaseOrderWithLines {
+    pub purchase_order: PurchaseOrder,
+    pub lines: Vec<PurchaseOrderLine>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct CreatePurchaseOrderLine {
+    pub product_id: i64,
+    pub quantity: i32,
+    pub unit_cost: Money,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct CreatePurchaseOrder {
+    pub supplier_id: i64,
+    pub staff_id: Option<i64>,
+    pub reference: Option<String>,
+    pub notes: Option<String>,
+    pub lines: Vec<CreatePurchaseOrderLine>,
+}
+
+/// One line of a delivery. `unit_cost` overrides the ordered cost when the
+/// invoice differs.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ReceiveLine {
+    pub line_id: i64,
+    pub quantity: i32,
+    pub unit_cost: Option<Money>,
+    /// Receives the delivery as a lot expiring on this `YYYY-MM-DD`
+    #[serde(default)]
+    pub expires_on: Option<String>,
+}
+
+/// A delivery against a purchase order. With no `lines`, everything still
+/// outstanding is received at the ordered cost.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ReceivePurchaseOrder {
+    pub staff_id: Option<i64>,
+    pub reference: Option<String>,
+    pub lines: Option<Vec<ReceiveLine>>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct SupplierBalance {
+    pub supplier_id: i64,
+    pub supplier_name: String,
+    pub open_orders: i32,
+    pub outstanding_value: Money,  // Not yet delivered, at ordered cost
+    pub received_value: Money,     // Delivered, at invoiced cost
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct PurchaseReceipt {
+    pub id: i64,
+    pub purchase_order_id: i64,
+    pub product_id: i64,
+    pub product_name: Option<String>,
+    pub quantity: i32,
+    pub unit_cost: Money,
+    pub line_total: Money,
+    pub received_by: Option<i64>,
+    pub reference: Option<String>,
+    pub received_at: String,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum StockTakeStatus {
+    Open,
+    Committed,
+    Cancelled,
+}
+
+impl StockTakeStatus {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            StockTakeStatus::Open => "open",
+            StockTakeStatus::Committed => "committed",
+            StockTakeStatus::Cancelled => "cancelled",
+        }
+    }
+
+    pub fn parse(s: &str) -> Option<Self> {
+        match s {
+            "open" => Some(StockTakeStatus::Open),
+            "committed" => Some(StockTakeStatus::Committed),
+            "cancelled" => Some(StockTakeStatus::Cancelled),
+            _ => None,
+        }
+    }
+}
+
+impl ToSql for StockTakeStatus {
+    fn to_sql(&self) -> rusqlite::Result<ToSqlOutput<'_>> {
+        Ok(ToSqlOutput::from(self.as_str()))
+    }
+}
+
+impl FromSql for StockTakeStatus {
+    fn column_result(value: ValueRef<'_>) -> FromSqlResult<Self> {
+        let s = value.as_str()?;
+        StockTakeStatus::parse(s)
+            .ok_or_else(|| FromSqlError::Other(format!("unknown stock-take status: {}", s).into()))
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct StockTake {
+    pub id: i64,
+    pub status: StockTakeStatus,
+    pub notes: Option<String>,
+    /// Where the stock was counted; variances are posted there
+    pub location_id: i64,
+    pub location_name: String,
+    pub started_by: i64,
+    pub started_by_name: Option<String>,
+    pub started_at: String,
+    pub committed_by: Option<i64>,
+    pub committed_at: Option<String>,
+}
+
+/// One staff member's count of one product. Counts from several staff for the
+/// same product (bar, cellar, fridge) add up to the counted quantity.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct StockCount {
+    pub id: i64,
+    pub product_id: i64,
+    pub product_name: Option<String>,
+    pub staff_id: i64,
+    pub staff_name: Option<String>,
+    pub counted_quantity: i32,
+    pub counted_at: String,
+}
+
+/// Counted against system quantity for one product. `unit_cost` is unknown
+/// for products never received through a purchase order. `unit_cost` and
+/// `unit_price` are per base unit, rounded to the nearest minor unit.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct StockTakeLine {
+    pub product_id: i64,
+    pub product_name: Option<String>,
+    pub system_quantity: i32,
+    pub counted_quantity: i32,
+    pub variance: i32,
+    pub unit_cost: Option<Money>,
+    pub unit_price: Money,
+    pub variance_cost: Option<Money>,
+    pub variance_value: Money,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct StockTakeWithLines {
+    pub stock_take: StockTake,
+    pub counts: Vec<StockCount>,
+    pub lines: Vec<StockTakeLine>,
+    pub total_variance_cost: Money,
+    pub total_variance_value: Money,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct VarianceHistoryEntry {
+    pub stock_take_id: i64,
+    pub committed_at: String,
+    pub system_quantity: i32,
+    pub counted_quantity: i32,
+    pub variance: i32,
+    pub variance_cost: Option<Money>,
+    pub variance_value: Money,
+}
+
+/// One ingredient of a recipe. `quantity` per serving and `available` are in
+/// base units of the component's `unit`, e.g. 50 for 5 cl of gin.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct RecipeComponent {
+    pub component_id: i64,
+    pub component_name: Option<String>,
+    pub quantity: i32,
+    pub available: i32,
+    pub unit: Unit,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct SetRecipeComponent {
+    pub component_id: i64,
+    pub quantity: i32,
+}
+
+/// A way of selling a product, e.g. a pint or half pint from the same keg.
+/// Each sale deducts `deduction` base units from the parent's stock.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct ProductVariant {
+    pub id: i64,
+    pub product_id: i64,
+    pub product_name: Option<String>,
+    pub name: String,
+    pub price: Money,
+    pub codes: Vec<String>,
+    pub deduction: i32,
+    pub created_at: String,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct CreateProductVariant {
+    pub product_id: i64,
+    pub name: String,
+    pub price: Money,
+    #[serde(default)]
+    pub codes: Vec<String>,
+    pub deduction: i32,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct UpdateProductVariant {
+    pub id: i64,
+    pub name: String,
+    pub price: Money,
+    pub deduction: i32,
+}
+
+/// A set of choices offered with a product, e.g. "Ice" or "Extras". At least
+/// `min_select` must be picked (0 makes the group optional) and at most
+/// `max_select`, if set.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct ModifierGroup {
+    pub id: i64,
+    pub name: String,
+    pub min_select: i32,
+    pub max_select: Option<i32>,
+    pub modifiers: Vec<Modifier>,
+    pub product_ids: Vec<i64>,
+    pub category_ids: Vec<i64>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct Modifier {
+    pub id: i64,
+    pub group_id: i64,
+    pub name: String,
+    pub price_delta: Money,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct CreateModifier {
+    pub name: String,
+    #[serde(default)]
+    pub price_delta: Money,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct CreateModifierGroup {
+    pub name: String,
+    #[serde(default)]
+    pub min_select: i32,
+    #[serde(default)]
+    pub max_select: Option<i32>,
+    #[serde(default)]
+    pub modifiers: Vec<CreateModifier>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct UpdateModifierGroup {
+    pub id: i64,
+    pub name: String,
+    pub min_select: i32,
+    pub max_select: Option<i32>,
+}
+
+/// A scannable SKU or EAN. `variant_id` is set when the code sells a variant.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct ProductCode {
+    pub id: i64,
+    pub code: String,
+    pub product_id: i64,
+    pub variant_id: Option<i64>,
+    pub created_at: String,
+    /// Base units one scan brings in at delivery; product codes only
+    pub pack_quantity: Option<i32>,
+}
+
+/// What a scanned code resolves to.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct CodeMatch {
+    pub code: String,
+    pub product: Product,
+    pub variant: Option<ProductVariant>,
+}
+
+/// How a pricing rule changes the price.
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum PricingRuleKind {
+    /// `percent` off the list price
+    PercentOff,
+    /// `amount` off the list price, down to zero
+    AmountOff,
+    /// For every `buy_quantity` rung up together, `free_quantity` more are free
+    BuyXGetY,
+}
+
+impl PricingRuleKind {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            PricingRuleKind::PercentOff => "percent_off",
+            PricingRuleKind::AmountOff => "amount_off",
+            PricingRuleKind::BuyXGetY => "buy_x_get_y",
+        }
+    }
+
+    pub fn parse(s: &str) -> Option<Self> {
+        match s {
+            "percent_off" => Some(PricingRuleKind::PercentOff),
+            "amount_off" => Some(PricingRuleKind::AmountOff),
+            "buy_x_get_y" => Some(PricingRuleKind::BuyXGetY),
+            _ => None,
+        }
+    }
+}
+
+impl ToSql for PricingRuleKind {
+    fn to_sql(&self) -> rusqlite::Result<ToSqlOutput<'_>> {
+        Ok(ToSqlOutput::from(self.as_str()))
+    }
+}
+
+impl FromSql for PricingRuleKind {
+    fn column_result(value: ValueRef<'_>) -> FromSqlResult<Self> {
+        let s = value.as_str()?;
+        PricingRuleKind::parse(s).ok_or_else(|| FromSqlError::Other(format!("unknown pricing rule kind: {}", s).into()))
+    }
+}
+
+/// A scheduled price change, e.g. happy hour. Applies to one product, one
+/// category, or everything when neither is set. `weekdays` are ISO numbers
+/// (1 = Monday), empty for every day; `start_time`/`end_time` are local
+/// "HH:MM" and may run past midnight.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct PricingRule {
+    pub id: i64,
+    pub name: String,
+    pub kind: PricingRuleKind,
+    pub percent: Option<i32>,
+    pub amount: Option<Money>,
+    pub buy_quantity: Option<i32>,
+    pub free_quantity: Option<i32>,
+    pub product_id: Option<i64>,
+    pub product_name: Option<String>,
+    pub category_id: Option<i64>,
+    pub category_name: Option<String>,
+    pub weekdays: Vec<u32>,
+    pub start_time: Option<String>,
+    pub end_time: Option<String>,
+    pub active: bool,
+    pub created_at: String,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct CreatePricingRule {
+    pub name: String,
+    pub kind: PricingRuleKind,
+    #[serde(default)]
+    pub percent: Option<i32>,
+    #[serde(default)]
+    pub amount: Option<Money>,
+    #[serde(default)]
+    pub buy_quantity: Option<i32>,
+    #[serde(default)]
+    pub free_quantity: Option<i32>,
+    #[serde(default)]
+    pub product_id: Option<i64>,
+    #[serde(default)]
+    pub category_id: Option<i64>,
+    #[serde(default)]
+    pub weekdays: Vec<u32>,
+    #[serde(default)]
+    pub start_time: Option<String>,
+    #[serde(default)]
+    pub end_time: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct UpdatePricingRule {
+    pub id: i64,
+    pub name: String,
+    pub kind: PricingRuleKind,
+    #[serde(default)]
+    pub percent: Option<i32>,
+    #[serde(default)]
+    pub amount: Option<Money>,
+    #[serde(default)]
+    pub buy_quantity: Option<i32>,
+    #[serde(default)]
+    pub free_quantity: Option<i32>,
+    #[serde(default)]
+    pub product_id: Option<i64>,
+    #[serde(default)]
+    pub category_id: Option<i64>,
+    #[serde(default)]
+    pub weekdays: Vec<u32>,
+    #[serde(default)]
+    pub start_time: Option<String>,
+    #[serde(default)]
+    pub end_time: Option<String>,
+    pub active: bool,
+}
+
+/// One change in a product's price. A change with no `applied_at` is
+/// scheduled for `effective_at` and has no `old_price` yet; the first entry
+/// for a product has none either. Times are UTC.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct PriceChange {
+    pub id: i64,
+    pub product_id: i64,
+    pub old_price: Option<Money>,
+    pub new_price: Money,
+    pub staff_id: Option<i64>,
+    pub staff_name: Option<String>,
+    pub effective_at: String,
+    pub applied_at: Option<String>,
+    pub created_at: String,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct SchedulePriceChange {
+    pub product_id: i64,
+    pub new_price: Money,
+    /// Local "YYYY-MM-DD HH:MM"; now when omitted or already past
+    #[serde(default)]
+    pub effective_at: Option<String>,
+    #[serde(default)]
+    pub staff_id: Option<i64>,
+}
+
+/// What an import did, or would do on a dry run. `line` is the line a CSV
+/// row starts on, or the position of an entry in a JSON array.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct ImportReport {
+    pub dry_run: bool,
+    /// False on a dry run, and whenever a row was rejected
+    pub applied: bool,
+    pub created: Vec<ImportedRow>,
+    pub updated: Vec<ImportedRow>,
+    pub rejected: Vec<RejectedRow>,
+    pub categories_created: Vec<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct ImportedRow {
+    pub line: usize,
+    pub name: String,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct RejectedRow {
+    pub line: usize,
+    pub message: String,
+}
+
+/// How fast a product is going and what to order so it lasts. Quantities are
+/// in the unit's base, usage is per trading session.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct ReorderSuggestion {
+    pub product_id: i64,
+    pub product_name: String,
+    pub unit: Unit,
+    pub quantity: i32,
+    /// Still due on sent purchase orders
+    pub on_order: i32,
+    pub daily_usage: f64,
+    /// How long current stock lasts at `daily_usage`
+    pub days_of_cover: f64,
+    /// Stock needed to last until an order placed now arrives
+    pub reorder_point: i32,
+    pub suggested_quantity: i32,
+    /// Last ordered cost per base unit
+    pub unit_cost: Option<Money>,
+}
+
+/// Suggestions grouped by the supplier each product was last ordered from;
+/// `supplier_id` is `None` for products never ordered.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct SupplierReorder {
+    pub supplier_id: Option<i64>,
+    pub supplier_name: Option<String>,
+    pub lines: Vec<ReorderSuggestion>,
+    /// At last ordered costs; lines without one are left out
+    pub estimated_cost: Money,
 }
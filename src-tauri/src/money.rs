@@ -0,0 +1,191 @@
+use rusqlite::types::{FromSql, FromSqlResult, ToSql, ToSqlOutput, ValueRef};
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use std::fmt;
+use std::iter::Sum;
+use std::ops::{Add, AddAssign, Mul, Neg, Sub, SubAssign};
+
+/// Currency every amount in the database is recorded in (Albanian lek).
+pub const DEFAULT_CURRENCY: Currency = Currency(*b"ALL");
+
+/// Number of minor units in one major unit (ISO 4217 exponent 2).
+pub const MINOR_PER_MAJOR: i64 = 100;
+
+/// ISO 4217 currency code, e.g. `ALL` or `EUR`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Currency([u8; 3]);
+
+impl Currency {
+    pub fn parse(code: &str) -> Option<Self> {
+        let bytes = code.as_bytes();
+        if bytes.len() == 3 && bytes.iter().all(|b| b.is_ascii_uppercase()) {
+            Some(Currency([bytes[0], bytes[1], bytes[2]]))
+        } else {
+            None
+        }
+    }
+
+    pub fn as_str(&self) -> &str {
+        // Only ever constructed from three ASCII uppercase bytes
+        std::str::from_utf8(&self.0).unwrap_or("???")
+    }
+}
+
+impl Default for Currency {
+    fn default() -> Self {
+        DEFAULT_CURRENCY
+    }
+}
+
+impl fmt::Display for Currency {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl Serialize for Currency {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(self.as_str())
+    }
+}
+
+impl<'de> Deserialize<'de> for Currency {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let code = String::deserialize(deserializer)?;
+        Currency::parse(&code)
+            .ok_or_else(|| serde::de::Error::custom(format!("invalid currency code: {}", code)))
+    }
+}
+
+/// A monetary amount held as an integer number of minor units, so totals
+/// add up exactly no matter how many items go on a tab.
+///
+/// Serialized as `{ "minor_units": 1250, "currency": "ALL" }`. In SQLite the
+/// amount is stored as an INTEGER column of minor units in `DEFAULT_CURRENCY`,
+/// so amounts in any other currency are refused when deserialized and every
+/// `Money` can be combined with every other.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default, Serialize, Deserialize)]
+#[serde(try_from = "MoneyInput")]
+pub struct Money {
+    pub minor_units: i64,
+    currency: Currency,
+}
+
+/// `Money` as received, before its currency is checked.
+#[derive(Deserialize)]
+struct MoneyInput {
+    minor_units: i64,
+    #[serde(default)]
+    currency: Currency,
+}
+
+impl TryFrom<MoneyInput> for Money {
+    type Error = String;
+
+    fn try_from(input: MoneyInput) -> Result<Self, Self::Error> {
+        if input.currency != DEFAULT_CURRENCY {
+            return Err(format!("amounts must be in {}, not {}", DEFAULT_CURRENCY, input.currency));
+        }
+        Ok(Money::from_minor(input.minor_units))
+    }
+}
+
+impl Money {
+    pub const fn from_minor(minor_units: i64) -> Self {
+        Money {
+            minor_units,
+            currency: DEFAULT_CURRENCY,
+        }
+    }
+
+    pub const fn zero() -> Self {
+        Money::from_minor(0)
+    }
+
+    pub const fn currency(&self) -> Currency {
+        self.currency
+    }
+}
+
+impl fmt::Display for Money {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let sign = if self.minor_units < 0 { "-" } else { "" };
+        let abs = self.minor_units.unsigned_abs();
+        let per = MINOR_PER_MAJOR as u64;
+        write!(f, "{}{}.{:02} {}", sign, abs / per, abs % per, self.currency)
+    }
+}
+
+impl Add for Money {
+    type Output = Money;
+
+    fn add(self, rhs: Money) -> Money {
+        Money {
+            minor_units: self.minor_units + rhs.minor_units,
+            currency: self.currency,
+        }
+    }
+}
+
+impl AddAssign for Money {
+    fn add_assign(&mut self, rhs: Money) {
+        *self = *self + rhs;
+    }
+}
+
+impl Sub for Money {
+    type Output = Money;
+
+    fn sub(self, rhs: Money) -> Money {
+        Money {
+            minor_units: self.minor_units - rhs.minor_units,
+            currency: self.currency,
+        }
+    }
+}
+
+impl SubAssign for Money {
+    fn sub_assign(&mut self, rhs: Money) {
+        *self = *self - rhs;
+    }
+}
+
+impl Neg for Money {
+    type Output = Money;
+
+    fn neg(self) -> Money {
+        Money {
+            minor_units: -self.minor_units,
+            currency: self.currency,
+        }
+    }
+}
+
+/// Line totals: unit price times quantity.
+impl Mul<i32> for Money {
+    type Output = Money;
+
+    fn mul(self, quantity: i32) -> Money {
+        Money {
+            minor_units: self.minor_units * quantity as i64,
+            currency: self.currency,
+        }
+    }
+}
+
+impl Sum for Money {
+    fn sum<I: Iterator<Item = Money>>(iter: I) -> Money {
+        iter.fold(Money::zero(), |acc, m| acc + m)
+    }
+}
+
+impl ToSql for Money {
+    fn to_sql(&self) -> rusqlite::Result<ToSqlOutput<'_>> {
+        Ok(ToSqlOutput::from(self.minor_units))
+    }
+}
+
+impl FromSql for Money {
+    fn column_result(value: ValueRef<'_>) -> FromSqlResult<Self> {
+        i64::column_result(value).map(Money::from_minor)
+    }
+}
@@ -0,0 +1,97 @@
+use crate::error::{AppError, AppResult};
+use crate::models::Product;
+use crate::services::products;
+use rusqlite::{Connection, OptionalExtension};
+
+/// Take a product off sale ("86" it) whatever its stock, until it is marked
+/// available again or, if given, until a local "YYYY-MM-DD HH:MM".
+pub fn mark_product_unavailable(
+    conn: &Connection,
+    product_id: i64,
+    reason: Option<String>,
+    until: Option<String>,
+) -> AppResult<Product> {
+    let until: Option<String> = match until.as_deref().map(str::trim).filter(|u| !u.is_empty()) {
+        None => None,
+        Some(local) => {
+            let (utc, due): (Option<String>, bool) = conn.query_row(
+                "SELECT datetime(?1, 'utc'), COALESCE(datetime(?1, 'utc') <= CURRENT_TIMESTAMP, 0)",
+                [local],
+                |row| Ok((row.get(0)?, row.get(1)?)),
+            )?;
+            let utc = utc.ok_or_else(|| AppError::Validation(format!("Invalid time: {}", local)))?;
+            if due {
+                return Err(AppError::Validation(format!("{} has already passed", local)));
+            }
+            Some(utc)
+        }
+    };
+    let reason = reason.map(|r| r.trim().to_string()).filter(|r| !r.is_empty());
+
+    let updated = conn.execute(
+        "UPDATE products SET unavailable_since = CURRENT_TIMESTAMP, unavailable_reason = ?1, unavailable_until = ?2
+         WHERE id = ?3",
+        rusqlite::params![reason, until, product_id],
+    )?;
+    if updated == 0 {
+        return Err(AppError::NotFound { entity: "product", id: product_id });
+    }
+
+    products::get_product(conn, product_id)
+}
+
+pub fn mark_product_available(conn: &Connection, product_id: i64) -> AppResult<Product> {
+    let updated = conn.execute(
+        "UPDATE products SET unavailable_since = NULL, unavailable_reason = NULL, unavailable_until = NULL
+         WHERE id = ?1",
+        [product_id],
+    )?;
+    if updated == 0 {
+        return Err(AppError::NotFound { entity: "product", id: product_id });
+    }
+
+    products::get_product(conn, product_id)
+}
+
+/// Fail with `ProductUnavailable` if the product, or an ingredient of its
+/// recipe, is 86'd.
+pub(crate) fn ensure_available(conn: &Connection, product_id: i64) -> AppResult<()> {
+    let unavailable = conn
+        .query_row(
+            "SELECT product_id, name, unavailable_reason, unavailable_until FROM unavailable_products
+             WHERE product_id = ?1
+                OR product_id IN (SELECT component_id FROM recipe_components WHERE product_id = ?1)
+             ORDER BY product_id = ?1 DESC
+             LIMIT 1",
+            [product_id],
+            |row| {
+                Ok(AppError::ProductUnavailable {
+                    product_id: row.get(0)?,
+                    product_name: row.get(1)?,
+                    reason: row.get(2)?,
+                    until: row.get(3)?,
+                })
+            },
+        )
+        .optional()?;
+
+    match unavailable {
+        Some(error) => Err(error),
+        None => Ok(()),
+    }
+}
+
+/// Bring back products whose restore time has passed. Returns them so the
+/// change can be announced.
+pub fn restore_due_availability(conn: &Connection) -> AppResult<Vec<Product>> {
+    let restored: Vec<i64> = conn
+        .prepare(
+            "UPDATE products SET unavailable_since = NULL, unavailable_reason = NULL, unavailable_until = NULL
+             WHERE unavailable_until <= CURRENT_TIMESTAMP
+             RETURNING id",
+        )?
+        .query_map([], |row| row.get(0))?
+        .collect::<Result<Vec<_>, _>>()?;
+
+    restored.into_iter().map(|id| products::get_product(conn, id)).collect()
+}
@@ -0,0 +1,490 @@
+use crate::db::begin_write;
+use crate::error::{AppError, AppResult};
+use crate::models::{CreateProduct, ImportReport, ImportedRow, RejectedRow, Unit, UpdateProduct};
+use crate::money::{Money, MINOR_PER_MAJOR};
+use crate::services::{categories, codes, products};
+use rusqlite::{Connection, OptionalExtension};
+use std::collections::HashMap;
+use std::fs;
+use std::path::Path;
+
+/// Columns of an import or export file. Prices are in major units ("4.50"),
+/// quantities and thresholds in the unit's base, and `sku` holds the
+/// product's codes separated by spaces.
+const COLUMNS: [&str; 9] = [
+    "name",
+    "sku",
+    "category",
+    "price",
+    "cost_price",
+    "quantity",
+    "low_stock_threshold",
+    "unit",
+    "serving_size",
+];
+
+#[derive(Clone, Copy)]
+enum Format {
+    Csv,
+    Json,
+}
+
+impl Format {
+    fn from_path(path: &Path) -> AppResult<Self> {
+        match path.extension().and_then(|ext| ext.to_str()).map(str::to_ascii_lowercase).as_deref() {
+            Some("csv") => Ok(Format::Csv),
+            Some("json") => Ok(Format::Json),
+            _ => Err(AppError::Validation(format!(
+                "{} is not a .csv or .json file",
+                path.display()
+            ))),
+        }
+    }
+}
+
+/// A file row as column name to value, or why it could not be read.
+type Record = (usize, Result<HashMap<String, String>, String>);
+
+// ============ READING ============
+
+/// Split CSV text into rows, each with the line it starts on. Fields may be
+/// quoted, with `""` for a quote and line breaks allowed inside quotes.
+fn parse_csv(text: &str) -> AppResult<Vec<(usize, Vec<String>)>> {
+    let mut rows = Vec::new();
+    let mut row = Vec::new();
+    let mut field = String::new();
+    let (mut line, mut start) = (1, 1);
+    let mut quoted = false;
+    let mut chars = text.trim_start_matches('\u{feff}').chars().peekable();
+
+    while let Some(c) = chars.next() {
+        if quoted {
+            match c {
+                '"' if chars.peek() == Some(&'"') => {
+                    chars.next();
+                    field.push('"');
+                }
+                '"' => quoted = false,
+                '\n' => {
+                    line += 1;
+                    field.push(c);
+                }
+                _ => field.push(c),
+            }
+            continue;
+        }
+
+        match c {
+            '"' if field.is_empty() => quoted = true,
+            ',' => row.push(std::mem::take(&mut field)),
+            '\r' => {}
+            '\n' => {
+                row.push(std::mem::take(&mut field));
+                if row.iter().any(|f| !f.trim().is_empty()) {
+                    rows.push((start, std::mem::take(&mut row)));
+                }
+                row.clear();
+                line += 1;
+                start = line;
+            }
+            _ => field.push(c),
+        }
+    }
+
+    if quoted {
+        return Err(AppError::Validation(format!("Line {}: a quoted field is never closed", start)));
+    }
+    row.push(field);
+    if row.iter().any(|f| !f.trim().is_empty()) {
+        rows.push((start, row));
+    }
+
+    Ok(rows)
+}
+
+fn check_column(column: &str) -> Result<(), String> {
+    if COLUMNS.contains(&column) {
+        Ok(())
+    } else {
+        Err(format!("unknown column \"{}\"", column))
+    }
+}
+
+fn csv_records(text: &str) -> AppResult<Vec<Record>> {
+    let mut rows = parse_csv(text)?.into_iter();
+    let Some((_, header)) = rows.next() else {
+        return Ok(Vec::new());
+    };
+
+    let header: Vec<String> = header.iter().map(|h| h.trim().to_ascii_lowercase()).collect();
+    for column in &header {
+        check_column(column).map_err(|e| AppError::Validation(format!("Line 1: {}", e)))?;
+    }
+
+    Ok(rows
+        .map(|(line, fields)| {
+            if fields.len() > header.len() {
+                return (line, Err(format!("{} fields for {} columns", fields.len(), header.len())));
+            }
+            let record = header.iter().cloned().zip(fields.into_iter().map(|f| f.trim().to_string())).collect();
+            (line, Ok(record))
+        })
+        .collect())
+}
+
+fn json_records(text: &str) -> AppResult<Vec<Record>> {
+    let entries: Vec<serde_json::Map<String, serde_json::Value>> = serde_json::from_str(text)
+        .map_err(|e| AppError::Validation(format!("Expected an array of products: {}", e)))?;
+
+    Ok(entries
+        .into_iter()
+        .enumerate()
+        .map(|(index, entry)| {
+            let record = entry
+                .into_iter()
+                .filter(|(_, value)| !value.is_null())
+                .map(|(key, value)| {
+                    check_column(&key)?;
+                    let value = match value {
+                        serde_json::Value::String(s) => s.trim().to_string(),
+                        serde_json::Value::Number(n) => n.to_string(),
+                        _ => return Err(format!("{} must be text or a number", key)),
+                    };
+                    Ok((key, value))
+                })
+                .collect();
+            (index + 1, record)
+        })
+        .collect())
+}
+
+/// A price in major units with at most two decimals, e.g. "4", "4.5" or "4.50".
+fn parse_money(text: &str) -> Option<Money> {
+    let (major, minor) = text.split_once('.').unwrap_or((text, ""));
+    if major.is_empty() && minor.is_empty() || minor.len() > 2 {
+        return None;
+    }
+    if !major.chars().chain(minor.chars()).all(|c| c.is_ascii_digit()) {
+        return None;
+    }
+
+    let major: i64 = if major.is_empty() { 0 } else { major.parse().ok()? };
+    let minor: i64 = format!("{:0<2}", minor).parse().ok()?;
+    Some(Money::from_minor(major.checked_mul(MINOR_PER_MAJOR)? + minor))
+}
+
+fn format_money(money: Money) -> String {
+    format!("{}.{:02}", money.minor_units / MINOR_PER_MAJOR, money.minor_units % MINOR_PER_MAJOR)
+}
+
+/// A row's values; anything left out keeps the product's current value.
+struct ImportRow {
+    name: String,
+    codes: Vec<String>,
+    category: Option<String>,
+    price: Option<Money>,
+    cost_price: Option<Money>,
+    quantity: Option<i32>,
+    low_stock_threshold: Option<i32>,
+    unit: Option<Unit>,
+    serving_size: Option<i32>,
+}
+
+fn parse_row(mut record: HashMap<String, String>) -> Result<ImportRow, String> {
+    let mut take = |column: &str| record.remove(column).filter(|value| !value.is_empty());
+
+    let money = |column: &str, value: Option<String>| {
+        value
+            .map(|v| parse_money(&v).ok_or_else(|| format!("{} \"{}\" is not a price", column, v)))
+            .transpose()
+    };
+    let number = |column: &str, value: Option<String>| {
+        value
+            .map(|v| v.parse::<i32>().map_err(|_| format!("{} \"{}\" is not a whole number", column, v)))
+            .transpose()
+    };
+
+    let name = take("name").ok_or_else(|| "name is required".to_string())?;
+    let codes = take("sku")
+        .map(|sku| sku.split_whitespace().map(str::to_string).collect())
+        .unwrap_or_default();
+    let category = take("category");
+    let price = money("price", take("price"))?;
+    let cost_price = money("cost_price", take("cost_price"))?;
+    let quantity = number("quantity", take("quantity"))?;
+    let low_stock_threshold = number("low_stock_threshold", take("low_stock_threshold"))?;
+    let unit = take("unit")
+        .map(|u| Unit::parse(&u.to_ascii_lowercase()).ok_or_else(|| format!("unknown unit \"{}\"", u)))
+        .transpose()?;
+    let serving_size = number("serving_size", take("serving_size"))?;
+
+    Ok(ImportRow {
+        name,
+        codes,
+        category,
+        price,
+        cost_price,
+        quantity,
+        low_stock_threshold,
+        unit,
+        serving_size,
+    })
+}
+
+// ============ IMPORT ============
+
+/// The product a row refers to: the owner of its SKU, otherwise the product
+/// with the same name.
+fn match_product(conn: &Connection, row: &ImportRow) -> AppResult<Option<i64>> {
+    let mut matched = None;
+    for code in &row.codes {
+        match codes::resolve_code(conn, code) {
+            Ok((_, Some(_))) => {
+                return Err(AppError::Validation(format!("SKU {} belongs to a variant", code)));
+            }
+            Ok((product_id, None)) if matched.is_some_and(|id| id != product_id) => {
+                return Err(AppError::Validation("SKUs belong to different products".to_string()));
+            }
+            Ok((product_id, None)) => matched = Some(product_id),
+            Err(AppError::UnknownCode { .. }) => {}
+            Err(e) => return Err(e),
+        }
+    }
+    if matched.is_some() {
+        return Ok(matched);
+    }
+
+    let ids: Vec<i64> = conn
+        .prepare("SELECT id FROM products WHERE name = ?1 COLLATE NOCASE ORDER BY id")?
+        .query_map([&row.name], |row| row.get(0))?
+        .collect::<Result<Vec<_>, _>>()?;
+    if ids.len() > 1 {
+        return Err(AppError::Validation(format!(
+            "{} products are named {}; add a SKU to pick one",
+            ids.len(),
+            row.name
+        )));
+    }
+
+    Ok(ids.first().copied())
+}
+
+/// The category with this name, created if there is none. Returns whether it
+/// was created.
+fn find_or_create_category(conn: &Connection, name: &str) -> AppResult<(i64, bool)> {
+    let existing: Option<i64> = conn
+        .query_row(
+            "SELECT id FROM categories WHERE name = ?1 COLLATE NOCASE AND archived_at IS NULL ORDER BY id",
+            [name],
+            |row| row.get(0),
+        )
+        .optional()?;
+
+    match existing {
+        Some(id) => Ok((id, false)),
+        None => Ok((categories::create_category(conn, name.to_string())?.id, true)),
+    }
+}
+
+/// Create or update the product for one row. Returns whether it was created
+/// and the category it created, if any.
+fn apply_row(conn: &Connection, row: ImportRow, staff_id: Option<i64>) -> AppResult<(bool, Option<String>)> {
+    let product_id = match_product(conn, &row)?;
+
+    let (category_id, new_category) = match row.category.as_deref() {
+        Some(name) => {
+            let (id, created) = find_or_create_category(conn, name)?;
+            (Some(id), created.then(|| name.to_string()))
+        }
+        None => (None, None),
+    };
+
+    match product_id {
+        Some(id) => {
+            let current = products::get_product(conn, id)?;
+            products::apply_update(
+                conn,
+                &UpdateProduct {
+                    id,
+                    name: row.name,
+                    price: row.price.unwrap_or(current.price),
+                    quantity: row.quantity.unwrap_or(current.quantity),
+                    category_id: category_id.or(current.category_id),
+                    low_stock_threshold: row.low_stock_threshold.unwrap_or(current.low_stock_threshold),
+                    unit: row.unit,
+                    serving_size: row.serving_size,
+                    cost_price: row.cost_price,
+                    aliases: None,
+                    staff_id,
+                },
+            )?;
+            for code in row.codes.iter().filter(|code| !current.codes.contains(code)) {
+                codes::insert_code(conn, id, None, code)?;
+            }
+            Ok((false, new_category))
+        }
+        None => {
+            let price = row
+                .price
+                .ok_or_else(|| AppError::Validation("price is required for a new product".to_string()))?;
+            products::insert_product(
+                conn,
+                &CreateProduct {
+                    name: row.name,
+                    price,
+                    quantity: row.quantity.unwrap_or(0),
+                    category_id,
+                    low_stock_threshold: row.low_stock_threshold,
+                    staff_id,
+                    unit: row.unit.unwrap_or_default(),
+                    serving_size: row.serving_size,
+                    codes: row.codes,
+                    aliases: Vec::new(),
+                    cost_price: row.cost_price,
+                },
+            )?;
+            Ok((true, new_category))
+        }
+    }
+}
+
+fn error_message(error: AppError) -> String {
+    match error {
+        AppError::Validation(message) => message,
+        other => other.to_string(),
+    }
+}
+
+/// Import products from a .csv or .json file, matching existing products by
+/// SKU or name and creating missing categories. Every row is tried, and the
+/// import is only kept if none was rejected and this is not a dry run.
+pub fn import_products(
+    conn: &mut Connection,
+    path: &Path,
+    dry_run: bool,
+    staff_id: Option<i64>,
+) -> AppResult<ImportReport> {
+    let format = Format::from_path(path)?;
+    let text = fs::read_to_string(path)
+        .map_err(|e| AppError::Validation(format!("Could not read {}: {}", path.display(), e)))?;
+    let records = match format {
+        Format::Csv => csv_records(&text)?,
+        Format::Json => json_records(&text)?,
+    };
+
+    let mut report = ImportReport {
+        dry_run,
+        applied: false,
+        created: Vec::new(),
+        updated: Vec::new(),
+        rejected: Vec::new(),
+        categories_created: Vec::new(),
+    };
+
+    let mut tx = begin_write(conn)?;
+
+    for (line, record) in records {
+        let row = match record.and_then(parse_row) {
+            Ok(row) => row,
+            Err(message) => {
+                report.rejected.push(RejectedRow { line, message });
+                continue;
+            }
+        };
+        let name = row.name.clone();
+
+        // A failed row is undone on its own so the rest still get checked
+        let savepoint = tx.savepoint()?;
+        match apply_row(&savepoint, row, staff_id) {
+            Ok((created, new_category)) => {
+                savepoint.commit()?;
+                let imported = ImportedRow { line, name };
+                if created {
+                    report.created.push(imported);
+                } else {
+                    report.updated.push(imported);
+                }
+                report.categories_created.extend(new_category);
+            }
+            Err(error) => report.rejected.push(RejectedRow {
+                line,
+                message: error_message(error),
+            }),
+        }
+    }
+
+    if !dry_run && report.rejected.is_empty() {
+        tx.commit()?;
+        report.applied = true;
+    }
+
+    Ok(report)
+}
+
+// ============ EXPORT ============
+
+fn csv_field(value: &str) -> String {
+    if value.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        value.to_string()
+    }
+}
+
+/// Write every product that is not archived to a .csv or .json file in the
+/// import layout. Returns how many were written.
+pub fn export_products(conn: &Connection, path: &Path) -> AppResult<usize> {
+    let format = Format::from_path(path)?;
+    let products = products::get_products(conn, false)?;
+
+    let rows: Vec<[String; 9]> = products
+        .iter()
+        .map(|p| {
+            [
+                p.name.clone(),
+                p.codes.join(" "),
+                p.category_name.clone().unwrap_or_default(),
+                format_money(p.price),
+                p.cost_price.map(format_money).unwrap_or_default(),
+                p.quantity.to_string(),
+                p.low_stock_threshold.to_string(),
+                p.unit.as_str().to_string(),
+                p.serving_size.to_string(),
+            ]
+        })
+        .collect();
+
+    let content = match format {
+        Format::Csv => {
+            let mut lines = vec![COLUMNS.join(",")];
+            lines.extend(rows.iter().map(|row| row.iter().map(|f| csv_field(f)).collect::<Vec<_>>().join(",")));
+            lines.join("\n") + "\n"
+        }
+        Format::Json => {
+            let entries: Vec<serde_json::Map<String, serde_json::Value>> = rows
+                .into_iter()
+                .map(|row| {
+                    COLUMNS
+                        .iter()
+                        .zip(row)
+                        .filter(|(_, value)| !value.is_empty())
+                        .map(|(column, value)| {
+                            let value = match value.parse::<i64>() {
+                                Ok(n) if !matches!(*column, "name" | "sku" | "category") => n.into(),
+                                _ => serde_json::Value::String(value),
+                            };
+                            (column.to_string(), value)
+                        })
+                        .collect()
+                })
+                .collect();
+            serde_json::to_string_pretty(&entries)
+                .map_err(|e| AppError::Validation(format!("Could not write products: {}", e)))?
+        }
+    };
+
+    fs::write(path, content)
+        .map_err(|e| AppError::Validation(format!("Could not write {}: {}", path.display(), e)))?;
+
+    Ok(products.len())
+}
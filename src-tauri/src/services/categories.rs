@@ -0,0 +1,275 @@
+use crate::db::{begin_write, referenced_by};
+use crate::error::{not_found, AppError, AppResult};
+use crate::models::{Category, UpdateCategory};
+use rusqlite::Connection;
+
+const CATEGORY_SELECT: &str =
+    "SELECT id, name, archived_at, parent_id, sort_order, color, icon, tax_rate_id FROM categories";
+
+/// Sits after the last of the parent's children.
+const NEXT_SORT_ORDER: &str =
+    "(SELECT COALESCE(MAX(sort_order) + 1, 0) FROM categories WHERE parent_id IS ?1)";
+
+fn category_from_row(row: &rusqlite::Row) -> rusqlite::Result<Category> {
+    Ok(Category {
+        id: row.get(0)?,
+        name: row.get(1)?,
+        archived_at: row.get(2)?,
+        parent_id: row.get(3)?,
+        sort_order: row.get(4)?,
+        color: row.get(5)?,
+        icon: row.get(6)?,
+        tax_rate_id: row.get(7)?,
+    })
+}
+
+/// Categories in use, or every category for the back office, depth first in
+/// display order so each parent comes right before its children. A category
+/// whose parent is not listed is shown at the top level.
+pub fn get_categories(conn: &Connection, include_archived: bool) -> AppResult<Vec<Category>> {
+    let filter = if include_archived { "" } else { "WHERE archived_at IS NULL" };
+    let mut stmt = conn.prepare(&format!("{} {} ORDER BY sort_order, name", CATEGORY_SELECT, filter))?;
+
+    let mut remaining = stmt
+        .query_map([], category_from_row)?
+        .collect::<Result<Vec<_>, _>>()?;
+
+    let listed: Vec<i64> = remaining.iter().map(|c| c.id).collect();
+    let mut categories = Vec::with_capacity(remaining.len());
+    // Parents still to visit, deepest last
+    let mut stack: Vec<Option<i64>> = vec![None];
+    while let Some(&parent) = stack.last() {
+        let next = remaining.iter().position(|c| match c.parent_id {
+            Some(id) if listed.contains(&id) => Some(id) == parent,
+            _ => parent.is_none(),
+        });
+        match next {
+            Some(index) => {
+                let category = remaining.remove(index);
+                stack.push(Some(category.id));
+                categories.push(category);
+            }
+            None => {
+                stack.pop();
+            }
+        }
+    }
+
+    Ok(categories)
+}
+
+pub(crate) fn get_category(conn: &Connection, id: i64) -> AppResult<Category> {
+    conn.query_row(&format!("{} WHERE id = ?1", CATEGORY_SELECT), [id], category_from_row)
+        .map_err(not_found("category", id))
+}
+
+/// A new category goes last at the top level.
+pub fn create_category(conn: &Connection, name: String) -> AppResult<Category> {
+    conn.execute(
+        &format!("INSERT INTO categories (name, sort_order) VALUES (?2, {})", NEXT_SORT_ORDER),
+        rusqlite::params![None::<i64>, name],
+    )?;
+
+    get_category(conn, conn.last_insert_rowid())
+}
+
+fn normalize_color(color: Option<String>) -> AppResult<Option<String>> {
+    let color = match color.map(|c| c.trim().to_lowercase()).filter(|c| !c.is_empty()) {
+        Some(color) => color,
+        None => return Ok(None),
+    };
+    let hex = color.strip_prefix('#').unwrap_or_default();
+    if hex.len() != 6 || !hex.chars().all(|c| c.is_ascii_hexdigit()) {
+        return Err(AppError::Validation(format!("Invalid color: {} (expected #rrggbb)", color)));
+    }
+    Ok(Some(color))
+}
+
+/// Rename a category, move it under another parent, or change how it looks.
+/// A category moved to a new parent goes last among its new siblings.
+pub fn update_category(conn: &mut Connection, category: UpdateCategory) -> AppResult<Category> {
+    let name = category.name.trim().to_string();
+    if name.is_empty() {
+        return Err(AppError::Validation("A category needs a name".to_string()));
+    }
+    let color = normalize_color(category.color)?;
+    let icon = category.icon.map(|i| i.trim().to_string()).filter(|i| !i.is_empty());
+
+    let tx = begin_write(conn)?;
+    let current = get_category(&tx, category.id)?;
+
+    if let Some(parent_id) = category.parent_id {
+        let parent = get_category(&tx, parent_id)?;
+        let inside: bool = tx.query_row(
+            "WITH RECURSIVE ancestors(id) AS (
+                 SELECT ?1
+                 UNION
+                 SELECT c.parent_id FROM categories c JOIN ancestors a ON c.id = a.id WHERE c.parent_id IS NOT NULL
+             )
+             SELECT EXISTS (SELECT 1 FROM ancestors WHERE id = ?2)",
+            [parent_id, category.id],
+            |row| row.get(0),
+        )?;
+        if inside {
+            return Err(AppError::Validation(format!(
+                "{} cannot go under {}: it would contain itself",
+                current.name, parent.name
+            )));
+        }
+    }
+
+    tx.execute(
+        "UPDATE categories SET name = ?1, color = ?2, icon = ?3 WHERE id = ?4",
+        rusqlite::params![name, color, icon, category.id],
+    )?;
+    if current.parent_id != category.parent_id {
+        tx.execute(
+            &format!("UPDATE categories SET parent_id = ?1, sort_order = {} WHERE id = ?2", NEXT_SORT_ORDER),
+            rusqlite::params![category.parent_id, category.id],
+        )?;
+    }
+
+    tx.commit()?;
+    get_category(conn, category.id)
+}
+
+/// Put a parent's children, or the top-level categories when `parent_id` is
+/// `None`, in the given order. Children left out follow in their old order.
+pub fn reorder_categories(
+    conn: &mut Connection,
+    parent_id: Option<i64>,
+    category_ids: Vec<i64>,
+) -> AppResult<Vec<Category>> {
+    let tx = begin_write(conn)?;
+
+    let siblings: Vec<i64> = tx
+        .prepare("SELECT id FROM categories WHERE parent_id IS ?1 ORDER BY sort_order, name")?
+        .query_map([parent_id], |row| row.get(0))?
+        .collect::<Result<Vec<_>, _>>()?;
+
+    for (index, id) in category_ids.iter().enumerate() {
+        if !siblings.contains(id) {
+            let category = get_category(&tx, *id)?;
+            return Err(AppError::Validation(format!("{} is not in that part of the tree", category.name)));
+        }
+        if category_ids[..index].contains(id) {
+            return Err(AppError::Validation(format!("Category {} is listed twice", id)));
+        }
+    }
+
+    let rest = siblings.iter().filter(|id| !category_ids.contains(id));
+    for (sort_order, id) in category_ids.iter().chain(rest).enumerate() {
+        tx.execute(
+            "UPDATE categories SET sort_order = ?1 WHERE id = ?2",
+            rusqlite::params![sort_order as i64, id],
+        )?;
+    }
+
+    tx.commit()?;
+    get_categories(conn, true)
+}
+
+/// Categories from the top of the tree down to `id`, for rolling figures up
+/// to an ancestor. Unknown ids give an empty path.
+pub(crate) fn path_to(categories: &[Category], id: i64) -> Vec<&Category> {
+    let mut path = Vec::new();
+    let mut next = Some(id);
+    while let Some(category) = next.and_then(|id| categories.iter().find(|c| c.id == id)) {
+        path.push(category);
+        next = category.parent_id;
+    }
+    path.reverse();
+    path
+}
+
+/// A category and its ancestors, nearest first, for settings a category
+/// passes down to everything under it.
+pub(crate) fn lineage(conn: &Connection, id: i64) -> AppResult<Vec<Category>> {
+    let mut stmt = conn.prepare(&format!(
+        "WITH RECURSIVE lineage (id, depth) AS (
+             SELECT ?1, 0
+             UNION ALL
+             SELECT c.parent_id, lineage.depth + 1
+             FROM categories c JOIN lineage ON c.id = lineage.id
+             WHERE c.parent_id IS NOT NULL
+         )
+         {} JOIN lineage USING (id)
+         ORDER BY lineage.depth",
+        CATEGORY_SELECT
+    ))?;
+
+    let lineage = stmt
+        .query_map([id], category_from_row)?
+        .collect::<Result<Vec<_>, _>>()?;
+
+    Ok(lineage)
+}
+
+/// Hide a category and take its products off sale with it. Its subcategories
+/// have to be archived or moved first.
+pub fn archive_category(conn: &Connection, id: i64) -> AppResult<Category> {
+    let children: i64 = conn.query_row(
+        "SELECT COUNT(*) FROM categories WHERE parent_id = ?1 AND archived_at IS NULL",
+        [id],
+        |row| row.get(0),
+    )?;
+    if children > 0 {
+        let category = get_category(conn, id)?;
+        return Err(AppError::Validation(format!(
+            "Archive or move the subcategories of {} first",
+            category.name
+        )));
+    }
+
+    let updated = conn.execute(
+        "UPDATE categories SET archived_at = COALESCE(archived_at, CURRENT_TIMESTAMP) WHERE id = ?1",
+        [id],
+    )?;
+    if updated == 0 {
+        return Err(AppError::NotFound { entity: "category", id });
+    }
+
+    get_category(conn, id)
+}
+
+pub fn restore_category(conn: &Connection, id: i64) -> AppResult<Category> {
+    let updated = conn.execute("UPDATE categories SET archived_at = NULL WHERE id = ?1", [id])?;
+    if updated == 0 {
+        return Err(AppError::NotFound { entity: "category", id });
+    }
+
+    get_category(conn, id)
+}
+
+/// Delete an archived category that no product or pricing rule refers to.
+pub fn purge_category(conn: &mut Connection, id: i64) -> AppResult<()> {
+    let tx = conn.transaction()?;
+
+    let category = get_category(&tx, id)?;
+    if category.archived_at.is_none() {
+        return Err(AppError::Validation(format!("Archive {} before purging it", category.name)));
+    }
+
+    let references = referenced_by(
+        &tx,
+        id,
+        &[
+            ("products", "category_id", "products"),
+            ("pricing_rules", "category_id", "pricing rules"),
+            ("categories", "parent_id", "subcategories"),
+        ],
+    )?;
+    if !references.is_empty() {
+        return Err(AppError::Validation(format!(
+            "Cannot purge {}: still referenced by {}",
+            category.name,
+            references.join(", ")
+        )));
+    }
+
+    tx.execute("DELETE FROM modifier_group_links WHERE category_id = ?1", [id])?;
+    tx.execute("DELETE FROM categories WHERE id = ?1", [id])?;
+
+    tx.commit()?;
+    Ok(())
+}
@@ -0,0 +1,185 @@
+use crate::error::{not_found, AppError, AppResult};
+use crate::models::{CodeMatch, ProductCode, Unit};
+use crate::services::{products, variants};
+use rusqlite::{Connection, OptionalExtension};
+
+fn code_from_row(row: &rusqlite::Row) -> rusqlite::Result<ProductCode> {
+    Ok(ProductCode {
+        id: row.get(0)?,
+        code: row.get(1)?,
+        product_id: row.get(2)?,
+        variant_id: row.get(3)?,
+        created_at: row.get(4)?,
+        pack_quantity: row.get(5)?,
+    })
+}
+
+/// Codes from a newline-separated `GROUP_CONCAT`.
+pub(crate) fn split_codes(codes: Option<String>) -> Vec<String> {
+    codes
+        .map(|c| c.split('\n').map(str::to_string).collect())
+        .unwrap_or_default()
+}
+
+/// Codes of a product, including those of its variants.
+pub fn get_product_codes(conn: &Connection, product_id: i64) -> AppResult<Vec<ProductCode>> {
+    let mut stmt = conn.prepare(
+        "SELECT id, code, product_id, variant_id, created_at, pack_quantity
+         FROM product_codes WHERE product_id = ?1
+         ORDER BY variant_id IS NOT NULL, variant_id, code",
+    )?;
+
+    let codes = stmt
+        .query_map([product_id], code_from_row)?
+        .collect::<Result<Vec<_>, _>>()?;
+
+    Ok(codes)
+}
+
+/// Scanners pad with whitespace; codes themselves never contain any.
+fn normalize_code(code: &str) -> AppResult<String> {
+    let code = code.trim();
+    if code.is_empty() || code.chars().any(char::is_whitespace) {
+        return Err(AppError::Validation(format!("Invalid code: {:?}", code)));
+    }
+
+    Ok(code.to_string())
+}
+
+/// Attach a code to a product, or to one of its variants. Codes are unique
+/// across all products and variants.
+pub(crate) fn insert_code(
+    conn: &Connection,
+    product_id: i64,
+    variant_id: Option<i64>,
+    code: &str,
+) -> AppResult<i64> {
+    let code = normalize_code(code)?;
+
+    let owner: Option<String> = conn
+        .query_row(
+            "SELECT p.name || COALESCE(' (' || v.name || ')', '')
+             FROM product_codes c
+             JOIN products p ON c.product_id = p.id
+             LEFT JOIN product_variants v ON c.variant_id = v.id
+             WHERE c.code = ?1",
+            [&code],
+            |row| row.get(0),
+        )
+        .optional()?;
+    if let Some(owner) = owner {
+        return Err(AppError::Validation(format!(
+            "Code {} is already used by {}",
+            code, owner
+        )));
+    }
+
+    conn.execute(
+        "INSERT INTO product_codes (code, product_id, variant_id) VALUES (?1, ?2, ?3)",
+        rusqlite::params![code, product_id, variant_id],
+    )?;
+
+    Ok(conn.last_insert_rowid())
+}
+
+/// Attach a code. A product code can stand for a pack of `pack_quantity`
+/// base units, received whole when scanned at delivery; a variant code
+/// always receives the variant's deduction.
+pub fn add_product_code(
+    conn: &Connection,
+    product_id: i64,
+    variant_id: Option<i64>,
+    code: String,
+    pack_quantity: Option<i32>,
+) -> AppResult<ProductCode> {
+    match (variant_id, pack_quantity) {
+        (_, Some(pack)) if pack <= 0 => {
+            return Err(AppError::Validation(format!("Invalid pack quantity {}", pack)));
+        }
+        (Some(_), Some(_)) => {
+            return Err(AppError::Validation(
+                "A variant code receives the variant's own deduction".to_string(),
+            ));
+        }
+        (Some(variant_id), None) => {
+            variants::variant_price(conn, product_id, variant_id)?;
+        }
+        (None, _) => {
+            conn.query_row("SELECT id FROM products WHERE id = ?1", [product_id], |row| row.get::<_, i64>(0))
+                .map_err(not_found("product", product_id))?;
+        }
+    }
+
+    let id = insert_code(conn, product_id, variant_id, &code)?;
+    conn.execute(
+        "UPDATE product_codes SET pack_quantity = ?1 WHERE id = ?2",
+        rusqlite::params![pack_quantity, id],
+    )?;
+
+    conn.query_row(
+        "SELECT id, code, product_id, variant_id, created_at, pack_quantity FROM product_codes WHERE id = ?1",
+        [id],
+        code_from_row,
+    )
+    .map_err(AppError::from)
+}
+
+pub fn remove_product_code(conn: &Connection, code: String) -> AppResult<()> {
+    let removed = conn.execute("DELETE FROM product_codes WHERE code = ?1", [code.trim()])?;
+    if removed == 0 {
+        return Err(AppError::UnknownCode { code });
+    }
+
+    Ok(())
+}
+
+/// Base units one scan of a code brings in at delivery: the variant's
+/// deduction, the pack a product code stands for, or a single piece.
+/// Measured products are not received by the pour, so their codes need a
+/// pack.
+pub(crate) fn pack_quantity(conn: &Connection, code: &str) -> AppResult<i32> {
+    let code = code.trim();
+    let (variant_id, pack, unit): (Option<i64>, Option<i32>, Unit) = conn
+        .query_row(
+            "SELECT c.variant_id, c.pack_quantity, p.unit
+             FROM product_codes c JOIN products p ON c.product_id = p.id
+             WHERE c.code = ?1",
+            [code],
+            |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?)),
+        )
+        .optional()?
+        .ok_or_else(|| AppError::UnknownCode { code: code.to_string() })?;
+
+    match (variant_id, pack, unit) {
+        (Some(variant_id), _, _) => variants::deduction(conn, variant_id),
+        (None, Some(pack), _) => Ok(pack),
+        (None, None, Unit::Piece) => Ok(1),
+        (None, None, unit) => Err(AppError::Validation(format!(
+            "Code {} has no pack size, so it cannot be received in {}",
+            code,
+            unit.as_str()
+        ))),
+    }
+}
+
+/// Product and variant a code sells. Unknown codes return `UnknownCode`.
+pub(crate) fn resolve_code(conn: &Connection, code: &str) -> AppResult<(i64, Option<i64>)> {
+    let code = code.trim();
+    conn.query_row(
+        "SELECT product_id, variant_id FROM product_codes WHERE code = ?1",
+        [code],
+        |row| Ok((row.get(0)?, row.get(1)?)),
+    )
+    .optional()?
+    .ok_or_else(|| AppError::UnknownCode { code: code.to_string() })
+}
+
+pub fn find_product_by_code(conn: &Connection, code: String) -> AppResult<CodeMatch> {
+    let (product_id, variant_id) = resolve_code(conn, &code)?;
+
+    Ok(CodeMatch {
+        code: code.trim().to_string(),
+        product: products::get_product(conn, product_id)?,
+        variant: variant_id.map(|id| variants::get_variant(conn, id)).transpose()?,
+    })
+}
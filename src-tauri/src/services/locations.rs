@@ -0,0 +1,308 @@
+use crate::db::begin_write;
+use crate::error::{not_found, AppError, AppResult};
+use crate::models::{
+    CreateStockMovement, LocationLowStock, LocationQuantity, StockLocation, StockReason, TransferStock,
+};
+use crate::services::{lots, recipes, stock};
+use rusqlite::Connection;
+
+const LOCATION_SELECT: &str = "SELECT id, name, is_default FROM stock_locations";
+
+/// What the default location holds: whatever the others do not.
+const DEFAULT_QUANTITY: &str =
+    "p.quantity - (SELECT COALESCE(SUM(quantity), 0) FROM location_stock WHERE product_id = p.id)";
+
+/// SQL for how much of product `p` a location holds.
+pub(crate) fn quantity_at(location: &StockLocation) -> String {
+    if location.is_default {
+        DEFAULT_QUANTITY.to_string()
+    } else {
+        format!(
+            "COALESCE((SELECT quantity FROM location_stock WHERE location_id = {} AND product_id = p.id), 0)",
+            location.id
+        )
+    }
+}
+
+fn location_from_row(row: &rusqlite::Row) -> rusqlite::Result<StockLocation> {
+    Ok(StockLocation {
+        id: row.get(0)?,
+        name: row.get(1)?,
+        is_default: row.get(2)?,
+    })
+}
+
+/// The default location first, then the rest by name.
+pub fn get_stock_locations(conn: &Connection) -> AppResult<Vec<StockLocation>> {
+    let mut stmt = conn.prepare(&format!("{} ORDER BY is_default DESC, name", LOCATION_SELECT))?;
+
+    let locations = stmt
+        .query_map([], location_from_row)?
+        .collect::<Result<Vec<_>, _>>()?;
+
+    Ok(locations)
+}
+
+pub fn get_location(conn: &Connection, id: i64) -> AppResult<StockLocation> {
+    conn.query_row(&format!("{} WHERE id = ?1", LOCATION_SELECT), [id], location_from_row)
+        .map_err(not_found("stock location", id))
+}
+
+pub fn create_stock_location(conn: &Connection, name: String) -> AppResult<StockLocation> {
+    let name = name.trim();
+    if name.is_empty() {
+        return Err(AppError::Validation("A location needs a name".to_string()));
+    }
+
+    conn.execute("INSERT INTO stock_locations (name) VALUES (?1)", [name])?;
+    get_location(conn, conn.last_insert_rowid())
+}
+
+fn default_location(conn: &Connection) -> AppResult<StockLocation> {
+    Ok(conn.query_row(&format!("{} WHERE is_default = 1", LOCATION_SELECT), [], location_from_row)?)
+}
+
+/// `location_id`, or the default location when `None`.
+pub(crate) fn location_or_default(conn: &Connection, location_id: Option<i64>) -> AppResult<StockLocation> {
+    match location_id {
+        Some(id) => get_location(conn, id),
+        None => default_location(conn),
+    }
+}
+
+/// The location whose stock is kept in `location_stock`, or `None` for the
+/// default location, whose stock is the product's remainder.
+pub(crate) fn stocked_apart(conn: &Connection, location_id: Option<i64>) -> AppResult<Option<i64>> {
+    match location_id {
+        None => Ok(None),
+        Some(id) => {
+            let location = get_location(conn, id)?;
+            Ok((!location.is_default).then_some(location.id))
+        }
+    }
+}
+
+/// Apply a signed change to the quantity held at a location other than the
+/// default, guarded like `stock::change_quantity`. Leaves the product total
+/// to the caller.
+fn shift(conn: &Connection, location_id: i64, product_id: i64, change: i32) -> AppResult<()> {
+    if change >= 0 {
+        conn.execute(
+            "INSERT INTO location_stock (location_id, product_id, quantity) VALUES (?1, ?2, ?3)
+             ON CONFLICT (location_id, product_id) DO UPDATE SET quantity = quantity + excluded.quantity",
+            rusqlite::params![location_id, product_id, change],
+        )?;
+        return Ok(());
+    }
+
+    let updated = conn.execute(
+        "UPDATE location_stock SET quantity = quantity + ?1
+         WHERE location_id = ?2 AND product_id = ?3 AND quantity >= -?1",
+        rusqlite::params![change, location_id, product_id],
+    )?;
+    if updated == 0 {
+        let (name, available): (String, i32) = conn
+            .query_row(
+                "SELECT p.name, COALESCE(ls.quantity, 0)
+                 FROM products p
+                 LEFT JOIN location_stock ls ON ls.product_id = p.id AND ls.location_id = ?2
+                 WHERE p.id = ?1",
+                [product_id, location_id],
+                |row| Ok((row.get(0)?, row.get(1)?)),
+            )
+            .map_err(not_found("product", product_id))?;
+        return Err(AppError::InsufficientStock {
+            product_id,
+            product_name: name,
+            requested: -change,
+            available,
+        });
+    }
+
+    Ok(())
+}
+
+/// Change a product's stock at a location other than the default.
+pub(crate) fn change_quantity(conn: &Connection, location_id: i64, product_id: i64, change: i32) -> AppResult<()> {
+    shift(conn, location_id, product_id, change)?;
+
+    let updated = conn.execute(
+        "UPDATE products SET quantity = quantity + ?1 WHERE id = ?2",
+        rusqlite::params![change, product_id],
+    )?;
+    if updated == 0 {
+        return Err(AppError::NotFound { entity: "product", id: product_id });
+    }
+
+    Ok(())
+}
+
+/// How much of a product each location holds, default location first.
+pub fn get_stock_by_location(conn: &Connection, product_id: i64) -> AppResult<Vec<LocationQuantity>> {
+    let mut stmt = conn.prepare(&format!(
+        "SELECT loc.id, loc.name, CASE WHEN loc.is_default THEN {} ELSE COALESCE(ls.quantity, 0) END
+         FROM stock_locations loc
+         JOIN products p ON p.id = ?1
+         LEFT JOIN location_stock ls ON ls.location_id = loc.id AND ls.product_id = p.id
+         ORDER BY loc.is_default DESC, loc.name",
+        DEFAULT_QUANTITY
+    ))?;
+
+    let quantities = stmt
+        .query_map([product_id], |row| {
+            Ok(LocationQuantity {
+                location_id: row.get(0)?,
+                location_name: row.get(1)?,
+                quantity: row.get(2)?,
+            })
+        })?
+        .collect::<Result<Vec<_>, _>>()?;
+
+    if quantities.is_empty() {
+        return Err(AppError::NotFound { entity: "product", id: product_id });
+    }
+    Ok(quantities)
+}
+
+/// Move stock between locations. The product's total is unchanged; the
+/// ledger gets a `transfer` out of one location and into the other, and the
+/// lots the stock came out of go with it.
+pub fn transfer_stock(conn: &mut Connection, transfer: TransferStock) -> AppResult<Vec<LocationQuantity>> {
+    if transfer.quantity <= 0 {
+        return Err(AppError::Validation("Transfer at least one unit".to_string()));
+    }
+    if transfer.from_location_id == transfer.to_location_id {
+        return Err(AppError::Validation("Choose two different locations".to_string()));
+    }
+
+    let tx = begin_write(conn)?;
+    recipes::ensure_stock_tracked(&tx, transfer.product_id)?;
+    let from = get_location(&tx, transfer.from_location_id)?;
+    let to = get_location(&tx, transfer.to_location_id)?;
+
+    if from.is_default {
+        let available = get_stock_by_location(&tx, transfer.product_id)?[0].quantity;
+        if available < transfer.quantity {
+            let product_name: String =
+                tx.query_row("SELECT name FROM products WHERE id = ?1", [transfer.product_id], |row| row.get(0))?;
+            return Err(AppError::InsufficientStock {
+                product_id: transfer.product_id,
+                product_name,
+                requested: transfer.quantity,
+                available,
+            });
+        }
+    } else {
+        shift(&tx, from.id, transfer.product_id, -transfer.quantity)?;
+    }
+    if !to.is_default {
+        shift(&tx, to.id, transfer.product_id, transfer.quantity)?;
+    }
+
+    let reference = format!("{} → {}", from.name, to.name);
+    let log = |location: &StockLocation, quantity_change| {
+        let movement = CreateStockMovement {
+            product_id: transfer.product_id,
+            quantity_change,
+            reason: StockReason::Transfer,
+            staff_id: transfer.staff_id,
+            reference: Some(reference.clone()),
+            note: transfer.note.clone(),
+            location_id: Some(location.id),
+        };
+        stock::log_movement(&tx, &movement, None)
+    };
+    let movement_ids = (log(&from, -transfer.quantity)?, log(&to, transfer.quantity)?);
+    lots::transfer(&tx, movement_ids, transfer.product_id, &from, &to, transfer.quantity)?;
+
+    tx.commit()?;
+    get_stock_by_location(conn, transfer.product_id)
+}
+
+/// Watch a product at a location: low stock there is flagged at or below
+/// `threshold`. `None` stops watching it. The default location uses the
+/// product's own threshold.
+pub fn set_location_threshold(
+    conn: &Connection,
+    location_id: i64,
+    product_id: i64,
+    threshold: Option<i32>,
+) -> AppResult<()> {
+    if threshold.is_some_and(|t| t < 0) {
+        return Err(AppError::Validation("Threshold cannot be negative".to_string()));
+    }
+
+    let location = get_location(conn, location_id)?;
+    let updated = if location.is_default {
+        let threshold = threshold.ok_or_else(|| {
+            AppError::Validation(format!("Every product is watched at {}", location.name))
+        })?;
+        conn.execute(
+            "UPDATE products SET low_stock_threshold = ?1 WHERE id = ?2",
+            [threshold as i64, product_id],
+        )?
+    } else {
+        conn.execute(
+            "INSERT INTO location_stock (location_id, product_id, low_stock_threshold)
+             SELECT ?1, id, ?3 FROM products WHERE id = ?2
+             ON CONFLICT (location_id, product_id) DO UPDATE SET low_stock_threshold = excluded.low_stock_threshold",
+            rusqlite::params![location_id, product_id, threshold],
+        )?
+    };
+    if updated == 0 {
+        return Err(AppError::NotFound { entity: "product", id: product_id });
+    }
+
+    Ok(())
+}
+
+/// Products at or below their threshold at a location, each with the other
+/// locations it could be restocked from.
+pub fn get_location_low_stock(conn: &Connection, location_id: i64) -> AppResult<Vec<LocationLowStock>> {
+    let location = get_location(conn, location_id)?;
+    let (quantity, threshold, join) = if location.is_default {
+        (DEFAULT_QUANTITY.to_string(), "p.low_stock_threshold", String::new())
+    } else {
+        (
+            "ls.quantity".to_string(),
+            "ls.low_stock_threshold",
+            format!("JOIN location_stock ls ON ls.product_id = p.id AND ls.location_id = {}", location.id),
+        )
+    };
+
+    let mut stmt = conn.prepare(&format!(
+        "SELECT p.id, p.name, {quantity}, {threshold}
+         FROM products p
+         LEFT JOIN categories c ON p.category_id = c.id
+         {join}
+         WHERE p.archived_at IS NULL AND c.archived_at IS NULL
+           AND p.id NOT IN (SELECT product_id FROM recipe_components)
+           AND {threshold} IS NOT NULL AND {quantity} <= {threshold}
+         ORDER BY {quantity}, p.name"
+    ))?;
+
+    let low = stmt
+        .query_map([], |row| {
+            Ok(LocationLowStock {
+                location_id: location.id,
+                product_id: row.get(0)?,
+                product_name: row.get(1)?,
+                quantity: row.get(2)?,
+                low_stock_threshold: row.get(3)?,
+                elsewhere: Vec::new(),
+            })
+        })?
+        .collect::<Result<Vec<_>, _>>()?;
+
+    low.into_iter()
+        .map(|mut line| {
+            let mut elsewhere: Vec<LocationQuantity> = get_stock_by_location(conn, line.product_id)?
+                .into_iter()
+                .filter(|q| q.location_id != location.id && q.quantity > 0)
+                .collect();
+            elsewhere.sort_by_key(|q| std::cmp::Reverse(q.quantity));
+            line.elsewhere = elsewhere;
+            Ok(line)
+        })
+        .collect()
+}
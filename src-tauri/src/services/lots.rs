@@ -0,0 +1,388 @@
+use crate::db::begin_write;
+use crate::error::{not_found, AppError, AppResult};
+use crate::models::{CreateStockLot, CreateStockMovement, KegYield, StockLocation, StockLot, StockReason};
+use crate::services::{locations, recipes, stock};
+use rusqlite::Connection;
+
+const LOT_SELECT: &str =
+    "SELECT l.id, l.product_id, p.name, l.keg_id, l.received_at, l.expires_on, l.initial_quantity, l.quantity,
+            l.tapped_at, l.retired_at, l.location_id, loc.name
+     FROM stock_lots l
+     JOIN products p ON l.product_id = p.id
+     JOIN stock_locations loc ON l.location_id = loc.id";
+
+/// First expired, first out; lots without an expiry go last, oldest first.
+const FEFO: &str = "l.expires_on IS NULL, l.expires_on, l.received_at, l.id";
+
+fn lot_from_row(row: &rusqlite::Row) -> rusqlite::Result<StockLot> {
+    Ok(StockLot {
+        id: row.get(0)?,
+        product_id: row.get(1)?,
+        product_name: row.get(2)?,
+        keg_id: row.get(3)?,
+        received_at: row.get(4)?,
+        expires_on: row.get(5)?,
+        initial_quantity: row.get(6)?,
+        quantity: row.get(7)?,
+        tapped_at: row.get(8)?,
+        retired_at: row.get(9)?,
+        location_id: row.get(10)?,
+        location_name: row.get(11)?,
+    })
+}
+
+pub fn get_lot(conn: &Connection, id: i64) -> AppResult<StockLot> {
+    conn.query_row(&format!("{} WHERE l.id = ?1", LOT_SELECT), [id], lot_from_row)
+        .map_err(not_found("stock lot", id))
+}
+
+/// Lots of a product still holding stock, plus tapped kegs not yet retired,
+/// in the order sales draw from them.
+pub fn get_stock_lots(conn: &Connection, product_id: i64) -> AppResult<Vec<StockLot>> {
+    let mut stmt = conn.prepare(&format!(
+        "{} WHERE l.product_id = ?1 AND l.retired_at IS NULL AND (l.quantity > 0 OR l.tapped_at IS NOT NULL)
+         ORDER BY {}",
+        LOT_SELECT, FEFO
+    ))?;
+
+    let lots = stmt
+        .query_map([product_id], lot_from_row)?
+        .collect::<Result<Vec<_>, _>>()?;
+
+    Ok(lots)
+}
+
+/// Lots with stock left that expire within `days` of today, including any
+/// already past their date, soonest first.
+pub fn get_expiring_lots(conn: &Connection, days: i32) -> AppResult<Vec<StockLot>> {
+    if days < 0 {
+        return Err(AppError::Validation("Days ahead cannot be negative".to_string()));
+    }
+
+    let mut stmt = conn.prepare(&format!(
+        "{} WHERE l.retired_at IS NULL AND l.quantity > 0
+           AND l.expires_on <= date('now', 'localtime', '+' || ?1 || ' days')
+         ORDER BY {}",
+        LOT_SELECT, FEFO
+    ))?;
+
+    let lots = stmt
+        .query_map([days], lot_from_row)?
+        .collect::<Result<Vec<_>, _>>()?;
+
+    Ok(lots)
+}
+
+/// Record a lot for stock that ledger entry `movement_id` has just added, at
+/// the location the entry added it to.
+pub(crate) fn insert_lot(
+    conn: &Connection,
+    movement_id: i64,
+    product_id: i64,
+    quantity: i32,
+    expires_on: Option<&str>,
+    keg_id: Option<&str>,
+) -> AppResult<i64> {
+    if let Some(expires_on) = expires_on {
+        let valid: bool = conn.query_row("SELECT date(?1) IS ?1", [expires_on], |row| row.get(0))?;
+        if !valid {
+            return Err(AppError::Validation(format!("Invalid expiry date: {} (expected YYYY-MM-DD)", expires_on)));
+        }
+    }
+
+    if let Some(keg_id) = keg_id {
+        let in_use: bool = conn.query_row(
+            "SELECT EXISTS (SELECT 1 FROM stock_lots WHERE keg_id = ?1 AND retired_at IS NULL)",
+            [keg_id],
+            |row| row.get(0),
+        )?;
+        if in_use {
+            return Err(AppError::Validation(format!("Keg {} has not been retired yet", keg_id)));
+        }
+    }
+
+    conn.execute(
+        "INSERT INTO stock_lots (product_id, keg_id, expires_on, initial_quantity, quantity, location_id)
+         SELECT ?1, ?2, ?3, ?4, 0, COALESCE(m.location_id, (SELECT id FROM stock_locations WHERE is_default = 1))
+         FROM stock_movements m WHERE m.id = ?5",
+        rusqlite::params![product_id, keg_id, expires_on, quantity, movement_id],
+    )?;
+    let lot_id = conn.last_insert_rowid();
+    move_lot(conn, movement_id, lot_id, quantity)?;
+
+    Ok(lot_id)
+}
+
+/// Receive stock as a lot, or as a keg when `keg_id` is given.
+pub fn receive_stock_lot(conn: &mut Connection, lot: CreateStockLot) -> AppResult<StockLot> {
+    if lot.quantity <= 0 {
+        return Err(AppError::Validation("A lot must hold some stock".to_string()));
+    }
+    let keg_id = lot.keg_id.as_deref().map(str::trim).filter(|k| !k.is_empty());
+
+    let tx = begin_write(conn)?;
+    recipes::ensure_stock_tracked(&tx, lot.product_id)?;
+
+    let movement = CreateStockMovement {
+        product_id: lot.product_id,
+        quantity_change: lot.quantity,
+        reason: StockReason::Delivery,
+        staff_id: lot.staff_id,
+        reference: lot.reference.clone(),
+        note: keg_id.map(|k| format!("Keg {}", k)),
+        location_id: None,
+    };
+    let movement_id = stock::apply_movement(&tx, &movement, None)?;
+    let lot_id = insert_lot(&tx, movement_id, lot.product_id, lot.quantity, lot.expires_on.as_deref(), keg_id)?;
+
+    tx.commit()?;
+    get_lot(conn, lot_id)
+}
+
+fn move_lot(conn: &Connection, movement_id: i64, lot_id: i64, change: i32) -> AppResult<()> {
+    conn.execute("UPDATE stock_lots SET quantity = quantity + ?1 WHERE id = ?2", [change as i64, lot_id])?;
+    conn.execute(
+        "INSERT INTO stock_lot_movements (movement_id, lot_id, quantity_change) VALUES (?1, ?2, ?3)",
+        rusqlite::params![movement_id, lot_id, change],
+    )?;
+    Ok(())
+}
+
+/// Split ledger entry `movement_id`, already applied to the product, across
+/// its lots. Decreases draw first-expired-first-out from the lots at the
+/// movement's location; returns go back to the lots the order drew from.
+/// Anything left over is untracked stock.
+pub(crate) fn allocate(
+    conn: &Connection,
+    movement_id: i64,
+    movement: &CreateStockMovement,
+    order_id: Option<i64>,
+) -> AppResult<()> {
+    match (movement.quantity_change, order_id) {
+        (change, _) if change < 0 => {
+            let location = locations::location_or_default(conn, movement.location_id)?;
+            draw(conn, movement_id, movement.product_id, &location, -change)
+        }
+        (change, Some(order_id)) if movement.reason == StockReason::Return => {
+            put_back(conn, movement_id, movement.product_id, order_id, change)
+        }
+        _ => Ok(()),
+    }
+}
+
+/// Stock a decrease takes out of one lot.
+struct Taken {
+    lot_id: i64,
+    available: i32,
+    quantity: i32,
+    /// An untapped keg, so the stock must have been poured from it
+    untapped: bool,
+}
+
+/// The lots at a location that `quantity` leaving it, already applied to the
+/// product, comes out of. Loose lots and tapped kegs go first, then untracked
+/// stock. If that is not enough the stock must have come from a keg nobody
+/// marked as tapped, so the next one is.
+fn take_from(conn: &Connection, product_id: i64, location: &StockLocation, quantity: i32) -> AppResult<Vec<Taken>> {
+    let lots: Vec<(i64, i32, bool)> = conn
+        .prepare(&format!(
+            "SELECT l.id, l.quantity, l.keg_id IS NOT NULL AND l.tapped_at IS NULL FROM stock_lots l
+             WHERE l.product_id = ?1 AND l.location_id = ?2 AND l.retired_at IS NULL AND l.quantity > 0
+             ORDER BY l.keg_id IS NOT NULL AND l.tapped_at IS NULL, {}",
+            FEFO
+        ))?
+        .query_map([product_id, location.id], |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?)))?
+        .collect::<Result<Vec<_>, _>>()?;
+
+    // Untracked stock is what the location holds beyond its lots; after the
+    // decrease a shortfall there is what has to come out of untapped kegs
+    let held: i32 = conn.query_row(
+        &format!("SELECT {} FROM products p WHERE p.id = ?1", locations::quantity_at(location)),
+        [product_id],
+        |row| row.get(0),
+    )?;
+    let mut shortfall = lots.iter().map(|(_, available, _)| available).sum::<i32>() - held;
+
+    let mut remaining = quantity;
+    let mut taken = Vec::new();
+    for (lot_id, available, untapped) in lots {
+        if untapped {
+            remaining = remaining.min(shortfall);
+        }
+        if remaining <= 0 {
+            break;
+        }
+        let quantity = remaining.min(available);
+        taken.push(Taken { lot_id, available, quantity, untapped });
+        remaining -= quantity;
+        shortfall -= quantity;
+    }
+
+    Ok(taken)
+}
+
+fn draw(
+    conn: &Connection,
+    movement_id: i64,
+    product_id: i64,
+    location: &StockLocation,
+    quantity: i32,
+) -> AppResult<()> {
+    for taken in take_from(conn, product_id, location, quantity)? {
+        if taken.untapped {
+            conn.execute("UPDATE stock_lots SET tapped_at = CURRENT_TIMESTAMP WHERE id = ?1", [taken.lot_id])?;
+        }
+        move_lot(conn, movement_id, taken.lot_id, -taken.quantity)?;
+    }
+
+    Ok(())
+}
+
+/// Carry the lots of a transfer, already applied to the product, from one
+/// location to the other, in the order a sale would draw them. A lot moving
+/// whole goes as it is; part of one moves as a loose lot of its own.
+pub(crate) fn transfer(
+    conn: &Connection,
+    movement_ids: (i64, i64),
+    product_id: i64,
+    from: &StockLocation,
+    to: &StockLocation,
+    quantity: i32,
+) -> AppResult<()> {
+    let (out_id, in_id) = movement_ids;
+
+    for taken in take_from(conn, product_id, from, quantity)? {
+        move_lot(conn, out_id, taken.lot_id, -taken.quantity)?;
+        let lot_id = if taken.quantity == taken.available {
+            conn.execute("UPDATE stock_lots SET location_id = ?1 WHERE id = ?2", [to.id, taken.lot_id])?;
+            taken.lot_id
+        } else {
+            conn.execute(
+                "INSERT INTO stock_lots (product_id, received_at, expires_on, initial_quantity, quantity, location_id)
+                 SELECT product_id, received_at, expires_on, ?2, 0, ?3 FROM stock_lots WHERE id = ?1",
+                rusqlite::params![taken.lot_id, taken.quantity, to.id],
+            )?;
+            conn.last_insert_rowid()
+        };
+        move_lot(conn, in_id, lot_id, taken.quantity)?;
+    }
+
+    Ok(())
+}
+
+fn put_back(conn: &Connection, movement_id: i64, product_id: i64, order_id: i64, quantity: i32) -> AppResult<()> {
+    let drawn: Vec<(i64, i32)> = conn
+        .prepare(
+            "SELECT lm.lot_id, -SUM(lm.quantity_change)
+             FROM stock_lot_movements lm
+             JOIN stock_movements m ON lm.movement_id = m.id
+             JOIN stock_lots l ON lm.lot_id = l.id
+             WHERE m.order_id = ?1 AND m.product_id = ?2 AND m.reason IN ('sale', 'return')
+               AND l.retired_at IS NULL
+             GROUP BY lm.lot_id
+             HAVING SUM(lm.quantity_change) < 0
+             ORDER BY MAX(lm.movement_id) DESC, lm.lot_id DESC",
+        )?
+        .query_map([order_id, product_id], |row| Ok((row.get(0)?, row.get(1)?)))?
+        .collect::<Result<Vec<_>, _>>()?;
+
+    let mut remaining = quantity;
+    for (lot_id, taken) in drawn {
+        if remaining == 0 {
+            break;
+        }
+        let returned = remaining.min(taken);
+        move_lot(conn, movement_id, lot_id, returned)?;
+        remaining -= returned;
+    }
+
+    Ok(())
+}
+
+fn get_keg(conn: &Connection, lot_id: i64) -> AppResult<StockLot> {
+    let lot = get_lot(conn, lot_id)?;
+    if lot.keg_id.is_none() {
+        return Err(AppError::Validation(format!("Lot {} is not a keg", lot_id)));
+    }
+    if lot.retired_at.is_some() {
+        return Err(AppError::Validation(format!(
+            "Keg {} has already been retired",
+            lot.keg_id.as_deref().unwrap_or_default()
+        )));
+    }
+    Ok(lot)
+}
+
+/// Put a keg on, so sales start pouring from it.
+pub fn tap_keg(conn: &Connection, lot_id: i64) -> AppResult<StockLot> {
+    let keg = get_keg(conn, lot_id)?;
+    if keg.tapped_at.is_some() {
+        return Err(AppError::Validation(format!(
+            "Keg {} is already tapped",
+            keg.keg_id.as_deref().unwrap_or_default()
+        )));
+    }
+
+    conn.execute("UPDATE stock_lots SET tapped_at = CURRENT_TIMESTAMP WHERE id = ?1", [lot_id])?;
+    get_lot(conn, lot_id)
+}
+
+/// Take an empty keg off. Whatever the ledger says is still in it was lost
+/// to foam, line cleaning or over-pouring and is written off as waste at the
+/// location the keg is at.
+pub fn retire_keg(conn: &mut Connection, lot_id: i64, staff_id: Option<i64>) -> AppResult<KegYield> {
+    let tx = begin_write(conn)?;
+    let keg = get_keg(&tx, lot_id)?;
+
+    if keg.quantity > 0 {
+        let location_id = Some(keg.location_id);
+        let movement = CreateStockMovement {
+            product_id: keg.product_id,
+            quantity_change: -keg.quantity,
+            reason: StockReason::Waste,
+            staff_id,
+            reference: None,
+            note: Some(format!("Keg {} retired", keg.keg_id.as_deref().unwrap_or_default())),
+            location_id,
+        };
+        stock::change_quantity_at(&tx, location_id, keg.product_id, movement.quantity_change)?;
+        let movement_id = stock::log_movement(&tx, &movement, None)?;
+        move_lot(&tx, movement_id, lot_id, movement.quantity_change)?;
+    }
+    tx.execute(
+        "UPDATE stock_lots SET retired_at = CURRENT_TIMESTAMP, tapped_at = COALESCE(tapped_at, CURRENT_TIMESTAMP)
+         WHERE id = ?1",
+        [lot_id],
+    )?;
+
+    tx.commit()?;
+    get_keg_yield(conn, lot_id)
+}
+
+/// Servings poured from a keg against what its volume should give.
+pub fn get_keg_yield(conn: &Connection, lot_id: i64) -> AppResult<KegYield> {
+    let lot = get_lot(conn, lot_id)?;
+    if lot.keg_id.is_none() {
+        return Err(AppError::Validation(format!("Lot {} is not a keg", lot_id)));
+    }
+
+    let (poured, loss, serving_size): (i32, i32, i32) = conn.query_row(
+        "SELECT COALESCE(-SUM(CASE WHEN m.reason IN ('sale', 'return') THEN lm.quantity_change END), 0),
+                COALESCE(-SUM(CASE WHEN m.reason = 'waste' THEN lm.quantity_change END), 0),
+                (SELECT serving_size FROM products WHERE id = ?2)
+         FROM stock_lot_movements lm
+         JOIN stock_movements m ON lm.movement_id = m.id
+         WHERE lm.lot_id = ?1",
+        [lot_id, lot.product_id],
+        |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?)),
+    )?;
+
+    Ok(KegYield {
+        poured,
+        servings_poured: poured as f64 / serving_size as f64,
+        theoretical_servings: lot.initial_quantity as f64 / serving_size as f64,
+        yield_percent: poured as f64 * 100.0 / lot.initial_quantity as f64,
+        loss,
+        lot,
+    })
+}
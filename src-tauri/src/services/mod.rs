@@ -0,0 +1,27 @@
+//! Business logic for the pub, independent of Tauri.
+//!
+//! Every function takes a plain `rusqlite::Connection`, so the same code runs
+//! behind the Tauri commands, in tests against an in-memory database, and from
+//! any other binary that opens a `Database`. Functions that write take
+//! `&mut Connection` and run in a single transaction.
+
+pub mod availability;
+pub mod catalog;
+pub mod categories;
+pub mod codes;
+pub mod locations;
+pub mod lots;
+pub mod modifiers;
+pub mod orders;
+pub mod prices;
+pub mod pricing;
+pub mod products;
+pub mod purchasing;
+pub mod recipes;
+pub mod reorder;
+pub mod reports;
+pub mod staff;
+pub mod stock;
+pub mod stock_takes;
+pub mod taxes;
+pub mod variants;
@@ -0,0 +1,300 @@
+use crate::db::begin_write;
+use crate::error::{not_found, AppError, AppResult};
+use crate::models::{
+    CreateModifier, CreateModifierGroup, Modifier, ModifierGroup, OrderItemModifier, UpdateModifierGroup,
+};
+use crate::services::categories;
+use rusqlite::{Connection, OptionalExtension};
+
+fn get_modifiers(conn: &Connection, group_id: i64) -> AppResult<Vec<Modifier>> {
+    let mut stmt = conn.prepare(
+        "SELECT id, group_id, name, price_delta FROM modifiers WHERE group_id = ?1 ORDER BY id",
+    )?;
+
+    let modifiers = stmt
+        .query_map([group_id], |row| {
+            Ok(Modifier {
+                id: row.get(0)?,
+                group_id: row.get(1)?,
+                name: row.get(2)?,
+                price_delta: row.get(3)?,
+            })
+        })?
+        .collect::<Result<Vec<_>, _>>()?;
+
+    Ok(modifiers)
+}
+
+fn linked_ids(conn: &Connection, group_id: i64, column: &str) -> AppResult<Vec<i64>> {
+    let mut stmt = conn.prepare(&format!(
+        "SELECT {0} FROM modifier_group_links WHERE group_id = ?1 AND {0} IS NOT NULL ORDER BY {0}",
+        column
+    ))?;
+
+    let ids = stmt
+        .query_map([group_id], |row| row.get(0))?
+        .collect::<Result<Vec<_>, _>>()?;
+
+    Ok(ids)
+}
+
+/// Load groups matching `filter`, e.g. `"WHERE g.id = ?1"`.
+fn query_groups<P: rusqlite::Params>(conn: &Connection, filter: &str, params: P) -> AppResult<Vec<ModifierGroup>> {
+    let mut stmt = conn.prepare(&format!(
+        "SELECT g.id, g.name, g.min_select, g.max_select FROM modifier_groups g {}",
+        filter
+    ))?;
+
+    let headers: Vec<(i64, String, i32, Option<i32>)> = stmt
+        .query_map(params, |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?, row.get(3)?)))?
+        .collect::<Result<Vec<_>, _>>()?;
+
+    let mut groups = Vec::new();
+    for (id, name, min_select, max_select) in headers {
+        groups.push(ModifierGroup {
+            id,
+            name,
+            min_select,
+            max_select,
+            modifiers: get_modifiers(conn, id)?,
+            product_ids: linked_ids(conn, id, "product_id")?,
+            category_ids: linked_ids(conn, id, "category_id")?,
+        });
+    }
+
+    Ok(groups)
+}
+
+pub fn get_modifier_groups(conn: &Connection) -> AppResult<Vec<ModifierGroup>> {
+    query_groups(conn, "ORDER BY g.name", [])
+}
+
+pub fn get_modifier_group(conn: &Connection, id: i64) -> AppResult<ModifierGroup> {
+    query_groups(conn, "WHERE g.id = ?1", [id])?
+        .pop()
+        .ok_or(AppError::NotFound { entity: "modifier group", id })
+}
+
+/// Groups offered with a product: linked to it directly or to its category or
+/// any category above it.
+pub fn get_product_modifier_groups(conn: &Connection, product_id: i64) -> AppResult<Vec<ModifierGroup>> {
+    let category_id: Option<i64> = conn
+        .query_row("SELECT category_id FROM products WHERE id = ?1", [product_id], |row| row.get(0))
+        .optional()?
+        .flatten();
+    let categories = match category_id {
+        Some(id) => categories::lineage(conn, id)?.iter().map(|c| c.id.to_string()).collect::<Vec<_>>(),
+        None => Vec::new(),
+    };
+
+    query_groups(
+        conn,
+        &format!(
+            "WHERE g.id IN (
+                 SELECT l.group_id FROM modifier_group_links l
+                 WHERE l.product_id = ?1 OR l.category_id IN ({})
+             )
+             ORDER BY g.name",
+            categories.join(", ")
+        ),
+        [product_id],
+    )
+}
+
+fn validate_limits(min_select: i32, max_select: Option<i32>) -> AppResult<()> {
+    let valid = min_select >= 0 && max_select.is_none_or(|max| max >= 1 && max >= min_select);
+    if !valid {
+        return Err(AppError::Validation(format!(
+            "Invalid selection limits: min {}, max {}",
+            min_select,
+            max_select.map_or("none".to_string(), |m| m.to_string())
+        )));
+    }
+
+    Ok(())
+}
+
+fn insert_modifier(conn: &Connection, group_id: i64, modifier: &CreateModifier) -> AppResult<()> {
+    if modifier.name.trim().is_empty() {
+        return Err(AppError::Validation("A modifier needs a name".to_string()));
+    }
+
+    conn.execute(
+        "INSERT INTO modifiers (group_id, name, price_delta) VALUES (?1, ?2, ?3)",
+        rusqlite::params![group_id, modifier.name, modifier.price_delta],
+    )?;
+
+    Ok(())
+}
+
+pub fn create_modifier_group(conn: &mut Connection, group: CreateModifierGroup) -> AppResult<ModifierGroup> {
+    validate_limits(group.min_select, group.max_select)?;
+
+    let tx = begin_write(conn)?;
+
+    tx.execute(
+        "INSERT INTO modifier_groups (name, min_select, max_select) VALUES (?1, ?2, ?3)",
+        rusqlite::params![group.name, group.min_select, group.max_select],
+    )?;
+    let id = tx.last_insert_rowid();
+
+    for modifier in &group.modifiers {
+        insert_modifier(&tx, id, modifier)?;
+    }
+
+    tx.commit()?;
+    get_modifier_group(conn, id)
+}
+
+pub fn update_modifier_group(conn: &Connection, group: UpdateModifierGroup) -> AppResult<ModifierGroup> {
+    validate_limits(group.min_select, group.max_select)?;
+
+    let updated = conn.execute(
+        "UPDATE modifier_groups SET name = ?1, min_select = ?2, max_select = ?3 WHERE id = ?4",
+        rusqlite::params![group.name, group.min_select, group.max_select, group.id],
+    )?;
+    if updated == 0 {
+        return Err(AppError::NotFound { entity: "modifier group", id: group.id });
+    }
+
+    get_modifier_group(conn, group.id)
+}
+
+/// Delete a group with its modifiers and links. Order lines keep their own
+/// copy of what was sold.
+pub fn delete_modifier_group(conn: &mut Connection, id: i64) -> AppResult<()> {
+    let tx = begin_write(conn)?;
+
+    tx.execute("DELETE FROM modifier_group_links WHERE group_id = ?1", [id])?;
+    tx.execute("DELETE FROM modifiers WHERE group_id = ?1", [id])?;
+    tx.execute("DELETE FROM modifier_groups WHERE id = ?1", [id])?;
+
+    tx.commit()?;
+    Ok(())
+}
+
+pub fn add_modifier(conn: &mut Connection, group_id: i64, modifier: CreateModifier) -> AppResult<ModifierGroup> {
+    let tx = begin_write(conn)?;
+
+    tx.query_row("SELECT id FROM modifier_groups WHERE id = ?1", [group_id], |row| row.get::<_, i64>(0))
+        .map_err(not_found("modifier group", group_id))?;
+    insert_modifier(&tx, group_id, &modifier)?;
+
+    tx.commit()?;
+    get_modifier_group(conn, group_id)
+}
+
+pub fn delete_modifier(conn: &Connection, id: i64) -> AppResult<()> {
+    conn.execute("DELETE FROM modifiers WHERE id = ?1", [id])?;
+
+    Ok(())
+}
+
+/// Replace the products and categories a group is offered with.
+pub fn set_modifier_group_links(
+    conn: &mut Connection,
+    group_id: i64,
+    product_ids: Vec<i64>,
+    category_ids: Vec<i64>,
+) -> AppResult<ModifierGroup> {
+    let tx = begin_write(conn)?;
+
+    tx.query_row("SELECT id FROM modifier_groups WHERE id = ?1", [group_id], |row| row.get::<_, i64>(0))
+        .map_err(not_found("modifier group", group_id))?;
+    tx.execute("DELETE FROM modifier_group_links WHERE group_id = ?1", [group_id])?;
+
+    for product_id in product_ids {
+        tx.execute(
+            "INSERT INTO modifier_group_links (group_id, product_id) VALUES (?1, ?2)",
+            [group_id, product_id],
+        )?;
+    }
+    for category_id in category_ids {
+        tx.execute(
+            "INSERT INTO modifier_group_links (group_id, category_id) VALUES (?1, ?2)",
+            [group_id, category_id],
+        )?;
+    }
+
+    tx.commit()?;
+    get_modifier_group(conn, group_id)
+}
+
+/// Check the chosen modifiers against the groups offered with the product and
+/// return them as they will be sold. Every chosen modifier must belong to one
+/// of those groups, each group's min/max must hold, and none may repeat.
+pub(crate) fn resolve_modifiers(
+    conn: &Connection,
+    product_id: i64,
+    modifier_ids: &[i64],
+) -> AppResult<Vec<OrderItemModifier>> {
+    let groups = get_product_modifier_groups(conn, product_id)?;
+    let mut chosen = Vec::new();
+
+    for (index, id) in modifier_ids.iter().enumerate() {
+        if modifier_ids[..index].contains(id) {
+            return Err(AppError::Validation(format!("Modifier {} was chosen twice", id)));
+        }
+
+        let modifier = groups
+            .iter()
+            .flat_map(|g| &g.modifiers)
+            .find(|m| m.id == *id)
+            .ok_or_else(|| AppError::Validation(format!("Modifier {} is not offered with this product", id)))?;
+
+        chosen.push(modifier);
+    }
+
+    for group in &groups {
+        let count = chosen.iter().filter(|m| m.group_id == group.id).count() as i32;
+        if count < group.min_select || group.max_select.is_some_and(|max| count > max) {
+            return Err(AppError::Validation(match group.max_select {
+                Some(max) if max == group.min_select => format!("Choose {} from {}", max, group.name),
+                Some(max) => format!("Choose {} to {} from {}", group.min_select, max, group.name),
+                None => format!("Choose at least {} from {}", group.min_select, group.name),
+            }));
+        }
+    }
+
+    Ok(chosen
+        .into_iter()
+        .map(|m| OrderItemModifier {
+            modifier_id: Some(m.id),
+            name: m.name.clone(),
+            price_delta: m.price_delta,
+        })
+        .collect())
+}
+
+pub(crate) fn get_order_item_modifiers(conn: &Connection, order_item_id: i64) -> AppResult<Vec<OrderItemModifier>> {
+    let mut stmt = conn.prepare(
+        "SELECT modifier_id, name, price_delta FROM order_item_modifiers WHERE order_item_id = ?1 ORDER BY id",
+    )?;
+
+    let modifiers = stmt
+        .query_map([order_item_id], |row| {
+            Ok(OrderItemModifier {
+                modifier_id: row.get(0)?,
+                name: row.get(1)?,
+                price_delta: row.get(2)?,
+            })
+        })?
+        .collect::<Result<Vec<_>, _>>()?;
+
+    Ok(modifiers)
+}
+
+pub(crate) fn save_order_item_modifiers(
+    conn: &Connection,
+    order_item_id: i64,
+    modifiers: &[OrderItemModifier],
+) -> AppResult<()> {
+    for modifier in modifiers {
+        conn.execute(
+            "INSERT INTO order_item_modifiers (order_item_id, modifier_id, name, price_delta) VALUES (?1, ?2, ?3, ?4)",
+            rusqlite::params![order_item_id, modifier.modifier_id, modifier.name, modifier.price_delta],
+        )?;
+    }
+
+    Ok(())
+}
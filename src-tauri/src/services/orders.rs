@@ -0,0 +1,621 @@
+use crate::db::begin_write;
+use crate::error::{not_found, AppError, AppResult};
+use crate::models::{
+    CreateOrder, CreateOrderItem, CreateStockMovement, Order, OrderItem, OrderItemModifier, OrderWithItems,
+    PricingRuleKind, StockReason, TaxRate,
+};
+use crate::money::Money;
+use crate::services::{
+    availability, codes, locations, modifiers, prices, pricing, products, recipes, staff, stock, taxes, variants,
+};
+use chrono::NaiveDateTime;
+use rusqlite::{Connection, OptionalExtension, Params};
+
+const ORDER_SELECT: &str =
+    "SELECT o.id, o.staff_id, s.name, o.table_number, o.total, o.customer_name, o.notes, o.status, o.created_at
+     FROM orders o
+     LEFT JOIN staff s ON o.staff_id = s.id";
+
+fn order_from_row(row: &rusqlite::Row) -> rusqlite::Result<Order> {
+    Ok(Order {
+        id: row.get(0)?,
+        staff_id: row.get(1)?,
+        staff_name: row.get(2)?,
+        table_number: row.get::<_, Option<i32>>(3)?.unwrap_or(1),
+        total: row.get(4)?,
+        customer_name: row.get(5)?,
+        notes: row.get(6)?,
+        status: row.get::<_, Option<String>>(7)?.unwrap_or_else(|| "paid".to_string()),
+        created_at: row.get(8)?,
+    })
+}
+
+fn get_order_items(conn: &Connection, order_id: i64) -> AppResult<Vec<OrderItem>> {
+    let mut stmt = conn.prepare(
+        "SELECT oi.id, oi.order_id, oi.product_id, p.name, oi.quantity, oi.price_at_sale, oi.cost_at_sale,
+                oi.pricing_rule_id, oi.pricing_rule_name, oi.variant_id, v.name,
+                oi.tax_name, oi.tax_basis_points, oi.tax_included, oi.tax_amount
+         FROM order_items oi
+         LEFT JOIN products p ON oi.product_id = p.id
+         LEFT JOIN product_variants v ON oi.variant_id = v.id
+         WHERE oi.order_id = ?1",
+    )?;
+
+    let mut items = stmt
+        .query_map([order_id], |row| {
+            Ok(OrderItem {
+                id: row.get(0)?,
+                order_id: row.get(1)?,
+                product_id: row.get(2)?,
+                product_name: row.get(3)?,
+                quantity: row.get(4)?,
+                price_at_sale: row.get(5)?,
+                cost_at_sale: row.get(6)?,
+                pricing_rule_id: row.get(7)?,
+                pricing_rule_name: row.get(8)?,
+                variant_id: row.get(9)?,
+                variant_name: row.get(10)?,
+                modifiers: Vec::new(),
+                tax_name: row.get(11)?,
+                tax_basis_points: row.get(12)?,
+                tax_included: row.get(13)?,
+                tax_amount: row.get(14)?,
+            })
+        })?
+        .collect::<Result<Vec<_>, _>>()?;
+
+    for item in &mut items {
+        item.modifiers = modifiers::get_order_item_modifiers(conn, item.id)?;
+    }
+
+    Ok(items)
+}
+
+/// Load orders with their items. `filter` is appended to the shared SELECT,
+/// e.g. `"WHERE o.session_id = ?1 ORDER BY o.created_at DESC"`.
+pub(crate) fn query_orders<P: Params>(
+    conn: &Connection,
+    filter: &str,
+    params: P,
+) -> AppResult<Vec<OrderWithItems>> {
+    let mut stmt = conn.prepare(&format!("{} {}", ORDER_SELECT, filter))?;
+
+    let orders: Vec<Order> = stmt
+        .query_map(params, order_from_row)?
+        .collect::<Result<Vec<_>, _>>()?;
+
+    let mut result = Vec::new();
+    for order in orders {
+        let items = get_order_items(conn, order.id)?;
+        result.push(OrderWithItems { order, items });
+    }
+
+    Ok(result)
+}
+
+/// Stock products and base units one sale takes. A product with a recipe
+/// takes each of its ingredients instead of itself, and a variant takes its
+/// deduction from the parent's stock.
+fn sale_stock_items(conn: &Connection, product_id: i64, variant_id: Option<i64>) -> AppResult<Vec<(i64, i32)>> {
+    match variant_id {
+        Some(variant_id) => Ok(vec![(product_id, variants::deduction(conn, variant_id)?)]),
+        None => recipes::stock_items(conn, product_id),
+    }
+}
+
+/// Cost of one sale at current average costs, or `None` if any stock item
+/// has not been costed yet.
+fn sale_cost(conn: &Connection, product_id: i64, variant_id: Option<i64>) -> AppResult<Option<Money>> {
+    let mut total = Money::zero();
+
+    for (stock_product_id, per_serving) in sale_stock_items(conn, product_id, variant_id)? {
+        let cost: Option<Money> = conn
+            .query_row("SELECT cost_price FROM products WHERE id = ?1", [stock_product_id], |row| row.get(0))
+            .map_err(not_found("product", stock_product_id))?;
+        match cost {
+            Some(cost) => total += cost * per_serving,
+            None => return Ok(None),
+        }
+    }
+
+    Ok(Some(total))
+}
+
+/// Move stock for an order line at the order's location and record it in the
+/// ledger against the order. Sales are guarded, so the last bottle cannot be
+/// sold twice.
+fn move_order_stock(
+    conn: &Connection,
+    order_id: i64,
+    staff_id: i64,
+    product_id: i64,
+    variant_id: Option<i64>,
+    quantity_change: i32,
+    reason: StockReason,
+) -> AppResult<()> {
+    let location_id: Option<i64> =
+        conn.query_row("SELECT location_id FROM orders WHERE id = ?1", [order_id], |row| row.get(0))?;
+
+    for (stock_product_id, per_serving) in sale_stock_items(conn, product_id, variant_id)? {
+        let movement = CreateStockMovement {
+            product_id: stock_product_id,
+            quantity_change: quantity_change * per_serving,
+            reason,
+            staff_id: Some(staff_id),
+            reference: None,
+            note: None,
+            location_id,
+        };
+        stock::apply_movement(conn, &movement, Some(order_id))?;
+    }
+    Ok(())
+}
+
+/// Recompute an order's line taxes and total instead of nudging them up and
+/// down, so the stored total always equals the sum of what is on the tab,
+/// tax charged on top of prices included.
+fn recalculate_order_total(conn: &Connection, order_id: i64) -> AppResult<()> {
+    taxes::update_line_taxes(conn, order_id)?;
+    conn.execute(
+        "UPDATE orders SET total = (
+            SELECT COALESCE(SUM(quantity * price_at_sale + CASE WHEN tax_included THEN 0 ELSE tax_amount END), 0)
+            FROM order_items WHERE order_id = ?1
+        ) WHERE id = ?1",
+        [order_id],
+    )?;
+
+    Ok(())
+}
+
+/// Fail with `OrderNotOpen` unless the order exists and is still open.
+/// Returns the staff member the order belongs to.
+fn ensure_order_open(conn: &Connection, order_id: i64) -> AppResult<i64> {
+    let (status, staff_id): (String, i64) = conn
+        .query_row(
+            "SELECT status, staff_id FROM orders WHERE id = ?1",
+            [order_id],
+            |row| Ok((row.get(0)?, row.get(1)?)),
+        )
+        .map_err(not_found("order", order_id))?;
+
+    if status != "open" {
+        return Err(AppError::OrderNotOpen { order_id });
+    }
+
+    Ok(staff_id)
+}
+
+/// An order line resolved to the unit price it sells at, modifiers included,
+/// the pricing rule behind that price, what one unit costs and the rate it is
+/// taxed at.
+struct PricedItem {
+    product_id: i64,
+    variant_id: Option<i64>,
+    modifiers: Vec<OrderItemModifier>,
+    quantity: i32,
+    price: Money,
+    pricing_rule: Option<(i64, String)>,
+    cost: Option<Money>,
+    tax: Option<TaxRate>,
+    /// Given away by a buy-X-get-Y rule with the line before it
+    free: bool,
+}
+
+/// Look up the price for each requested line under the pricing rules in
+/// effect at `at`. Rules discount the product or variant price, not its
+/// modifiers. Units a buy-X-get-Y rule gives away go on a line of their own.
+/// Stock is checked when it is deducted, so a short ingredient is reported by
+/// name.
+fn price_items(conn: &Connection, items: &[CreateOrderItem], at: NaiveDateTime) -> AppResult<Vec<PricedItem>> {
+    let mut priced = Vec::new();
+
+    for item in items {
+        if item.quantity <= 0 {
+            return Err(AppError::Validation(format!(
+                "Invalid quantity {} for product {}",
+                item.quantity, item.product_id
+            )));
+        }
+        products::ensure_on_sale(conn, item.product_id)?;
+        availability::ensure_available(conn, item.product_id)?;
+
+        let base_price: Money = match item.variant_id {
+            Some(variant_id) => variants::variant_price(conn, item.product_id, variant_id)?,
+            None => conn
+                .query_row("SELECT price FROM products WHERE id = ?1", [item.product_id], |row| row.get(0))
+                .map_err(not_found("product", item.product_id))?,
+        };
+
+        let modifiers = modifiers::resolve_modifiers(conn, item.product_id, &item.modifier_ids)?;
+        let extras: Money = modifiers.iter().map(|m| m.price_delta).sum();
+        if (base_price + extras).minor_units < 0 {
+            return Err(AppError::Validation("Modifiers cannot take a price below zero".to_string()));
+        }
+
+        let rule = pricing::best_price(conn, item.product_id, base_price, item.quantity, at)?;
+        let cost = sale_cost(conn, item.product_id, item.variant_id)?;
+        let tax = taxes::product_tax_rate(conn, item.product_id)?;
+        let line = |quantity, price: Money, pricing_rule, free| PricedItem {
+            product_id: item.product_id,
+            variant_id: item.variant_id,
+            modifiers: modifiers.clone(),
+            quantity,
+            price: Money::from_minor(price.minor_units.max(0)),
+            pricing_rule,
+            cost,
+            tax: tax.clone(),
+            free,
+        };
+
+        match rule {
+            None => priced.push(line(item.quantity, base_price + extras, None, false)),
+            Some(rule) if rule.free_quantity == 0 => {
+                let pricing_rule = Some((rule.rule_id, rule.rule_name));
+                priced.push(line(item.quantity, rule.unit_price + extras, pricing_rule, false));
+            }
+            Some(rule) => {
+                priced.push(line(item.quantity - rule.free_quantity, base_price + extras, None, false));
+                priced.push(line(rule.free_quantity, extras, Some((rule.rule_id, rule.rule_name)), true));
+            }
+        }
+    }
+
+    Ok(priced)
+}
+
+/// Add priced lines to an order and take their stock. Every request becomes
+/// its own line, so the same product with different modifiers stays apart.
+/// Free lines point at the line that earned them. Line taxes are left to
+/// `recalculate_order_total`.
+fn insert_items(conn: &Connection, order_id: i64, staff_id: i64, items: &[PricedItem]) -> AppResult<()> {
+    let mut paid_item_id = None;
+
+    for line in items {
+        conn.execute(
+            "INSERT INTO order_items
+                (order_id, product_id, quantity, price_at_sale, cost_at_sale, pricing_rule_id, pricing_rule_name,
+                 variant_id, tax_name, tax_basis_points, tax_included, free_with_item_id)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12)",
+            rusqlite::params![
+                order_id,
+                line.product_id,
+                line.quantity,
+                line.price,
+                line.cost,
+                line.pricing_rule.as_ref().map(|(id, _)| *id),
+                line.pricing_rule.as_ref().map(|(_, name)| name),
+                line.variant_id,
+                line.tax.as_ref().map(|t| &t.name),
+                line.tax.as_ref().map_or(0, |t| t.basis_points),
+                line.tax.as_ref().is_none_or(|t| t.included),
+                if line.free { paid_item_id } else { None }
+            ],
+        )?;
+        let item_id = conn.last_insert_rowid();
+        modifiers::save_order_item_modifiers(conn, item_id, &line.modifiers)?;
+        if !line.free {
+            paid_item_id = Some(item_id);
+        }
+
+        move_order_stock(conn, order_id, staff_id, line.product_id, line.variant_id, -line.quantity, StockReason::Sale)?;
+    }
+
+    Ok(())
+}
+
+pub fn create_order(conn: &mut Connection, order: CreateOrder) -> AppResult<OrderWithItems> {
+    let tx = begin_write(conn)?;
+
+    // Check if there's an active day session and get its ID
+    let session_id: i64 = tx
+        .query_row(
+            "SELECT id FROM day_sessions WHERE is_active = 1",
+            [],
+            |row| row.get(0),
+        )
+        .map_err(|_| AppError::DayNotStarted)?;
+
+    staff::ensure_active(&tx, order.staff_id)?;
+    if let Some(location_id) = order.location_id {
+        locations::get_location(&tx, location_id)?;
+    }
+
+    // Scheduled price changes that have fallen due apply from this order on
+    prices::apply_due(&tx)?;
+
+    // Price the lines; stock is validated as it is deducted below
+    let item_details = price_items(&tx, &order.items, chrono::Local::now().naive_local())?;
+
+    // Create order with status 'open' and link to session; the total follows its lines
+    tx.execute(
+        "INSERT INTO orders (staff_id, table_number, total, customer_name, notes, status, session_id, location_id)
+         VALUES (?1, ?2, 0, ?3, ?4, 'open', ?5, ?6)",
+        rusqlite::params![
+            order.staff_id,
+            order.table_number,
+            order.customer_name,
+            order.notes,
+            session_id,
+            order.location_id
+        ],
+    )?;
+
+    let order_id = tx.last_insert_rowid();
+
+    // Create order items and deduct inventory
+    insert_items(&tx, order_id, order.staff_id, &item_details)?;
+    recalculate_order_total(&tx, order_id)?;
+
+    tx.commit()?;
+    get_order(conn, order_id)
+}
+
+pub fn add_items_to_order(
+    conn: &mut Connection,
+    order_id: i64,
+    items: Vec<CreateOrderItem>,
+) -> AppResult<OrderWithItems> {
+    let tx = begin_write(conn)?;
+
+    let staff_id = ensure_order_open(&tx, order_id)?;
+    prices::apply_due(&tx)?;
+    let item_details = price_items(&tx, &items, chrono::Local::now().naive_local())?;
+
+    // Add items and deduct inventory
+    insert_items(&tx, order_id, staff_id, &item_details)?;
+
+    recalculate_order_total(&tx, order_id)?;
+
+    tx.commit()?;
+    get_order(conn, order_id)
+}
+
+/// Add a scanned item. A variant's code sells that variant; modifiers are
+/// added through `add_items_to_order`.
+pub fn add_item_by_code(
+    conn: &mut Connection,
+    order_id: i64,
+    code: String,
+    quantity: i32,
+) -> AppResult<OrderWithItems> {
+    let (product_id, variant_id) = codes::resolve_code(conn, &code)?;
+
+    let item = CreateOrderItem {
+        product_id,
+        quantity,
+        variant_id,
+        modifier_ids: Vec::new(),
+    };
+    add_items_to_order(conn, order_id, vec![item])
+}
+
+pub fn mark_order_paid(conn: &mut Connection, order_id: i64) -> AppResult<OrderWithItems> {
+    let tx = begin_write(conn)?;
+
+    ensure_order_open(&tx, order_id)?;
+    tx.execute("UPDATE orders SET status = 'paid' WHERE id = ?1", [order_id])?;
+
+    tx.commit()?;
+    get_order(conn, order_id)
+}
+
+/// Keep a paid line's buy-X-get-Y deal in step with its quantity. Units it
+/// no longer earns come off the free line and are charged at its price, and
+/// units it now earns move onto a free line. A deal already given keeps its
+/// rule. The tab holds as many units either way, so no stock moves.
+fn rebalance_deal(conn: &Connection, item_id: i64) -> AppResult<()> {
+    let (product_id, quantity, price, pricing_rule_id): (i64, i32, Money, Option<i64>) = conn.query_row(
+        "SELECT product_id, quantity, price_at_sale, pricing_rule_id FROM order_items WHERE id = ?1",
+        [item_id],
+        |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?, row.get(3)?)),
+    )?;
+    // Discounted lines carry no deal
+    if pricing_rule_id.is_some() {
+        return Ok(());
+    }
+
+    let free_line: Option<(i64, i32, i32, i32)> = conn
+        .query_row(
+            "SELECT oi.id, oi.quantity, r.buy_quantity, r.free_quantity
+             FROM order_items oi JOIN pricing_rules r ON oi.pricing_rule_id = r.id
+             WHERE oi.free_with_item_id = ?1",
+            [item_id],
+            |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?, row.get(3)?)),
+        )
+        .optional()?;
+    let extras: Money = conn.query_row(
+        "SELECT COALESCE(SUM(price_delta), 0) FROM order_item_modifiers WHERE order_item_id = ?1",
+        [item_id],
+        |row| row.get(0),
+    )?;
+
+    let (free_item_id, given, earned, rule) = match free_line {
+        Some((id, given, buy, free)) => (Some(id), given, (quantity + given) / (buy + free) * free, None),
+        None => {
+            let at = chrono::Local::now().naive_local();
+            match pricing::best_price(conn, product_id, price - extras, quantity, at)? {
+                Some(rule) if rule.free_quantity > 0 => (None, 0, rule.free_quantity, Some(rule)),
+                _ => return Ok(()),
+            }
+        }
+    };
+    if earned == given {
+        return Ok(());
+    }
+
+    conn.execute(
+        "UPDATE order_items SET quantity = ?1 WHERE id = ?2",
+        rusqlite::params![quantity + given - earned, item_id],
+    )?;
+    match (free_item_id, rule) {
+        (Some(free_item_id), _) if earned == 0 => {
+            conn.execute("DELETE FROM order_item_modifiers WHERE order_item_id = ?1", [free_item_id])?;
+            conn.execute("DELETE FROM order_items WHERE id = ?1", [free_item_id])?;
+        }
+        (Some(free_item_id), _) => {
+            conn.execute(
+                "UPDATE order_items SET quantity = ?1 WHERE id = ?2",
+                rusqlite::params![earned, free_item_id],
+            )?;
+        }
+        (None, Some(rule)) => {
+            conn.execute(
+                "INSERT INTO order_items
+                    (order_id, product_id, quantity, price_at_sale, cost_at_sale, pricing_rule_id, pricing_rule_name,
+                     variant_id, tax_name, tax_basis_points, tax_included, free_with_item_id)
+                 SELECT order_id, product_id, ?2, ?3, cost_at_sale, ?4, ?5,
+                        variant_id, tax_name, tax_basis_points, tax_included, id
+                 FROM order_items WHERE id = ?1",
+                rusqlite::params![item_id, earned, extras, rule.rule_id, rule.rule_name],
+            )?;
+            conn.execute(
+                "INSERT INTO order_item_modifiers (order_item_id, modifier_id, name, price_delta)
+                 SELECT ?2, modifier_id, name, price_delta FROM order_item_modifiers WHERE order_item_id = ?1",
+                rusqlite::params![item_id, conn.last_insert_rowid()],
+            )?;
+        }
+        (None, None) => {}
+    }
+
+    Ok(())
+}
+
+/// Decrease item quantity by 1. If quantity becomes 0, remove the item.
+/// A buy-X-get-Y deal on the line is re-checked, so units it no longer earns
+/// are charged. If order has no items left, delete the order and return `None`.
+pub fn decrease_item_quantity(conn: &mut Connection, item_id: i64) -> AppResult<Option<OrderWithItems>> {
+    let tx = begin_write(conn)?;
+
+    let (order_id, product_id, variant_id, free_with_item_id): (i64, i64, Option<i64>, Option<i64>) = tx
+        .query_row(
+            "SELECT order_id, product_id, variant_id, free_with_item_id FROM order_items WHERE id = ?1",
+            [item_id],
+            |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?, row.get(3)?)),
+        )
+        .map_err(not_found("order item", item_id))?;
+
+    let staff_id = ensure_order_open(&tx, order_id)?;
+
+    tx.execute(
+        "UPDATE order_items SET quantity = quantity - 1 WHERE id = ?1",
+        [item_id],
+    )?;
+
+    // Restore 1 unit to inventory
+    move_order_stock(&tx, order_id, staff_id, product_id, variant_id, 1, StockReason::Return)?;
+
+    rebalance_deal(&tx, free_with_item_id.unwrap_or(item_id))?;
+    tx.execute(
+        "DELETE FROM order_item_modifiers
+         WHERE order_item_id IN (SELECT id FROM order_items WHERE order_id = ?1 AND quantity = 0)",
+        [order_id],
+    )?;
+    tx.execute("DELETE FROM order_items WHERE order_id = ?1 AND quantity = 0", [order_id])?;
+
+    recalculate_order_total(&tx, order_id)?;
+
+    let remaining_items: i32 = tx.query_row(
+        "SELECT COUNT(*) FROM order_items WHERE order_id = ?1",
+        [order_id],
+        |row| row.get(0),
+    )?;
+
+    // If no items left, delete the order
+    if remaining_items == 0 {
+        tx.execute("DELETE FROM orders WHERE id = ?1", [order_id])?;
+        tx.commit()?;
+        return Ok(None);
+    }
+
+    tx.commit()?;
+    Ok(Some(get_order(conn, order_id)?))
+}
+
+/// Increase item quantity by 1 at the line's price (if stock is available).
+/// Lines a buy-X-get-Y deal gave away cannot grow; the deal is re-checked
+/// on the line that earns it instead.
+pub fn increase_item_quantity(conn: &mut Connection, item_id: i64) -> AppResult<OrderWithItems> {
+    let tx = begin_write(conn)?;
+
+    let (order_id, product_id, variant_id): (i64, i64, Option<i64>) = tx
+        .query_row(
+            "SELECT order_id, product_id, variant_id FROM order_items WHERE id = ?1",
+            [item_id],
+            |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?)),
+        )
+        .map_err(not_found("order item", item_id))?;
+
+    let staff_id = ensure_order_open(&tx, order_id)?;
+    products::ensure_on_sale(&tx, product_id)?;
+    availability::ensure_available(&tx, product_id)?;
+
+    let free_line: bool = tx.query_row(
+        "SELECT EXISTS (
+            SELECT 1 FROM order_items oi JOIN pricing_rules r ON oi.pricing_rule_id = r.id
+            WHERE oi.id = ?1 AND r.kind = ?2
+         )",
+        rusqlite::params![item_id, PricingRuleKind::BuyXGetY],
+        |row| row.get(0),
+    )?;
+    if free_line {
+        return Err(AppError::Validation(
+            "Free items cannot be increased; ring the product up again".to_string(),
+        ));
+    }
+
+    tx.execute(
+        "UPDATE order_items SET quantity = quantity + 1 WHERE id = ?1",
+        [item_id],
+    )?;
+
+    // Deduct 1 from inventory (fails if the last unit is already gone)
+    move_order_stock(&tx, order_id, staff_id, product_id, variant_id, -1, StockReason::Sale)?;
+
+    rebalance_deal(&tx, item_id)?;
+    recalculate_order_total(&tx, order_id)?;
+
+    tx.commit()?;
+    get_order(conn, order_id)
+}
+
+/// Change who an open order is for and its notes.
+pub fn update_order_notes(
+    conn: &mut Connection,
+    order_id: i64,
+    customer_name: Option<String>,
+    notes: Option<String>,
+) -> AppResult<OrderWithItems> {
+    let tx = begin_write(conn)?;
+    ensure_order_open(&tx, order_id)?;
+
+    tx.execute(
+        "UPDATE orders SET customer_name = ?1, notes = ?2 WHERE id = ?3",
+        rusqlite::params![customer_name, notes, order_id],
+    )?;
+
+    tx.commit()?;
+    get_order(conn, order_id)
+}
+
+pub fn get_today_orders(conn: &Connection) -> AppResult<Vec<OrderWithItems>> {
+    query_orders(
+        conn,
+        "WHERE date(o.created_at, 'localtime') = date('now', 'localtime')
+         ORDER BY o.status DESC, o.created_at DESC",
+        [],
+    )
+}
+
+pub fn get_open_orders(conn: &Connection) -> AppResult<Vec<OrderWithItems>> {
+    query_orders(conn, "WHERE o.status = 'open' ORDER BY o.table_number ASC", [])
+}
+
+pub fn get_order(conn: &Connection, id: i64) -> AppResult<OrderWithItems> {
+    let order = conn
+        .query_row(&format!("{} WHERE o.id = ?1", ORDER_SELECT), [id], order_from_row)
+        .map_err(not_found("order", id))?;
+
+    let items = get_order_items(conn, id)?;
+
+    Ok(OrderWithItems { order, items })
+}
@@ -0,0 +1,157 @@
+use crate::db::begin_write;
+use crate::error::{not_found, AppError, AppResult};
+use crate::models::{PriceChange, SchedulePriceChange};
+use crate::money::Money;
+use rusqlite::Connection;
+
+const CHANGE_SELECT: &str =
+    "SELECT h.id, h.product_id, h.old_price, h.new_price, h.staff_id, s.name, h.effective_at, h.applied_at, h.created_at
+     FROM product_price_history h
+     LEFT JOIN staff s ON h.staff_id = s.id";
+
+fn change_from_row(row: &rusqlite::Row) -> rusqlite::Result<PriceChange> {
+    Ok(PriceChange {
+        id: row.get(0)?,
+        product_id: row.get(1)?,
+        old_price: row.get(2)?,
+        new_price: row.get(3)?,
+        staff_id: row.get(4)?,
+        staff_name: row.get(5)?,
+        effective_at: row.get(6)?,
+        applied_at: row.get(7)?,
+        created_at: row.get(8)?,
+    })
+}
+
+fn get_price_change(conn: &Connection, id: i64) -> AppResult<PriceChange> {
+    conn.query_row(&format!("{} WHERE h.id = ?1", CHANGE_SELECT), [id], change_from_row)
+        .map_err(not_found("price change", id))
+}
+
+/// A product's prices in the order they took effect, scheduled ones last.
+pub fn get_price_history(conn: &Connection, product_id: i64) -> AppResult<Vec<PriceChange>> {
+    let mut stmt = conn.prepare(&format!(
+        "{} WHERE h.product_id = ?1 ORDER BY h.applied_at IS NULL, h.effective_at, h.id",
+        CHANGE_SELECT
+    ))?;
+
+    let changes = stmt
+        .query_map([product_id], change_from_row)?
+        .collect::<Result<Vec<_>, _>>()?;
+
+    Ok(changes)
+}
+
+/// Record a price change that takes effect now. Called wherever
+/// `products.price` is written.
+pub(crate) fn record_price_change(
+    conn: &Connection,
+    product_id: i64,
+    old_price: Option<Money>,
+    new_price: Money,
+    staff_id: Option<i64>,
+) -> AppResult<i64> {
+    conn.execute(
+        "INSERT INTO product_price_history (product_id, old_price, new_price, staff_id, effective_at, applied_at)
+         VALUES (?1, ?2, ?3, ?4, CURRENT_TIMESTAMP, CURRENT_TIMESTAMP)",
+        rusqlite::params![product_id, old_price, new_price, staff_id],
+    )?;
+
+    Ok(conn.last_insert_rowid())
+}
+
+/// Change a price now, or schedule it for a later local time.
+pub fn schedule_price_change(conn: &mut Connection, change: SchedulePriceChange) -> AppResult<PriceChange> {
+    if change.new_price.minor_units < 0 {
+        return Err(AppError::Validation("Price cannot be negative".to_string()));
+    }
+
+    let tx = begin_write(conn)?;
+
+    let current: Money = tx
+        .query_row("SELECT price FROM products WHERE id = ?1", [change.product_id], |row| row.get(0))
+        .map_err(not_found("product", change.product_id))?;
+
+    let scheduled_for: Option<String> = match change.effective_at.as_deref() {
+        None => None,
+        Some(local) => {
+            let (utc, due): (Option<String>, bool) = tx.query_row(
+                "SELECT datetime(?1, 'utc'), COALESCE(datetime(?1, 'utc') <= CURRENT_TIMESTAMP, 0)",
+                [local.trim()],
+                |row| Ok((row.get(0)?, row.get(1)?)),
+            )?;
+            let utc = utc.ok_or_else(|| AppError::Validation(format!("Invalid time: {}", local)))?;
+            (!due).then_some(utc)
+        }
+    };
+
+    let id = match scheduled_for {
+        Some(effective_at) => {
+            tx.execute(
+                "INSERT INTO product_price_history (product_id, new_price, staff_id, effective_at) VALUES (?1, ?2, ?3, ?4)",
+                rusqlite::params![change.product_id, change.new_price, change.staff_id, effective_at],
+            )?;
+            tx.last_insert_rowid()
+        }
+        None => {
+            tx.execute(
+                "UPDATE products SET price = ?1 WHERE id = ?2",
+                rusqlite::params![change.new_price, change.product_id],
+            )?;
+            record_price_change(&tx, change.product_id, Some(current), change.new_price, change.staff_id)?
+        }
+    };
+
+    tx.commit()?;
+    get_price_change(conn, id)
+}
+
+/// Drop a scheduled change before it takes effect.
+pub fn cancel_price_change(conn: &Connection, id: i64) -> AppResult<()> {
+    let applied: Option<String> = conn
+        .query_row("SELECT applied_at FROM product_price_history WHERE id = ?1", [id], |row| row.get(0))
+        .map_err(not_found("price change", id))?;
+
+    if applied.is_some() {
+        return Err(AppError::Validation("This price change has already taken effect".to_string()));
+    }
+
+    conn.execute("DELETE FROM product_price_history WHERE id = ?1", [id])?;
+
+    Ok(())
+}
+
+/// Apply scheduled changes that have fallen due, oldest first. Returns how
+/// many were applied.
+pub(crate) fn apply_due(conn: &Connection) -> AppResult<usize> {
+    let due: Vec<(i64, i64, Money)> = conn
+        .prepare(
+            "SELECT id, product_id, new_price FROM product_price_history
+             WHERE applied_at IS NULL AND effective_at <= CURRENT_TIMESTAMP
+             ORDER BY effective_at, id",
+        )?
+        .query_map([], |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?)))?
+        .collect::<Result<Vec<_>, _>>()?;
+
+    for (id, product_id, new_price) in &due {
+        conn.execute(
+            "UPDATE product_price_history
+             SET old_price = (SELECT price FROM products WHERE id = ?1), applied_at = CURRENT_TIMESTAMP
+             WHERE id = ?2",
+            [product_id, id],
+        )?;
+        conn.execute(
+            "UPDATE products SET price = ?1 WHERE id = ?2",
+            rusqlite::params![new_price, product_id],
+        )?;
+    }
+
+    Ok(due.len())
+}
+
+pub fn apply_due_price_changes(conn: &mut Connection) -> AppResult<usize> {
+    let tx = begin_write(conn)?;
+    let applied = apply_due(&tx)?;
+    tx.commit()?;
+    Ok(applied)
+}
@@ -0,0 +1,341 @@
+use crate::error::{not_found, AppError, AppResult};
+use crate::models::{CreatePricingRule, PricingRule, PricingRuleKind, UpdatePricingRule};
+use crate::money::Money;
+use crate::services::categories;
+use chrono::{Datelike, Days, NaiveDateTime, NaiveTime};
+use rusqlite::{Connection, Params};
+
+const RULE_SELECT: &str =
+    "SELECT r.id, r.name, r.kind, r.percent, r.amount, r.buy_quantity, r.free_quantity,
+            r.product_id, p.name, r.category_id, c.name, r.weekdays, r.start_time, r.end_time, r.active, r.created_at
+     FROM pricing_rules r
+     LEFT JOIN products p ON r.product_id = p.id
+     LEFT JOIN categories c ON r.category_id = c.id";
+
+const TIME_FORMAT: &str = "%H:%M";
+
+fn weekdays_from_mask(mask: i64) -> Vec<u32> {
+    (1..=7).filter(|day| mask & (1 << (day - 1)) != 0).collect()
+}
+
+fn weekdays_to_mask(weekdays: &[u32]) -> i64 {
+    weekdays.iter().fold(0, |mask, day| mask | (1 << (day - 1)))
+}
+
+fn rule_from_row(row: &rusqlite::Row) -> rusqlite::Result<PricingRule> {
+    Ok(PricingRule {
+        id: row.get(0)?,
+        name: row.get(1)?,
+        kind: row.get(2)?,
+        percent: row.get(3)?,
+        amount: row.get(4)?,
+        buy_quantity: row.get(5)?,
+        free_quantity: row.get(6)?,
+        product_id: row.get(7)?,
+        product_name: row.get(8)?,
+        category_id: row.get(9)?,
+        category_name: row.get(10)?,
+        weekdays: weekdays_from_mask(row.get(11)?),
+        start_time: row.get(12)?,
+        end_time: row.get(13)?,
+        active: row.get::<_, i32>(14)? == 1,
+        created_at: row.get(15)?,
+    })
+}
+
+fn query_rules<P: Params>(conn: &Connection, filter: &str, params: P) -> AppResult<Vec<PricingRule>> {
+    let mut stmt = conn.prepare(&format!("{} {}", RULE_SELECT, filter))?;
+
+    let rules = stmt
+        .query_map(params, rule_from_row)?
+        .collect::<Result<Vec<_>, _>>()?;
+
+    Ok(rules)
+}
+
+pub fn get_pricing_rules(conn: &Connection) -> AppResult<Vec<PricingRule>> {
+    query_rules(conn, "ORDER BY r.active DESC, r.name", [])
+}
+
+pub fn get_pricing_rule(conn: &Connection, id: i64) -> AppResult<PricingRule> {
+    conn.query_row(&format!("{} WHERE r.id = ?1", RULE_SELECT), [id], rule_from_row)
+        .map_err(not_found("pricing rule", id))
+}
+
+/// Rules in effect at `at`, local time.
+pub fn get_active_pricing_rules(conn: &Connection, at: NaiveDateTime) -> AppResult<Vec<PricingRule>> {
+    Ok(query_rules(conn, "WHERE r.active = 1 ORDER BY r.name", [])?
+        .into_iter()
+        .filter(|rule| in_effect(rule, at))
+        .collect())
+}
+
+fn invalid(name: &str, message: &str) -> AppError {
+    AppError::Validation(format!("{}: {}", name, message))
+}
+
+/// Check a rule and keep only the fields its kind uses.
+fn normalize(mut rule: CreatePricingRule) -> AppResult<CreatePricingRule> {
+    if rule.name.trim().is_empty() {
+        return Err(AppError::Validation("A pricing rule needs a name".to_string()));
+    }
+    if rule.product_id.is_some() && rule.category_id.is_some() {
+        return Err(invalid(&rule.name, "apply to a product or a category, not both"));
+    }
+    if rule.weekdays.iter().any(|day| !(1..=7).contains(day)) {
+        return Err(invalid(&rule.name, "weekdays run from 1 (Monday) to 7 (Sunday)"));
+    }
+
+    match rule.kind {
+        PricingRuleKind::PercentOff if rule.percent.is_none_or(|p| !(1..=100).contains(&p)) => {
+            return Err(invalid(&rule.name, "percent off must be between 1 and 100"));
+        }
+        PricingRuleKind::AmountOff if rule.amount.is_none_or(|a| a.minor_units <= 0) => {
+            return Err(invalid(&rule.name, "amount off must be above zero"));
+        }
+        PricingRuleKind::BuyXGetY
+            if !(rule.buy_quantity.is_some_and(|q| q > 0) && rule.free_quantity.is_some_and(|q| q > 0)) =>
+        {
+            return Err(invalid(&rule.name, "buy and free quantities must be above zero"));
+        }
+        _ => {}
+    }
+
+    match (rule.start_time.as_deref(), rule.end_time.as_deref()) {
+        (None, None) => {}
+        (Some(start), Some(end)) => {
+            let (Ok(start), Ok(end)) = (
+                NaiveTime::parse_from_str(start.trim(), TIME_FORMAT),
+                NaiveTime::parse_from_str(end.trim(), TIME_FORMAT),
+            ) else {
+                return Err(invalid(&rule.name, "times must be HH:MM"));
+            };
+            if start == end {
+                return Err(invalid(&rule.name, "the time window is empty"));
+            }
+            rule.start_time = Some(start.format(TIME_FORMAT).to_string());
+            rule.end_time = Some(end.format(TIME_FORMAT).to_string());
+        }
+        _ => return Err(invalid(&rule.name, "give both a start and an end time, or neither")),
+    }
+
+    if rule.kind != PricingRuleKind::PercentOff {
+        rule.percent = None;
+    }
+    if rule.kind != PricingRuleKind::AmountOff {
+        rule.amount = None;
+    }
+    if rule.kind != PricingRuleKind::BuyXGetY {
+        rule.buy_quantity = None;
+        rule.free_quantity = None;
+    }
+    rule.weekdays.sort_unstable();
+    rule.weekdays.dedup();
+
+    Ok(rule)
+}
+
+pub fn create_pricing_rule(conn: &Connection, rule: CreatePricingRule) -> AppResult<PricingRule> {
+    let rule = normalize(rule)?;
+
+    conn.execute(
+        "INSERT INTO pricing_rules
+            (name, kind, percent, amount, buy_quantity, free_quantity, product_id, category_id, weekdays, start_time, end_time)
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11)",
+        rusqlite::params![
+            rule.name,
+            rule.kind,
+            rule.percent,
+            rule.amount,
+            rule.buy_quantity,
+            rule.free_quantity,
+            rule.product_id,
+            rule.category_id,
+            weekdays_to_mask(&rule.weekdays),
+            rule.start_time,
+            rule.end_time
+        ],
+    )?;
+
+    get_pricing_rule(conn, conn.last_insert_rowid())
+}
+
+pub fn update_pricing_rule(conn: &Connection, update: UpdatePricingRule) -> AppResult<PricingRule> {
+    let rule = normalize(CreatePricingRule {
+        name: update.name,
+        kind: update.kind,
+        percent: update.percent,
+        amount: update.amount,
+        buy_quantity: update.buy_quantity,
+        free_quantity: update.free_quantity,
+        product_id: update.product_id,
+        category_id: update.category_id,
+        weekdays: update.weekdays,
+        start_time: update.start_time,
+        end_time: update.end_time,
+    })?;
+
+    let updated = conn.execute(
+        "UPDATE pricing_rules SET name = ?1, kind = ?2, percent = ?3, amount = ?4, buy_quantity = ?5,
+                free_quantity = ?6, product_id = ?7, category_id = ?8, weekdays = ?9, start_time = ?10,
+                end_time = ?11, active = ?12
+         WHERE id = ?13",
+        rusqlite::params![
+            rule.name,
+            rule.kind,
+            rule.percent,
+            rule.amount,
+            rule.buy_quantity,
+            rule.free_quantity,
+            rule.product_id,
+            rule.category_id,
+            weekdays_to_mask(&rule.weekdays),
+            rule.start_time,
+            rule.end_time,
+            update.active,
+            update.id
+        ],
+    )?;
+    if updated == 0 {
+        return Err(AppError::NotFound { entity: "pricing rule", id: update.id });
+    }
+
+    get_pricing_rule(conn, update.id)
+}
+
+/// Delete a rule that has never priced a sale; used rules are deactivated
+/// instead so order lines still point at them.
+pub fn delete_pricing_rule(conn: &Connection, id: i64) -> AppResult<()> {
+    let applied: i32 = conn.query_row(
+        "SELECT COUNT(*) FROM order_items WHERE pricing_rule_id = ?1",
+        [id],
+        |row| row.get(0),
+    )?;
+
+    if applied > 0 {
+        return Err(AppError::Validation(format!(
+            "Cannot delete a pricing rule applied to {} order lines; deactivate it instead",
+            applied
+        )));
+    }
+
+    conn.execute("DELETE FROM pricing_rules WHERE id = ?1", [id])?;
+
+    Ok(())
+}
+
+/// Whether a rule's schedule covers `at`. A window past midnight belongs to
+/// the day it started, so Friday 22:00-02:00 still holds at 01:00 Saturday.
+fn in_effect(rule: &PricingRule, at: NaiveDateTime) -> bool {
+    let window = match (rule.start_time.as_deref(), rule.end_time.as_deref()) {
+        (Some(start), Some(end)) => match (
+            NaiveTime::parse_from_str(start, TIME_FORMAT),
+            NaiveTime::parse_from_str(end, TIME_FORMAT),
+        ) {
+            (Ok(start), Ok(end)) => Some((start, end)),
+            _ => return false,
+        },
+        _ => None,
+    };
+
+    let time = at.time();
+    let day = match window {
+        None => at.date(),
+        Some((start, end)) if start < end => {
+            if time < start || time >= end {
+                return false;
+            }
+            at.date()
+        }
+        Some((start, end)) => {
+            if time >= start {
+                at.date()
+            } else if time < end {
+                at.date() - Days::new(1)
+            } else {
+                return false;
+            }
+        }
+    };
+
+    rule.weekdays.is_empty() || rule.weekdays.contains(&day.weekday().number_from_monday())
+}
+
+/// The rule a line is priced under: `unit_price` for every unit except
+/// `free_quantity`, which cost nothing.
+pub(crate) struct RulePrice {
+    pub rule_id: i64,
+    pub rule_name: String,
+    pub unit_price: Money,
+    pub free_quantity: i32,
+}
+
+/// The rule in effect at `at` that gives `quantity` of a product the lowest
+/// total, if any beats `list_price`. On a tie the oldest rule wins.
+pub(crate) fn best_price(
+    conn: &Connection,
+    product_id: i64,
+    list_price: Money,
+    quantity: i32,
+    at: NaiveDateTime,
+) -> AppResult<Option<RulePrice>> {
+    let category_id: Option<i64> = conn
+        .query_row("SELECT category_id FROM products WHERE id = ?1", [product_id], |row| row.get(0))
+        .map_err(not_found("product", product_id))?;
+    // A rule on a category covers its subcategories too
+    let categories = match category_id {
+        Some(id) => categories::lineage(conn, id)?.iter().map(|c| c.id.to_string()).collect::<Vec<_>>(),
+        None => Vec::new(),
+    };
+
+    let rules = query_rules(
+        conn,
+        &format!(
+            "WHERE r.active = 1
+               AND (r.product_id = ?1 OR r.category_id IN ({}) OR (r.product_id IS NULL AND r.category_id IS NULL))
+             ORDER BY r.id",
+            categories.join(", ")
+        ),
+        [product_id],
+    )?;
+
+    let mut best: Option<(Money, RulePrice)> = None;
+    let full_total = list_price * quantity;
+
+    for rule in rules.into_iter().filter(|rule| in_effect(rule, at)) {
+        let (unit_price, free_quantity) = match rule.kind {
+            PricingRuleKind::PercentOff => {
+                let percent = rule.percent.unwrap_or(0) as i64;
+                let discount = (list_price.minor_units * percent + 50) / 100;
+                (list_price - Money::from_minor(discount), 0)
+            }
+            PricingRuleKind::AmountOff => {
+                let discount = rule.amount.unwrap_or_else(Money::zero);
+                (Money::from_minor((list_price - discount).minor_units.max(0)), 0)
+            }
+            PricingRuleKind::BuyXGetY => {
+                let (buy, free) = (rule.buy_quantity.unwrap_or(1), rule.free_quantity.unwrap_or(0));
+                (list_price, quantity.max(0) / (buy + free) * free)
+            }
+        };
+
+        let total = unit_price * (quantity - free_quantity);
+        let beats = match &best {
+            Some((best_total, _)) => total.minor_units < best_total.minor_units,
+            None => total.minor_units < full_total.minor_units,
+        };
+        if beats {
+            best = Some((
+                total,
+                RulePrice {
+                    rule_id: rule.id,
+                    rule_name: rule.name,
+                    unit_price,
+                    free_quantity,
+                },
+            ));
+        }
+    }
+
+    Ok(best.map(|(_, price)| price))
+}
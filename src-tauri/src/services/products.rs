@@ -0,0 +1,430 @@
+use crate::db::{begin_write, referenced_by};
+use crate::error::{not_found, AppError, AppResult};
+use crate::models::{CreateProduct, CreateStockMovement, Product, StockReason, Unit, UpdateProduct};
+use crate::money::Money;
+use crate::services::{codes, prices, recipes, stock};
+use rusqlite::Connection;
+
+/// Servings come from the stock on hand, or for a product with a recipe from
+/// whichever ingredient runs out first.
+const PRODUCT_SELECT: &str =
+    "SELECT p.id, p.name, p.price, p.quantity, p.category_id, c.name, p.low_stock_threshold, p.created_at,
+            r.product_id IS NOT NULL, p.unit, p.serving_size,
+            CASE WHEN r.product_id IS NULL THEN p.quantity / p.serving_size ELSE r.servings END,
+            (SELECT GROUP_CONCAT(code, char(10)) FROM (
+                SELECT code FROM product_codes WHERE product_id = p.id AND variant_id IS NULL ORDER BY code
+            )),
+            p.cost_price, p.archived_at, p.aliases,
+            a.product_id IS NULL, a.unavailable_reason, a.unavailable_until, p.tax_rate_id
+     FROM products p
+     LEFT JOIN categories c ON p.category_id = c.id
+     LEFT JOIN (
+         SELECT rc.product_id, MIN(COALESCE(i.quantity, 0) / rc.quantity) AS servings
+         FROM recipe_components rc
+         LEFT JOIN products i ON rc.component_id = i.id
+         GROUP BY rc.product_id
+     ) r ON r.product_id = p.id
+     LEFT JOIN unavailable_products a ON a.product_id = p.id";
+
+fn product_from_row(row: &rusqlite::Row) -> rusqlite::Result<Product> {
+    Ok(Product {
+        id: row.get(0)?,
+        name: row.get(1)?,
+        price: row.get(2)?,
+        quantity: row.get(3)?,
+        category_id: row.get(4)?,
+        category_name: row.get(5)?,
+        low_stock_threshold: row.get(6)?,
+        created_at: row.get(7)?,
+        has_recipe: row.get(8)?,
+        unit: row.get(9)?,
+        serving_size: row.get(10)?,
+        servings: row.get(11)?,
+        codes: codes::split_codes(row.get(12)?),
+        cost_price: row.get(13)?,
+        archived_at: row.get(14)?,
+        aliases: row
+            .get::<_, Option<String>>(15)?
+            .map(|aliases| aliases.lines().map(str::to_string).collect())
+            .unwrap_or_default(),
+        available: row.get(16)?,
+        unavailable_reason: row.get(17)?,
+        unavailable_until: row.get(18)?,
+        tax_rate_id: row.get(19)?,
+    })
+}
+
+/// Aliases stored one per line, trimmed and without repeats.
+fn join_aliases(aliases: &[String]) -> Option<String> {
+    let mut kept: Vec<&str> = Vec::new();
+    for alias in aliases.iter().map(|a| a.trim()).filter(|a| !a.is_empty()) {
+        if !kept.contains(&alias) {
+            kept.push(alias);
+        }
+    }
+    (!kept.is_empty()).then(|| kept.join("\n"))
+}
+
+/// A piece sells one at a time; anything measured needs an explicit pour.
+fn resolve_serving_size(unit: Unit, serving_size: Option<i32>) -> AppResult<i32> {
+    match (unit, serving_size) {
+        (_, Some(size)) if size > 0 => Ok(size),
+        (Unit::Piece, None) => Ok(1),
+        (_, Some(size)) => Err(AppError::Validation(format!("Invalid serving size {}", size))),
+        (unit, None) => Err(AppError::Validation(format!(
+            "A serving size in {} is required for products measured in {}",
+            unit.base().as_str(),
+            unit.as_str()
+        ))),
+    }
+}
+
+/// Products on sale are those neither archived themselves nor in an
+/// archived category.
+const ON_SALE: &str = "p.archived_at IS NULL AND c.archived_at IS NULL";
+
+/// Products for sale, or every product for the back office.
+pub fn get_products(conn: &Connection, include_archived: bool) -> AppResult<Vec<Product>> {
+    let filter = if include_archived { "1" } else { ON_SALE };
+    let mut stmt = conn.prepare(&format!("{} WHERE {} ORDER BY p.name", PRODUCT_SELECT, filter))?;
+
+    let products = stmt
+        .query_map([], product_from_row)?
+        .collect::<Result<Vec<_>, _>>()?;
+
+    Ok(products)
+}
+
+pub fn get_product(conn: &Connection, id: i64) -> AppResult<Product> {
+    conn.query_row(
+        &format!("{} WHERE p.id = ?1", PRODUCT_SELECT),
+        [id],
+        product_from_row,
+    )
+    .map_err(not_found("product", id))
+}
+
+/// Turn what was typed into an FTS5 query in which every word must start a
+/// word of the product, so `rak sk` becomes `"rak"* "sk"*`.
+fn search_terms(query: &str) -> Option<String> {
+    let terms: Vec<String> = query
+        .split(|c: char| !c.is_alphanumeric())
+        .filter(|term| !term.is_empty())
+        .map(|term| format!("\"{}\"*", term))
+        .collect();
+    (!terms.is_empty()).then(|| terms.join(" "))
+}
+
+/// Products on sale matching `query` by name, category, code or alias, best
+/// first. The text score is boosted by units sold in the last 90 days so the
+/// regulars come up first; an empty query lists the best sellers.
+pub fn search_products(
+    conn: &Connection,
+    query: &str,
+    category_id: Option<i64>,
+    limit: usize,
+) -> AppResult<Vec<Product>> {
+    let terms = search_terms(query);
+    // Name counts most, then codes and aliases, then the category
+    let (score, search_join, search_filter) = match terms {
+        Some(_) => (
+            "bm25(product_search, 10.0, 2.0, 5.0, 5.0)",
+            "JOIN product_search ON product_search.rowid = p.id",
+            "product_search MATCH ?1",
+        ),
+        None => ("0.0", "", "?1 IS NULL"),
+    };
+
+    let mut matches: Vec<(i64, String, f64)> = conn
+        .prepare(&format!(
+            "SELECT p.id, p.name, {}, COALESCE(sold.quantity, 0)
+             FROM products p
+             {}
+             LEFT JOIN categories c ON p.category_id = c.id
+             LEFT JOIN (
+                 SELECT oi.product_id, SUM(oi.quantity) AS quantity
+                 FROM order_items oi
+                 JOIN orders o ON oi.order_id = o.id
+                 WHERE o.created_at >= datetime('now', '-90 days')
+                 GROUP BY oi.product_id
+             ) sold ON sold.product_id = p.id
+             WHERE {} AND {} AND (?2 IS NULL OR p.category_id = ?2)",
+            score, search_join, search_filter, ON_SALE
+        ))?
+        .query_map(rusqlite::params![terms, category_id], |row| {
+            let (bm25, sold): (f64, i64) = (row.get(2)?, row.get(3)?);
+            // bm25 is negative, lower being a better match
+            let score = (1.0 - bm25) * (1.0 + (sold.max(0) as f64).ln_1p() / 4.0);
+            Ok((row.get(0)?, row.get(1)?, score))
+        })?
+        .collect::<Result<Vec<_>, _>>()?;
+
+    matches.sort_by(|a, b| b.2.total_cmp(&a.2).then_with(|| a.1.cmp(&b.1)));
+    matches.truncate(limit);
+
+    matches.into_iter().map(|(id, _, _)| get_product(conn, id)).collect()
+}
+
+fn validate_cost(cost_price: Option<Money>) -> AppResult<()> {
+    if cost_price.is_some_and(|cost| cost.minor_units < 0) {
+        return Err(AppError::Validation("Cost price cannot be negative".to_string()));
+    }
+
+    Ok(())
+}
+
+pub fn create_product(conn: &mut Connection, product: CreateProduct) -> AppResult<Product> {
+    let tx = begin_write(conn)?;
+    let id = insert_product(&tx, &product)?;
+    tx.commit()?;
+    get_product(conn, id)
+}
+
+/// Insert a product with its codes, first price and opening stock inside the
+/// caller's transaction.
+pub(crate) fn insert_product(conn: &Connection, product: &CreateProduct) -> AppResult<i64> {
+    let serving_size = resolve_serving_size(product.unit, product.serving_size)?;
+    validate_cost(product.cost_price)?;
+    let threshold = product.low_stock_threshold.unwrap_or(5);
+
+    conn.execute(
+        "INSERT INTO products (name, price, quantity, category_id, low_stock_threshold, unit, serving_size, cost_price, aliases)
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9)",
+        rusqlite::params![
+            product.name,
+            product.price,
+            product.quantity,
+            product.category_id,
+            threshold,
+            product.unit,
+            serving_size,
+            product.cost_price,
+            join_aliases(&product.aliases)
+        ],
+    )?;
+
+    let id = conn.last_insert_rowid();
+
+    for code in &product.codes {
+        codes::insert_code(conn, id, None, code)?;
+    }
+    prices::record_price_change(conn, id, None, product.price, product.staff_id)?;
+
+    // Starting stock opens the product's ledger
+    if product.quantity != 0 {
+        let movement = CreateStockMovement {
+            product_id: id,
+            quantity_change: product.quantity,
+            reason: StockReason::OpeningBalance,
+            staff_id: product.staff_id,
+            reference: None,
+            note: None,
+            location_id: None,
+        };
+        stock::log_movement(conn, &movement, None)?;
+    }
+
+    Ok(id)
+}
+
+/// Update a product. A changed price goes into the price history, and a
+/// changed quantity is applied as a manual adjustment in the stock ledger
+/// rather than overwritten. Quantity is ignored for products
+/// with a recipe, whose stock is their ingredients'. The unit can only move
+/// to a different base (say pieces to millilitres) while nothing is in stock.
+pub fn update_product(conn: &mut Connection, product: UpdateProduct) -> AppResult<Product> {
+    let tx = begin_write(conn)?;
+    apply_update(&tx, &product)?;
+    tx.commit()?;
+    get_product(conn, product.id)
+}
+
+/// `update_product` inside the caller's transaction.
+pub(crate) fn apply_update(tx: &Connection, product: &UpdateProduct) -> AppResult<()> {
+    validate_cost(product.cost_price)?;
+
+    let (current, current_unit, current_serving_size, current_price): (i32, Unit, i32, Money) = tx
+        .query_row(
+            "SELECT quantity, unit, serving_size, price FROM products WHERE id = ?1",
+            [product.id],
+            |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?, row.get(3)?)),
+        )
+        .map_err(not_found("product", product.id))?;
+
+    let unit = product.unit.unwrap_or(current_unit);
+    let serving_size = if unit.base() == current_unit.base() {
+        resolve_serving_size(unit, product.serving_size.or(Some(current_serving_size)))?
+    } else {
+        if current != 0 || product.quantity != 0 {
+            return Err(AppError::Validation(format!(
+                "Cannot change {} from {} to {} while it has stock",
+                product.name,
+                current_unit.as_str(),
+                unit.as_str()
+            )));
+        }
+        resolve_serving_size(unit, product.serving_size)?
+    };
+
+    tx.execute(
+        "UPDATE products SET name = ?1, price = ?2, category_id = ?3, low_stock_threshold = ?4, unit = ?5, serving_size = ?6,
+                cost_price = COALESCE(?7, cost_price),
+                aliases = CASE WHEN ?8 THEN ?9 ELSE aliases END
+         WHERE id = ?10",
+        rusqlite::params![
+            product.name,
+            product.price,
+            product.category_id,
+            product.low_stock_threshold,
+            unit,
+            serving_size,
+            product.cost_price,
+            product.aliases.is_some(),
+            product.aliases.as_deref().and_then(join_aliases),
+            product.id
+        ],
+    )?;
+
+    if product.price != current_price {
+        prices::record_price_change(tx, product.id, Some(current_price), product.price, product.staff_id)?;
+    }
+
+    if product.quantity != current && !recipes::has_recipe(tx, product.id)? {
+        let movement = CreateStockMovement {
+            product_id: product.id,
+            quantity_change: product.quantity - current,
+            reason: StockReason::Adjustment,
+            staff_id: product.staff_id,
+            reference: None,
+            note: None,
+            location_id: None,
+        };
+        stock::apply_movement(tx, &movement, None)?;
+    }
+
+    Ok(())
+}
+
+/// Fail unless the product exists and is on sale.
+pub(crate) fn ensure_on_sale(conn: &Connection, id: i64) -> AppResult<()> {
+    let (name, on_sale): (String, bool) = conn
+        .query_row(
+            &format!(
+                "SELECT p.name, {} FROM products p LEFT JOIN categories c ON p.category_id = c.id WHERE p.id = ?1",
+                ON_SALE
+            ),
+            [id],
+            |row| Ok((row.get(0)?, row.get(1)?)),
+        )
+        .map_err(not_found("product", id))?;
+
+    if !on_sale {
+        return Err(AppError::Validation(format!("{} is archived and cannot be sold", name)));
+    }
+
+    Ok(())
+}
+
+fn recipes_using(conn: &Connection, id: i64) -> AppResult<Vec<String>> {
+    let names = conn
+        .prepare(
+            "SELECT p.name FROM recipe_components rc
+             JOIN products p ON rc.product_id = p.id
+             WHERE rc.component_id = ?1 AND p.archived_at IS NULL
+             ORDER BY p.name",
+        )?
+        .query_map([id], |row| row.get(0))?
+        .collect::<Result<Vec<_>, _>>()?;
+    Ok(names)
+}
+
+/// Take a product off sale. Its orders, stock and price history stay as
+/// they are. A product still used in a live recipe is refused.
+pub fn archive_product(conn: &Connection, id: i64) -> AppResult<Product> {
+    let used_in = recipes_using(conn, id)?;
+    if !used_in.is_empty() {
+        return Err(AppError::Validation(format!(
+            "Cannot archive a product used in recipes: {}",
+            used_in.join(", ")
+        )));
+    }
+
+    let updated = conn.execute(
+        "UPDATE products SET archived_at = COALESCE(archived_at, CURRENT_TIMESTAMP) WHERE id = ?1",
+        [id],
+    )?;
+    if updated == 0 {
+        return Err(AppError::NotFound { entity: "product", id });
+    }
+
+    get_product(conn, id)
+}
+
+pub fn restore_product(conn: &Connection, id: i64) -> AppResult<Product> {
+    let updated = conn.execute("UPDATE products SET archived_at = NULL WHERE id = ?1", [id])?;
+    if updated == 0 {
+        return Err(AppError::NotFound { entity: "product", id });
+    }
+
+    get_product(conn, id)
+}
+
+/// Delete an archived product with its recipe, variants, codes and price
+/// history. Refused while anything else still refers to it.
+pub fn purge_product(conn: &mut Connection, id: i64) -> AppResult<()> {
+    let tx = begin_write(conn)?;
+
+    let product = get_product(&tx, id)?;
+    if product.archived_at.is_none() {
+        return Err(AppError::Validation(format!("Archive {} before purging it", product.name)));
+    }
+
+    let references = referenced_by(
+        &tx,
+        id,
+        &[
+            ("order_items", "product_id", "orders"),
+            ("stock_movements", "product_id", "stock movements"),
+            ("stock_lots", "product_id", "stock lots"),
+            ("stock_lot_movements JOIN stock_lots ON lot_id = stock_lots.id", "product_id", "stock lots"),
+            ("purchase_order_lines", "product_id", "purchase orders"),
+            ("stock_take_counts", "product_id", "stock takes"),
+            ("stock_take_lines", "product_id", "stock takes"),
+            ("recipe_components", "component_id", "recipes"),
+            ("pricing_rules", "product_id", "pricing rules"),
+        ],
+    )?;
+    if !references.is_empty() {
+        return Err(AppError::Validation(format!(
+            "Cannot purge {}: still referenced by {}",
+            product.name,
+            references.join(", ")
+        )));
+    }
+
+    tx.execute("DELETE FROM recipe_components WHERE product_id = ?1", [id])?;
+    tx.execute("DELETE FROM product_variants WHERE product_id = ?1", [id])?;
+    tx.execute("DELETE FROM product_codes WHERE product_id = ?1", [id])?;
+    tx.execute("DELETE FROM product_price_history WHERE product_id = ?1", [id])?;
+    tx.execute("DELETE FROM modifier_group_links WHERE product_id = ?1", [id])?;
+    tx.execute("DELETE FROM location_stock WHERE product_id = ?1", [id])?;
+    tx.execute("DELETE FROM products WHERE id = ?1", [id])?;
+
+    tx.commit()?;
+    Ok(())
+}
+
+/// Products at or below their threshold, compared in base units so "under
+/// 1.5 bottles" of a 70 cl spirit is a threshold of 1050 ml.
+pub fn get_low_stock(conn: &Connection) -> AppResult<Vec<Product>> {
+    let mut stmt = conn.prepare(&format!(
+        "{} WHERE {} AND r.product_id IS NULL AND p.quantity <= p.low_stock_threshold ORDER BY p.quantity ASC",
+        PRODUCT_SELECT, ON_SALE
+    ))?;
+
+    let products = stmt
+        .query_map([], product_from_row)?
+        .collect::<Result<Vec<_>, _>>()?;
+
+    Ok(products)
+}
@@ -0,0 +1,496 @@
+use crate::db::begin_write;
+use crate::error::{not_found, AppError, AppResult};
+use crate::models::{
+    CreatePurchaseOrder, CreatePurchaseOrderLine, CreateStockMovement, CreateSupplier, PurchaseOrder,
+    PurchaseOrderLine, PurchaseOrderStatus, PurchaseOrderWithLines, PurchaseReceipt, ReceiveLine,
+    ReceivePurchaseOrder, StockReason, Supplier, SupplierBalance, UpdateSupplier,
+};
+use crate::money::Money;
+use crate::services::{codes, lots, recipes, stock};
+use rusqlite::Connection;
+
+// ============ SUPPLIERS ============
+
+const SUPPLIER_SELECT: &str =
+    "SELECT id, name, contact_name, phone, email, notes, created_at FROM suppliers";
+
+fn supplier_from_row(row: &rusqlite::Row) -> rusqlite::Result<Supplier> {
+    Ok(Supplier {
+        id: row.get(0)?,
+        name: row.get(1)?,
+        contact_name: row.get(2)?,
+        phone: row.get(3)?,
+        email: row.get(4)?,
+        notes: row.get(5)?,
+        created_at: row.get(6)?,
+    })
+}
+
+pub fn get_suppliers(conn: &Connection) -> AppResult<Vec<Supplier>> {
+    let mut stmt = conn.prepare(&format!("{} ORDER BY name", SUPPLIER_SELECT))?;
+
+    let suppliers = stmt
+        .query_map([], supplier_from_row)?
+        .collect::<Result<Vec<_>, _>>()?;
+
+    Ok(suppliers)
+}
+
+pub fn get_supplier(conn: &Connection, id: i64) -> AppResult<Supplier> {
+    conn.query_row(&format!("{} WHERE id = ?1", SUPPLIER_SELECT), [id], supplier_from_row)
+        .map_err(not_found("supplier", id))
+}
+
+pub fn create_supplier(conn: &Connection, supplier: CreateSupplier) -> AppResult<Supplier> {
+    conn.execute(
+        "INSERT INTO suppliers (name, contact_name, phone, email, notes) VALUES (?1, ?2, ?3, ?4, ?5)",
+        rusqlite::params![supplier.name, supplier.contact_name, supplier.phone, supplier.email, supplier.notes],
+    )?;
+
+    get_supplier(conn, conn.last_insert_rowid())
+}
+
+pub fn update_supplier(conn: &Connection, supplier: UpdateSupplier) -> AppResult<Supplier> {
+    conn.execute(
+        "UPDATE suppliers SET name = ?1, contact_name = ?2, phone = ?3, email = ?4, notes = ?5 WHERE id = ?6",
+        rusqlite::params![supplier.name, supplier.contact_name, supplier.phone, supplier.email, supplier.notes, supplier.id],
+    )?;
+
+    get_supplier(conn, supplier.id)
+}
+
+// ============ PURCHASE ORDERS ============
+
+const PURCHASE_ORDER_SELECT: &str =
+    "SELECT po.id, po.supplier_id, s.name, po.status, po.reference, po.notes, po.created_by,
+            (SELECT COALESCE(SUM(quantity_ordered * unit_cost), 0) FROM purchase_order_lines WHERE purchase_order_id = po.id),
+            po.created_at, po.sent_at, po.received_at
+     FROM purchase_orders po
+     LEFT JOIN suppliers s ON po.supplier_id = s.id";
+
+fn purchase_order_from_row(row: &rusqlite::Row) -> rusqlite::Result<PurchaseOrder> {
+    Ok(PurchaseOrder {
+        id: row.get(0)?,
+        supplier_id: row.get(1)?,
+        supplier_name: row.get(2)?,
+        status: row.get(3)?,
+        reference: row.get(4)?,
+        notes: row.get(5)?,
+        created_by: row.get(6)?,
+        total: row.get(7)?,
+        created_at: row.get(8)?,
+        sent_at: row.get(9)?,
+        received_at: row.get(10)?,
+    })
+}
+
+fn get_lines(conn: &Connection, purchase_order_id: i64) -> AppResult<Vec<PurchaseOrderLine>> {
+    let mut stmt = conn.prepare(
+        "SELECT l.id, l.purchase_order_id, l.product_id, p.name, l.quantity_ordered, l.quantity_received, l.unit_cost
+         FROM purchase_order_lines l
+         LEFT JOIN products p ON l.product_id = p.id
+         WHERE l.purchase_order_id = ?1
+         ORDER BY l.id",
+    )?;
+
+    let lines = stmt
+        .query_map([purchase_order_id], |row| {
+            Ok(PurchaseOrderLine {
+                id: row.get(0)?,
+                purchase_order_id: row.get(1)?,
+                product_id: row.get(2)?,
+                product_name: row.get(3)?,
+                quantity_ordered: row.get(4)?,
+                quantity_received: row.get(5)?,
+                unit_cost: row.get(6)?,
+            })
+        })?
+        .collect::<Result<Vec<_>, _>>()?;
+
+    Ok(lines)
+}
+
+pub fn get_purchase_order(conn: &Connection, id: i64) -> AppResult<PurchaseOrderWithLines> {
+    let purchase_order = conn
+        .query_row(
+            &format!("{} WHERE po.id = ?1", PURCHASE_ORDER_SELECT),
+            [id],
+            purchase_order_from_row,
+        )
+        .map_err(not_found("purchase order", id))?;
+
+    let lines = get_lines(conn, id)?;
+
+    Ok(PurchaseOrderWithLines { purchase_order, lines })
+}
+
+/// Purchase orders, newest first, optionally for one supplier and/or status.
+pub fn get_purchase_orders(
+    conn: &Connection,
+    supplier_id: Option<i64>,
+    status: Option<PurchaseOrderStatus>,
+) -> AppResult<Vec<PurchaseOrder>> {
+    let mut stmt = conn.prepare(&format!(
+        "{} WHERE (?1 IS NULL OR po.supplier_id = ?1) AND (?2 IS NULL OR po.status = ?2)
+         ORDER BY po.created_at DESC, po.id DESC",
+        PURCHASE_ORDER_SELECT
+    ))?;
+
+    let orders = stmt
+        .query_map(rusqlite::params![supplier_id, status], purchase_order_from_row)?
+        .collect::<Result<Vec<_>, _>>()?;
+
+    Ok(orders)
+}
+
+fn get_status(conn: &Connection, id: i64) -> AppResult<PurchaseOrderStatus> {
+    conn.query_row("SELECT status FROM purchase_orders WHERE id = ?1", [id], |row| row.get(0))
+        .map_err(not_found("purchase order", id))
+}
+
+fn wrong_status(id: i64, status: PurchaseOrderStatus, action: &str) -> AppError {
+    AppError::Validation(format!(
+        "Purchase order {} is {} and cannot be {}",
+        id,
+        status.as_str(),
+        action
+    ))
+}
+
+fn insert_lines(conn: &Connection, purchase_order_id: i64, lines: &[CreatePurchaseOrderLine]) -> AppResult<()> {
+    if lines.is_empty() {
+        return Err(AppError::Validation("A purchase order needs at least one line".to_string()));
+    }
+
+    for line in lines {
+        if line.quantity <= 0 || line.unit_cost.minor_units < 0 {
+            return Err(AppError::Validation(format!(
+                "Invalid quantity or cost for product {}",
+                line.product_id
+            )));
+        }
+        recipes::ensure_stock_tracked(conn, line.product_id)?;
+
+        conn.execute(
+            "INSERT INTO purchase_order_lines (purchase_order_id, product_id, quantity_ordered, unit_cost)
+             VALUES (?1, ?2, ?3, ?4)",
+            rusqlite::params![purchase_order_id, line.product_id, line.quantity, line.unit_cost],
+        )?;
+    }
+
+    Ok(())
+}
+
+pub fn create_purchase_order(
+    conn: &mut Connection,
+    order: CreatePurchaseOrder,
+) -> AppResult<PurchaseOrderWithLines> {
+    let tx = begin_write(conn)?;
+
+    get_supplier(&tx, order.supplier_id)?;
+
+    tx.execute(
+        "INSERT INTO purchase_orders (supplier_id, status, reference, notes, created_by) VALUES (?1, ?2, ?3, ?4, ?5)",
+        rusqlite::params![order.supplier_id, PurchaseOrderStatus::Draft, order.reference, order.notes, order.staff_id],
+    )?;
+
+    let id = tx.last_insert_rowid();
+    insert_lines(&tx, id, &order.lines)?;
+
+    tx.commit()?;
+    get_purchase_order(conn, id)
+}
+
+/// Replace the lines of a draft purchase order.
+pub fn update_purchase_order_lines(
+    conn: &mut Connection,
+    id: i64,
+    lines: Vec<CreatePurchaseOrderLine>,
+) -> AppResult<PurchaseOrderWithLines> {
+    let tx = begin_write(conn)?;
+
+    let status = get_status(&tx, id)?;
+    if status != PurchaseOrderStatus::Draft {
+        return Err(wrong_status(id, status, "edited"));
+    }
+
+    tx.execute("DELETE FROM purchase_order_lines WHERE purchase_order_id = ?1", [id])?;
+    insert_lines(&tx, id, &lines)?;
+
+    tx.commit()?;
+    get_purchase_order(conn, id)
+}
+
+pub fn send_purchase_order(conn: &mut Connection, id: i64) -> AppResult<PurchaseOrderWithLines> {
+    let tx = begin_write(conn)?;
+
+    let status = get_status(&tx, id)?;
+    if status != PurchaseOrderStatus::Draft {
+        return Err(wrong_status(id, status, "sent"));
+    }
+
+    tx.execute(
+        "UPDATE purchase_orders SET status = ?1, sent_at = CURRENT_TIMESTAMP WHERE id = ?2",
+        rusqlite::params![PurchaseOrderStatus::Sent, id],
+    )?;
+
+    tx.commit()?;
+    get_purchase_order(conn, id)
+}
+
+pub fn cancel_purchase_order(conn: &mut Connection, id: i64) -> AppResult<PurchaseOrderWithLines> {
+    let tx = begin_write(conn)?;
+
+    let status = get_status(&tx, id)?;
+    if !matches!(status, PurchaseOrderStatus::Draft | PurchaseOrderStatus::Sent) {
+        return Err(wrong_status(id, status, "cancelled"));
+    }
+
+    tx.execute(
+        "UPDATE purchase_orders SET status = ?1 WHERE id = ?2",
+        rusqlite::params![PurchaseOrderStatus::Cancelled, id],
+    )?;
+
+    tx.commit()?;
+    get_purchase_order(conn, id)
+}
+
+/// Receive a delivery against a sent purchase order. Each received line adds
+/// stock through the ledger (as a `delivery`, referenced to the PO), keeps
+/// the invoiced unit cost in `purchase_receipts` and averages it into the
+/// product's cost price.
+pub fn receive_purchase_order(
+    conn: &mut Connection,
+    id: i64,
+    receipt: ReceivePurchaseOrder,
+) -> AppResult<PurchaseOrderWithLines> {
+    let tx = begin_write(conn)?;
+
+    let status = get_status(&tx, id)?;
+    if !matches!(status, PurchaseOrderStatus::Sent | PurchaseOrderStatus::PartiallyReceived) {
+        return Err(wrong_status(id, status, "received"));
+    }
+
+    let mut lines = get_lines(&tx, id)?;
+    let deliveries: Vec<ReceiveLine> = match receipt.lines {
+        Some(deliveries) => deliveries,
+        None => lines
+            .iter()
+            .filter(|l| l.quantity_received < l.quantity_ordered)
+            .map(|l| ReceiveLine {
+                line_id: l.id,
+                quantity: l.quantity_ordered - l.quantity_received,
+                unit_cost: None,
+                expires_on: None,
+            })
+            .collect(),
+    };
+
+    if deliveries.is_empty() {
+        return Err(AppError::Validation("Nothing to receive".to_string()));
+    }
+
+    let reference = receipt.reference.clone().unwrap_or_else(|| format!("PO-{}", id));
+
+    for delivery in &deliveries {
+        // Kept up to date as we go, so a line named twice cannot be over-received
+        let line = lines
+            .iter_mut()
+            .find(|l| l.id == delivery.line_id)
+            .ok_or(AppError::NotFound { entity: "purchase order line", id: delivery.line_id })?;
+
+        let outstanding = line.quantity_ordered - line.quantity_received;
+        if delivery.quantity <= 0 || delivery.quantity > outstanding {
+            return Err(AppError::Validation(format!(
+                "Cannot receive {} of {}: {} outstanding",
+                delivery.quantity,
+                line.product_name.as_deref().unwrap_or("product"),
+                outstanding
+            )));
+        }
+
+        let unit_cost: Money = delivery.unit_cost.unwrap_or(line.unit_cost);
+        if unit_cost.minor_units < 0 {
+            return Err(AppError::Validation(format!(
+                "Invalid cost for {}",
+                line.product_name.as_deref().unwrap_or("product")
+            )));
+        }
+
+        tx.execute(
+            "UPDATE purchase_order_lines SET quantity_received = quantity_received + ?1 WHERE id = ?2",
+            rusqlite::params![delivery.quantity, line.id],
+        )?;
+        line.quantity_received += delivery.quantity;
+        tx.execute(
+            "INSERT INTO purchase_receipts (line_id, quantity, unit_cost, received_by, reference) VALUES (?1, ?2, ?3, ?4, ?5)",
+            rusqlite::params![line.id, delivery.quantity, unit_cost, receipt.staff_id, reference],
+        )?;
+
+        average_in_cost(&tx, line.product_id, delivery.quantity, unit_cost)?;
+        let movement = CreateStockMovement {
+            product_id: line.product_id,
+            quantity_change: delivery.quantity,
+            reason: StockReason::Delivery,
+            staff_id: receipt.staff_id,
+            reference: Some(reference.clone()),
+            note: None,
+            location_id: None,
+        };
+        let movement_id = stock::apply_movement(&tx, &movement, None)?;
+        if delivery.expires_on.is_some() {
+            let expires_on = delivery.expires_on.as_deref();
+            lots::insert_lot(&tx, movement_id, line.product_id, delivery.quantity, expires_on, None)?;
+        }
+    }
+
+    let outstanding_lines: i32 = tx.query_row(
+        "SELECT COUNT(*) FROM purchase_order_lines WHERE purchase_order_id = ?1 AND quantity_received < quantity_ordered",
+        [id],
+        |row| row.get(0),
+    )?;
+
+    if outstanding_lines == 0 {
+        tx.execute(
+            "UPDATE purchase_orders SET status = ?1, received_at = CURRENT_TIMESTAMP WHERE id = ?2",
+            rusqlite::params![PurchaseOrderStatus::Received, id],
+        )?;
+    } else {
+        tx.execute(
+            "UPDATE purchase_orders SET status = ?1 WHERE id = ?2",
+            rusqlite::params![PurchaseOrderStatus::PartiallyReceived, id],
+        )?;
+    }
+
+    tx.commit()?;
+    get_purchase_order(conn, id)
+}
+
+/// Fold a delivery into the product's weighted-average cost. Stock that was
+/// never costed, or is already gone, takes the delivery's cost as it is.
+fn average_in_cost(conn: &Connection, product_id: i64, quantity: i32, unit_cost: Money) -> AppResult<()> {
+    conn.execute(
+        "UPDATE products SET cost_price = CASE
+            WHEN quantity <= 0 OR cost_price IS NULL THEN ?2
+            ELSE (quantity * cost_price + ?1 * ?2 + (quantity + ?1) / 2) / (quantity + ?1)
+         END
+         WHERE id = ?3",
+        rusqlite::params![quantity, unit_cost, product_id],
+    )?;
+
+    Ok(())
+}
+
+/// Receive a scanned delivery against the outstanding line for the code's
+/// product. Each scan counts as the pack the code stands for.
+pub fn receive_by_code(
+    conn: &mut Connection,
+    id: i64,
+    code: String,
+    quantity: i32,
+    staff_id: Option<i64>,
+) -> AppResult<PurchaseOrderWithLines> {
+    let (product_id, _) = codes::resolve_code(conn, &code)?;
+    let per_unit = codes::pack_quantity(conn, &code)?;
+
+    let line = get_lines(conn, id)?
+        .into_iter()
+        .find(|l| l.product_id == product_id && l.quantity_received < l.quantity_ordered)
+        .ok_or_else(|| {
+            AppError::Validation(format!(
+                "Nothing outstanding for code {} on purchase order {}",
+                code.trim(),
+                id
+            ))
+        })?;
+
+    let receipt = ReceivePurchaseOrder {
+        staff_id,
+        reference: None,
+        lines: Some(vec![ReceiveLine {
+            line_id: line.id,
+            quantity: quantity * per_unit,
+            unit_cost: None,
+            expires_on: None,
+        }]),
+    };
+    receive_purchase_order(conn, id, receipt)
+}
+
+// ============ REPORTS ============
+
+/// Per supplier: how many orders are still awaiting delivery, what is still
+/// to come at ordered cost, and what has been delivered at invoiced cost.
+pub fn get_supplier_balances(conn: &Connection) -> AppResult<Vec<SupplierBalance>> {
+    let mut stmt = conn.prepare(
+        "SELECT s.id, s.name,
+                (SELECT COUNT(*) FROM purchase_orders po
+                  WHERE po.supplier_id = s.id AND po.status IN ('sent', 'partially_received')),
+                (SELECT COALESCE(SUM((l.quantity_ordered - l.quantity_received) * l.unit_cost), 0)
+                   FROM purchase_order_lines l
+                   JOIN purchase_orders po ON l.purchase_order_id = po.id
+                  WHERE po.supplier_id = s.id AND po.status IN ('sent', 'partially_received')),
+                (SELECT COALESCE(SUM(r.quantity * r.unit_cost), 0)
+                   FROM purchase_receipts r
+                   JOIN purchase_order_lines l ON r.line_id = l.id
+                   JOIN purchase_orders po ON l.purchase_order_id = po.id
+                  WHERE po.supplier_id = s.id)
+         FROM suppliers s
+         ORDER BY s.name",
+    )?;
+
+    let balances = stmt
+        .query_map([], |row| {
+            Ok(SupplierBalance {
+                supplier_id: row.get(0)?,
+                supplier_name: row.get(1)?,
+                open_orders: row.get(2)?,
+                outstanding_value: row.get(3)?,
+                received_value: row.get(4)?,
+            })
+        })?
+        .collect::<Result<Vec<_>, _>>()?;
+
+    Ok(balances)
+}
+
+/// Every delivery received from a supplier, newest first, for reconciling
+/// invoices. Dates are `YYYY-MM-DD` in local time, both ends inclusive.
+pub fn get_supplier_purchase_history(
+    conn: &Connection,
+    supplier_id: i64,
+    start_date: Option<&str>,
+    end_date: Option<&str>,
+) -> AppResult<Vec<PurchaseReceipt>> {
+    let mut stmt = conn.prepare(
+        "SELECT r.id, l.purchase_order_id, l.product_id, p.name, r.quantity, r.unit_cost,
+                r.quantity * r.unit_cost, r.received_by, r.reference, r.received_at
+         FROM purchase_receipts r
+         JOIN purchase_order_lines l ON r.line_id = l.id
+         JOIN purchase_orders po ON l.purchase_order_id = po.id
+         LEFT JOIN products p ON l.product_id = p.id
+         WHERE po.supplier_id = ?1
+           AND (?2 IS NULL OR date(r.received_at, 'localtime') >= ?2)
+           AND (?3 IS NULL OR date(r.received_at, 'localtime') <= ?3)
+         ORDER BY r.received_at DESC, r.id DESC",
+    )?;
+
+    let receipts = stmt
+        .query_map(rusqlite::params![supplier_id, start_date, end_date], |row| {
+            Ok(PurchaseReceipt {
+                id: row.get(0)?,
+                purchase_order_id: row.get(1)?,
+                product_id: row.get(2)?,
+                product_name: row.get(3)?,
+                quantity: row.get(4)?,
+                unit_cost: row.get(5)?,
+                line_total: row.get(6)?,
+                received_by: row.get(7)?,
+                reference: row.get(8)?,
+                received_at: row.get(9)?,
+            })
+        })?
+        .collect::<Result<Vec<_>, _>>()?;
+
+    Ok(receipts)
+}
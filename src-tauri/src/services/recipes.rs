@@ -0,0 +1,155 @@
+use crate::db::begin_write;
+use crate::error::{not_found, AppError, AppResult};
+use crate::models::{RecipeComponent, SetRecipeComponent};
+use rusqlite::Connection;
+
+pub fn get_recipe(conn: &Connection, product_id: i64) -> AppResult<Vec<RecipeComponent>> {
+    let mut stmt = conn.prepare(
+        "SELECT rc.component_id, p.name, rc.quantity, COALESCE(p.quantity, 0), COALESCE(p.unit, 'piece')
+         FROM recipe_components rc
+         LEFT JOIN products p ON rc.component_id = p.id
+         WHERE rc.product_id = ?1
+         ORDER BY p.name",
+    )?;
+
+    let components = stmt
+        .query_map([product_id], |row| {
+            Ok(RecipeComponent {
+                component_id: row.get(0)?,
+                component_name: row.get(1)?,
+                quantity: row.get(2)?,
+                available: row.get(3)?,
+                unit: row.get(4)?,
+            })
+        })?
+        .collect::<Result<Vec<_>, _>>()?;
+
+    Ok(components)
+}
+
+pub(crate) fn has_recipe(conn: &Connection, product_id: i64) -> AppResult<bool> {
+    Ok(conn.query_row(
+        "SELECT EXISTS (SELECT 1 FROM recipe_components WHERE product_id = ?1)",
+        [product_id],
+        |row| row.get(0),
+    )?)
+}
+
+/// Replace a product's recipe. An empty list turns it back into a product
+/// with its own stock. Recipes are one level deep: a component cannot itself
+/// have a recipe, and a product used as a component cannot get one.
+pub fn set_recipe(
+    conn: &mut Connection,
+    product_id: i64,
+    components: Vec<SetRecipeComponent>,
+) -> AppResult<Vec<RecipeComponent>> {
+    let tx = begin_write(conn)?;
+
+    let (name, quantity): (String, i32) = tx
+        .query_row(
+            "SELECT name, quantity FROM products WHERE id = ?1",
+            [product_id],
+            |row| Ok((row.get(0)?, row.get(1)?)),
+        )
+        .map_err(not_found("product", product_id))?;
+
+    if !components.is_empty() {
+        if quantity != 0 {
+            return Err(AppError::Validation(format!(
+                "{} still has {} in stock; adjust it to zero before giving it a recipe",
+                name, quantity
+            )));
+        }
+
+        let used_in: i32 = tx.query_row(
+            "SELECT COUNT(*) FROM recipe_components WHERE component_id = ?1",
+            [product_id],
+            |row| row.get(0),
+        )?;
+        if used_in > 0 {
+            return Err(AppError::Validation(format!(
+                "{} is an ingredient of another recipe and cannot have one itself",
+                name
+            )));
+        }
+
+        let variants: i32 = tx.query_row(
+            "SELECT COUNT(*) FROM product_variants WHERE product_id = ?1",
+            [product_id],
+            |row| row.get(0),
+        )?;
+        if variants > 0 {
+            return Err(AppError::Validation(format!("{} has variants and cannot have a recipe", name)));
+        }
+    }
+
+    tx.execute("DELETE FROM recipe_components WHERE product_id = ?1", [product_id])?;
+
+    for component in &components {
+        if component.component_id == product_id {
+            return Err(AppError::Validation(format!("{} cannot be an ingredient of itself", name)));
+        }
+        if component.quantity <= 0 {
+            return Err(AppError::Validation(format!(
+                "Invalid ingredient quantity {}",
+                component.quantity
+            )));
+        }
+
+        let component_name: String = tx
+            .query_row(
+                "SELECT name FROM products WHERE id = ?1",
+                [component.component_id],
+                |row| row.get(0),
+            )
+            .map_err(not_found("product", component.component_id))?;
+        if has_recipe(&tx, component.component_id)? {
+            return Err(AppError::Validation(format!(
+                "{} has its own recipe and cannot be used as an ingredient",
+                component_name
+            )));
+        }
+
+        tx.execute(
+            "INSERT INTO recipe_components (product_id, component_id, quantity) VALUES (?1, ?2, ?3)
+             ON CONFLICT (product_id, component_id) DO UPDATE SET quantity = quantity + excluded.quantity",
+            rusqlite::params![product_id, component.component_id, component.quantity],
+        )?;
+    }
+
+    tx.commit()?;
+    get_recipe(conn, product_id)
+}
+
+/// The stock items one serving of a product consumes, in base units: its
+/// components, or its own serving size when it has no recipe.
+pub(crate) fn stock_items(conn: &Connection, product_id: i64) -> AppResult<Vec<(i64, i32)>> {
+    let mut stmt = conn.prepare(
+        "SELECT component_id, quantity FROM recipe_components WHERE product_id = ?1 ORDER BY component_id",
+    )?;
+
+    let components = stmt
+        .query_map([product_id], |row| Ok((row.get(0)?, row.get(1)?)))?
+        .collect::<Result<Vec<(i64, i32)>, _>>()?;
+
+    if components.is_empty() {
+        let serving_size: i32 = conn
+            .query_row("SELECT serving_size FROM products WHERE id = ?1", [product_id], |row| row.get(0))
+            .map_err(not_found("product", product_id))?;
+        Ok(vec![(product_id, serving_size)])
+    } else {
+        Ok(components)
+    }
+}
+
+/// Fail unless the product holds its own stock. Stock for a product with a
+/// recipe lives in its ingredients, so it cannot be adjusted or counted.
+pub(crate) fn ensure_stock_tracked(conn: &Connection, product_id: i64) -> AppResult<()> {
+    if has_recipe(conn, product_id)? {
+        return Err(AppError::Validation(
+            "This product is made from a recipe; adjust its ingredients instead".to_string(),
+        ));
+    }
+
+    Ok(())
+}
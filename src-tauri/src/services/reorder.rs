@@ -0,0 +1,184 @@
+use crate::db::begin_write;
+use crate::error::{AppError, AppResult};
+use crate::models::{Product, ReorderSuggestion, SupplierReorder, Unit};
+use crate::money::Money;
+use crate::services::products;
+use rusqlite::Connection;
+
+pub const DEFAULT_COVER_DAYS: i32 = 7;
+pub const DEFAULT_SESSIONS: i32 = 14;
+
+struct Velocity {
+    product_id: i64,
+    product_name: String,
+    unit: Unit,
+    quantity: i32,
+    on_order: i32,
+    used: i64,
+    supplier_id: Option<i64>,
+    supplier_name: Option<String>,
+    unit_cost: Option<Money>,
+}
+
+/// Net base units each stocked product on sale used across the last
+/// `sessions` trading sessions, with the supplier and cost of its latest
+/// purchase order line. Returns the number of sessions actually found.
+fn sales_velocity(conn: &Connection, sessions: i32) -> AppResult<(i64, Vec<Velocity>)> {
+    if sessions < 1 {
+        return Err(AppError::Validation("Average over at least one session".to_string()));
+    }
+
+    let found: i64 = conn.query_row(
+        "SELECT COUNT(*) FROM (SELECT id FROM day_sessions ORDER BY started_at DESC, id DESC LIMIT ?1)",
+        [sessions],
+        |row| row.get(0),
+    )?;
+    if found == 0 {
+        return Ok((0, Vec::new()));
+    }
+
+    let mut stmt = conn.prepare(
+        "WITH recent AS (
+             SELECT id FROM day_sessions ORDER BY started_at DESC, id DESC LIMIT ?1
+         ),
+         used AS (
+             SELECT m.product_id, -SUM(m.quantity_change) AS used
+             FROM stock_movements m
+             JOIN orders o ON m.order_id = o.id
+             WHERE m.reason IN ('sale', 'return') AND o.session_id IN (SELECT id FROM recent)
+             GROUP BY m.product_id
+         ),
+         on_order AS (
+             SELECT l.product_id, SUM(MAX(l.quantity_ordered - l.quantity_received, 0)) AS quantity
+             FROM purchase_order_lines l
+             JOIN purchase_orders po ON l.purchase_order_id = po.id
+             WHERE po.status IN ('sent', 'partially_received')
+             GROUP BY l.product_id
+         ),
+         last_line AS (
+             SELECT l.product_id, po.supplier_id, l.unit_cost,
+                    ROW_NUMBER() OVER (PARTITION BY l.product_id ORDER BY po.created_at DESC, l.id DESC) AS n
+             FROM purchase_order_lines l
+             JOIN purchase_orders po ON l.purchase_order_id = po.id
+             WHERE po.status <> 'cancelled'
+         )
+         SELECT p.id, p.name, p.unit, p.quantity, COALESCE(oo.quantity, 0), u.used,
+                ll.supplier_id, s.name, ll.unit_cost
+         FROM products p
+         JOIN used u ON u.product_id = p.id
+         LEFT JOIN categories c ON p.category_id = c.id
+         LEFT JOIN on_order oo ON oo.product_id = p.id
+         LEFT JOIN last_line ll ON ll.product_id = p.id AND ll.n = 1
+         LEFT JOIN suppliers s ON ll.supplier_id = s.id
+         WHERE p.archived_at IS NULL AND c.archived_at IS NULL AND u.used > 0
+           AND p.id NOT IN (SELECT product_id FROM recipe_components)",
+    )?;
+
+    let velocities = stmt
+        .query_map([sessions], |row| {
+            Ok(Velocity {
+                product_id: row.get(0)?,
+                product_name: row.get(1)?,
+                unit: row.get(2)?,
+                quantity: row.get(3)?,
+                on_order: row.get(4)?,
+                used: row.get(5)?,
+                supplier_id: row.get(6)?,
+                supplier_name: row.get(7)?,
+                unit_cost: row.get(8)?,
+            })
+        })?
+        .collect::<Result<Vec<_>, _>>()?;
+
+    Ok((found, velocities))
+}
+
+/// What to order, per supplier, so stock lasts until a delivery placed now
+/// arrives in `lead_time_days` and for `cover_days` after. Usage is averaged
+/// over the last `sessions` trading sessions, each counted as a day.
+pub fn get_reorder_suggestions(
+    conn: &Connection,
+    lead_time_days: i32,
+    cover_days: i32,
+    sessions: i32,
+) -> AppResult<Vec<SupplierReorder>> {
+    if lead_time_days < 0 || cover_days < 0 {
+        return Err(AppError::Validation("Lead time and cover cannot be negative".to_string()));
+    }
+
+    let (found, velocities) = sales_velocity(conn, sessions)?;
+
+    let mut groups: Vec<SupplierReorder> = Vec::new();
+    for v in velocities {
+        let daily_usage = v.used as f64 / found as f64;
+        let target = (daily_usage * (lead_time_days + cover_days) as f64).ceil() as i32;
+        let suggested_quantity = (target - v.quantity.max(0) - v.on_order).max(0);
+
+        let line = ReorderSuggestion {
+            product_id: v.product_id,
+            product_name: v.product_name,
+            unit: v.unit,
+            quantity: v.quantity,
+            on_order: v.on_order,
+            daily_usage,
+            days_of_cover: v.quantity.max(0) as f64 / daily_usage,
+            reorder_point: (daily_usage * lead_time_days as f64).ceil() as i32,
+            suggested_quantity,
+            unit_cost: v.unit_cost,
+        };
+
+        let index = match groups.iter().position(|g| g.supplier_id == v.supplier_id) {
+            Some(index) => index,
+            None => {
+                groups.push(SupplierReorder {
+                    supplier_id: v.supplier_id,
+                    supplier_name: v.supplier_name,
+                    lines: Vec::new(),
+                    estimated_cost: Money::zero(),
+                });
+                groups.len() - 1
+            }
+        };
+        let group = &mut groups[index];
+        if let Some(cost) = line.unit_cost {
+            group.estimated_cost += cost * suggested_quantity;
+        }
+        group.lines.push(line);
+    }
+
+    // Suppliers by name with never-ordered products last; most urgent first
+    groups.sort_by(|a, b| {
+        (a.supplier_name.is_none(), &a.supplier_name).cmp(&(b.supplier_name.is_none(), &b.supplier_name))
+    });
+    for group in &mut groups {
+        group.lines.sort_by(|a, b| a.days_of_cover.total_cmp(&b.days_of_cover));
+    }
+
+    Ok(groups)
+}
+
+/// Set `low_stock_threshold` to each selling product's reorder point, so it
+/// shows as low while there is still time to restock. Products without
+/// recent sales keep their threshold. Returns the products changed.
+pub fn tune_low_stock_thresholds(
+    conn: &mut Connection,
+    lead_time_days: i32,
+    sessions: i32,
+) -> AppResult<Vec<Product>> {
+    let suggestions = get_reorder_suggestions(conn, lead_time_days, 0, sessions)?;
+
+    let tx = begin_write(conn)?;
+    let mut tuned = Vec::new();
+    for line in suggestions.iter().flat_map(|g| &g.lines) {
+        let updated = tx.execute(
+            "UPDATE products SET low_stock_threshold = ?1 WHERE id = ?2 AND low_stock_threshold <> ?1",
+            [line.reorder_point as i64, line.product_id],
+        )?;
+        if updated > 0 {
+            tuned.push(line.product_id);
+        }
+    }
+    tx.commit()?;
+
+    tuned.into_iter().map(|id| products::get_product(conn, id)).collect()
+}
@@ -0,0 +1,599 @@
+use crate::db::begin_write;
+use crate::error::{AppError, AppResult};
+use crate::models::{
+    Category, CategoryMargin, CategorySales, DaySession, DaySummary, OrderWithItems, ProductMargin, ProductSales,
+    SessionMargin, TaxBreakdown, VariantSales,
+};
+use crate::money::Money;
+use crate::services::orders::query_orders;
+use crate::services::{categories, staff};
+use rusqlite::{Connection, Params};
+use std::fs;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+const SESSION_SELECT: &str =
+    "SELECT ds.id, ds.date, ds.started_by, s.name, ds.started_at, ds.closed_at, ds.is_active, ds.total_revenue, ds.total_orders
+     FROM day_sessions ds
+     LEFT JOIN staff s ON ds.started_by = s.id";
+
+fn session_from_row(row: &rusqlite::Row) -> rusqlite::Result<DaySession> {
+    Ok(DaySession {
+        id: row.get(0)?,
+        date: row.get(1)?,
+        started_by: row.get(2)?,
+        started_by_name: row.get(3)?,
+        started_at: row.get(4)?,
+        closed_at: row.get(5)?,
+        is_active: row.get::<_, i32>(6)? == 1,
+        total_revenue: row.get(7)?,
+        total_orders: row.get(8)?,
+        taxes: Vec::new(),
+    })
+}
+
+fn get_session(conn: &Connection, id: i64) -> AppResult<DaySession> {
+    let mut session = conn.query_row(&format!("{} WHERE ds.id = ?1", SESSION_SELECT), [id], session_from_row)?;
+    session.taxes = session_taxes(conn, id)?;
+    Ok(session)
+}
+
+/// Net, tax and gross per tax rate across a session's orders, highest rate
+/// first. Net is what the prices come to before tax, gross what was charged.
+fn session_taxes(conn: &Connection, session_id: i64) -> AppResult<Vec<TaxBreakdown>> {
+    let mut stmt = conn.prepare(
+        "SELECT oi.tax_name, oi.tax_basis_points,
+                SUM(oi.quantity * oi.price_at_sale - CASE WHEN oi.tax_included THEN oi.tax_amount ELSE 0 END),
+                SUM(oi.tax_amount),
+                SUM(oi.quantity * oi.price_at_sale + CASE WHEN oi.tax_included THEN 0 ELSE oi.tax_amount END)
+         FROM order_items oi
+         JOIN orders o ON oi.order_id = o.id
+         WHERE o.session_id = ?1
+         GROUP BY oi.tax_name, oi.tax_basis_points
+         ORDER BY oi.tax_basis_points DESC, oi.tax_name",
+    )?;
+
+    let taxes = stmt
+        .query_map([session_id], |row| {
+            Ok(TaxBreakdown {
+                tax_name: row.get(0)?,
+                basis_points: row.get(1)?,
+                net: row.get(2)?,
+                tax: row.get(3)?,
+                gross: row.get(4)?,
+            })
+        })?
+        .collect::<Result<Vec<_>, _>>()?;
+
+    Ok(taxes)
+}
+
+/// Get all orders within a date range (for recovery/reporting)
+pub fn get_orders_by_date_range(
+    conn: &Connection,
+    start_date: &str,
+    end_date: &str,
+) -> AppResult<Vec<OrderWithItems>> {
+    query_orders(
+        conn,
+        "WHERE date(o.created_at, 'localtime') >= ?1 AND date(o.created_at, 'localtime') <= ?2
+         ORDER BY o.created_at DESC",
+        [start_date, end_date],
+    )
+}
+
+/// Manually create a day closing for a specific date (for recovery)
+/// This creates a closed session for orders from legacy data or missed closings
+pub fn create_day_closing_for_date(conn: &mut Connection, date: &str) -> AppResult<DaySession> {
+    let tx = begin_write(conn)?;
+
+    // Check if a closed session already exists for this date
+    let existing: Result<i64, _> = tx.query_row(
+        "SELECT id FROM day_sessions WHERE date = ?1 AND is_active = 0",
+        [date],
+        |row| row.get(0),
+    );
+
+    if existing.is_ok() {
+        return Err(AppError::Validation(format!("A closed session already exists for {}", date)));
+    }
+
+    // Calculate totals for that date (orders without session_id or with matching date)
+    let (total_revenue, total_orders): (Money, i32) = tx.query_row(
+        "SELECT COALESCE(SUM(total), 0), COUNT(*) FROM orders WHERE date(created_at, 'localtime') = ?1",
+        [date],
+        |row| Ok((row.get(0)?, row.get(1)?)),
+    )?;
+
+    if total_orders == 0 {
+        return Err(AppError::Validation(format!("No orders found for {}", date)));
+    }
+
+    // Get first staff member as the "started_by" (for recovery purposes)
+    let staff_id: i64 = tx
+        .query_row("SELECT id FROM staff LIMIT 1", [], |row| row.get(0))
+        .unwrap_or(1);
+
+    // Create a closed session for recovery
+    tx.execute(
+        "INSERT INTO day_sessions (date, started_by, started_at, is_active, closed_at, total_revenue, total_orders)
+         VALUES (?1, ?2, ?1 || ' 00:00:00', 0, CURRENT_TIMESTAMP, ?3, ?4)",
+        rusqlite::params![date, staff_id, total_revenue, total_orders],
+    )?;
+
+    let id = tx.last_insert_rowid();
+
+    // Link orders to this session
+    tx.execute(
+        "UPDATE orders SET session_id = ?1 WHERE date(created_at, 'localtime') = ?2 AND session_id IS NULL",
+        rusqlite::params![id, date],
+    )?;
+
+    let session = get_session(&tx, id)?;
+    tx.commit()?;
+
+    Ok(session)
+}
+
+/// Close the active session: refuse while tables are open or nothing was sold,
+/// then store the session totals. The returned session is already committed.
+pub fn close_day(conn: &mut Connection) -> AppResult<DaySession> {
+    let tx = begin_write(conn)
+        .map_err(|e| AppError::Database(format!("Failed to begin transaction: {}", e)))?;
+
+    // Check for active session first - get the start time
+    let (session_id, session_started_at): (i64, String) = tx
+        .query_row(
+            "SELECT id, started_at FROM day_sessions WHERE is_active = 1",
+            [],
+            |row| Ok((row.get(0)?, row.get(1)?)),
+        )
+        .map_err(|_| AppError::DayNotStarted)?;
+
+    println!("[close_day] Closing session {} started at {}", session_id, session_started_at);
+
+    // Calculate totals for ALL orders linked to this session
+    let (total_revenue, total_orders): (Money, i32) = tx
+        .query_row(
+            "SELECT COALESCE(SUM(total), 0), COUNT(*) FROM orders WHERE session_id = ?1",
+            [session_id],
+            |row| Ok((row.get(0)?, row.get(1)?)),
+        )
+        .map_err(|e| AppError::Database(format!("Failed to calculate totals: {}", e)))?;
+
+    println!("[close_day] Found {} orders for session, total revenue: {}", total_orders, total_revenue);
+
+    if total_orders == 0 {
+        return Err(AppError::Validation(
+            "No orders found for this session. Cannot close an empty day.".to_string(),
+        ));
+    }
+
+    // Check for open orders (tables) - they must be closed first
+    let open_orders: i32 = tx
+        .query_row(
+            "SELECT COUNT(*) FROM orders WHERE session_id = ?1 AND status = 'open'",
+            [session_id],
+            |row| row.get(0),
+        )
+        .map_err(|e| AppError::Database(format!("Failed to check for open orders: {}", e)))?;
+
+    if open_orders > 0 {
+        return Err(AppError::OpenTablesRemain { count: open_orders });
+    }
+
+    // Update the session record with closing data (no longer using day_closings table)
+    tx.execute(
+        "UPDATE day_sessions SET is_active = 0, closed_at = CURRENT_TIMESTAMP, total_revenue = ?1, total_orders = ?2 WHERE id = ?3",
+        rusqlite::params![total_revenue, total_orders, session_id],
+    )
+    .map_err(|e| AppError::Database(format!("Failed to close day session: {}", e)))?;
+
+    let session = get_session(&tx, session_id)?;
+
+    tx.commit()
+        .map_err(|e| AppError::Database(format!("Failed to commit day closing: {}", e)))?;
+
+    let date_str = session.date.clone().unwrap_or_else(|| "unknown".to_string());
+    println!("[close_day] Successfully closed session for {} with {} orders", date_str, total_orders);
+
+    Ok(session)
+}
+
+/// Save a closed session's orders to a timestamped JSON file in `backups_dir`.
+pub fn write_session_backup(
+    conn: &Connection,
+    session: &DaySession,
+    backups_dir: &Path,
+) -> Result<PathBuf, String> {
+    let orders = query_orders(conn, "WHERE o.session_id = ?1 ORDER BY o.created_at ASC", [session.id])
+        .map_err(|e| e.to_string())?;
+
+    // Create backup data structure
+    let backup_data = serde_json::json!({
+        "session_id": session.id,
+        "date": session.date,
+        "session_started_at": session.started_at,
+        "closed_at": session.closed_at,
+        "total_revenue": session.total_revenue,
+        "total_orders": session.total_orders,
+        "taxes": session.taxes,
+        "orders": orders,
+    });
+
+    fs::create_dir_all(backups_dir)
+        .map_err(|e| format!("Failed to create backups directory: {}", e))?;
+
+    // Write backup file (include timestamp to handle multiple sessions per day)
+    let timestamp = chrono::Local::now().format("%Y%m%d_%H%M%S");
+    let backup_file = backups_dir.join(format!("day-backup-{}.json", timestamp));
+    let mut file = fs::File::create(&backup_file)
+        .map_err(|e| format!("Failed to create backup file: {}", e))?;
+
+    let json_content = serde_json::to_string_pretty(&backup_data)
+        .map_err(|e| format!("Failed to serialize backup: {}", e))?;
+
+    file.write_all(json_content.as_bytes())
+        .map_err(|e| format!("Failed to write backup: {}", e))?;
+
+    Ok(backup_file)
+}
+
+pub fn get_sales_history(conn: &Connection, limit: i32) -> AppResult<Vec<DaySession>> {
+    let mut stmt = conn.prepare(&format!(
+        "{} WHERE ds.is_active = 0 AND ds.closed_at IS NOT NULL
+         ORDER BY ds.closed_at DESC
+         LIMIT ?1",
+        SESSION_SELECT
+    ))?;
+
+    let mut sessions = stmt
+        .query_map([limit], session_from_row)?
+        .collect::<Result<Vec<_>, _>>()?;
+
+    for session in &mut sessions {
+        session.taxes = session_taxes(conn, session.id)?;
+    }
+
+    Ok(sessions)
+}
+
+/// Summary for a past session, or for the active session when `session_id` is `None`.
+/// Category sales are rolled up to `depth` like `get_category_margins`.
+pub fn get_day_summary(conn: &Connection, session_id: Option<i64>, depth: Option<usize>) -> AppResult<DaySummary> {
+    let (session_id, date) = match session_id {
+        // Historical view: use the session's own date
+        Some(id) => {
+            let date: Option<String> = conn
+                .query_row("SELECT date FROM day_sessions WHERE id = ?1", [id], |row| {
+                    row.get::<_, Option<String>>(0)
+                })
+                .ok()
+                .flatten();
+            (id, date.unwrap_or_else(|| "Unknown".to_string()))
+        }
+        // "Today": use the active session, or an empty summary if there is none
+        None => {
+            let today = chrono::Local::now().format("%Y-%m-%d").to_string();
+            let active: Option<i64> = conn
+                .query_row("SELECT id FROM day_sessions WHERE is_active = 1", [], |row| row.get(0))
+                .ok();
+            match active {
+                Some(id) => (id, today),
+                None => {
+                    return Ok(DaySummary {
+                        date: today,
+                        total_revenue: Money::zero(),
+                        total_orders: 0,
+                        orders: Vec::new(),
+                        product_sales: Vec::new(),
+                        category_sales: Vec::new(),
+                        taxes: Vec::new(),
+                    });
+                }
+            }
+        }
+    };
+
+    let orders = query_orders(conn, "WHERE o.session_id = ?1 ORDER BY o.created_at DESC", [session_id])?;
+    let total_revenue = orders.iter().map(|o| o.order.total).sum();
+    let product_sales = product_sales(conn, &orders)?;
+    let category_sales = category_sales(conn, &product_sales, depth)?;
+
+    Ok(DaySummary {
+        date,
+        total_revenue,
+        total_orders: orders.len() as i32,
+        orders,
+        product_sales,
+        category_sales,
+        taxes: session_taxes(conn, session_id)?,
+    })
+}
+
+/// Sales per category for orders placed between two local dates, inclusive,
+/// rolled up to `depth` like `get_category_margins`.
+pub fn get_category_sales(
+    conn: &Connection,
+    start_date: &str,
+    end_date: &str,
+    depth: Option<usize>,
+) -> AppResult<Vec<CategorySales>> {
+    let orders = get_orders_by_date_range(conn, start_date, end_date)?;
+    category_sales(conn, &product_sales(conn, &orders)?, depth)
+}
+
+/// Quantity and revenue per product, best sellers first. Variants count
+/// towards their parent and are broken out underneath it.
+fn product_sales(conn: &Connection, orders: &[OrderWithItems]) -> AppResult<Vec<ProductSales>> {
+    let mut sales: Vec<ProductSales> = Vec::new();
+
+    for item in orders.iter().flat_map(|o| &o.items) {
+        let revenue = item.price_at_sale * item.quantity;
+
+        let index = match sales.iter().position(|p| p.product_id == item.product_id) {
+            Some(index) => index,
+            None => {
+                let (category_id, category_name) = conn.query_row(
+                    "SELECT p.category_id, c.name FROM products p LEFT JOIN categories c ON p.category_id = c.id
+                     WHERE p.id = ?1",
+                    [item.product_id],
+                    |row| Ok((row.get(0)?, row.get(1)?)),
+                )?;
+                sales.push(ProductSales {
+                    product_id: item.product_id,
+                    product_name: item.product_name.clone(),
+                    category_id,
+                    category_name,
+                    quantity: 0,
+                    revenue: Money::zero(),
+                    variants: Vec::new(),
+                });
+                sales.len() - 1
+            }
+        };
+        let product = &mut sales[index];
+        product.quantity += item.quantity;
+        product.revenue += revenue;
+
+        if let Some(variant_id) = item.variant_id {
+            match product.variants.iter_mut().find(|v| v.variant_id == variant_id) {
+                Some(variant) => {
+                    variant.quantity += item.quantity;
+                    variant.revenue += revenue;
+                }
+                None => product.variants.push(VariantSales {
+                    variant_id,
+                    variant_name: item.variant_name.clone(),
+                    quantity: item.quantity,
+                    revenue,
+                }),
+            }
+        }
+    }
+
+    sales.sort_by_key(|p| std::cmp::Reverse(p.revenue.minor_units));
+    Ok(sales)
+}
+
+/// The category figures for `category_id` are reported under: the category
+/// itself, or with a `depth` its ancestor at that level of the tree, 0 being
+/// the top. Categories shallower than `depth` stay as they are.
+fn roll_up(
+    tree: &[Category],
+    category_id: Option<i64>,
+    category_name: &Option<String>,
+    depth: Option<usize>,
+) -> (Option<i64>, Option<String>) {
+    if let (Some(id), Some(depth)) = (category_id, depth) {
+        let path = categories::path_to(tree, id);
+        if let Some(ancestor) = path.get(depth).or(path.last()) {
+            return (Some(ancestor.id), Some(ancestor.name.clone()));
+        }
+    }
+    (category_id, category_name.clone())
+}
+
+/// Product sales rolled up to their category, best sellers first.
+fn category_sales(conn: &Connection, products: &[ProductSales], depth: Option<usize>) -> AppResult<Vec<CategorySales>> {
+    let tree = categories::get_categories(conn, true)?;
+    let mut categories: Vec<CategorySales> = Vec::new();
+
+    for product in products {
+        let (category_id, category_name) = roll_up(&tree, product.category_id, &product.category_name, depth);
+        match categories.iter_mut().find(|c| c.category_id == category_id) {
+            Some(category) => {
+                category.quantity += product.quantity;
+                category.revenue += product.revenue;
+            }
+            None => categories.push(CategorySales {
+                category_id,
+                category_name,
+                quantity: product.quantity,
+                revenue: product.revenue,
+            }),
+        }
+    }
+
+    categories.sort_by_key(|c| std::cmp::Reverse(c.revenue.minor_units));
+    Ok(categories)
+}
+
+// ============ MARGINS ============
+
+/// Margin per product over the orders matched by `filter`, e.g.
+/// `"WHERE o.session_id = ?1"`. Margin is taken over costed units only, so
+/// lines sold before a product had a cost do not inflate it.
+fn query_product_margins<P: Params>(conn: &Connection, filter: &str, params: P) -> AppResult<Vec<ProductMargin>> {
+    let mut stmt = conn.prepare(&format!(
+        "SELECT oi.product_id, p.name, p.category_id, c.name,
+                SUM(oi.quantity),
+                SUM(oi.quantity * oi.price_at_sale),
+                SUM(oi.quantity * COALESCE(oi.cost_at_sale, 0)),
+                SUM(CASE WHEN oi.cost_at_sale IS NULL THEN 0 ELSE oi.quantity * (oi.price_at_sale - oi.cost_at_sale) END),
+                SUM(CASE WHEN oi.price_at_sale < oi.cost_at_sale THEN oi.quantity ELSE 0 END),
+                SUM(CASE WHEN oi.cost_at_sale IS NULL THEN oi.quantity ELSE 0 END)
+         FROM order_items oi
+         JOIN orders o ON oi.order_id = o.id
+         LEFT JOIN products p ON oi.product_id = p.id
+         LEFT JOIN categories c ON p.category_id = c.id
+         {}
+         GROUP BY oi.product_id
+         ORDER BY SUM(oi.quantity * oi.price_at_sale) DESC, p.name",
+        filter
+    ))?;
+
+    let margins = stmt
+        .query_map(params, |row| {
+            Ok(ProductMargin {
+                product_id: row.get(0)?,
+                product_name: row.get(1)?,
+                category_id: row.get(2)?,
+                category_name: row.get(3)?,
+                quantity: row.get(4)?,
+                revenue: row.get(5)?,
+                cost: row.get(6)?,
+                margin: row.get(7)?,
+                below_cost_quantity: row.get(8)?,
+                uncosted_quantity: row.get(9)?,
+            })
+        })?
+        .collect::<Result<Vec<_>, _>>()?;
+
+    Ok(margins)
+}
+
+/// Margin per product for orders placed between two local dates, inclusive.
+pub fn get_product_margins(conn: &Connection, start_date: &str, end_date: &str) -> AppResult<Vec<ProductMargin>> {
+    query_product_margins(
+        conn,
+        "WHERE date(o.created_at, 'localtime') >= ?1 AND date(o.created_at, 'localtime') <= ?2",
+        [start_date, end_date],
+    )
+}
+
+/// Product margins rolled up to their current category, best earners first.
+/// With a `depth`, categories deeper in the tree are rolled up into their
+/// ancestor at that level, 0 being the top.
+pub fn get_category_margins(
+    conn: &Connection,
+    start_date: &str,
+    end_date: &str,
+    depth: Option<usize>,
+) -> AppResult<Vec<CategoryMargin>> {
+    let tree = categories::get_categories(conn, true)?;
+    let mut categories: Vec<CategoryMargin> = Vec::new();
+
+    for product in get_product_margins(conn, start_date, end_date)? {
+        let (category_id, category_name) = roll_up(&tree, product.category_id, &product.category_name, depth);
+
+        let index = match categories.iter().position(|c| c.category_id == category_id) {
+            Some(index) => index,
+            None => {
+                categories.push(CategoryMargin {
+                    category_id,
+                    category_name,
+                    quantity: 0,
+                    revenue: Money::zero(),
+                    cost: Money::zero(),
+                    margin: Money::zero(),
+                    below_cost_quantity: 0,
+                    uncosted_quantity: 0,
+                });
+                categories.len() - 1
+            }
+        };
+        let category = &mut categories[index];
+        category.quantity += product.quantity;
+        category.revenue += product.revenue;
+        category.cost += product.cost;
+        category.margin += product.margin;
+        category.below_cost_quantity += product.below_cost_quantity;
+        category.uncosted_quantity += product.uncosted_quantity;
+    }
+
+    categories.sort_by_key(|c| std::cmp::Reverse(c.margin.minor_units));
+    Ok(categories)
+}
+
+pub fn get_session_margins(conn: &Connection, session_id: i64) -> AppResult<SessionMargin> {
+    let session = get_session(conn, session_id).map_err(|_| AppError::NotFound { entity: "session", id: session_id })?;
+    let products = query_product_margins(conn, "WHERE o.session_id = ?1", [session_id])?;
+
+    Ok(SessionMargin {
+        session_id,
+        date: session.date,
+        revenue: products.iter().map(|p| p.revenue).sum(),
+        cost: products.iter().map(|p| p.cost).sum(),
+        margin: products.iter().map(|p| p.margin).sum(),
+        products,
+    })
+}
+
+// ============ DAY SESSION MANAGEMENT ============
+
+/// Get the current active day session (if any)
+pub fn get_active_session(conn: &Connection) -> AppResult<Option<DaySession>> {
+    let result = conn.query_row(
+        &format!("{} WHERE ds.is_active = 1 ORDER BY ds.started_at DESC LIMIT 1", SESSION_SELECT),
+        [],
+        session_from_row,
+    );
+
+    match result {
+        Ok(mut session) => {
+            session.taxes = session_taxes(conn, session.id)?;
+            Ok(Some(session))
+        }
+        Err(rusqlite::Error::QueryReturnedNoRows) => Ok(None),
+        Err(e) => Err(e.into()),
+    }
+}
+
+/// Start a new day session
+pub fn start_day(conn: &mut Connection, staff_id: i64) -> AppResult<DaySession> {
+    let tx = begin_write(conn)?;
+
+    let today = chrono::Local::now().format("%Y-%m-%d").to_string();
+    println!("[start_day] Starting day for date: {}", today);
+
+    // Check if there's already an active session (any date)
+    let existing: Result<i64, _> = tx.query_row(
+        "SELECT id FROM day_sessions WHERE is_active = 1",
+        [],
+        |row| row.get(0),
+    );
+
+    if existing.is_ok() {
+        return Err(AppError::Validation(
+            "A day session is already active. Close it first.".to_string(),
+        ));
+    }
+
+    staff::ensure_active(&tx, staff_id)?;
+
+    // Create new session (allow multiple sessions per day - no ON CONFLICT)
+    tx.execute(
+        "INSERT INTO day_sessions (date, started_by, is_active) VALUES (?1, ?2, 1)",
+        rusqlite::params![today, staff_id],
+    )
+    .map_err(|e| AppError::Database(format!("Failed to start day: {}", e)))?;
+
+    let session = get_session(&tx, tx.last_insert_rowid())?;
+    tx.commit()?;
+
+    println!("[start_day] Day started successfully by staff_id: {}", staff_id);
+
+    Ok(session)
+}
+
+/// Check if day is active (for order validation)
+pub fn is_day_active(conn: &Connection) -> AppResult<bool> {
+    let result: Result<i32, _> = conn.query_row(
+        "SELECT 1 FROM day_sessions WHERE is_active = 1",
+        [],
+        |row| row.get(0),
+    );
+
+    Ok(result.is_ok())
+}
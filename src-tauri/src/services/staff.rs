@@ -0,0 +1,150 @@
+use crate::db::referenced_by;
+use crate::error::{not_found, AppError, AppResult};
+use crate::models::{CreateStaff, Staff};
+use rusqlite::Connection;
+
+fn staff_from_row(row: &rusqlite::Row) -> rusqlite::Result<Staff> {
+    Ok(Staff {
+        id: row.get(0)?,
+        name: row.get(1)?,
+        pin: row.get(2)?,
+        created_at: row.get(3)?,
+        archived_at: row.get(4)?,
+    })
+}
+
+/// Staff who can log in, or everyone for reports and the back office.
+pub fn get_staff(conn: &Connection, include_archived: bool) -> AppResult<Vec<Staff>> {
+    let filter = if include_archived { "" } else { "WHERE archived_at IS NULL" };
+    let mut stmt = conn.prepare(&format!(
+        "SELECT id, name, pin, created_at, archived_at FROM staff {} ORDER BY name",
+        filter
+    ))?;
+
+    let staff = stmt
+        .query_map([], staff_from_row)?
+        .collect::<Result<Vec<_>, _>>()?;
+
+    Ok(staff)
+}
+
+pub fn create_staff(conn: &Connection, staff: CreateStaff) -> AppResult<Staff> {
+    conn.execute(
+        "INSERT INTO staff (name, pin) VALUES (?1, ?2)",
+        rusqlite::params![staff.name, staff.pin],
+    )?;
+
+    let id = conn.last_insert_rowid();
+
+    get_staff_member(conn, id)
+}
+
+fn get_staff_member(conn: &Connection, id: i64) -> AppResult<Staff> {
+    conn.query_row(
+        "SELECT id, name, pin, created_at, archived_at FROM staff WHERE id = ?1",
+        [id],
+        staff_from_row,
+    )
+    .map_err(not_found("staff", id))
+}
+
+/// Fail if the staff member has been archived. Unknown ids are left to the
+/// caller.
+pub(crate) fn ensure_active(conn: &Connection, id: i64) -> AppResult<()> {
+    let archived: Option<(String, bool)> = conn
+        .query_row(
+            "SELECT name, archived_at IS NOT NULL FROM staff WHERE id = ?1",
+            [id],
+            |row| Ok((row.get(0)?, row.get(1)?)),
+        )
+        .ok();
+
+    match archived {
+        Some((name, true)) => Err(AppError::Validation(format!("{} is archived and cannot log in", name))),
+        _ => Ok(()),
+    }
+}
+
+/// Remove a staff member from the login list. Their orders keep their name.
+/// Refused while they still have open tabs.
+pub fn archive_staff(conn: &Connection, id: i64) -> AppResult<Staff> {
+    let open: i64 = conn.query_row(
+        "SELECT COUNT(*) FROM orders WHERE staff_id = ?1 AND status = 'open'",
+        [id],
+        |row| row.get(0),
+    )?;
+    if open > 0 {
+        return Err(AppError::Validation(format!(
+            "Cannot archive a staff member with {} open orders",
+            open
+        )));
+    }
+
+    let updated = conn.execute(
+        "UPDATE staff SET archived_at = COALESCE(archived_at, CURRENT_TIMESTAMP) WHERE id = ?1",
+        [id],
+    )?;
+    if updated == 0 {
+        return Err(AppError::NotFound { entity: "staff", id });
+    }
+
+    get_staff_member(conn, id)
+}
+
+pub fn restore_staff(conn: &Connection, id: i64) -> AppResult<Staff> {
+    let updated = conn.execute("UPDATE staff SET archived_at = NULL WHERE id = ?1", [id])?;
+    if updated == 0 {
+        return Err(AppError::NotFound { entity: "staff", id });
+    }
+
+    get_staff_member(conn, id)
+}
+
+/// Delete an archived staff member who never took an order, moved stock or
+/// ran a session.
+pub fn purge_staff(conn: &Connection, id: i64) -> AppResult<()> {
+    let staff = get_staff_member(conn, id)?;
+    if staff.archived_at.is_none() {
+        return Err(AppError::Validation(format!("Archive {} before purging them", staff.name)));
+    }
+
+    let references = referenced_by(
+        conn,
+        id,
+        &[
+            ("orders", "staff_id", "orders"),
+            ("day_sessions", "started_by", "day sessions"),
+            ("stock_movements", "staff_id", "stock movements"),
+            ("purchase_orders", "created_by", "purchase orders"),
+            ("purchase_receipts", "received_by", "purchase orders"),
+            ("stock_takes", "started_by", "stock takes"),
+            ("stock_takes", "committed_by", "stock takes"),
+            ("stock_take_counts", "staff_id", "stock takes"),
+            ("product_price_history", "staff_id", "price changes"),
+        ],
+    )?;
+    if !references.is_empty() {
+        return Err(AppError::Validation(format!(
+            "Cannot purge {}: still referenced by {}",
+            staff.name,
+            references.join(", ")
+        )));
+    }
+
+    conn.execute("DELETE FROM staff WHERE id = ?1", [id])?;
+
+    Ok(())
+}
+
+pub fn verify_staff_pin(conn: &Connection, id: i64, pin: &str) -> AppResult<bool> {
+    ensure_active(conn, id)?;
+
+    let stored_pin: Option<String> = conn
+        .query_row("SELECT pin FROM staff WHERE id = ?1", [id], |row| row.get(0))
+        .map_err(not_found("staff", id))?;
+
+    match stored_pin {
+        Some(p) => Ok(p == pin),
+        None => Ok(true), // No PIN set, allow access
+    }
+}
@@ -0,0 +1,259 @@
+use crate::db::begin_write;
+use crate::error::{not_found, AppError, AppResult};
+use crate::models::{CreateStockMovement, StockDiscrepancy, StockMovement, StockReason};
+use crate::services::{locations, lots, recipes};
+use rusqlite::{Connection, OptionalExtension};
+
+const MOVEMENT_SELECT: &str =
+    "SELECT m.id, m.product_id, p.name, m.quantity_change, m.reason, m.staff_id, s.name, m.order_id, m.reference, m.note, m.created_at,
+            loc.id, loc.name
+     FROM stock_movements m
+     LEFT JOIN products p ON m.product_id = p.id
+     LEFT JOIN staff s ON m.staff_id = s.id
+     JOIN stock_locations loc
+       ON loc.id = COALESCE(m.location_id, (SELECT id FROM stock_locations WHERE is_default = 1))";
+
+fn movement_from_row(row: &rusqlite::Row) -> rusqlite::Result<StockMovement> {
+    Ok(StockMovement {
+        id: row.get(0)?,
+        product_id: row.get(1)?,
+        product_name: row.get(2)?,
+        quantity_change: row.get(3)?,
+        reason: row.get(4)?,
+        staff_id: row.get(5)?,
+        staff_name: row.get(6)?,
+        order_id: row.get(7)?,
+        reference: row.get(8)?,
+        note: row.get(9)?,
+        created_at: row.get(10)?,
+        location_id: row.get(11)?,
+        location_name: row.get(12)?,
+    })
+}
+
+/// Apply a signed change to a product's quantity at the default location.
+/// Decreases carry a guard on what the other locations do not hold, so the
+/// check and the deduction are one statement and stock can never go below zero.
+fn change_quantity(conn: &Connection, product_id: i64, change: i32) -> AppResult<()> {
+    let updated = if change < 0 {
+        conn.execute(
+            "UPDATE products SET quantity = quantity + ?1
+             WHERE id = ?2
+               AND quantity - (SELECT COALESCE(SUM(quantity), 0) FROM location_stock WHERE product_id = ?2) >= -?1",
+            rusqlite::params![change, product_id],
+        )?
+    } else {
+        conn.execute(
+            "UPDATE products SET quantity = quantity + ?1 WHERE id = ?2",
+            rusqlite::params![change, product_id],
+        )?
+    };
+
+    if updated == 0 {
+        let (name, available): (String, i32) = conn
+            .query_row(
+                "SELECT name, quantity - (SELECT COALESCE(SUM(quantity), 0) FROM location_stock WHERE product_id = ?1)
+                 FROM products WHERE id = ?1",
+                [product_id],
+                |row| Ok((row.get(0)?, row.get(1)?)),
+            )
+            .map_err(not_found("product", product_id))?;
+        return Err(AppError::InsufficientStock {
+            product_id,
+            product_name: name,
+            requested: -change,
+            available,
+        });
+    }
+
+    Ok(())
+}
+
+/// Append an entry to the ledger without touching `products.quantity`, for
+/// callers that have already written the quantity themselves.
+pub(crate) fn log_movement(
+    conn: &Connection,
+    movement: &CreateStockMovement,
+    order_id: Option<i64>,
+) -> AppResult<i64> {
+    conn.execute(
+        "INSERT INTO stock_movements
+             (product_id, quantity_change, reason, staff_id, order_id, reference, note, location_id)
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)",
+        rusqlite::params![
+            movement.product_id,
+            movement.quantity_change,
+            movement.reason,
+            movement.staff_id,
+            order_id,
+            movement.reference,
+            movement.note,
+            movement.location_id
+        ],
+    )?;
+
+    Ok(conn.last_insert_rowid())
+}
+
+/// Change a product's quantity at a location, the default when `None`.
+pub(crate) fn change_quantity_at(
+    conn: &Connection,
+    location_id: Option<i64>,
+    product_id: i64,
+    change: i32,
+) -> AppResult<()> {
+    match locations::stocked_apart(conn, location_id)? {
+        Some(location_id) => locations::change_quantity(conn, location_id, product_id, change),
+        None => change_quantity(conn, product_id, change),
+    }
+}
+
+/// Change a product's quantity at the movement's location and record why, in
+/// the caller's transaction. Decreases come out of the product's lots
+/// first-expired-first-out.
+pub(crate) fn apply_movement(
+    conn: &Connection,
+    movement: &CreateStockMovement,
+    order_id: Option<i64>,
+) -> AppResult<i64> {
+    change_quantity_at(conn, movement.location_id, movement.product_id, movement.quantity_change)?;
+    let id = log_movement(conn, movement, order_id)?;
+    lots::allocate(conn, id, movement, order_id)?;
+    Ok(id)
+}
+
+/// Record a stock change entered by hand. Sales and returns only ever come
+/// from orders, so they are rejected here.
+pub fn record_stock_movement(
+    conn: &mut Connection,
+    movement: CreateStockMovement,
+) -> AppResult<StockMovement> {
+    let sign_ok = match movement.reason {
+        StockReason::Delivery => movement.quantity_change > 0,
+        StockReason::Waste | StockReason::Comp => movement.quantity_change < 0,
+        StockReason::Adjustment | StockReason::StockTake => movement.quantity_change != 0,
+        StockReason::Sale | StockReason::Return | StockReason::OpeningBalance | StockReason::Transfer => {
+            return Err(AppError::Validation(format!(
+                "Stock movements of type '{}' are recorded automatically",
+                movement.reason.as_str()
+            )));
+        }
+    };
+
+    if !sign_ok {
+        return Err(AppError::Validation(format!(
+            "Invalid quantity {} for a '{}' movement",
+            movement.quantity_change,
+            movement.reason.as_str()
+        )));
+    }
+
+    let tx = begin_write(conn)?;
+    recipes::ensure_stock_tracked(&tx, movement.product_id)?;
+    let id = apply_movement(&tx, &movement, None)?;
+    tx.commit()?;
+
+    Ok(conn.query_row(&format!("{} WHERE m.id = ?1", MOVEMENT_SELECT), [id], movement_from_row)?)
+}
+
+/// Movements for one product, newest first. Dates are `YYYY-MM-DD` in local
+/// time and both ends are inclusive; either may be omitted.
+pub fn get_stock_movements(
+    conn: &Connection,
+    product_id: i64,
+    start_date: Option<&str>,
+    end_date: Option<&str>,
+) -> AppResult<Vec<StockMovement>> {
+    let mut stmt = conn.prepare(&format!(
+        "{} WHERE m.product_id = ?1
+           AND (?2 IS NULL OR date(m.created_at, 'localtime') >= ?2)
+           AND (?3 IS NULL OR date(m.created_at, 'localtime') <= ?3)
+         ORDER BY m.created_at DESC, m.id DESC",
+        MOVEMENT_SELECT
+    ))?;
+
+    let movements = stmt
+        .query_map(rusqlite::params![product_id, start_date, end_date], movement_from_row)?
+        .collect::<Result<Vec<_>, _>>()?;
+
+    Ok(movements)
+}
+
+/// Products whose stored quantity differs from the sum of their ledger.
+pub fn get_stock_discrepancies(conn: &Connection) -> AppResult<Vec<StockDiscrepancy>> {
+    let mut stmt = conn.prepare(
+        "SELECT p.id, p.name, p.quantity, COALESCE(SUM(m.quantity_change), 0) AS ledger
+         FROM products p
+         LEFT JOIN stock_movements m ON m.product_id = p.id
+         GROUP BY p.id
+         HAVING p.quantity <> ledger
+         ORDER BY p.name",
+    )?;
+
+    let discrepancies = stmt
+        .query_map([], |row| {
+            Ok(StockDiscrepancy {
+                product_id: row.get(0)?,
+                product_name: row.get(1)?,
+                recorded_quantity: row.get(2)?,
+                ledger_quantity: row.get(3)?,
+            })
+        })?
+        .collect::<Result<Vec<_>, _>>()?;
+
+    Ok(discrepancies)
+}
+
+/// Reset every product's quantity, and what each location holds of it, to
+/// the sum of its ledger. Refuses if the ledger leaves a location short.
+/// Returns the products whose total was out of step, with their quantities
+/// before the rebuild.
+pub fn rebuild_stock_from_ledger(conn: &mut Connection) -> AppResult<Vec<StockDiscrepancy>> {
+    let tx = begin_write(conn)?;
+
+    let short: Option<(String, String, i32)> = tx
+        .query_row(
+            "SELECT p.name, loc.name, SUM(m.quantity_change)
+             FROM stock_movements m
+             JOIN products p ON m.product_id = p.id
+             JOIN stock_locations loc
+               ON loc.id = COALESCE(m.location_id, (SELECT id FROM stock_locations WHERE is_default = 1))
+             GROUP BY m.product_id, loc.id
+             HAVING SUM(m.quantity_change) < 0
+             ORDER BY p.name, loc.name
+             LIMIT 1",
+            [],
+            |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?)),
+        )
+        .optional()?;
+    if let Some((product, location, quantity)) = short {
+        return Err(AppError::Validation(format!(
+            "Cannot rebuild stock: the ledger leaves {} at {} in {}",
+            product, quantity, location
+        )));
+    }
+
+    let discrepancies = get_stock_discrepancies(&tx)?;
+    for d in &discrepancies {
+        tx.execute(
+            "UPDATE products SET quantity = ?1 WHERE id = ?2",
+            rusqlite::params![d.ledger_quantity, d.product_id],
+        )?;
+    }
+
+    // The default location keeps the remainder, which is then its own ledger
+    tx.execute("UPDATE location_stock SET quantity = 0", [])?;
+    tx.execute(
+        "INSERT INTO location_stock (location_id, product_id, quantity)
+         SELECT m.location_id, m.product_id, SUM(m.quantity_change)
+         FROM stock_movements m
+         JOIN stock_locations loc ON m.location_id = loc.id
+         WHERE loc.is_default = 0
+         GROUP BY m.location_id, m.product_id
+         ON CONFLICT (location_id, product_id) DO UPDATE SET quantity = excluded.quantity",
+        [],
+    )?;
+
+    tx.commit()?;
+    Ok(discrepancies)
+}
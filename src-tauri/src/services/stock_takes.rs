@@ -0,0 +1,315 @@
+use crate::db::begin_write;
+use crate::error::{not_found, AppError, AppResult};
+use crate::models::{
+    CreateStockMovement, StockCount, StockReason, StockTake, StockTakeLine, StockTakeStatus,
+    StockTakeWithLines, VarianceHistoryEntry,
+};
+use crate::money::Money;
+use crate::services::{locations, recipes, stock};
+use rusqlite::Connection;
+
+const STOCK_TAKE_SELECT: &str =
+    "SELECT st.id, st.status, st.notes, st.started_by, s.name, st.started_at, st.committed_by, st.committed_at,
+            st.location_id, loc.name
+     FROM stock_takes st
+     LEFT JOIN staff s ON st.started_by = s.id
+     JOIN stock_locations loc ON st.location_id = loc.id";
+
+fn stock_take_from_row(row: &rusqlite::Row) -> rusqlite::Result<StockTake> {
+    Ok(StockTake {
+        id: row.get(0)?,
+        status: row.get(1)?,
+        notes: row.get(2)?,
+        started_by: row.get(3)?,
+        started_by_name: row.get(4)?,
+        started_at: row.get(5)?,
+        committed_by: row.get(6)?,
+        committed_at: row.get(7)?,
+        location_id: row.get(8)?,
+        location_name: row.get(9)?,
+    })
+}
+
+fn line_from_row(row: &rusqlite::Row) -> rusqlite::Result<StockTakeLine> {
+    let system_quantity: i32 = row.get(2)?;
+    let counted_quantity: i32 = row.get(3)?;
+    let unit_cost: Option<Money> = row.get(4)?;
+    let unit_price: Money = row.get(5)?;
+    let variance = counted_quantity - system_quantity;
+
+    Ok(StockTakeLine {
+        product_id: row.get(0)?,
+        product_name: row.get(1)?,
+        system_quantity,
+        counted_quantity,
+        variance,
+        unit_cost,
+        unit_price,
+        variance_cost: unit_cost.map(|cost| cost * variance),
+        variance_value: unit_price * variance,
+    })
+}
+
+fn get_header(conn: &Connection, id: i64) -> AppResult<StockTake> {
+    conn.query_row(&format!("{} WHERE st.id = ?1", STOCK_TAKE_SELECT), [id], stock_take_from_row)
+        .map_err(not_found("stock take", id))
+}
+
+fn ensure_open(conn: &Connection, id: i64) -> AppResult<()> {
+    let status = get_header(conn, id)?.status;
+    if status != StockTakeStatus::Open {
+        return Err(AppError::Validation(format!(
+            "Stock take {} is {} and can no longer be changed",
+            id,
+            status.as_str()
+        )));
+    }
+    Ok(())
+}
+
+fn get_counts(conn: &Connection, id: i64) -> AppResult<Vec<StockCount>> {
+    let mut stmt = conn.prepare(
+        "SELECT c.id, c.product_id, p.name, c.staff_id, s.name, c.counted_quantity, c.counted_at
+         FROM stock_take_counts c
+         LEFT JOIN products p ON c.product_id = p.id
+         LEFT JOIN staff s ON c.staff_id = s.id
+         WHERE c.stock_take_id = ?1
+         ORDER BY p.name, s.name",
+    )?;
+
+    let counts = stmt
+        .query_map([id], |row| {
+            Ok(StockCount {
+                id: row.get(0)?,
+                product_id: row.get(1)?,
+                product_name: row.get(2)?,
+                staff_id: row.get(3)?,
+                staff_name: row.get(4)?,
+                counted_quantity: row.get(5)?,
+                counted_at: row.get(6)?,
+            })
+        })?
+        .collect::<Result<Vec<_>, _>>()?;
+
+    Ok(counts)
+}
+
+/// Variance lines: live against the stock at the counted location and average
+/// cost while the count is open, frozen at commit time afterwards. Quantities
+/// are in base units, so the sale price is valued per base unit like the cost.
+fn get_lines(conn: &Connection, stock_take: &StockTake) -> AppResult<Vec<StockTakeLine>> {
+    let sql = if stock_take.status == StockTakeStatus::Open {
+        let location = locations::get_location(conn, stock_take.location_id)?;
+        format!(
+            "SELECT p.id, p.name, {}, SUM(c.counted_quantity), p.cost_price,
+                    (p.price + p.serving_size / 2) / p.serving_size
+             FROM stock_take_counts c
+             JOIN products p ON c.product_id = p.id
+             WHERE c.stock_take_id = ?1
+             GROUP BY p.id
+             ORDER BY p.name",
+            locations::quantity_at(&location)
+        )
+    } else {
+        "SELECT l.product_id, p.name, l.system_quantity, l.counted_quantity, l.unit_cost, l.unit_price
+         FROM stock_take_lines l
+         LEFT JOIN products p ON l.product_id = p.id
+         WHERE l.stock_take_id = ?1
+         ORDER BY p.name"
+            .to_string()
+    };
+
+    let mut stmt = conn.prepare(&sql)?;
+    let lines = stmt
+        .query_map([stock_take.id], line_from_row)?
+        .collect::<Result<Vec<_>, _>>()?;
+
+    Ok(lines)
+}
+
+pub fn get_stock_take(conn: &Connection, id: i64) -> AppResult<StockTakeWithLines> {
+    let stock_take = get_header(conn, id)?;
+    let counts = get_counts(conn, id)?;
+    let lines = get_lines(conn, &stock_take)?;
+
+    let total_variance_cost = lines.iter().filter_map(|l| l.variance_cost).sum();
+    let total_variance_value = lines.iter().map(|l| l.variance_value).sum();
+
+    Ok(StockTakeWithLines {
+        stock_take,
+        counts,
+        lines,
+        total_variance_cost,
+        total_variance_value,
+    })
+}
+
+pub fn get_stock_takes(conn: &Connection, limit: i32) -> AppResult<Vec<StockTake>> {
+    let mut stmt = conn.prepare(&format!(
+        "{} ORDER BY st.started_at DESC, st.id DESC LIMIT ?1",
+        STOCK_TAKE_SELECT
+    ))?;
+
+    let stock_takes = stmt
+        .query_map([limit], stock_take_from_row)?
+        .collect::<Result<Vec<_>, _>>()?;
+
+    Ok(stock_takes)
+}
+
+/// Open a new count of a location, the default when `location_id` is `None`.
+/// Only one stock take can be open at a time per location.
+pub fn start_stock_take(
+    conn: &mut Connection,
+    staff_id: i64,
+    location_id: Option<i64>,
+    notes: Option<String>,
+) -> AppResult<StockTakeWithLines> {
+    let tx = begin_write(conn)?;
+
+    let location = locations::location_or_default(&tx, location_id)?;
+    let open: i32 = tx.query_row(
+        "SELECT COUNT(*) FROM stock_takes WHERE status = ?1 AND location_id = ?2",
+        rusqlite::params![StockTakeStatus::Open, location.id],
+        |row| row.get(0),
+    )?;
+    if open > 0 {
+        return Err(AppError::Validation(format!(
+            "A stock take of {} is already open. Commit or cancel it first.",
+            location.name
+        )));
+    }
+
+    tx.execute(
+        "INSERT INTO stock_takes (status, notes, started_by, location_id) VALUES (?1, ?2, ?3, ?4)",
+        rusqlite::params![StockTakeStatus::Open, notes, staff_id, location.id],
+    )?;
+    let id = tx.last_insert_rowid();
+
+    tx.commit()?;
+    get_stock_take(conn, id)
+}
+
+/// Enter or correct one staff member's count of a product.
+pub fn record_stock_count(
+    conn: &mut Connection,
+    stock_take_id: i64,
+    product_id: i64,
+    staff_id: i64,
+    counted_quantity: i32,
+) -> AppResult<StockTakeWithLines> {
+    if counted_quantity < 0 {
+        return Err(AppError::Validation("Counted quantity cannot be negative".to_string()));
+    }
+
+    let tx = begin_write(conn)?;
+    ensure_open(&tx, stock_take_id)?;
+    recipes::ensure_stock_tracked(&tx, product_id)?;
+
+    tx.execute(
+        "INSERT INTO stock_take_counts (stock_take_id, product_id, staff_id, counted_quantity)
+         VALUES (?1, ?2, ?3, ?4)
+         ON CONFLICT (stock_take_id, product_id, staff_id)
+         DO UPDATE SET counted_quantity = excluded.counted_quantity, counted_at = CURRENT_TIMESTAMP",
+        rusqlite::params![stock_take_id, product_id, staff_id, counted_quantity],
+    )?;
+
+    tx.commit()?;
+    get_stock_take(conn, stock_take_id)
+}
+
+/// Post the count: every counted product is corrected to its counted quantity
+/// at the counted location through a `stock_take` movement there, and the
+/// variance is frozen for history. Products nobody counted are left alone.
+pub fn commit_stock_take(conn: &mut Connection, id: i64, staff_id: i64) -> AppResult<StockTakeWithLines> {
+    let tx = begin_write(conn)?;
+
+    let stock_take = get_header(&tx, id)?;
+    ensure_open(&tx, id)?;
+
+    let lines = get_lines(&tx, &stock_take)?;
+    if lines.is_empty() {
+        return Err(AppError::Validation("Nothing has been counted yet".to_string()));
+    }
+
+    for line in &lines {
+        tx.execute(
+            "INSERT INTO stock_take_lines (stock_take_id, product_id, system_quantity, counted_quantity, unit_cost, unit_price)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+            rusqlite::params![
+                id,
+                line.product_id,
+                line.system_quantity,
+                line.counted_quantity,
+                line.unit_cost,
+                line.unit_price
+            ],
+        )?;
+
+        if line.variance != 0 {
+            let movement = CreateStockMovement {
+                product_id: line.product_id,
+                quantity_change: line.variance,
+                reason: StockReason::StockTake,
+                staff_id: Some(staff_id),
+                reference: Some(format!("ST-{}", id)),
+                note: None,
+                location_id: Some(stock_take.location_id),
+            };
+            stock::apply_movement(&tx, &movement, None)?;
+        }
+    }
+
+    tx.execute(
+        "UPDATE stock_takes SET status = ?1, committed_by = ?2, committed_at = CURRENT_TIMESTAMP WHERE id = ?3",
+        rusqlite::params![StockTakeStatus::Committed, staff_id, id],
+    )?;
+
+    tx.commit()?;
+    get_stock_take(conn, id)
+}
+
+pub fn cancel_stock_take(conn: &mut Connection, id: i64) -> AppResult<StockTakeWithLines> {
+    let tx = begin_write(conn)?;
+    ensure_open(&tx, id)?;
+
+    tx.execute(
+        "UPDATE stock_takes SET status = ?1 WHERE id = ?2",
+        rusqlite::params![StockTakeStatus::Cancelled, id],
+    )?;
+
+    tx.commit()?;
+    get_stock_take(conn, id)
+}
+
+/// Committed variances for one product, newest first, to spot shrinkage trends.
+pub fn get_variance_history(conn: &Connection, product_id: i64) -> AppResult<Vec<VarianceHistoryEntry>> {
+    let mut stmt = conn.prepare(
+        "SELECT l.stock_take_id, st.committed_at, l.system_quantity, l.counted_quantity, l.unit_cost, l.unit_price
+         FROM stock_take_lines l
+         JOIN stock_takes st ON l.stock_take_id = st.id
+         WHERE l.product_id = ?1 AND st.status = 'committed'
+         ORDER BY st.committed_at DESC, st.id DESC",
+    )?;
+
+    let history = stmt
+        .query_map([product_id], |row| {
+            let system_quantity: i32 = row.get(2)?;
+            let counted_quantity: i32 = row.get(3)?;
+            let unit_cost: Option<Money> = row.get(4)?;
+            let unit_price: Money = row.get(5)?;
+            let variance = counted_quantity - system_quantity;
+            Ok(VarianceHistoryEntry {
+                stock_take_id: row.get(0)?,
+                committed_at: row.get(1)?,
+                system_quantity,
+                counted_quantity,
+                variance,
+                variance_cost: unit_cost.map(|cost| cost * variance),
+                variance_value: unit_price * variance,
+            })
+        })?
+        .collect::<Result<Vec<_>, _>>()?;
+
+    Ok(history)
+}
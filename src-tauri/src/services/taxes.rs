@@ -0,0 +1,165 @@
+use crate::db::referenced_by;
+use crate::error::{not_found, AppError, AppResult};
+use crate::models::{Category, CreateTaxRate, Product, TaxRate, UpdateTaxRate};
+use crate::services::{categories, products};
+use rusqlite::Connection;
+
+const TAX_RATE_SELECT: &str = "SELECT id, name, basis_points, included FROM tax_rates";
+
+/// Basis points in 100%.
+const WHOLE: i32 = 10_000;
+
+fn tax_rate_from_row(row: &rusqlite::Row) -> rusqlite::Result<TaxRate> {
+    Ok(TaxRate {
+        id: row.get(0)?,
+        name: row.get(1)?,
+        basis_points: row.get(2)?,
+        included: row.get(3)?,
+    })
+}
+
+pub fn get_tax_rates(conn: &Connection) -> AppResult<Vec<TaxRate>> {
+    let mut stmt = conn.prepare(&format!("{} ORDER BY name", TAX_RATE_SELECT))?;
+
+    let rates = stmt
+        .query_map([], tax_rate_from_row)?
+        .collect::<Result<Vec<_>, _>>()?;
+
+    Ok(rates)
+}
+
+pub fn get_tax_rate(conn: &Connection, id: i64) -> AppResult<TaxRate> {
+    conn.query_row(&format!("{} WHERE id = ?1", TAX_RATE_SELECT), [id], tax_rate_from_row)
+        .map_err(not_found("tax rate", id))
+}
+
+fn validate(name: &str, basis_points: i32) -> AppResult<()> {
+    if name.trim().is_empty() {
+        return Err(AppError::Validation("A tax rate needs a name".to_string()));
+    }
+    if !(0..=WHOLE).contains(&basis_points) {
+        return Err(AppError::Validation("A tax rate must be between 0% and 100%".to_string()));
+    }
+    Ok(())
+}
+
+pub fn create_tax_rate(conn: &Connection, rate: CreateTaxRate) -> AppResult<TaxRate> {
+    validate(&rate.name, rate.basis_points)?;
+
+    conn.execute(
+        "INSERT INTO tax_rates (name, basis_points, included) VALUES (?1, ?2, ?3)",
+        rusqlite::params![rate.name.trim(), rate.basis_points, rate.included],
+    )?;
+    get_tax_rate(conn, conn.last_insert_rowid())
+}
+
+/// Change a rate from the next sale on; lines already sold keep theirs.
+pub fn update_tax_rate(conn: &Connection, rate: UpdateTaxRate) -> AppResult<TaxRate> {
+    validate(&rate.name, rate.basis_points)?;
+
+    let updated = conn.execute(
+        "UPDATE tax_rates SET name = ?1, basis_points = ?2, included = ?3 WHERE id = ?4",
+        rusqlite::params![rate.name.trim(), rate.basis_points, rate.included, rate.id],
+    )?;
+    if updated == 0 {
+        return Err(AppError::NotFound { entity: "tax rate", id: rate.id });
+    }
+
+    get_tax_rate(conn, rate.id)
+}
+
+/// Delete a rate nothing is taxed at any more.
+pub fn delete_tax_rate(conn: &Connection, id: i64) -> AppResult<()> {
+    let rate = get_tax_rate(conn, id)?;
+
+    let references = referenced_by(
+        conn,
+        id,
+        &[("products", "tax_rate_id", "products"), ("categories", "tax_rate_id", "categories")],
+    )?;
+    if !references.is_empty() {
+        return Err(AppError::Validation(format!(
+            "Cannot delete {}: still used by {}",
+            rate.name,
+            references.join(", ")
+        )));
+    }
+
+    conn.execute("DELETE FROM tax_rates WHERE id = ?1", [id])?;
+    Ok(())
+}
+
+/// Tax a product at its own rate; `None` falls back to its category's.
+pub fn set_product_tax_rate(conn: &Connection, product_id: i64, tax_rate_id: Option<i64>) -> AppResult<Product> {
+    if let Some(id) = tax_rate_id {
+        get_tax_rate(conn, id)?;
+    }
+
+    let updated = conn.execute(
+        "UPDATE products SET tax_rate_id = ?1 WHERE id = ?2",
+        rusqlite::params![tax_rate_id, product_id],
+    )?;
+    if updated == 0 {
+        return Err(AppError::NotFound { entity: "product", id: product_id });
+    }
+
+    products::get_product(conn, product_id)
+}
+
+/// Tax a category's products at a rate; `None` falls back to its parent's.
+pub fn set_category_tax_rate(conn: &Connection, category_id: i64, tax_rate_id: Option<i64>) -> AppResult<Category> {
+    if let Some(id) = tax_rate_id {
+        get_tax_rate(conn, id)?;
+    }
+
+    let updated = conn.execute(
+        "UPDATE categories SET tax_rate_id = ?1 WHERE id = ?2",
+        rusqlite::params![tax_rate_id, category_id],
+    )?;
+    if updated == 0 {
+        return Err(AppError::NotFound { entity: "category", id: category_id });
+    }
+
+    categories::get_category(conn, category_id)
+}
+
+/// The rate a product sells at: its own, else the nearest category up the
+/// tree that sets one. `None` when nothing does and it sells untaxed.
+pub(crate) fn product_tax_rate(conn: &Connection, product_id: i64) -> AppResult<Option<TaxRate>> {
+    let (own, category_id): (Option<i64>, Option<i64>) = conn
+        .query_row(
+            "SELECT tax_rate_id, category_id FROM products WHERE id = ?1",
+            [product_id],
+            |row| Ok((row.get(0)?, row.get(1)?)),
+        )
+        .map_err(not_found("product", product_id))?;
+
+    let id = match (own, category_id) {
+        (Some(id), _) => Some(id),
+        (None, Some(category_id)) => categories::lineage(conn, category_id)?.iter().find_map(|c| c.tax_rate_id),
+        (None, None) => None,
+    };
+
+    id.map(|id| get_tax_rate(conn, id)).transpose()
+}
+
+/// Recompute the tax on each of an order's lines from its quantity, price and
+/// rate, rounded to the nearest minor unit. Included tax is the share of the
+/// price above its net; excluded tax is charged on top.
+pub(crate) fn update_line_taxes(conn: &Connection, order_id: i64) -> AppResult<()> {
+    conn.execute(
+        &format!(
+            "UPDATE order_items SET tax_amount = CASE
+                 WHEN tax_included THEN
+                     (quantity * price_at_sale * tax_basis_points + ({whole} + tax_basis_points) / 2)
+                         / ({whole} + tax_basis_points)
+                 ELSE (quantity * price_at_sale * tax_basis_points + {whole} / 2) / {whole}
+             END
+             WHERE order_id = ?1",
+            whole = WHOLE
+        ),
+        [order_id],
+    )?;
+
+    Ok(())
+}
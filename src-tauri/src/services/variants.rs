@@ -0,0 +1,172 @@
+use crate::db::begin_write;
+use crate::error::{not_found, AppError, AppResult};
+use crate::models::{CreateProductVariant, ProductVariant, UpdateProductVariant};
+use crate::money::Money;
+use crate::services::{codes, recipes};
+use rusqlite::Connection;
+
+const VARIANT_SELECT: &str =
+    "SELECT v.id, v.product_id, p.name, v.name, v.price,
+            (SELECT GROUP_CONCAT(code, char(10)) FROM (
+                SELECT code FROM product_codes WHERE variant_id = v.id ORDER BY code
+            )),
+            v.deduction, v.created_at
+     FROM product_variants v
+     LEFT JOIN products p ON v.product_id = p.id";
+
+fn variant_from_row(row: &rusqlite::Row) -> rusqlite::Result<ProductVariant> {
+    Ok(ProductVariant {
+        id: row.get(0)?,
+        product_id: row.get(1)?,
+        product_name: row.get(2)?,
+        name: row.get(3)?,
+        price: row.get(4)?,
+        codes: codes::split_codes(row.get(5)?),
+        deduction: row.get(6)?,
+        created_at: row.get(7)?,
+    })
+}
+
+/// Variants of one product, or of every product when `product_id` is `None`.
+pub fn get_variants(conn: &Connection, product_id: Option<i64>) -> AppResult<Vec<ProductVariant>> {
+    let mut stmt = conn.prepare(&format!(
+        "{} WHERE ?1 IS NULL OR v.product_id = ?1 ORDER BY p.name, v.price, v.name",
+        VARIANT_SELECT
+    ))?;
+
+    let variants = stmt
+        .query_map([product_id], variant_from_row)?
+        .collect::<Result<Vec<_>, _>>()?;
+
+    Ok(variants)
+}
+
+pub fn get_variant(conn: &Connection, id: i64) -> AppResult<ProductVariant> {
+    conn.query_row(&format!("{} WHERE v.id = ?1", VARIANT_SELECT), [id], variant_from_row)
+        .map_err(not_found("variant", id))
+}
+
+fn validate(name: &str, price: Money, deduction: i32) -> AppResult<()> {
+    if name.trim().is_empty() {
+        return Err(AppError::Validation("A variant needs a name".to_string()));
+    }
+    if price.minor_units < 0 || deduction <= 0 {
+        return Err(AppError::Validation(format!(
+            "Invalid price or deduction for variant {}",
+            name
+        )));
+    }
+
+    Ok(())
+}
+
+/// Map a unique-index failure to a message naming the clashing variant.
+fn unique_violation(name: &str) -> impl FnOnce(rusqlite::Error) -> AppError {
+    let message = format!("A variant named {} already exists", name);
+    move |e| match e {
+        rusqlite::Error::SqliteFailure(err, _) if err.code == rusqlite::ErrorCode::ConstraintViolation => {
+            AppError::Validation(message)
+        }
+        other => AppError::from(other),
+    }
+}
+
+/// Add a variant. Products with a recipe sell through their ingredients and
+/// cannot have variants.
+pub fn create_variant(conn: &mut Connection, variant: CreateProductVariant) -> AppResult<ProductVariant> {
+    validate(&variant.name, variant.price, variant.deduction)?;
+
+    let tx = begin_write(conn)?;
+
+    tx.query_row("SELECT id FROM products WHERE id = ?1", [variant.product_id], |row| row.get::<_, i64>(0))
+        .map_err(not_found("product", variant.product_id))?;
+    if recipes::has_recipe(&tx, variant.product_id)? {
+        return Err(AppError::Validation(
+            "Products made from a recipe cannot have variants".to_string(),
+        ));
+    }
+
+    tx.execute(
+        "INSERT INTO product_variants (product_id, name, price, deduction) VALUES (?1, ?2, ?3, ?4)",
+        rusqlite::params![variant.product_id, variant.name, variant.price, variant.deduction],
+    )
+    .map_err(unique_violation(&variant.name))?;
+
+    let id = tx.last_insert_rowid();
+    for code in &variant.codes {
+        codes::insert_code(&tx, variant.product_id, Some(id), code)?;
+    }
+
+    tx.commit()?;
+    get_variant(conn, id)
+}
+
+pub fn update_variant(conn: &mut Connection, variant: UpdateProductVariant) -> AppResult<ProductVariant> {
+    validate(&variant.name, variant.price, variant.deduction)?;
+
+    let tx = begin_write(conn)?;
+
+    let updated = tx
+        .execute(
+            "UPDATE product_variants SET name = ?1, price = ?2, deduction = ?3 WHERE id = ?4",
+            rusqlite::params![variant.name, variant.price, variant.deduction, variant.id],
+        )
+        .map_err(unique_violation(&variant.name))?;
+    if updated == 0 {
+        return Err(AppError::NotFound { entity: "variant", id: variant.id });
+    }
+
+    tx.commit()?;
+    get_variant(conn, variant.id)
+}
+
+/// Delete a variant that has never been sold, with its codes.
+pub fn delete_variant(conn: &Connection, id: i64) -> AppResult<()> {
+    let sold: i32 = conn.query_row(
+        "SELECT COUNT(*) FROM order_items WHERE variant_id = ?1",
+        [id],
+        |row| row.get(0),
+    )?;
+
+    if sold > 0 {
+        return Err(AppError::Validation(format!(
+            "Cannot delete a variant that has been sold on {} order lines",
+            sold
+        )));
+    }
+
+    conn.execute("DELETE FROM product_codes WHERE variant_id = ?1", [id])?;
+    conn.execute("DELETE FROM product_variants WHERE id = ?1", [id])?;
+
+    Ok(())
+}
+
+/// Price of `variant_id`, checking it is a variant of `product_id`.
+pub(crate) fn variant_price(conn: &Connection, product_id: i64, variant_id: i64) -> AppResult<Money> {
+    let (parent_id, price): (i64, Money) = conn
+        .query_row(
+            "SELECT product_id, price FROM product_variants WHERE id = ?1",
+            [variant_id],
+            |row| Ok((row.get(0)?, row.get(1)?)),
+        )
+        .map_err(not_found("variant", variant_id))?;
+
+    if parent_id != product_id {
+        return Err(AppError::Validation(format!(
+            "Variant {} does not belong to product {}",
+            variant_id, product_id
+        )));
+    }
+
+    Ok(price)
+}
+
+/// Base units of the parent's stock one sale of the variant takes.
+pub(crate) fn deduction(conn: &Connection, variant_id: i64) -> AppResult<i32> {
+    conn.query_row(
+        "SELECT deduction FROM product_variants WHERE id = ?1",
+        [variant_id],
+        |row| row.get(0),
+    )
+    .map_err(not_found("variant", variant_id))
+}
@@ -3,78 +3,34 @@
 
 #[cfg(test)]
 mod tests {
+    use crate::db::{self, Migration, MigrationError};
+    use crate::error::{not_found, AppError};
+    use crate::models::{
+        CategorySales, CreateModifier, CreateModifierGroup, CreateOrder, CreateOrderItem, CreatePricingRule,
+        CreateProduct, CreateProductVariant, CreatePurchaseOrder, CreatePurchaseOrderLine, CreateStaff,
+        CreateStockLot, CreateStockMovement, CreateSupplier, CreateTaxRate, ImportedRow, OrderWithItems,
+        PricingRuleKind, PurchaseOrderStatus, PurchaseOrderWithLines, ReceiveLine, ReceivePurchaseOrder,
+        SchedulePriceChange, SetRecipeComponent, StockReason, StockTakeStatus, TransferStock, Unit, UpdateCategory,
+        UpdatePricingRule, UpdateProduct, UpdateProductVariant, UpdateTaxRate,
+    };
+    use crate::money::Money;
+    use crate::services::{
+        availability, catalog, categories, codes, locations, lots, modifiers, orders, prices, pricing, products,
+        purchasing, recipes, reorder, reports, staff, stock, stock_takes, taxes, variants,
+    };
     use rusqlite::Connection;
 
-    /// Create a test database with schema
+    /// Create a test database with the real, fully migrated schema
     fn setup_test_db() -> Connection {
-        let conn = Connection::open_in_memory().expect("Failed to create in-memory database");
-
-        conn.execute_batch(
-            "
-            CREATE TABLE categories (
-                id INTEGER PRIMARY KEY AUTOINCREMENT,
-                name TEXT NOT NULL UNIQUE
-            );
-
-            CREATE TABLE products (
-                id INTEGER PRIMARY KEY AUTOINCREMENT,
-                name TEXT NOT NULL,
-                price REAL NOT NULL,
-                quantity INTEGER NOT NULL DEFAULT 0,
-                category_id INTEGER,
-                low_stock_threshold INTEGER DEFAULT 5,
-                created_at DATETIME DEFAULT CURRENT_TIMESTAMP,
-                FOREIGN KEY (category_id) REFERENCES categories(id)
-            );
-
-            CREATE TABLE staff (
-                id INTEGER PRIMARY KEY AUTOINCREMENT,
-                name TEXT NOT NULL UNIQUE,
-                pin TEXT,
-                created_at DATETIME DEFAULT CURRENT_TIMESTAMP
-            );
-
-            CREATE TABLE orders (
-                id INTEGER PRIMARY KEY AUTOINCREMENT,
-                staff_id INTEGER NOT NULL,
-                table_number INTEGER NOT NULL DEFAULT 1,
-                total REAL NOT NULL,
-                customer_name TEXT,
-                notes TEXT,
-                status TEXT DEFAULT 'open',
-                session_id INTEGER,
-                created_at DATETIME DEFAULT CURRENT_TIMESTAMP,
-                FOREIGN KEY (staff_id) REFERENCES staff(id)
-            );
-
-            CREATE TABLE order_items (
-                id INTEGER PRIMARY KEY AUTOINCREMENT,
-                order_id INTEGER NOT NULL,
-                product_id INTEGER NOT NULL,
-                quantity INTEGER NOT NULL,
-                price_at_sale REAL NOT NULL,
-                FOREIGN KEY (order_id) REFERENCES orders(id),
-                FOREIGN KEY (product_id) REFERENCES products(id)
-            );
-
-            CREATE TABLE day_sessions (
-                id INTEGER PRIMARY KEY AUTOINCREMENT,
-                date DATE,
-                started_by INTEGER NOT NULL,
-                started_at DATETIME DEFAULT CURRENT_TIMESTAMP,
-                is_active INTEGER DEFAULT 1,
-                closed_at DATETIME,
-                total_revenue REAL,
-                total_orders INTEGER,
-                FOREIGN KEY (started_by) REFERENCES staff(id)
-            );
-            ",
-        )
-        .expect("Failed to create schema");
-
+        let mut conn = Connection::open_in_memory().expect("Failed to create in-memory database");
+        db::run_migrations(&mut conn).expect("Failed to migrate schema");
         conn
     }
 
+    fn latest_version() -> i64 {
+        db::MIGRATIONS.last().unwrap().version
+    }
+
     /// Seed test data
     fn seed_test_data(conn: &Connection) {
         // Create category
@@ -83,17 +39,17 @@ mod tests {
 
         // Create products
         conn.execute(
-            "INSERT INTO products (name, price, quantity, category_id, low_stock_threshold) VALUES ('Heineken', 5.0, 100, 1, 10)",
+            "INSERT INTO products (name, price, quantity, category_id, low_stock_threshold) VALUES ('Heineken', 500, 100, 1, 10)",
             [],
         )
         .unwrap();
         conn.execute(
-            "INSERT INTO products (name, price, quantity, category_id, low_stock_threshold) VALUES ('Corona', 6.0, 50, 1, 5)",
+            "INSERT INTO products (name, price, quantity, category_id, low_stock_threshold) VALUES ('Corona', 600, 50, 1, 5)",
             [],
         )
         .unwrap();
         conn.execute(
-            "INSERT INTO products (name, price, quantity, category_id, low_stock_threshold) VALUES ('Guinness', 7.0, 3, 1, 5)",
+            "INSERT INTO products (name, price, quantity, category_id, low_stock_threshold) VALUES ('Guinness', 700, 3, 1, 5)",
             [],
         )
         .unwrap();
@@ -143,7 +99,7 @@ mod tests {
         let conn = setup_test_db();
         seed_test_data(&conn);
 
-        let (name, price, quantity): (String, f64, i32) = conn
+        let (name, price, quantity): (String, Money, i32) = conn
             .query_row(
                 "SELECT name, price, quantity FROM products WHERE id = 1",
                 [],
@@ -152,7 +108,7 @@ mod tests {
             .unwrap();
 
         assert_eq!(name, "Heineken");
-        assert!((price - 5.0).abs() < 0.01);
+        assert_eq!(price, Money::from_minor(500));
         assert_eq!(quantity, 100);
     }
 
@@ -238,12 +194,12 @@ mod tests {
 
         // Create order
         conn.execute(
-            "INSERT INTO orders (staff_id, table_number, total, status, session_id) VALUES (1, 5, 25.0, 'open', 1)",
+            "INSERT INTO orders (staff_id, table_number, total, status, session_id) VALUES (1, 5, 2500, 'open', 1)",
             [],
         )
         .unwrap();
 
-        let (table, total, status): (i32, f64, String) = conn
+        let (table, total, status): (i32, Money, String) = conn
             .query_row(
                 "SELECT table_number, total, status FROM orders WHERE id = 1",
                 [],
@@ -252,7 +208,7 @@ mod tests {
             .unwrap();
 
         assert_eq!(table, 5);
-        assert!((total - 25.0).abs() < 0.01);
+        assert_eq!(total, Money::from_minor(2500));
         assert_eq!(status, "open");
     }
 
@@ -265,14 +221,14 @@ mod tests {
         conn.execute("INSERT INTO day_sessions (started_by) VALUES (1)", [])
             .unwrap();
         conn.execute(
-            "INSERT INTO orders (staff_id, table_number, total, status, session_id) VALUES (1, 1, 15.0, 'open', 1)",
+            "INSERT INTO orders (staff_id, table_number, total, status, session_id) VALUES (1, 1, 1500, 'open', 1)",
             [],
         )
         .unwrap();
 
         // Add order item (3 Heinekens)
         conn.execute(
-            "INSERT INTO order_items (order_id, product_id, quantity, price_at_sale) VALUES (1, 1, 3, 5.0)",
+            "INSERT INTO order_items (order_id, product_id, quantity, price_at_sale) VALUES (1, 1, 3, 500)",
             [],
         )
         .unwrap();
@@ -295,7 +251,7 @@ mod tests {
         conn.execute("INSERT INTO day_sessions (started_by) VALUES (1)", [])
             .unwrap();
         conn.execute(
-            "INSERT INTO orders (staff_id, table_number, total, status, session_id) VALUES (1, 1, 25.0, 'open', 1)",
+            "INSERT INTO orders (staff_id, table_number, total, status, session_id) VALUES (1, 1, 2500, 'open', 1)",
             [],
         )
         .unwrap();
@@ -318,7 +274,7 @@ mod tests {
         conn.execute("INSERT INTO day_sessions (started_by) VALUES (1)", [])
             .unwrap();
         conn.execute(
-            "INSERT INTO orders (staff_id, table_number, total, status, session_id) VALUES (1, 1, 25.0, 'paid', 1)",
+            "INSERT INTO orders (staff_id, table_number, total, status, session_id) VALUES (1, 1, 2500, 'paid', 1)",
             [],
         )
         .unwrap();
@@ -342,25 +298,25 @@ mod tests {
         conn.execute("INSERT INTO day_sessions (started_by) VALUES (1)", [])
             .unwrap();
         conn.execute(
-            "INSERT INTO orders (staff_id, table_number, total, status, session_id) VALUES (1, 1, 0.0, 'open', 1)",
+            "INSERT INTO orders (staff_id, table_number, total, status, session_id) VALUES (1, 1, 0, 'open', 1)",
             [],
         )
         .unwrap();
 
         // Add items
         conn.execute(
-            "INSERT INTO order_items (order_id, product_id, quantity, price_at_sale) VALUES (1, 1, 2, 5.0)",
+            "INSERT INTO order_items (order_id, product_id, quantity, price_at_sale) VALUES (1, 1, 2, 500)",
             [],
         )
         .unwrap();
         conn.execute(
-            "INSERT INTO order_items (order_id, product_id, quantity, price_at_sale) VALUES (1, 2, 3, 6.0)",
+            "INSERT INTO order_items (order_id, product_id, quantity, price_at_sale) VALUES (1, 2, 3, 600)",
             [],
         )
         .unwrap();
 
         // Calculate total from items
-        let total: f64 = conn
+        let total: Money = conn
             .query_row(
                 "SELECT SUM(quantity * price_at_sale) FROM order_items WHERE order_id = 1",
                 [],
@@ -368,7 +324,7 @@ mod tests {
             )
             .unwrap();
 
-        assert!((total - 28.0).abs() < 0.01); // 2*5 + 3*6 = 28
+        assert_eq!(total, Money::from_minor(2800)); // 2*5 + 3*6 = 28
     }
 
     #[test]
@@ -446,12 +402,12 @@ mod tests {
 
         // Close session with totals
         conn.execute(
-            "UPDATE day_sessions SET is_active = 0, closed_at = CURRENT_TIMESTAMP, total_revenue = 150.0, total_orders = 5 WHERE id = 1",
+            "UPDATE day_sessions SET is_active = 0, closed_at = CURRENT_TIMESTAMP, total_revenue = 15000, total_orders = 5 WHERE id = 1",
             [],
         )
         .unwrap();
 
-        let (is_active, revenue, orders): (i32, f64, i32) = conn
+        let (is_active, revenue, orders): (i32, Money, i32) = conn
             .query_row(
                 "SELECT is_active, total_revenue, total_orders FROM day_sessions WHERE id = 1",
                 [],
@@ -460,7 +416,7 @@ mod tests {
             .unwrap();
 
         assert_eq!(is_active, 0);
-        assert!((revenue - 150.0).abs() < 0.01);
+        assert_eq!(revenue, Money::from_minor(15000));
         assert_eq!(orders, 5);
     }
 
@@ -472,7 +428,7 @@ mod tests {
         conn.execute("INSERT INTO day_sessions (started_by, is_active) VALUES (1, 1)", [])
             .unwrap();
         conn.execute(
-            "INSERT INTO orders (staff_id, table_number, total, status, session_id) VALUES (1, 1, 25.0, 'open', 1)",
+            "INSERT INTO orders (staff_id, table_number, total, status, session_id) VALUES (1, 1, 2500, 'open', 1)",
             [],
         )
         .unwrap();
@@ -535,17 +491,17 @@ mod tests {
 
         // Create orders for multiple tables
         conn.execute(
-            "INSERT INTO orders (staff_id, table_number, total, status, session_id) VALUES (1, 1, 25.0, 'open', 1)",
+            "INSERT INTO orders (staff_id, table_number, total, status, session_id) VALUES (1, 1, 2500, 'open', 1)",
             [],
         )
         .unwrap();
         conn.execute(
-            "INSERT INTO orders (staff_id, table_number, total, status, session_id) VALUES (1, 5, 50.0, 'open', 1)",
+            "INSERT INTO orders (staff_id, table_number, total, status, session_id) VALUES (1, 5, 5000, 'open', 1)",
             [],
         )
         .unwrap();
         conn.execute(
-            "INSERT INTO orders (staff_id, table_number, total, status, session_id) VALUES (1, 10, 75.0, 'open', 1)",
+            "INSERT INTO orders (staff_id, table_number, total, status, session_id) VALUES (1, 10, 7500, 'open', 1)",
             [],
         )
         .unwrap();
@@ -565,7 +521,7 @@ mod tests {
             .unwrap();
 
         conn.execute(
-            "INSERT INTO orders (staff_id, table_number, total, status, session_id) VALUES (1, 5, 25.0, 'open', 1)",
+            "INSERT INTO orders (staff_id, table_number, total, status, session_id) VALUES (1, 5, 2500, 'open', 1)",
             [],
         )
         .unwrap();
@@ -592,23 +548,23 @@ mod tests {
 
         // Create paid orders
         conn.execute(
-            "INSERT INTO orders (staff_id, table_number, total, status, session_id) VALUES (1, 1, 50.0, 'paid', 1)",
+            "INSERT INTO orders (staff_id, table_number, total, status, session_id) VALUES (1, 1, 5000, 'paid', 1)",
             [],
         )
         .unwrap();
         conn.execute(
-            "INSERT INTO orders (staff_id, table_number, total, status, session_id) VALUES (1, 2, 75.0, 'paid', 1)",
+            "INSERT INTO orders (staff_id, table_number, total, status, session_id) VALUES (1, 2, 7500, 'paid', 1)",
             [],
         )
         .unwrap();
         // Open order should not count
         conn.execute(
-            "INSERT INTO orders (staff_id, table_number, total, status, session_id) VALUES (1, 3, 100.0, 'open', 1)",
+            "INSERT INTO orders (staff_id, table_number, total, status, session_id) VALUES (1, 3, 10000, 'open', 1)",
             [],
         )
         .unwrap();
 
-        let total_revenue: f64 = conn
+        let total_revenue: Money = conn
             .query_row(
                 "SELECT COALESCE(SUM(total), 0) FROM orders WHERE session_id = 1 AND status = 'paid'",
                 [],
@@ -616,7 +572,7 @@ mod tests {
             )
             .unwrap();
 
-        assert!((total_revenue - 125.0).abs() < 0.01); // 50 + 75
+        assert_eq!(total_revenue, Money::from_minor(12500)); // 50 + 75
     }
 
     #[test]
@@ -628,17 +584,17 @@ mod tests {
             .unwrap();
 
         conn.execute(
-            "INSERT INTO orders (staff_id, table_number, total, status, session_id) VALUES (1, 1, 50.0, 'paid', 1)",
+            "INSERT INTO orders (staff_id, table_number, total, status, session_id) VALUES (1, 1, 5000, 'paid', 1)",
             [],
         )
         .unwrap();
         conn.execute(
-            "INSERT INTO orders (staff_id, table_number, total, status, session_id) VALUES (1, 2, 75.0, 'paid', 1)",
+            "INSERT INTO orders (staff_id, table_number, total, status, session_id) VALUES (1, 2, 7500, 'paid', 1)",
             [],
         )
         .unwrap();
         conn.execute(
-            "INSERT INTO orders (staff_id, table_number, total, status, session_id) VALUES (1, 3, 25.0, 'paid', 1)",
+            "INSERT INTO orders (staff_id, table_number, total, status, session_id) VALUES (1, 3, 2500, 'paid', 1)",
             [],
         )
         .unwrap();
@@ -683,7 +639,7 @@ mod tests {
 
         // Create open order
         conn.execute(
-            "INSERT INTO orders (staff_id, table_number, total, status, session_id) VALUES (1, 1, 25.0, 'open', 1)",
+            "INSERT INTO orders (staff_id, table_number, total, status, session_id) VALUES (1, 1, 2500, 'open', 1)",
             [],
         )
         .unwrap();
@@ -711,17 +667,17 @@ mod tests {
 
         // Create multiple open orders on different tables
         conn.execute(
-            "INSERT INTO orders (staff_id, table_number, total, status, session_id) VALUES (1, 1, 25.0, 'open', 1)",
+            "INSERT INTO orders (staff_id, table_number, total, status, session_id) VALUES (1, 1, 2500, 'open', 1)",
             [],
         )
         .unwrap();
         conn.execute(
-            "INSERT INTO orders (staff_id, table_number, total, status, session_id) VALUES (1, 5, 50.0, 'open', 1)",
+            "INSERT INTO orders (staff_id, table_number, total, status, session_id) VALUES (1, 5, 5000, 'open', 1)",
             [],
         )
         .unwrap();
         conn.execute(
-            "INSERT INTO orders (staff_id, table_number, total, status, session_id) VALUES (1, 10, 75.0, 'paid', 1)",
+            "INSERT INTO orders (staff_id, table_number, total, status, session_id) VALUES (1, 10, 7500, 'paid', 1)",
             [],
         )
         .unwrap();
@@ -769,22 +725,22 @@ mod tests {
 
         // Create paid orders with specific totals
         conn.execute(
-            "INSERT INTO orders (staff_id, table_number, total, status, session_id) VALUES (1, 1, 100.50, 'paid', 1)",
+            "INSERT INTO orders (staff_id, table_number, total, status, session_id) VALUES (1, 1, 10050, 'paid', 1)",
             [],
         )
         .unwrap();
         conn.execute(
-            "INSERT INTO orders (staff_id, table_number, total, status, session_id) VALUES (1, 2, 75.25, 'paid', 1)",
+            "INSERT INTO orders (staff_id, table_number, total, status, session_id) VALUES (1, 2, 7525, 'paid', 1)",
             [],
         )
         .unwrap();
         conn.execute(
-            "INSERT INTO orders (staff_id, table_number, total, status, session_id) VALUES (1, 3, 50.00, 'paid', 1)",
+            "INSERT INTO orders (staff_id, table_number, total, status, session_id) VALUES (1, 3, 5000, 'paid', 1)",
             [],
         )
         .unwrap();
 
-        let total_revenue: f64 = conn
+        let total_revenue: Money = conn
             .query_row(
                 "SELECT COALESCE(SUM(total), 0) FROM orders WHERE session_id = 1",
                 [],
@@ -792,7 +748,7 @@ mod tests {
             )
             .unwrap();
 
-        assert!((total_revenue - 225.75).abs() < 0.01, "Revenue should be 225.75");
+        assert_eq!(total_revenue, Money::from_minor(22575), "Revenue should be 225.75");
     }
 
     #[test]
@@ -804,13 +760,13 @@ mod tests {
             .unwrap();
 
         conn.execute(
-            "INSERT INTO orders (staff_id, table_number, total, status, session_id) VALUES (1, 1, 100.0, 'paid', 1)",
+            "INSERT INTO orders (staff_id, table_number, total, status, session_id) VALUES (1, 1, 10000, 'paid', 1)",
             [],
         )
         .unwrap();
 
         // Simulate close_day
-        let (total_revenue, total_orders): (f64, i32) = conn
+        let (total_revenue, total_orders): (Money, i32) = conn
             .query_row(
                 "SELECT COALESCE(SUM(total), 0), COUNT(*) FROM orders WHERE session_id = 1",
                 [],
@@ -825,7 +781,7 @@ mod tests {
         .unwrap();
 
         // Verify all fields updated
-        let (is_active, stored_revenue, stored_orders, closed_at): (i32, f64, i32, Option<String>) = conn
+        let (is_active, stored_revenue, stored_orders, closed_at): (i32, Money, i32, Option<String>) = conn
             .query_row(
                 "SELECT is_active, total_revenue, total_orders, closed_at FROM day_sessions WHERE id = 1",
                 [],
@@ -834,7 +790,7 @@ mod tests {
             .unwrap();
 
         assert_eq!(is_active, 0, "Session should be inactive");
-        assert!((stored_revenue - 100.0).abs() < 0.01, "Revenue should be stored");
+        assert_eq!(stored_revenue, Money::from_minor(10000), "Revenue should be stored");
         assert_eq!(stored_orders, 1, "Order count should be stored");
         assert!(closed_at.is_some(), "Closed_at should be set");
     }
@@ -849,7 +805,7 @@ mod tests {
 
         // Create order and mark paid
         conn.execute(
-            "INSERT INTO orders (staff_id, table_number, total, status, session_id) VALUES (1, 1, 50.0, 'open', 1)",
+            "INSERT INTO orders (staff_id, table_number, total, status, session_id) VALUES (1, 1, 5000, 'open', 1)",
             [],
         )
         .unwrap();
@@ -890,18 +846,18 @@ mod tests {
 
         // Mix of paid and open orders
         conn.execute(
-            "INSERT INTO orders (staff_id, table_number, total, status, session_id) VALUES (1, 1, 100.0, 'paid', 1)",
+            "INSERT INTO orders (staff_id, table_number, total, status, session_id) VALUES (1, 1, 10000, 'paid', 1)",
             [],
         )
         .unwrap();
         conn.execute(
-            "INSERT INTO orders (staff_id, table_number, total, status, session_id) VALUES (1, 2, 50.0, 'open', 1)",
+            "INSERT INTO orders (staff_id, table_number, total, status, session_id) VALUES (1, 2, 5000, 'open', 1)",
             [],
         )
         .unwrap();
 
         // Revenue should include ALL orders for session (the close prevents with open orders anyway)
-        let total_revenue: f64 = conn
+        let total_revenue: Money = conn
             .query_row(
                 "SELECT COALESCE(SUM(total), 0) FROM orders WHERE session_id = 1",
                 [],
@@ -910,7 +866,7 @@ mod tests {
             .unwrap();
 
         // Both orders count toward session total
-        assert!((total_revenue - 150.0).abs() < 0.01);
+        assert_eq!(total_revenue, Money::from_minor(15000));
 
         // But we can't close because there are open orders
         let open_count: i32 = conn
@@ -952,7 +908,7 @@ mod tests {
         // Start and close first session
         conn.execute("INSERT INTO day_sessions (started_by, is_active) VALUES (1, 1)", [])
             .unwrap();
-        conn.execute("INSERT INTO orders (staff_id, table_number, total, status, session_id) VALUES (1, 1, 50.0, 'paid', 1)", [])
+        conn.execute("INSERT INTO orders (staff_id, table_number, total, status, session_id) VALUES (1, 1, 5000, 'paid', 1)", [])
             .unwrap();
         conn.execute("UPDATE day_sessions SET is_active = 0, closed_at = CURRENT_TIMESTAMP WHERE id = 1", [])
             .unwrap();
@@ -987,24 +943,24 @@ mod tests {
 
         // Create orders for a past date (simulating legacy data)
         conn.execute(
-            &format!("INSERT INTO orders (staff_id, table_number, total, status, created_at) VALUES (1, 1, 100.0, 'paid', '{}T10:00:00')", test_date),
+            &format!("INSERT INTO orders (staff_id, table_number, total, status, created_at) VALUES (1, 1, 10000, 'paid', '{}T10:00:00')", test_date),
             [],
         )
         .unwrap();
         conn.execute(
-            &format!("INSERT INTO orders (staff_id, table_number, total, status, created_at) VALUES (1, 2, 50.0, 'paid', '{}T14:00:00')", test_date),
+            &format!("INSERT INTO orders (staff_id, table_number, total, status, created_at) VALUES (1, 2, 5000, 'paid', '{}T14:00:00')", test_date),
             [],
         )
         .unwrap();
 
         // Create recovery session
         conn.execute(
-            "INSERT INTO day_sessions (date, started_by, started_at, is_active, closed_at, total_revenue, total_orders) VALUES (?1, 1, ?1 || ' 00:00:00', 0, CURRENT_TIMESTAMP, 150.0, 2)",
+            "INSERT INTO day_sessions (date, started_by, started_at, is_active, closed_at, total_revenue, total_orders) VALUES (?1, 1, ?1 || ' 00:00:00', 0, CURRENT_TIMESTAMP, 15000, 2)",
             [test_date],
         )
         .unwrap();
 
-        let (revenue, orders): (f64, i32) = conn
+        let (revenue, orders): (Money, i32) = conn
             .query_row(
                 "SELECT total_revenue, total_orders FROM day_sessions WHERE date = ?1",
                 [test_date],
@@ -1012,7 +968,7 @@ mod tests {
             )
             .unwrap();
 
-        assert!((revenue - 150.0).abs() < 0.01);
+        assert_eq!(revenue, Money::from_minor(15000));
         assert_eq!(orders, 2);
     }
 
@@ -1070,12 +1026,12 @@ mod tests {
 
         // Create orders for this session
         conn.execute(
-            "INSERT INTO orders (staff_id, table_number, total, status, session_id) VALUES (1, 1, 50.0, 'paid', 1)",
+            "INSERT INTO orders (staff_id, table_number, total, status, session_id) VALUES (1, 1, 5000, 'paid', 1)",
             [],
         )
         .unwrap();
         conn.execute(
-            "INSERT INTO orders (staff_id, table_number, total, status, session_id) VALUES (1, 2, 75.0, 'paid', 1)",
+            "INSERT INTO orders (staff_id, table_number, total, status, session_id) VALUES (1, 2, 7500, 'paid', 1)",
             [],
         )
         .unwrap();
@@ -1106,7 +1062,7 @@ mod tests {
         )
         .unwrap();
         conn.execute(
-            "INSERT INTO orders (staff_id, table_number, total, status, session_id) VALUES (1, 1, 50.0, 'paid', 1)",
+            "INSERT INTO orders (staff_id, table_number, total, status, session_id) VALUES (1, 1, 5000, 'paid', 1)",
             [],
         )
         .unwrap();
@@ -1141,22 +1097,22 @@ mod tests {
 
         // Orders with precise decimal values
         conn.execute(
-            "INSERT INTO orders (staff_id, table_number, total, status, session_id) VALUES (1, 1, 33.33, 'paid', 1)",
+            "INSERT INTO orders (staff_id, table_number, total, status, session_id) VALUES (1, 1, 3333, 'paid', 1)",
             [],
         )
         .unwrap();
         conn.execute(
-            "INSERT INTO orders (staff_id, table_number, total, status, session_id) VALUES (1, 2, 33.33, 'paid', 1)",
+            "INSERT INTO orders (staff_id, table_number, total, status, session_id) VALUES (1, 2, 3333, 'paid', 1)",
             [],
         )
         .unwrap();
         conn.execute(
-            "INSERT INTO orders (staff_id, table_number, total, status, session_id) VALUES (1, 3, 33.34, 'paid', 1)",
+            "INSERT INTO orders (staff_id, table_number, total, status, session_id) VALUES (1, 3, 3334, 'paid', 1)",
             [],
         )
         .unwrap();
 
-        let total: f64 = conn
+        let total: Money = conn
             .query_row(
                 "SELECT SUM(total) FROM orders WHERE session_id = 1",
                 [],
@@ -1164,7 +1120,7 @@ mod tests {
             )
             .unwrap();
 
-        assert!((total - 100.0).abs() < 0.01, "Decimal precision should work: got {}", total);
+        assert_eq!(total, Money::from_minor(10000), "Minor-unit totals must add up exactly: got {}", total);
     }
 
     #[test]
@@ -1196,7 +1152,7 @@ mod tests {
 
         // Create closed session
         conn.execute(
-            "INSERT INTO day_sessions (date, started_by, is_active, closed_at, total_revenue, total_orders) VALUES ('2024-01-14', 1, 0, CURRENT_TIMESTAMP, 100.0, 5)",
+            "INSERT INTO day_sessions (date, started_by, is_active, closed_at, total_revenue, total_orders) VALUES ('2024-01-14', 1, 0, CURRENT_TIMESTAMP, 10000, 5)",
             [],
         )
         .unwrap();
@@ -1229,12 +1185,12 @@ mod tests {
 
         // Create order with items
         conn.execute(
-            "INSERT INTO orders (staff_id, table_number, total, status, session_id) VALUES (1, 1, 25.0, 'paid', 1)",
+            "INSERT INTO orders (staff_id, table_number, total, status, session_id) VALUES (1, 1, 2500, 'paid', 1)",
             [],
         )
         .unwrap();
         conn.execute(
-            "INSERT INTO order_items (order_id, product_id, quantity, price_at_sale) VALUES (1, 1, 5, 5.0)",
+            "INSERT INTO order_items (order_id, product_id, quantity, price_at_sale) VALUES (1, 1, 5, 500)",
             [],
         )
         .unwrap();
@@ -1265,7 +1221,7 @@ mod tests {
         conn.execute("INSERT INTO day_sessions (started_by, is_active) VALUES (1, 1)", [])
             .unwrap();
         conn.execute(
-            "INSERT INTO orders (staff_id, table_number, total, status, session_id) VALUES (1, 1, 100.0, 'paid', 1)",
+            "INSERT INTO orders (staff_id, table_number, total, status, session_id) VALUES (1, 1, 10000, 'paid', 1)",
             [],
         )
         .unwrap();
@@ -1296,7 +1252,7 @@ mod tests {
         conn.execute("INSERT INTO day_sessions (started_by, is_active) VALUES (1, 1)", [])
             .unwrap();
         conn.execute(
-            "INSERT INTO orders (staff_id, table_number, total, status, session_id) VALUES (1, 1, 100.0, 'paid', 1)",
+            "INSERT INTO orders (staff_id, table_number, total, status, session_id) VALUES (1, 1, 10000, 'paid', 1)",
             [],
         )
         .unwrap();
@@ -1341,7 +1297,7 @@ mod tests {
             .unwrap();
 
         // COALESCE ensures we get 0 instead of NULL
-        let (revenue, count): (f64, i32) = conn
+        let (revenue, count): (Money, i32) = conn
             .query_row(
                 "SELECT COALESCE(SUM(total), 0), COUNT(*) FROM orders WHERE session_id = 1",
                 [],
@@ -1349,7 +1305,7 @@ mod tests {
             )
             .unwrap();
 
-        assert!((revenue - 0.0).abs() < 0.01, "Revenue should be 0 for no orders");
+        assert_eq!(revenue, Money::zero(), "Revenue should be 0 for no orders");
         assert_eq!(count, 0, "Count should be 0 for no orders");
     }
 
@@ -1379,7 +1335,7 @@ mod tests {
         conn.execute("INSERT INTO day_sessions (started_by, is_active) VALUES (1, 1)", [])
             .unwrap();
         conn.execute(
-            "INSERT INTO orders (staff_id, table_number, total, status, session_id) VALUES (1, 1, 100.0, 'paid', 1)",
+            "INSERT INTO orders (staff_id, table_number, total, status, session_id) VALUES (1, 1, 10000, 'paid', 1)",
             [],
         )
         .unwrap();
@@ -1413,12 +1369,12 @@ mod tests {
 
         // Order with explicit total
         conn.execute(
-            "INSERT INTO orders (staff_id, table_number, total, status, session_id) VALUES (1, 1, 50.0, 'paid', 1)",
+            "INSERT INTO orders (staff_id, table_number, total, status, session_id) VALUES (1, 1, 5000, 'paid', 1)",
             [],
         )
         .unwrap();
 
-        let revenue: f64 = conn
+        let revenue: Money = conn
             .query_row(
                 "SELECT COALESCE(SUM(total), 0) FROM orders WHERE session_id = 1",
                 [],
@@ -1426,7 +1382,7 @@ mod tests {
             )
             .unwrap();
 
-        assert!((revenue - 50.0).abs() < 0.01);
+        assert_eq!(revenue, Money::from_minor(5000));
     }
 
     #[test]
@@ -1437,20 +1393,20 @@ mod tests {
         conn.execute("INSERT INTO day_sessions (started_by, is_active) VALUES (1, 1)", [])
             .unwrap();
         conn.execute(
-            "INSERT INTO orders (staff_id, table_number, total, status, session_id) VALUES (1, 1, 200.0, 'paid', 1)",
+            "INSERT INTO orders (staff_id, table_number, total, status, session_id) VALUES (1, 1, 20000, 'paid', 1)",
             [],
         )
         .unwrap();
 
         // Perform the close update
         conn.execute(
-            "UPDATE day_sessions SET is_active = 0, closed_at = CURRENT_TIMESTAMP, total_revenue = 200.0, total_orders = 1 WHERE id = 1",
+            "UPDATE day_sessions SET is_active = 0, closed_at = CURRENT_TIMESTAMP, total_revenue = 20000, total_orders = 1 WHERE id = 1",
             [],
         )
         .unwrap();
 
         // Verify ALL fields are set
-        let (is_active, closed_at, revenue, orders): (i32, Option<String>, Option<f64>, Option<i32>) = conn
+        let (is_active, closed_at, revenue, orders): (i32, Option<String>, Option<Money>, Option<i32>) = conn
             .query_row(
                 "SELECT is_active, closed_at, total_revenue, total_orders FROM day_sessions WHERE id = 1",
                 [],
@@ -1462,7 +1418,7 @@ mod tests {
         assert!(closed_at.is_some(), "closed_at should be set");
         assert!(revenue.is_some(), "total_revenue should be set");
         assert!(orders.is_some(), "total_orders should be set");
-        assert!((revenue.unwrap() - 200.0).abs() < 0.01);
+        assert_eq!(revenue.unwrap(), Money::from_minor(20000));
         assert_eq!(orders.unwrap(), 1);
     }
 
@@ -1489,7 +1445,7 @@ mod tests {
 
         // Create order with session_id
         conn.execute(
-            "INSERT INTO orders (staff_id, table_number, total, status, session_id) VALUES (1, 1, 100.0, 'paid', 1)",
+            "INSERT INTO orders (staff_id, table_number, total, status, session_id) VALUES (1, 1, 10000, 'paid', 1)",
             [],
         )
         .unwrap();
@@ -1512,7 +1468,7 @@ mod tests {
 
         // Create order with all fields
         conn.execute(
-            "INSERT INTO orders (staff_id, table_number, total, customer_name, notes, status, session_id) VALUES (1, 5, 150.0, 'Test Customer', 'Test notes', 'paid', 1)",
+            "INSERT INTO orders (staff_id, table_number, total, customer_name, notes, status, session_id) VALUES (1, 5, 15000, 'Test Customer', 'Test notes', 'paid', 1)",
             [],
         )
         .unwrap();
@@ -1522,7 +1478,7 @@ mod tests {
             .unwrap();
 
         // Verify all order data preserved
-        let (customer, notes, total): (Option<String>, Option<String>, f64) = conn
+        let (customer, notes, total): (Option<String>, Option<String>, Money) = conn
             .query_row(
                 "SELECT customer_name, notes, total FROM orders WHERE id = 1",
                 [],
@@ -1532,7 +1488,7 @@ mod tests {
 
         assert_eq!(customer, Some("Test Customer".to_string()));
         assert_eq!(notes, Some("Test notes".to_string()));
-        assert!((total - 150.0).abs() < 0.01);
+        assert_eq!(total, Money::from_minor(15000));
     }
 
     #[test]
@@ -1546,13 +1502,13 @@ mod tests {
         // Create many orders with large totals
         for i in 1..=100 {
             conn.execute(
-                &format!("INSERT INTO orders (staff_id, table_number, total, status, session_id) VALUES (1, {}, 999.99, 'paid', 1)", i % 20 + 1),
+                &format!("INSERT INTO orders (staff_id, table_number, total, status, session_id) VALUES (1, {}, 99999, 'paid', 1)", i % 20 + 1),
                 [],
             )
             .unwrap();
         }
 
-        let (revenue, count): (f64, i32) = conn
+        let (revenue, count): (Money, i32) = conn
             .query_row(
                 "SELECT SUM(total), COUNT(*) FROM orders WHERE session_id = 1",
                 [],
@@ -1561,7 +1517,7 @@ mod tests {
             .unwrap();
 
         assert_eq!(count, 100);
-        assert!((revenue - 99999.0).abs() < 0.1, "Large revenue should calculate correctly");
+        assert_eq!(revenue, Money::from_minor(9999900), "Large revenue should calculate correctly");
     }
 
     #[test]
@@ -1595,17 +1551,17 @@ mod tests {
 
         // Some paid, some open
         conn.execute(
-            "INSERT INTO orders (staff_id, table_number, total, status, session_id) VALUES (1, 1, 50.0, 'paid', 1)",
+            "INSERT INTO orders (staff_id, table_number, total, status, session_id) VALUES (1, 1, 5000, 'paid', 1)",
             [],
         )
         .unwrap();
         conn.execute(
-            "INSERT INTO orders (staff_id, table_number, total, status, session_id) VALUES (1, 2, 30.0, 'paid', 1)",
+            "INSERT INTO orders (staff_id, table_number, total, status, session_id) VALUES (1, 2, 3000, 'paid', 1)",
             [],
         )
         .unwrap();
         conn.execute(
-            "INSERT INTO orders (staff_id, table_number, total, status, session_id) VALUES (1, 3, 100.0, 'open', 1)",
+            "INSERT INTO orders (staff_id, table_number, total, status, session_id) VALUES (1, 3, 10000, 'open', 1)",
             [],
         )
         .unwrap();
@@ -1621,4 +1577,2955 @@ mod tests {
         assert_eq!(open_count, 1, "Should detect open order");
         // Business logic: open_count > 0 means block close
     }
+
+    // ===== MIGRATION TESTS =====
+
+    #[test]
+    fn test_migrations_bring_fresh_db_to_latest_version() {
+        let conn = setup_test_db();
+
+        let version = db::schema_version(&conn).unwrap();
+        assert_eq!(version, latest_version());
+
+        let (recorded, missing_timestamps): (i64, i64) = conn
+            .query_row(
+                "SELECT COUNT(*), SUM(applied_at IS NULL) FROM schema_migrations",
+                [],
+                |row| Ok((row.get(0)?, row.get(1)?)),
+            )
+            .unwrap();
+        assert_eq!(recorded, db::MIGRATIONS.len() as i64);
+        assert_eq!(missing_timestamps, 0, "Every migration should record when it ran");
+
+        // Core tables exist and accept data
+        conn.execute("INSERT INTO staff (name) VALUES ('John')", []).unwrap();
+        conn.execute("INSERT INTO day_sessions (started_by, is_active) VALUES (1, 1)", [])
+            .unwrap();
+    }
+
+    #[test]
+    fn test_migrations_are_idempotent() {
+        let mut conn = setup_test_db();
+
+        db::run_migrations(&mut conn).unwrap();
+
+        let recorded: i64 = conn
+            .query_row("SELECT COUNT(*) FROM schema_migrations", [], |row| row.get(0))
+            .unwrap();
+        assert_eq!(recorded, db::MIGRATIONS.len() as i64, "Re-running must not re-apply");
+    }
+
+    #[test]
+    fn test_migrations_upgrade_legacy_database() {
+        let mut conn = Connection::open_in_memory().unwrap();
+
+        // Schema shipped before sessions and open tables existed
+        conn.execute_batch(
+            "
+            CREATE TABLE staff (id INTEGER PRIMARY KEY AUTOINCREMENT, name TEXT NOT NULL UNIQUE, pin TEXT, created_at DATETIME DEFAULT CURRENT_TIMESTAMP);
+            CREATE TABLE orders (id INTEGER PRIMARY KEY AUTOINCREMENT, staff_id INTEGER NOT NULL, total REAL NOT NULL, created_at DATETIME DEFAULT CURRENT_TIMESTAMP);
+            CREATE TABLE day_sessions (id INTEGER PRIMARY KEY AUTOINCREMENT, date DATE NOT NULL UNIQUE, started_by INTEGER NOT NULL, started_at DATETIME DEFAULT CURRENT_TIMESTAMP, is_active INTEGER DEFAULT 1);
+            INSERT INTO staff (name) VALUES ('John');
+            INSERT INTO day_sessions (date, started_by, started_at, is_active) VALUES ('2024-01-01', 1, '2024-01-01 08:00:00', 1);
+            INSERT INTO orders (staff_id, total, created_at) VALUES (1, 12.5, '2024-01-01 10:00:00');
+            ",
+        )
+        .unwrap();
+
+        db::run_migrations(&mut conn).unwrap();
+
+        let (status, table_number, session_id, total): (String, i32, Option<i64>, Money) = conn
+            .query_row(
+                "SELECT status, table_number, session_id, total FROM orders WHERE id = 1",
+                [],
+                |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?, row.get(3)?)),
+            )
+            .unwrap();
+        assert_eq!(status, "paid", "Legacy orders predate open tables");
+        assert_eq!(total, Money::from_minor(1250), "REAL amounts should become minor units");
+        assert_eq!(table_number, 1);
+        assert_eq!(session_id, Some(1), "Legacy orders should be linked to their session");
+
+        // UNIQUE(date) was dropped, so a second session on the same date is allowed
+        let result = conn.execute(
+            "INSERT INTO day_sessions (date, started_by, is_active) VALUES ('2024-01-01', 1, 0)",
+            [],
+        );
+        assert!(result.is_ok(), "Multiple sessions per date should be allowed");
+    }
+
+    #[test]
+    fn test_migrations_refuse_newer_database() {
+        let mut conn = setup_test_db();
+
+        let future = latest_version() + 1;
+        conn.execute_batch(&format!("PRAGMA user_version = {}", future))
+            .unwrap();
+
+        match db::run_migrations(&mut conn) {
+            Err(MigrationError::DatabaseTooNew { found, supported }) => {
+                assert_eq!(found, future);
+                assert_eq!(supported, latest_version());
+            }
+            other => panic!("Expected DatabaseTooNew, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_failed_migration_rolls_back() {
+        fn broken(conn: &Connection) -> rusqlite::Result<()> {
+            conn.execute("CREATE TABLE half_done (id INTEGER)", [])?;
+            conn.execute("INSERT INTO no_such_table VALUES (1)", [])?;
+            Ok(())
+        }
+
+        let mut conn = setup_test_db();
+        let baseline = latest_version();
+
+        let migrations = [
+            Migration { version: baseline, name: "baseline", up: |_| Ok(()) },
+            Migration { version: baseline + 1, name: "broken", up: broken },
+        ];
+
+        assert!(db::apply_migrations(&mut conn, &migrations).is_err());
+        assert_eq!(db::schema_version(&conn).unwrap(), baseline, "Version must not advance");
+
+        let leftover: i64 = conn
+            .query_row(
+                "SELECT COUNT(*) FROM sqlite_master WHERE name = 'half_done'",
+                [],
+                |row| row.get(0),
+            )
+            .unwrap();
+        assert_eq!(leftover, 0, "Partial migration changes must be rolled back");
+    }
+
+    // ===== TRANSACTION TESTS =====
+
+    #[test]
+    fn test_guarded_deduction_prevents_oversell() {
+        let conn = setup_test_db();
+        seed_test_data(&conn);
+
+        // Guinness has 3 in stock; two terminals each try to sell 2
+        let deduct = "UPDATE products SET quantity = quantity - ?1 WHERE id = ?2 AND quantity >= ?1";
+
+        let first = conn.execute(deduct, rusqlite::params![2, 3]).unwrap();
+        let second = conn.execute(deduct, rusqlite::params![2, 3]).unwrap();
+
+        assert_eq!(first, 1);
+        assert_eq!(second, 0, "Second sale must not go through");
+
+        let stock: i32 = conn
+            .query_row("SELECT quantity FROM products WHERE id = 3", [], |row| row.get(0))
+            .unwrap();
+        assert_eq!(stock, 1, "Stock must never go negative");
+    }
+
+    #[test]
+    fn test_failed_order_rolls_back_completely() {
+        let mut conn = setup_test_db();
+        seed_test_data(&conn);
+
+        {
+            let tx = db::begin_write(&mut conn).unwrap();
+            tx.execute(
+                "INSERT INTO orders (staff_id, table_number, total, status) VALUES (1, 1, 1000, 'open')",
+                [],
+            )
+            .unwrap();
+            tx.execute(
+                "INSERT INTO order_items (order_id, product_id, quantity, price_at_sale) VALUES (1, 1, 2, 500)",
+                [],
+            )
+            .unwrap();
+            tx.execute("UPDATE products SET quantity = quantity - 2 WHERE id = 1", [])
+                .unwrap();
+
+            // Second line fails: not enough Guinness
+            let updated = tx
+                .execute("UPDATE products SET quantity = quantity - 10 WHERE id = 3 AND quantity >= 10", [])
+                .unwrap();
+            assert_eq!(updated, 0);
+            // tx dropped without commit -> rollback
+        }
+
+        let (orders, items): (i32, i32) = conn
+            .query_row(
+                "SELECT (SELECT COUNT(*) FROM orders), (SELECT COUNT(*) FROM order_items)",
+                [],
+                |row| Ok((row.get(0)?, row.get(1)?)),
+            )
+            .unwrap();
+        assert_eq!(orders, 0, "Half-built order must not survive");
+        assert_eq!(items, 0);
+
+        let stock: i32 = conn
+            .query_row("SELECT quantity FROM products WHERE id = 1", [], |row| row.get(0))
+            .unwrap();
+        assert_eq!(stock, 100, "Deducted stock must be restored");
+    }
+
+    #[test]
+    fn test_orders_reject_non_positive_quantities() {
+        let mut conn = setup_test_db();
+        seed_test_data(&conn);
+        reports::start_day(&mut conn, 1).unwrap();
+
+        for quantity in [0, -2] {
+            let err = orders::create_order(&mut conn, new_order(1, &[(1, 1), (2, quantity)])).unwrap_err();
+            assert_eq!(err.code(), "VALIDATION");
+        }
+        let order = orders::create_order(&mut conn, new_order(1, &[(1, 1)])).unwrap();
+        let err = orders::add_items_to_order(&mut conn, order.order.id, new_order(1, &[(2, -1)]).items).unwrap_err();
+        assert_eq!(err.code(), "VALIDATION");
+
+        // Nothing was sold back into stock
+        assert_eq!((stock_of(&conn, 1), stock_of(&conn, 2)), (99, 50));
+        assert_eq!(orders::get_order(&conn, order.order.id).unwrap().items.len(), 1);
+    }
+
+    // ===== MONEY TESTS =====
+
+    #[test]
+    fn test_money_arithmetic_is_exact() {
+        // 0.10 added a thousand times drifts with f64; minor units do not
+        let total: Money = (0..1000).map(|_| Money::from_minor(10)).sum();
+        assert_eq!(total, Money::from_minor(10000));
+
+        let line = Money::from_minor(333) * 3;
+        assert_eq!(line, Money::from_minor(999));
+        assert_eq!(line - Money::from_minor(333), Money::from_minor(666));
+    }
+
+    #[test]
+    fn test_money_display_and_serialization() {
+        assert_eq!(Money::from_minor(22575).to_string(), "225.75 ALL");
+        assert_eq!(Money::from_minor(-5).to_string(), "-0.05 ALL");
+
+        let json = serde_json::to_value(Money::from_minor(1250)).unwrap();
+        assert_eq!(json, serde_json::json!({ "minor_units": 1250, "currency": "ALL" }));
+
+        let parsed: Money = serde_json::from_value(serde_json::json!({ "minor_units": 99 })).unwrap();
+        assert_eq!(parsed, Money::from_minor(99), "Currency should default to ALL");
+
+        // Only lek is stored, so a foreign amount is refused rather than recorded as lek
+        let foreign = serde_json::from_value::<Money>(serde_json::json!({ "minor_units": 99, "currency": "EUR" }));
+        assert!(foreign.unwrap_err().to_string().contains("must be in ALL"));
+        let product = serde_json::from_value::<CreateProduct>(serde_json::json!({
+            "name": "Aperol",
+            "price": { "minor_units": 800, "currency": "EUR" },
+            "quantity": 1,
+            "category_id": null,
+            "low_stock_threshold": null,
+        }));
+        assert!(product.is_err());
+    }
+
+    #[test]
+    fn test_order_total_stays_exact_over_long_tab() {
+        let conn = setup_test_db();
+        seed_test_data(&conn);
+
+        conn.execute("INSERT INTO day_sessions (started_by, is_active) VALUES (1, 1)", [])
+            .unwrap();
+        conn.execute(
+            "INSERT INTO orders (staff_id, table_number, total, status, session_id) VALUES (1, 1, 0, 'open', 1)",
+            [],
+        )
+        .unwrap();
+        conn.execute(
+            "INSERT INTO order_items (order_id, product_id, quantity, price_at_sale) VALUES (1, 1, 0, 10)",
+            [],
+        )
+        .unwrap();
+
+        // Add and remove single items many times, recomputing the total each time
+        for i in 0..500 {
+            let delta = if i % 3 == 2 { -1 } else { 1 };
+            conn.execute(
+                "UPDATE order_items SET quantity = quantity + ?1 WHERE id = 1",
+                [delta],
+            )
+            .unwrap();
+            conn.execute(
+                "UPDATE orders SET total = (
+                    SELECT COALESCE(SUM(quantity * price_at_sale), 0) FROM order_items WHERE order_id = ?1
+                ) WHERE id = ?1",
+                [1],
+            )
+            .unwrap();
+        }
+
+        let (quantity, total): (i32, Money) = conn
+            .query_row(
+                "SELECT oi.quantity, o.total FROM orders o JOIN order_items oi ON oi.order_id = o.id WHERE o.id = 1",
+                [],
+                |row| Ok((row.get(0)?, row.get(1)?)),
+            )
+            .unwrap();
+        assert_eq!(total, Money::from_minor(10) * quantity);
+    }
+
+    // ===== ERROR TESTS =====
+
+    #[test]
+    fn test_app_error_serializes_code_message_and_details() {
+        let err = AppError::InsufficientStock {
+            product_id: 7,
+            product_name: "Peroni".to_string(),
+            requested: 3,
+            available: 1,
+        };
+
+        let json = serde_json::to_value(&err).unwrap();
+        assert_eq!(json["code"], "INSUFFICIENT_STOCK");
+        assert_eq!(json["message"], "Insufficient stock for Peroni: requested 3, available 1");
+        assert_eq!(json["details"]["product_id"], 7);
+        assert_eq!(json["details"]["requested"], 3);
+        assert_eq!(json["details"]["available"], 1);
+    }
+
+    #[test]
+    fn test_app_error_open_tables_carries_count() {
+        let json = serde_json::to_value(AppError::OpenTablesRemain { count: 2 }).unwrap();
+        assert_eq!(json["code"], "OPEN_TABLES_REMAIN");
+        assert_eq!(json["details"]["count"], 2);
+        assert!(json["message"].as_str().unwrap().contains("2 tables are still open"));
+    }
+
+    #[test]
+    fn test_app_error_without_details_serializes_null() {
+        let json = serde_json::to_value(AppError::DayNotStarted).unwrap();
+        assert_eq!(json["code"], "DAY_NOT_STARTED");
+        assert!(json["details"].is_null());
+    }
+
+    #[test]
+    fn test_not_found_maps_missing_row_only() {
+        let conn = setup_test_db();
+
+        let missing = conn
+            .query_row("SELECT id FROM orders WHERE id = ?1", [42], |row| row.get::<_, i64>(0))
+            .map_err(not_found("order", 42))
+            .unwrap_err();
+        assert_eq!(missing, AppError::NotFound { entity: "order", id: 42 });
+
+        let broken = conn
+            .query_row("SELECT nope FROM orders WHERE id = ?1", [42], |row| row.get::<_, i64>(0))
+            .map_err(not_found("order", 42))
+            .unwrap_err();
+        assert_eq!(broken.code(), "DATABASE");
+    }
+
+    // ===== SERVICE TESTS =====
+
+    fn new_order(table_number: i32, items: &[(i64, i32)]) -> CreateOrder {
+        CreateOrder {
+            staff_id: 1,
+            table_number,
+            customer_name: None,
+            notes: None,
+            location_id: None,
+            items: items
+                .iter()
+                .map(|&(product_id, quantity)| CreateOrderItem {
+                    product_id,
+                    quantity,
+                    variant_id: None,
+                    modifier_ids: Vec::new(),
+                })
+                .collect(),
+        }
+    }
+
+    fn stock_of(conn: &Connection, product_id: i64) -> i32 {
+        conn.query_row("SELECT quantity FROM products WHERE id = ?1", [product_id], |row| row.get(0))
+            .unwrap()
+    }
+
+    #[test]
+    fn test_service_create_order_requires_started_day() {
+        let mut conn = setup_test_db();
+        seed_test_data(&conn);
+
+        let err = orders::create_order(&mut conn, new_order(1, &[(1, 1)])).unwrap_err();
+        assert_eq!(err, AppError::DayNotStarted);
+        assert_eq!(stock_of(&conn, 1), 100);
+    }
+
+    #[test]
+    fn test_service_create_order_deducts_stock_and_totals() {
+        let mut conn = setup_test_db();
+        seed_test_data(&conn);
+        reports::start_day(&mut conn, 1).unwrap();
+
+        let order = orders::create_order(&mut conn, new_order(4, &[(1, 2), (2, 1)])).unwrap();
+
+        assert_eq!(order.order.status, "open");
+        assert_eq!(order.order.table_number, 4);
+        assert_eq!(order.order.staff_name.as_deref(), Some("John"));
+        assert_eq!(order.order.total, Money::from_minor(1600));
+        assert_eq!(order.items.len(), 2);
+        assert_eq!(stock_of(&conn, 1), 98);
+        assert_eq!(stock_of(&conn, 2), 49);
+    }
+
+    #[test]
+    fn test_service_create_order_insufficient_stock_changes_nothing() {
+        let mut conn = setup_test_db();
+        seed_test_data(&conn);
+        reports::start_day(&mut conn, 1).unwrap();
+
+        let err = orders::create_order(&mut conn, new_order(1, &[(1, 1), (3, 4)])).unwrap_err();
+        assert_eq!(
+            err,
+            AppError::InsufficientStock {
+                product_id: 3,
+                product_name: "Guinness".to_string(),
+                requested: 4,
+                available: 3,
+            }
+        );
+
+        let order_count: i32 = conn.query_row("SELECT COUNT(*) FROM orders", [], |row| row.get(0)).unwrap();
+        assert_eq!(order_count, 0);
+        assert_eq!(stock_of(&conn, 1), 100);
+    }
+
+    #[test]
+    fn test_service_item_quantity_round_trip() {
+        let mut conn = setup_test_db();
+        seed_test_data(&conn);
+        reports::start_day(&mut conn, 1).unwrap();
+
+        let order = orders::create_order(&mut conn, new_order(1, &[(3, 2)])).unwrap();
+        let item_id = order.items[0].id;
+
+        let order = orders::increase_item_quantity(&mut conn, item_id).unwrap();
+        assert_eq!(order.order.total, Money::from_minor(2100));
+        assert_eq!(stock_of(&conn, 3), 0);
+
+        // The last bottle is gone
+        let err = orders::increase_item_quantity(&mut conn, item_id).unwrap_err();
+        assert_eq!(err.code(), "INSUFFICIENT_STOCK");
+
+        for _ in 0..2 {
+            assert!(orders::decrease_item_quantity(&mut conn, item_id).unwrap().is_some());
+        }
+        // Removing the last item deletes the order
+        assert!(orders::decrease_item_quantity(&mut conn, item_id).unwrap().is_none());
+        assert_eq!(stock_of(&conn, 3), 3);
+        assert_eq!(
+            orders::get_order(&conn, order.order.id).unwrap_err(),
+            AppError::NotFound { entity: "order", id: order.order.id }
+        );
+    }
+
+    #[test]
+    fn test_service_paid_order_is_locked() {
+        let mut conn = setup_test_db();
+        seed_test_data(&conn);
+        reports::start_day(&mut conn, 1).unwrap();
+
+        let order = orders::create_order(&mut conn, new_order(1, &[(1, 1)])).unwrap();
+        let order_id = order.order.id;
+        let noted = orders::update_order_notes(&mut conn, order_id, Some("Sam".to_string()), None).unwrap();
+        assert_eq!(noted.order.customer_name.as_deref(), Some("Sam"));
+        orders::mark_order_paid(&mut conn, order_id).unwrap();
+
+        assert_eq!(
+            orders::mark_order_paid(&mut conn, order_id).unwrap_err(),
+            AppError::OrderNotOpen { order_id }
+        );
+        assert_eq!(
+            orders::update_order_notes(&mut conn, order_id, None, Some("Void".to_string())).unwrap_err(),
+            AppError::OrderNotOpen { order_id }
+        );
+        assert_eq!(orders::get_order(&conn, order_id).unwrap().order.customer_name.as_deref(), Some("Sam"));
+        assert_eq!(
+            orders::add_items_to_order(&mut conn, order_id, new_order(1, &[(1, 1)]).items).unwrap_err(),
+            AppError::OrderNotOpen { order_id }
+        );
+        assert_eq!(
+            orders::mark_order_paid(&mut conn, 999).unwrap_err(),
+            AppError::NotFound { entity: "order", id: 999 }
+        );
+        assert_eq!(
+            orders::update_order_notes(&mut conn, 999, None, None).unwrap_err(),
+            AppError::NotFound { entity: "order", id: 999 }
+        );
+    }
+
+    #[test]
+    fn test_service_close_day_flow() {
+        let mut conn = setup_test_db();
+        seed_test_data(&conn);
+
+        assert_eq!(reports::close_day(&mut conn).unwrap_err(), AppError::DayNotStarted);
+
+        let session = reports::start_day(&mut conn, 1).unwrap();
+        assert!(session.is_active);
+        assert_eq!(session.started_by_name.as_deref(), Some("John"));
+
+        let first = orders::create_order(&mut conn, new_order(1, &[(1, 2)])).unwrap();
+        let second = orders::create_order(&mut conn, new_order(2, &[(2, 1)])).unwrap();
+        orders::mark_order_paid(&mut conn, first.order.id).unwrap();
+
+        assert_eq!(
+            reports::close_day(&mut conn).unwrap_err(),
+            AppError::OpenTablesRemain { count: 1 }
+        );
+        assert!(reports::is_day_active(&conn).unwrap());
+
+        orders::mark_order_paid(&mut conn, second.order.id).unwrap();
+        let closed = reports::close_day(&mut conn).unwrap();
+
+        assert_eq!(closed.id, session.id);
+        assert!(!closed.is_active);
+        assert!(closed.closed_at.is_some());
+        assert_eq!(closed.total_revenue, Some(Money::from_minor(1600)));
+        assert_eq!(closed.total_orders, Some(2));
+        assert!(!reports::is_day_active(&conn).unwrap());
+
+        let history = reports::get_sales_history(&conn, 30).unwrap();
+        assert_eq!(history.len(), 1);
+        let summary = reports::get_day_summary(&conn, Some(session.id), None).unwrap();
+        assert_eq!(summary.total_orders, 2);
+        assert_eq!(summary.total_revenue, Money::from_minor(1600));
+    }
+
+    #[test]
+    fn test_service_session_backup_written_to_directory() {
+        let mut conn = setup_test_db();
+        seed_test_data(&conn);
+        reports::start_day(&mut conn, 1).unwrap();
+        let order = orders::create_order(&mut conn, new_order(1, &[(1, 1)])).unwrap();
+        orders::mark_order_paid(&mut conn, order.order.id).unwrap();
+        let session = reports::close_day(&mut conn).unwrap();
+
+        let dir = tempfile::tempdir().unwrap();
+        let path = reports::write_session_backup(&conn, &session, &dir.path().join("backups")).unwrap();
+
+        let backup: serde_json::Value = serde_json::from_str(&std::fs::read_to_string(path).unwrap()).unwrap();
+        assert_eq!(backup["session_id"], session.id);
+        assert_eq!(backup["orders"].as_array().unwrap().len(), 1);
+    }
+
+    #[test]
+    fn test_database_open_from_path_persists() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("pub.db");
+
+        {
+            let database = db::Database::open(&path).unwrap();
+            database.initialize().unwrap();
+            let conn = database.conn.lock().unwrap();
+            seed_test_data(&conn);
+        }
+
+        let database = db::Database::open(&path).unwrap();
+        database.initialize().unwrap();
+        let conn = database.conn.lock().unwrap();
+        let products = products::get_products(&conn, false).unwrap();
+        assert_eq!(products.len(), 3);
+        assert_eq!(db::schema_version(&conn).unwrap(), latest_version());
+    }
+
+    #[test]
+    fn test_database_open_in_memory_is_isolated() {
+        let first = db::Database::open_in_memory().unwrap();
+        let second = db::Database::open_in_memory().unwrap();
+        first.initialize().unwrap();
+        second.initialize().unwrap();
+
+        seed_test_data(&first.conn.lock().unwrap());
+
+        let count: i32 = second
+            .conn
+            .lock()
+            .unwrap()
+            .query_row("SELECT COUNT(*) FROM products", [], |row| row.get(0))
+            .unwrap();
+        assert_eq!(count, 0);
+    }
+
+
+    // ===== STOCK LEDGER TESTS =====
+
+    fn movement(product_id: i64, quantity_change: i32, reason: StockReason) -> CreateStockMovement {
+        CreateStockMovement {
+            product_id,
+            quantity_change,
+            reason,
+            staff_id: Some(1),
+            reference: None,
+            note: None,
+            location_id: None,
+        }
+    }
+
+    #[test]
+    fn test_migration_opens_ledger_with_existing_stock() {
+        let mut conn = Connection::open_in_memory().unwrap();
+        db::apply_migrations(&mut conn, &db::MIGRATIONS[..2]).unwrap();
+        seed_test_data(&conn);
+
+        db::run_migrations(&mut conn).unwrap();
+
+        assert!(stock::get_stock_discrepancies(&conn).unwrap().is_empty());
+        let history = stock::get_stock_movements(&conn, 1, None, None).unwrap();
+        assert_eq!(history.len(), 1);
+        assert_eq!(history[0].reason, StockReason::OpeningBalance);
+        assert_eq!(history[0].quantity_change, 100);
+    }
+
+    #[test]
+    fn test_order_changes_are_recorded_in_ledger() {
+        let mut conn = setup_test_db();
+        seed_test_data(&conn);
+        reports::start_day(&mut conn, 1).unwrap();
+
+        let order = orders::create_order(&mut conn, new_order(1, &[(2, 3)])).unwrap();
+        let item_id = order.items[0].id;
+        orders::decrease_item_quantity(&mut conn, item_id).unwrap();
+        orders::increase_item_quantity(&mut conn, item_id).unwrap();
+
+        let history = stock::get_stock_movements(&conn, 2, None, None).unwrap();
+        let entries: Vec<(StockReason, i32)> = history.iter().rev().map(|m| (m.reason, m.quantity_change)).collect();
+        assert_eq!(
+            entries,
+            vec![(StockReason::Sale, -3), (StockReason::Return, 1), (StockReason::Sale, -1)]
+        );
+        assert!(history.iter().all(|m| m.order_id == Some(order.order.id)));
+        assert!(history.iter().all(|m| m.staff_name.as_deref() == Some("John")));
+        assert_eq!(stock_of(&conn, 2), 47);
+    }
+
+    #[test]
+    fn test_update_product_records_adjustment() {
+        let mut conn = setup_test_db();
+        seed_test_data(&conn);
+
+        let mut product = products::get_product(&conn, 2).unwrap();
+        products::update_product(
+            &mut conn,
+            UpdateProduct {
+                id: product.id,
+                name: product.name.clone(),
+                price: product.price,
+                quantity: 38,
+                category_id: product.category_id,
+                low_stock_threshold: product.low_stock_threshold,
+                unit: None,
+                serving_size: None,
+                cost_price: None,
+                aliases: None,
+                staff_id: Some(2),
+            },
+        )
+        .unwrap();
+
+        product = products::get_product(&conn, 2).unwrap();
+        assert_eq!(product.quantity, 38);
+
+        let history = stock::get_stock_movements(&conn, 2, None, None).unwrap();
+        assert_eq!(history.len(), 1);
+        assert_eq!(history[0].reason, StockReason::Adjustment);
+        assert_eq!(history[0].quantity_change, -12);
+        assert_eq!(history[0].staff_name.as_deref(), Some("Jane"));
+    }
+
+    #[test]
+    fn test_record_stock_movement_validates_reason_and_sign() {
+        let mut conn = setup_test_db();
+        seed_test_data(&conn);
+
+        let err = stock::record_stock_movement(&mut conn, movement(1, -1, StockReason::Sale)).unwrap_err();
+        assert_eq!(err.code(), "VALIDATION");
+        let err = stock::record_stock_movement(&mut conn, movement(1, 2, StockReason::Waste)).unwrap_err();
+        assert_eq!(err.code(), "VALIDATION");
+        let err = stock::record_stock_movement(&mut conn, movement(1, -5, StockReason::Delivery)).unwrap_err();
+        assert_eq!(err.code(), "VALIDATION");
+
+        // Cannot waste more than is on the shelf
+        let err = stock::record_stock_movement(&mut conn, movement(3, -4, StockReason::Waste)).unwrap_err();
+        assert_eq!(err.code(), "INSUFFICIENT_STOCK");
+        assert_eq!(stock_of(&conn, 3), 3);
+
+        let mut delivery = movement(3, 24, StockReason::Delivery);
+        delivery.reference = Some("INV-2041".to_string());
+        let recorded = stock::record_stock_movement(&mut conn, delivery).unwrap();
+        assert_eq!(recorded.reference.as_deref(), Some("INV-2041"));
+        assert_eq!(recorded.product_name.as_deref(), Some("Guinness"));
+        stock::record_stock_movement(&mut conn, movement(3, -1, StockReason::Waste)).unwrap();
+        stock::record_stock_movement(&mut conn, movement(3, -2, StockReason::Comp)).unwrap();
+        assert_eq!(stock_of(&conn, 3), 24);
+    }
+
+    #[test]
+    fn test_stock_movements_filtered_by_date() {
+        let mut conn = setup_test_db();
+        seed_test_data(&conn);
+
+        stock::record_stock_movement(&mut conn, movement(1, 12, StockReason::Delivery)).unwrap();
+        conn.execute(
+            "INSERT INTO stock_movements (product_id, quantity_change, reason, created_at) VALUES (1, -2, 'waste', '2024-03-10 12:00:00')",
+            [],
+        )
+        .unwrap();
+
+        assert_eq!(stock::get_stock_movements(&conn, 1, None, None).unwrap().len(), 2);
+        let march = stock::get_stock_movements(&conn, 1, Some("2024-03-01"), Some("2024-03-31")).unwrap();
+        assert_eq!(march.len(), 1);
+        assert_eq!(march[0].reason, StockReason::Waste);
+        assert!(stock::get_stock_movements(&conn, 1, Some("2024-04-01"), Some("2024-04-30")).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_rebuild_stock_from_ledger() {
+        let mut conn = setup_test_db();
+        seed_test_data(&conn);
+        // Ledger for the seeded products starts empty, so give them opening balances
+        conn.execute(
+            "INSERT INTO stock_movements (product_id, quantity_change, reason) SELECT id, quantity, 'opening_balance' FROM products",
+            [],
+        )
+        .unwrap();
+        stock::record_stock_movement(&mut conn, movement(1, -4, StockReason::Waste)).unwrap();
+
+        // Someone edits the table by hand
+        conn.execute("UPDATE products SET quantity = 80 WHERE id = 1", []).unwrap();
+
+        let drift = stock::get_stock_discrepancies(&conn).unwrap();
+        assert_eq!(drift.len(), 1);
+        assert_eq!((drift[0].recorded_quantity, drift[0].ledger_quantity), (80, 96));
+
+        let fixed = stock::rebuild_stock_from_ledger(&mut conn).unwrap();
+        assert_eq!(fixed.len(), 1);
+        assert_eq!(stock_of(&conn, 1), 96);
+        assert!(stock::get_stock_discrepancies(&conn).unwrap().is_empty());
+    }
+
+
+    // ===== PURCHASING TESTS =====
+
+    fn seed_supplier(conn: &Connection) -> i64 {
+        purchasing::create_supplier(
+            conn,
+            CreateSupplier {
+                name: "Birra Tirana".to_string(),
+                contact_name: Some("Arben".to_string()),
+                phone: None,
+                email: None,
+                notes: None,
+            },
+        )
+        .unwrap()
+        .id
+    }
+
+    fn po_line(product_id: i64, quantity: i32, unit_cost: i64) -> CreatePurchaseOrderLine {
+        CreatePurchaseOrderLine {
+            product_id,
+            quantity,
+            unit_cost: Money::from_minor(unit_cost),
+        }
+    }
+
+    fn draft_po(conn: &mut Connection, supplier_id: i64) -> PurchaseOrderWithLines {
+        purchasing::create_purchase_order(
+            conn,
+            CreatePurchaseOrder {
+                supplier_id,
+                staff_id: Some(1),
+                reference: None,
+                notes: None,
+                lines: vec![po_line(1, 24, 250), po_line(2, 12, 300)],
+            },
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn test_purchase_order_draft_send_receive_in_full() {
+        let mut conn = setup_test_db();
+        seed_test_data(&conn);
+        let supplier_id = seed_supplier(&conn);
+
+        let po = draft_po(&mut conn, supplier_id);
+        assert_eq!(po.purchase_order.status, PurchaseOrderStatus::Draft);
+        assert_eq!(po.purchase_order.total, Money::from_minor(24 * 250 + 12 * 300));
+        let id = po.purchase_order.id;
+
+        // Drafts cannot be received
+        let receive_all = || ReceivePurchaseOrder { staff_id: Some(2), reference: Some("DN-77".to_string()), lines: None };
+        assert_eq!(purchasing::receive_purchase_order(&mut conn, id, receive_all()).unwrap_err().code(), "VALIDATION");
+
+        let sent = purchasing::send_purchase_order(&mut conn, id).unwrap();
+        assert_eq!(sent.purchase_order.status, PurchaseOrderStatus::Sent);
+        assert!(sent.purchase_order.sent_at.is_some());
+
+        let received = purchasing::receive_purchase_order(&mut conn, id, receive_all()).unwrap();
+        assert_eq!(received.purchase_order.status, PurchaseOrderStatus::Received);
+        assert!(received.purchase_order.received_at.is_some());
+        assert!(received.lines.iter().all(|l| l.quantity_received == l.quantity_ordered));
+        assert_eq!(stock_of(&conn, 1), 124);
+        assert_eq!(stock_of(&conn, 2), 62);
+
+        let deliveries = stock::get_stock_movements(&conn, 1, None, None).unwrap();
+        assert_eq!(deliveries[0].reason, StockReason::Delivery);
+        assert_eq!(deliveries[0].reference.as_deref(), Some("DN-77"));
+        assert_eq!(deliveries[0].staff_name.as_deref(), Some("Jane"));
+
+        // Nothing left to receive
+        assert!(purchasing::receive_purchase_order(&mut conn, id, receive_all()).is_err());
+    }
+
+    #[test]
+    fn test_purchase_order_partial_receipt_with_invoiced_cost() {
+        let mut conn = setup_test_db();
+        seed_test_data(&conn);
+        let supplier_id = seed_supplier(&conn);
+        let po = draft_po(&mut conn, supplier_id);
+        let id = po.purchase_order.id;
+        let heineken_line = po.lines[0].id;
+        purchasing::send_purchase_order(&mut conn, id).unwrap();
+
+        // More than was ordered is rejected and nothing changes
+        let too_many = ReceivePurchaseOrder {
+            staff_id: Some(1),
+            reference: None,
+            lines: Some(vec![ReceiveLine { line_id: heineken_line, quantity: 25, unit_cost: None, expires_on: None }]),
+        };
+        assert_eq!(purchasing::receive_purchase_order(&mut conn, id, too_many).unwrap_err().code(), "VALIDATION");
+        assert_eq!(stock_of(&conn, 1), 100);
+
+        // So is the same line named twice for more than was ordered, or a negative invoiced cost
+        let delivery = |quantity, unit_cost: i64| ReceiveLine {
+            line_id: heineken_line,
+            quantity,
+            unit_cost: Some(Money::from_minor(unit_cost)),
+            expires_on: None,
+        };
+        for lines in [vec![delivery(15, 250), delivery(15, 250)], vec![delivery(10, -1)]] {
+            let receipt = ReceivePurchaseOrder { staff_id: Some(1), reference: None, lines: Some(lines) };
+            assert_eq!(purchasing::receive_purchase_order(&mut conn, id, receipt).unwrap_err().code(), "VALIDATION");
+        }
+        assert_eq!(stock_of(&conn, 1), 100);
+
+        let partial = ReceivePurchaseOrder {
+            staff_id: Some(1),
+            reference: None,
+            lines: Some(vec![ReceiveLine {
+                line_id: heineken_line,
+                quantity: 10,
+                unit_cost: Some(Money::from_minor(260)),
+                expires_on: None,
+            }]),
+        };
+        let po = purchasing::receive_purchase_order(&mut conn, id, partial).unwrap();
+        assert_eq!(po.purchase_order.status, PurchaseOrderStatus::PartiallyReceived);
+        assert_eq!(po.lines[0].quantity_received, 10);
+        assert_eq!(stock_of(&conn, 1), 110);
+
+        let history = purchasing::get_supplier_purchase_history(&conn, supplier_id, None, None).unwrap();
+        assert_eq!(history.len(), 1);
+        assert_eq!(history[0].unit_cost, Money::from_minor(260));
+        assert_eq!(history[0].line_total, Money::from_minor(2600));
+        assert_eq!(history[0].reference.as_deref(), Some(format!("PO-{}", id).as_str()));
+
+        // Partially received orders can no longer be cancelled
+        assert!(purchasing::cancel_purchase_order(&mut conn, id).is_err());
+    }
+
+    #[test]
+    fn test_supplier_balances() {
+        let mut conn = setup_test_db();
+        seed_test_data(&conn);
+        let supplier_id = seed_supplier(&conn);
+
+        // Drafts are not outstanding yet
+        let first = draft_po(&mut conn, supplier_id).purchase_order.id;
+        let balances = purchasing::get_supplier_balances(&conn).unwrap();
+        assert_eq!(balances[0].open_orders, 0);
+        assert_eq!(balances[0].outstanding_value, Money::zero());
+
+        purchasing::send_purchase_order(&mut conn, first).unwrap();
+        let second = draft_po(&mut conn, supplier_id).purchase_order.id;
+        purchasing::send_purchase_order(&mut conn, second).unwrap();
+        purchasing::receive_purchase_order(
+            &mut conn,
+            second,
+            ReceivePurchaseOrder { staff_id: None, reference: None, lines: None },
+        )
+        .unwrap();
+
+        let balances = purchasing::get_supplier_balances(&conn).unwrap();
+        assert_eq!(balances.len(), 1);
+        assert_eq!(balances[0].open_orders, 1);
+        assert_eq!(balances[0].outstanding_value, Money::from_minor(9600));
+        assert_eq!(balances[0].received_value, Money::from_minor(9600));
+
+        purchasing::cancel_purchase_order(&mut conn, first).unwrap();
+        let balances = purchasing::get_supplier_balances(&conn).unwrap();
+        assert_eq!(balances[0].open_orders, 0);
+        assert_eq!(
+            purchasing::get_purchase_orders(&conn, Some(supplier_id), Some(PurchaseOrderStatus::Cancelled))
+                .unwrap()
+                .len(),
+            1
+        );
+    }
+
+    #[test]
+    fn test_only_drafts_can_be_edited() {
+        let mut conn = setup_test_db();
+        seed_test_data(&conn);
+        let supplier_id = seed_supplier(&conn);
+        let id = draft_po(&mut conn, supplier_id).purchase_order.id;
+
+        let po = purchasing::update_purchase_order_lines(&mut conn, id, vec![po_line(3, 6, 400)]).unwrap();
+        assert_eq!(po.lines.len(), 1);
+        assert_eq!(po.purchase_order.total, Money::from_minor(2400));
+        assert!(purchasing::update_purchase_order_lines(&mut conn, id, vec![]).is_err());
+
+        purchasing::send_purchase_order(&mut conn, id).unwrap();
+        assert!(purchasing::update_purchase_order_lines(&mut conn, id, vec![po_line(3, 1, 400)]).is_err());
+    }
+
+
+    // ===== STOCK TAKE TESTS =====
+
+    /// Receive 24 Heineken at 2.50 so the product has a known unit cost
+    fn receive_heineken_at_cost(conn: &mut Connection) {
+        let supplier_id = seed_supplier(conn);
+        let id = purchasing::create_purchase_order(
+            conn,
+            CreatePurchaseOrder {
+                supplier_id,
+                staff_id: Some(1),
+                reference: None,
+                notes: None,
+                lines: vec![po_line(1, 24, 250)],
+            },
+        )
+        .unwrap()
+        .purchase_order
+        .id;
+        purchasing::send_purchase_order(conn, id).unwrap();
+        purchasing::receive_purchase_order(conn, id, ReceivePurchaseOrder { staff_id: None, reference: None, lines: None })
+            .unwrap();
+    }
+
+    #[test]
+    fn test_stock_take_counts_add_up_across_staff() {
+        let mut conn = setup_test_db();
+        seed_test_data(&conn);
+        receive_heineken_at_cost(&mut conn);
+
+        let id = stock_takes::start_stock_take(&mut conn, 1, None, Some("Monday count".to_string()))
+            .unwrap()
+            .stock_take
+            .id;
+        stock_takes::record_stock_count(&mut conn, id, 1, 1, 100).unwrap(); // John: bar
+        stock_takes::record_stock_count(&mut conn, id, 1, 2, 20).unwrap(); // Jane: cellar
+        stock_takes::record_stock_count(&mut conn, id, 3, 2, 5).unwrap();
+        let take = stock_takes::record_stock_count(&mut conn, id, 2, 1, 50).unwrap();
+
+        assert_eq!(take.counts.len(), 4);
+        let heineken = take.lines.iter().find(|l| l.product_id == 1).unwrap();
+        assert_eq!((heineken.system_quantity, heineken.counted_quantity, heineken.variance), (124, 120, -4));
+        assert_eq!(heineken.variance_cost, Some(Money::from_minor(-1000)));
+        assert_eq!(heineken.variance_value, Money::from_minor(-2000));
+
+        // Never purchased, so there is no cost to value it at
+        let guinness = take.lines.iter().find(|l| l.product_id == 3).unwrap();
+        assert_eq!(guinness.variance, 2);
+        assert_eq!(guinness.variance_cost, None);
+
+        assert_eq!(take.total_variance_cost, Money::from_minor(-1000));
+        assert_eq!(take.total_variance_value, Money::from_minor(-2000 + 1400));
+
+        // Recounting replaces that staff member's own count
+        let take = stock_takes::record_stock_count(&mut conn, id, 1, 1, 101).unwrap();
+        assert_eq!(take.lines.iter().find(|l| l.product_id == 1).unwrap().counted_quantity, 121);
+    }
+
+    #[test]
+    fn test_commit_stock_take_posts_adjustments_and_freezes_variance() {
+        let mut conn = setup_test_db();
+        seed_test_data(&conn);
+        receive_heineken_at_cost(&mut conn);
+
+        let id = stock_takes::start_stock_take(&mut conn, 1, None, None).unwrap().stock_take.id;
+        stock_takes::record_stock_count(&mut conn, id, 1, 1, 118).unwrap();
+        stock_takes::record_stock_count(&mut conn, id, 2, 1, 50).unwrap();
+
+        let committed = stock_takes::commit_stock_take(&mut conn, id, 2).unwrap();
+        assert_eq!(committed.stock_take.status, StockTakeStatus::Committed);
+        assert_eq!(committed.stock_take.committed_by, Some(2));
+        assert_eq!(stock_of(&conn, 1), 118);
+        assert_eq!(stock_of(&conn, 2), 50);
+        // Uncounted products are left alone
+        assert_eq!(stock_of(&conn, 3), 3);
+
+        let movements = stock::get_stock_movements(&conn, 1, None, None).unwrap();
+        assert_eq!(movements[0].reason, StockReason::StockTake);
+        assert_eq!(movements[0].quantity_change, -6);
+        assert_eq!(movements[0].reference, Some(format!("ST-{}", id)));
+        // No movement for a product that matched
+        assert!(stock::get_stock_movements(&conn, 2, None, None).unwrap().is_empty());
+
+        // Later sales do not rewrite the committed variance
+        conn.execute("UPDATE products SET quantity = 90 WHERE id = 1", []).unwrap();
+        let frozen = stock_takes::get_stock_take(&conn, id).unwrap();
+        assert_eq!(frozen.lines.iter().find(|l| l.product_id == 1).unwrap().system_quantity, 124);
+
+        assert_eq!(stock_takes::record_stock_count(&mut conn, id, 1, 1, 1).unwrap_err().code(), "VALIDATION");
+        assert!(stock_takes::commit_stock_take(&mut conn, id, 1).is_err());
+
+        let history = stock_takes::get_variance_history(&conn, 1).unwrap();
+        assert_eq!(history.len(), 1);
+        assert_eq!(history[0].variance, -6);
+        assert_eq!(history[0].variance_cost, Some(Money::from_minor(-1500)));
+    }
+
+    #[test]
+    fn test_only_one_stock_take_open_at_a_time() {
+        let mut conn = setup_test_db();
+        seed_test_data(&conn);
+
+        let id = stock_takes::start_stock_take(&mut conn, 1, None, None).unwrap().stock_take.id;
+        assert!(stock_takes::start_stock_take(&mut conn, 2, None, None).is_err());
+        assert_eq!(stock_takes::commit_stock_take(&mut conn, id, 1).unwrap_err().code(), "VALIDATION");
+
+        stock_takes::record_stock_count(&mut conn, id, 1, 1, 10).unwrap();
+        let cancelled = stock_takes::cancel_stock_take(&mut conn, id).unwrap();
+        assert_eq!(cancelled.stock_take.status, StockTakeStatus::Cancelled);
+        assert_eq!(stock_of(&conn, 1), 100);
+
+        stock_takes::start_stock_take(&mut conn, 2, None, None).unwrap();
+        assert_eq!(stock_takes::get_stock_takes(&conn, 10).unwrap().len(), 2);
+        assert!(stock_takes::get_variance_history(&conn, 1).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_stock_take_values_measured_products_per_base_unit() {
+        let mut conn = setup_test_db();
+        seed_test_data(&conn);
+        // A 70 cl bottle sold in 4 cl shots at 8.00, so 0.20 a millilitre
+        let vodka = products::create_product(
+            &mut conn,
+            CreateProduct { price: Money::from_minor(800), ..measured_product("Vodka", 700, Unit::Ml, Some(40)) },
+        )
+        .unwrap();
+
+        let id = stock_takes::start_stock_take(&mut conn, 1, None, None).unwrap().stock_take.id;
+        let take = stock_takes::record_stock_count(&mut conn, id, vodka.id, 1, 0).unwrap();
+        assert_eq!((take.lines[0].variance, take.lines[0].unit_price), (-700, Money::from_minor(20)));
+        assert_eq!(take.total_variance_value, Money::from_minor(-14000));
+
+        stock_takes::commit_stock_take(&mut conn, id, 1).unwrap();
+        let history = stock_takes::get_variance_history(&conn, vodka.id).unwrap();
+        assert_eq!(history[0].variance_value, Money::from_minor(-14000));
+    }
+
+
+    // ===== RECIPE TESTS =====
+
+    /// Gin (id 4, 700 in stock) and tonic (id 5, 1000) with a Gin & Tonic
+    /// (id 6) made from 50 gin and 200 tonic.
+    fn seed_gin_and_tonic(conn: &mut Connection) -> i64 {
+        conn.execute(
+            "INSERT INTO products (name, price, quantity, low_stock_threshold) VALUES ('Gin', 0, 700, 100), ('Tonic', 0, 1000, 200), ('Gin & Tonic', 800, 0, 5)",
+            [],
+        )
+        .unwrap();
+        recipes::set_recipe(
+            conn,
+            6,
+            vec![
+                SetRecipeComponent { component_id: 4, quantity: 50 },
+                SetRecipeComponent { component_id: 5, quantity: 200 },
+            ],
+        )
+        .unwrap();
+        6
+    }
+
+    #[test]
+    fn test_recipe_reports_servings_from_ingredients() {
+        let mut conn = setup_test_db();
+        seed_test_data(&conn);
+        let gt = seed_gin_and_tonic(&mut conn);
+
+        let product = products::get_product(&conn, gt).unwrap();
+        assert!(product.has_recipe);
+        // Tonic runs out first: 1000 / 200
+        assert_eq!(product.servings, 5);
+        assert!(!products::get_product(&conn, 4).unwrap().has_recipe);
+
+        let recipe = recipes::get_recipe(&conn, gt).unwrap();
+        assert_eq!(recipe.len(), 2);
+        assert_eq!(recipe[0].component_name.as_deref(), Some("Gin"));
+        assert_eq!((recipe[0].quantity, recipe[0].available), (50, 700));
+
+        // Composite products never show up as low stock themselves
+        let low = products::get_low_stock(&conn).unwrap();
+        assert!(low.iter().all(|p| p.id != gt));
+    }
+
+    #[test]
+    fn test_recipe_sale_and_void_move_ingredients() {
+        let mut conn = setup_test_db();
+        seed_test_data(&conn);
+        let gt = seed_gin_and_tonic(&mut conn);
+        reports::start_day(&mut conn, 1).unwrap();
+
+        let order = orders::create_order(&mut conn, new_order(1, &[(gt, 2)])).unwrap();
+        assert_eq!(order.order.total, Money::from_minor(1600));
+        assert_eq!((stock_of(&conn, 4), stock_of(&conn, 5)), (600, 600));
+        assert_eq!(stock_of(&conn, gt), 0);
+
+        let item_id = order.items[0].id;
+        orders::increase_item_quantity(&mut conn, item_id).unwrap();
+        assert_eq!((stock_of(&conn, 4), stock_of(&conn, 5)), (550, 400));
+
+        orders::decrease_item_quantity(&mut conn, item_id).unwrap();
+        assert_eq!((stock_of(&conn, 4), stock_of(&conn, 5)), (600, 600));
+
+        let tonic = stock::get_stock_movements(&conn, 5, None, None).unwrap();
+        assert_eq!(tonic[0].reason, StockReason::Return);
+        assert_eq!(tonic[0].quantity_change, 200);
+        assert_eq!(tonic[0].order_id, Some(order.order.id));
+        assert!(stock::get_stock_movements(&conn, gt, None, None).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_recipe_short_ingredient_is_named_and_nothing_moves() {
+        let mut conn = setup_test_db();
+        seed_test_data(&conn);
+        let gt = seed_gin_and_tonic(&mut conn);
+        reports::start_day(&mut conn, 1).unwrap();
+
+        // Enough gin for 14, tonic for only 5
+        let err = orders::create_order(&mut conn, new_order(1, &[(1, 1), (gt, 6)])).unwrap_err();
+        assert_eq!(
+            err,
+            AppError::InsufficientStock {
+                product_id: 5,
+                product_name: "Tonic".to_string(),
+                requested: 1200,
+                available: 1000,
+            }
+        );
+        assert_eq!((stock_of(&conn, 1), stock_of(&conn, 4), stock_of(&conn, 5)), (100, 700, 1000));
+
+        let order = orders::create_order(&mut conn, new_order(1, &[(gt, 5)])).unwrap();
+        let err = orders::increase_item_quantity(&mut conn, order.items[0].id).unwrap_err();
+        assert_eq!(err.code(), "INSUFFICIENT_STOCK");
+        assert_eq!(orders::get_order(&conn, order.order.id).unwrap().items[0].quantity, 5);
+        assert_eq!(stock_of(&conn, 4), 450);
+    }
+
+    #[test]
+    fn test_set_recipe_rejects_nesting_and_tracked_stock() {
+        let mut conn = setup_test_db();
+        seed_test_data(&conn);
+        let gt = seed_gin_and_tonic(&mut conn);
+
+        let component = |component_id| vec![SetRecipeComponent { component_id, quantity: 1 }];
+        // Itself, a composite as ingredient, an ingredient getting a recipe, stock on hand
+        assert!(recipes::set_recipe(&mut conn, gt, component(gt)).is_err());
+        conn.execute("INSERT INTO products (name, price, quantity) VALUES ('Double G&T', 1400, 0)", []).unwrap();
+        assert!(recipes::set_recipe(&mut conn, 7, component(gt)).is_err());
+        assert!(recipes::set_recipe(&mut conn, 4, component(5)).is_err());
+        assert!(recipes::set_recipe(&mut conn, 1, component(4)).is_err());
+        assert!(recipes::set_recipe(&mut conn, gt, component(99)).is_err());
+        assert_eq!(recipes::get_recipe(&conn, gt).unwrap().len(), 2);
+
+        // Stock for a composite only moves through its ingredients
+        let err = stock::record_stock_movement(&mut conn, movement(gt, 5, StockReason::Delivery)).unwrap_err();
+        assert_eq!(err.code(), "VALIDATION");
+        assert!(products::archive_product(&conn, 4).is_err());
+
+        assert!(recipes::set_recipe(&mut conn, gt, Vec::new()).unwrap().is_empty());
+        assert!(!products::get_product(&conn, gt).unwrap().has_recipe);
+        products::archive_product(&conn, 4).unwrap();
+    }
+
+
+    // ===== UNIT OF MEASURE TESTS =====
+
+    fn measured_product(name: &str, quantity: i32, unit: Unit, serving_size: Option<i32>) -> CreateProduct {
+        CreateProduct {
+            name: name.to_string(),
+            price: Money::from_minor(300),
+            quantity,
+            category_id: None,
+            low_stock_threshold: Some(1050),
+            staff_id: Some(1),
+            unit,
+            serving_size,
+            codes: Vec::new(),
+            aliases: Vec::new(),
+            cost_price: None,
+        }
+    }
+
+    #[test]
+    fn test_measured_product_sells_per_serving() {
+        let mut conn = setup_test_db();
+        seed_test_data(&conn);
+        reports::start_day(&mut conn, 1).unwrap();
+
+        // Two 70 cl bottles poured in 4 cl shots
+        let vodka = products::create_product(&mut conn, measured_product("Vodka", 1400, Unit::Cl, Some(40))).unwrap();
+        assert_eq!((vodka.unit, vodka.serving_size, vodka.servings), (Unit::Cl, 40, 35));
+
+        let order = orders::create_order(&mut conn, new_order(1, &[(vodka.id, 3)])).unwrap();
+        assert_eq!(order.items[0].quantity, 3);
+        assert_eq!(order.order.total, Money::from_minor(900));
+        assert_eq!(stock_of(&conn, vodka.id), 1280);
+
+        let movements = stock::get_stock_movements(&conn, vodka.id, None, None).unwrap();
+        assert_eq!(movements[0].quantity_change, -120);
+
+        orders::decrease_item_quantity(&mut conn, order.items[0].id).unwrap();
+        assert_eq!(stock_of(&conn, vodka.id), 1320);
+
+        // Not enough left for two more pints of a nearly empty keg
+        let keg = products::create_product(&mut conn, measured_product("Lager keg", 700, Unit::L, Some(500))).unwrap();
+        assert_eq!(keg.servings, 1);
+        let err = orders::create_order(&mut conn, new_order(2, &[(keg.id, 2)])).unwrap_err();
+        assert_eq!(
+            err,
+            AppError::InsufficientStock {
+                product_id: keg.id,
+                product_name: "Lager keg".to_string(),
+                requested: 1000,
+                available: 700,
+            }
+        );
+    }
+
+    #[test]
+    fn test_low_stock_threshold_in_base_units() {
+        let mut conn = setup_test_db();
+        seed_test_data(&conn);
+
+        // Alert under 1.5 bottles (1050 ml)
+        let gin = products::create_product(&mut conn, measured_product("Gin", 1400, Unit::Cl, Some(50))).unwrap();
+        assert!(products::get_low_stock(&conn).unwrap().iter().all(|p| p.id != gin.id));
+
+        stock::record_stock_movement(&mut conn, movement(gin.id, -400, StockReason::Waste)).unwrap();
+        let low = products::get_low_stock(&conn).unwrap();
+        assert!(low.iter().any(|p| p.id == gin.id && p.quantity == 1000));
+    }
+
+    #[test]
+    fn test_units_require_serving_size_and_stable_base() {
+        let mut conn = setup_test_db();
+        seed_test_data(&conn);
+
+        let err = products::create_product(&mut conn, measured_product("Wine", 750, Unit::Ml, None)).unwrap_err();
+        assert_eq!(err.code(), "VALIDATION");
+        assert!(products::create_product(&mut conn, measured_product("Wine", 750, Unit::Ml, Some(0))).is_err());
+
+        // Existing products are pieces that sell one at a time
+        let heineken = products::get_product(&conn, 1).unwrap();
+        assert_eq!((heineken.unit, heineken.serving_size, heineken.servings), (Unit::Piece, 1, 100));
+
+        let update = |unit, quantity| UpdateProduct {
+            id: 1,
+            name: "Heineken".to_string(),
+            price: heineken.price,
+            quantity,
+            category_id: heineken.category_id,
+            low_stock_threshold: 10,
+            unit: Some(unit),
+            serving_size: Some(330),
+            cost_price: None,
+            aliases: None,
+            staff_id: None,
+        };
+        // Pieces cannot become millilitres while bottles are on the shelf
+        assert!(products::update_product(&mut conn, update(Unit::Ml, 100)).is_err());
+        assert_eq!(stock_of(&conn, 1), 100);
+
+        products::update_product(&mut conn, update(Unit::Piece, 0)).unwrap();
+        let draught = products::update_product(&mut conn, update(Unit::L, 0)).unwrap();
+        assert_eq!((draught.unit, draught.serving_size), (Unit::L, 330));
+
+        // Same base, so stock can stay
+        conn.execute("UPDATE products SET quantity = 5000 WHERE id = 1", []).unwrap();
+        let draught = products::update_product(&mut conn, update(Unit::Cl, 5000)).unwrap();
+        assert_eq!((draught.unit, draught.servings), (Unit::Cl, 15));
+    }
+
+
+    // ===== VARIANT TESTS =====
+
+    /// A 30 l keg (id 4) sold as a pint (id 1, 500 ml) or a half (id 2, 250 ml)
+    fn seed_draught_variants(conn: &mut Connection) -> i64 {
+        conn.execute(
+            "INSERT INTO products (name, price, quantity, unit, serving_size) VALUES ('Peroni draught', 0, 30000, 'l', 500)",
+            [],
+        )
+        .unwrap();
+        for (name, price, code, deduction) in [("Pint", 450, Some("PER-PINT"), 500), ("Half", 250, None, 250)] {
+            variants::create_variant(
+                conn,
+                CreateProductVariant {
+                    product_id: 4,
+                    name: name.to_string(),
+                    price: Money::from_minor(price),
+                    codes: code.map(str::to_string).into_iter().collect(),
+                    deduction,
+                },
+            )
+            .unwrap();
+        }
+        4
+    }
+
+    fn variant_item(product_id: i64, variant_id: i64, quantity: i32) -> CreateOrderItem {
+        CreateOrderItem {
+            product_id,
+            quantity,
+            variant_id: Some(variant_id),
+            modifier_ids: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn test_variant_sales_deduct_shared_stock() {
+        let mut conn = setup_test_db();
+        seed_test_data(&conn);
+        let keg = seed_draught_variants(&mut conn);
+        reports::start_day(&mut conn, 1).unwrap();
+
+        let mut order = new_order(1, &[]);
+        order.items = vec![variant_item(keg, 1, 2), variant_item(keg, 2, 1)];
+        let order = orders::create_order(&mut conn, order).unwrap();
+
+        assert_eq!(order.order.total, Money::from_minor(2 * 450 + 250));
+        assert_eq!(order.items[0].variant_name.as_deref(), Some("Pint"));
+        assert_eq!(order.items[0].product_name.as_deref(), Some("Peroni draught"));
+        assert_eq!(stock_of(&conn, keg), 30000 - 1250);
+
+        let half = order.items.iter().find(|i| i.variant_id == Some(2)).unwrap().id;
+        orders::increase_item_quantity(&mut conn, half).unwrap();
+        assert_eq!(stock_of(&conn, keg), 30000 - 1500);
+        orders::decrease_item_quantity(&mut conn, order.items[0].id).unwrap();
+        assert_eq!(stock_of(&conn, keg), 30000 - 1000);
+
+        // A variant must be sold under its own parent
+        let err = orders::create_order(&mut conn, {
+            let mut other = new_order(2, &[]);
+            other.items = vec![variant_item(1, 1, 1)];
+            other
+        })
+        .unwrap_err();
+        assert_eq!(err.code(), "VALIDATION");
+        assert_eq!(stock_of(&conn, 1), 100);
+    }
+
+    #[test]
+    fn test_day_summary_rolls_variants_up_to_parent() {
+        let mut conn = setup_test_db();
+        seed_test_data(&conn);
+        let keg = seed_draught_variants(&mut conn);
+        reports::start_day(&mut conn, 1).unwrap();
+
+        let mut first = new_order(1, &[(1, 1)]);
+        first.items.push(variant_item(keg, 1, 2));
+        orders::create_order(&mut conn, first).unwrap();
+        let mut second = new_order(2, &[]);
+        second.items = vec![variant_item(keg, 2, 1), variant_item(keg, 1, 1)];
+        orders::create_order(&mut conn, second).unwrap();
+
+        let summary = reports::get_day_summary(&conn, None, None).unwrap();
+        assert_eq!(summary.product_sales.len(), 2);
+
+        let peroni = &summary.product_sales[0];
+        assert_eq!(peroni.product_id, keg);
+        assert_eq!(peroni.quantity, 4);
+        assert_eq!(peroni.revenue, Money::from_minor(3 * 450 + 250));
+        assert_eq!(peroni.variants.len(), 2);
+        let pint = peroni.variants.iter().find(|v| v.variant_id == 1).unwrap();
+        assert_eq!((pint.quantity, pint.revenue), (3, Money::from_minor(1350)));
+
+        assert_eq!(summary.product_sales[1].product_id, 1);
+        assert!(summary.product_sales[1].variants.is_empty());
+    }
+
+    #[test]
+    fn test_variant_validation() {
+        let mut conn = setup_test_db();
+        seed_test_data(&conn);
+        let keg = seed_draught_variants(&mut conn);
+
+        let new_variant = |name: &str, code: Option<&str>, deduction| CreateProductVariant {
+            product_id: keg,
+            name: name.to_string(),
+            price: Money::from_minor(300),
+            codes: code.map(str::to_string).into_iter().collect(),
+            deduction,
+        };
+        assert_eq!(variants::create_variant(&mut conn, new_variant("Pint", None, 500)).unwrap_err().code(), "VALIDATION");
+        assert!(variants::create_variant(&mut conn, new_variant("Jug", Some("PER-PINT"), 1500)).is_err());
+        assert!(variants::create_variant(&mut conn, new_variant("Jug", None, 0)).is_err());
+        assert!(variants::create_variant(&mut conn, new_variant("Jug", Some(" "), 1500)).is_err());
+        variants::create_variant(&mut conn, new_variant("Jug", None, 1500)).unwrap();
+        assert_eq!(variants::get_variants(&conn, Some(keg)).unwrap().len(), 3);
+
+        let pint = variants::update_variant(
+            &mut conn,
+            UpdateProductVariant {
+                id: 1,
+                name: "Pint".to_string(),
+                price: Money::from_minor(500),
+                deduction: 568,
+            },
+        )
+        .unwrap();
+        assert_eq!((pint.price, pint.deduction), (Money::from_minor(500), 568));
+        assert_eq!(pint.codes, vec!["PER-PINT".to_string()]);
+
+        // Sold variants stay for the history
+        reports::start_day(&mut conn, 1).unwrap();
+        let mut order = new_order(1, &[]);
+        order.items = vec![variant_item(keg, 1, 1)];
+        orders::create_order(&mut conn, order).unwrap();
+        assert!(variants::delete_variant(&conn, 1).is_err());
+        variants::delete_variant(&conn, 2).unwrap();
+        assert_eq!(variants::get_variants(&conn, None).unwrap().len(), 2);
+    }
+
+
+    // ===== MODIFIER TESTS =====
+
+    /// "Ice" (required, exactly one: 1 with ice, 2 no ice) on the Beer category
+    /// and "Extras" (optional, up to two: 3 lemon +50, 4 double shot +200) on
+    /// Heineken only.
+    fn seed_modifier_groups(conn: &mut Connection) {
+        let modifier = |name: &str, delta| CreateModifier {
+            name: name.to_string(),
+            price_delta: Money::from_minor(delta),
+        };
+        let ice = modifiers::create_modifier_group(
+            conn,
+            CreateModifierGroup {
+                name: "Ice".to_string(),
+                min_select: 1,
+                max_select: Some(1),
+                modifiers: vec![modifier("With ice", 0), modifier("No ice", 0)],
+            },
+        )
+        .unwrap();
+        modifiers::set_modifier_group_links(conn, ice.id, Vec::new(), vec![1]).unwrap();
+
+        let extras = modifiers::create_modifier_group(
+            conn,
+            CreateModifierGroup {
+                name: "Extras".to_string(),
+                min_select: 0,
+                max_select: Some(2),
+                modifiers: vec![modifier("Lemon", 50), modifier("Double shot", 200)],
+            },
+        )
+        .unwrap();
+        modifiers::set_modifier_group_links(conn, extras.id, vec![1], Vec::new()).unwrap();
+    }
+
+    fn with_modifiers(product_id: i64, quantity: i32, modifier_ids: &[i64]) -> CreateOrderItem {
+        CreateOrderItem {
+            product_id,
+            quantity,
+            variant_id: None,
+            modifier_ids: modifier_ids.to_vec(),
+        }
+    }
+
+    #[test]
+    fn test_modifiers_priced_into_lines_and_total() {
+        let mut conn = setup_test_db();
+        seed_test_data(&conn);
+        seed_modifier_groups(&mut conn);
+        reports::start_day(&mut conn, 1).unwrap();
+
+        assert_eq!(modifiers::get_product_modifier_groups(&conn, 1).unwrap().len(), 2);
+        assert_eq!(modifiers::get_product_modifier_groups(&conn, 2).unwrap().len(), 1);
+
+        let mut order = new_order(1, &[]);
+        order.items = vec![with_modifiers(1, 2, &[1, 3]), with_modifiers(1, 1, &[2])];
+        let order = orders::create_order(&mut conn, order).unwrap();
+
+        // Same product, different modifiers: two lines
+        assert_eq!(order.items.len(), 2);
+        assert_eq!(order.items[0].price_at_sale, Money::from_minor(550));
+        assert_eq!(order.items[0].modifiers.len(), 2);
+        assert_eq!(order.items[0].modifiers[1].name, "Lemon");
+        assert_eq!(order.items[1].price_at_sale, Money::from_minor(500));
+        assert_eq!(order.order.total, Money::from_minor(2 * 550 + 500));
+        assert_eq!(stock_of(&conn, 1), 97);
+
+        // The line keeps its sale-time modifiers when the menu changes
+        conn.execute("UPDATE modifiers SET price_delta = 999 WHERE id = 3", []).unwrap();
+        let added = orders::add_items_to_order(&mut conn, order.order.id, vec![with_modifiers(1, 1, &[1, 3, 4])]).unwrap();
+        assert_eq!(added.items[2].price_at_sale, Money::from_minor(500 + 999 + 200));
+
+        let item = orders::increase_item_quantity(&mut conn, order.items[0].id).unwrap();
+        assert_eq!(item.items[0].price_at_sale, Money::from_minor(550));
+        assert_eq!(item.order.total, Money::from_minor(3 * 550 + 500 + 1699));
+    }
+
+    #[test]
+    fn test_modifier_selection_rules() {
+        let mut conn = setup_test_db();
+        seed_test_data(&conn);
+        seed_modifier_groups(&mut conn);
+        reports::start_day(&mut conn, 1).unwrap();
+
+        let try_order = |conn: &mut Connection, item| {
+            let mut order = new_order(1, &[]);
+            order.items = vec![item];
+            orders::create_order(conn, order)
+        };
+
+        // Required group left out, both ice options, an extra from another product, a repeat
+        for item in [
+            with_modifiers(2, 1, &[]),
+            with_modifiers(2, 1, &[1, 2]),
+            with_modifiers(2, 1, &[1, 3]),
+            with_modifiers(1, 1, &[1, 3, 3]),
+            with_modifiers(1, 1, &[99]),
+        ] {
+            assert_eq!(try_order(&mut conn, item).unwrap_err().code(), "VALIDATION");
+        }
+        assert_eq!(stock_of(&conn, 2), 50);
+
+        // Products outside linked groups need no modifiers at all
+        conn.execute("INSERT INTO products (name, price, quantity) VALUES ('Water', 200, 10)", []).unwrap();
+        try_order(&mut conn, with_modifiers(4, 1, &[])).unwrap();
+        try_order(&mut conn, with_modifiers(2, 1, &[2])).unwrap();
+
+        assert!(modifiers::create_modifier_group(
+            &mut conn,
+            CreateModifierGroup { name: "Bad".to_string(), min_select: 2, max_select: Some(1), modifiers: Vec::new() },
+        )
+        .is_err());
+    }
+
+    #[test]
+    fn test_removing_lines_removes_their_modifiers() {
+        let mut conn = setup_test_db();
+        seed_test_data(&conn);
+        seed_modifier_groups(&mut conn);
+        reports::start_day(&mut conn, 1).unwrap();
+
+        let mut order = new_order(1, &[]);
+        order.items = vec![with_modifiers(1, 1, &[1, 3]), with_modifiers(2, 1, &[2])];
+        let order = orders::create_order(&mut conn, order).unwrap();
+        let modifier_rows = |conn: &Connection| -> i32 {
+            conn.query_row("SELECT COUNT(*) FROM order_item_modifiers", [], |row| row.get(0)).unwrap()
+        };
+        assert_eq!(modifier_rows(&conn), 3);
+
+        orders::decrease_item_quantity(&mut conn, order.items[0].id).unwrap().unwrap();
+        assert_eq!(modifier_rows(&conn), 1);
+        assert!(orders::decrease_item_quantity(&mut conn, order.items[1].id).unwrap().is_none());
+        assert_eq!(modifier_rows(&conn), 0);
+    }
+
+    #[test]
+    fn test_category_modifier_groups_reach_subcategories() {
+        let mut conn = setup_test_db();
+        seed_test_data(&conn);
+        seed_modifier_groups(&mut conn);
+        let stout = categories::create_category(&conn, "Stout".to_string()).unwrap();
+        categories::update_category(&mut conn, move_category(stout.id, "Stout", Some(1))).unwrap();
+        conn.execute("UPDATE products SET category_id = ?1 WHERE id = 3", [stout.id]).unwrap();
+        reports::start_day(&mut conn, 1).unwrap();
+
+        // Ice is linked to Beer, so Guinness under Beer > Stout must choose one
+        let groups = modifiers::get_product_modifier_groups(&conn, 3).unwrap();
+        assert_eq!(groups.iter().map(|g| g.name.as_str()).collect::<Vec<_>>(), vec!["Ice"]);
+        let err = orders::create_order(&mut conn, new_order(1, &[(3, 1)])).unwrap_err();
+        assert_eq!(err.code(), "VALIDATION");
+
+        let mut order = new_order(1, &[]);
+        order.items = vec![with_modifiers(3, 1, &[2])];
+        assert_eq!(orders::create_order(&mut conn, order).unwrap().items[0].modifiers.len(), 1);
+    }
+
+
+    // ===== BARCODE TESTS =====
+
+    #[test]
+    fn test_migration_moves_variant_skus_to_codes() {
+        let mut conn = Connection::open_in_memory().unwrap();
+        db::apply_migrations(&mut conn, &db::MIGRATIONS[..9]).unwrap();
+        seed_test_data(&conn);
+        conn.execute(
+            "INSERT INTO product_variants (product_id, name, price, sku, deduction)
+             VALUES (1, 'Pint', 450, 'HEI-PINT', 1)",
+            [],
+        )
+        .unwrap();
+
+        db::run_migrations(&mut conn).unwrap();
+
+        let found = codes::find_product_by_code(&conn, "HEI-PINT".to_string()).unwrap();
+        assert_eq!(found.variant.map(|v| v.name).as_deref(), Some("Pint"));
+        let sku_columns: i32 = conn
+            .query_row("SELECT COUNT(*) FROM pragma_table_info('product_variants') WHERE name = 'sku'", [], |row| {
+                row.get(0)
+            })
+            .unwrap();
+        assert_eq!(sku_columns, 0);
+    }
+
+    #[test]
+    fn test_scanned_codes_sell_products_and_variants() {
+        let mut conn = setup_test_db();
+        seed_test_data(&conn);
+        let keg = seed_draught_variants(&mut conn);
+        codes::add_product_code(&conn, 1, None, "8714800011".to_string(), None).unwrap();
+        codes::add_product_code(&conn, 1, None, "HEI-33".to_string(), None).unwrap();
+
+        let heineken = products::get_product(&conn, 1).unwrap();
+        assert_eq!(heineken.codes, vec!["8714800011".to_string(), "HEI-33".to_string()]);
+
+        let found = codes::find_product_by_code(&conn, " PER-PINT ".to_string()).unwrap();
+        assert_eq!(found.product.id, keg);
+        assert_eq!(found.variant.map(|v| v.name).as_deref(), Some("Pint"));
+
+        // Codes are unique across products and variants
+        let err = codes::add_product_code(&conn, 2, None, "PER-PINT".to_string(), None).unwrap_err();
+        assert_eq!(err.code(), "VALIDATION");
+
+        let err = codes::find_product_by_code(&conn, "0000".to_string()).unwrap_err();
+        assert_eq!(err.code(), "UNKNOWN_CODE");
+        assert_eq!(serde_json::to_value(&err).unwrap()["details"]["code"], "0000");
+
+        reports::start_day(&mut conn, 1).unwrap();
+        let order = orders::create_order(&mut conn, new_order(1, &[(2, 1)])).unwrap();
+        let id = order.order.id;
+        orders::add_item_by_code(&mut conn, id, "HEI-33".to_string(), 2).unwrap();
+        let order = orders::add_item_by_code(&mut conn, id, "PER-PINT".to_string(), 1).unwrap();
+
+        assert_eq!(order.items.len(), 3);
+        assert_eq!(order.order.total, Money::from_minor(600 + 2 * 500 + 450));
+        assert_eq!(stock_of(&conn, 1), 98);
+        assert_eq!(stock_of(&conn, keg), 30000 - 500);
+
+        let err = orders::add_item_by_code(&mut conn, id, "0000".to_string(), 1).unwrap_err();
+        assert_eq!(err.code(), "UNKNOWN_CODE");
+    }
+
+    #[test]
+    fn test_receive_delivery_by_code() {
+        let mut conn = setup_test_db();
+        seed_test_data(&conn);
+        codes::add_product_code(&conn, 1, None, "HEI-33".to_string(), None).unwrap();
+        codes::add_product_code(&conn, 3, None, "GUI-44".to_string(), None).unwrap();
+        let supplier_id = seed_supplier(&conn);
+        let id = draft_po(&mut conn, supplier_id).purchase_order.id;
+        purchasing::send_purchase_order(&mut conn, id).unwrap();
+
+        let po = purchasing::receive_by_code(&mut conn, id, "HEI-33".to_string(), 10, Some(1)).unwrap();
+        assert_eq!(po.purchase_order.status, PurchaseOrderStatus::PartiallyReceived);
+        assert_eq!(po.lines.iter().find(|l| l.product_id == 1).unwrap().quantity_received, 10);
+        assert_eq!(stock_of(&conn, 1), 110);
+
+        // Guinness is not on this order
+        let err = purchasing::receive_by_code(&mut conn, id, "GUI-44".to_string(), 1, Some(1)).unwrap_err();
+        assert_eq!(err.code(), "VALIDATION");
+        let err = purchasing::receive_by_code(&mut conn, id, "0000".to_string(), 1, Some(1)).unwrap_err();
+        assert_eq!(err.code(), "UNKNOWN_CODE");
+        assert_eq!(stock_of(&conn, 3), 3);
+
+        // Wine sold by the 150 ml glass arrives by the 750 ml bottle
+        let wine = products::create_product(&mut conn, measured_product("House red", 0, Unit::Ml, Some(150))).unwrap();
+        codes::add_product_code(&conn, wine.id, None, "WINE-1".to_string(), None).unwrap();
+        let bottle = codes::add_product_code(&conn, wine.id, None, "WINE-75".to_string(), Some(750)).unwrap();
+        assert_eq!(bottle.pack_quantity, Some(750));
+        let order = CreatePurchaseOrder {
+            supplier_id,
+            staff_id: Some(1),
+            reference: None,
+            notes: None,
+            lines: vec![po_line(wine.id, 4500, 1)],
+        };
+        let id = purchasing::create_purchase_order(&mut conn, order).unwrap().purchase_order.id;
+        purchasing::send_purchase_order(&mut conn, id).unwrap();
+        purchasing::receive_by_code(&mut conn, id, "WINE-75".to_string(), 4, Some(1)).unwrap();
+        assert_eq!(stock_of(&conn, wine.id), 3000);
+
+        // A code without a pack size would receive by the glass
+        let err = purchasing::receive_by_code(&mut conn, id, "WINE-1".to_string(), 1, Some(1)).unwrap_err();
+        assert_eq!(err.code(), "VALIDATION");
+        assert!(codes::add_product_code(&conn, wine.id, None, "WINE-0".to_string(), Some(0)).is_err());
+        assert_eq!(stock_of(&conn, wine.id), 3000);
+    }
+
+
+    // ===== COST AND MARGIN TESTS =====
+
+    #[test]
+    fn test_deliveries_average_cost_and_sales_snapshot_it() {
+        let mut conn = setup_test_db();
+        seed_test_data(&conn);
+        let supplier_id = seed_supplier(&conn);
+        let receive_all = || ReceivePurchaseOrder { staff_id: Some(1), reference: None, lines: None };
+
+        // Uncosted stock takes the first delivery's cost as it is
+        let first = draft_po(&mut conn, supplier_id).purchase_order.id;
+        purchasing::send_purchase_order(&mut conn, first).unwrap();
+        purchasing::receive_purchase_order(&mut conn, first, receive_all()).unwrap();
+        assert_eq!(products::get_product(&conn, 1).unwrap().cost_price, Some(Money::from_minor(250)));
+
+        // 124 at 2.50 and 100 at 3.50 average to 2.95
+        let second = purchasing::create_purchase_order(
+            &mut conn,
+            CreatePurchaseOrder {
+                supplier_id,
+                staff_id: Some(1),
+                reference: None,
+                notes: None,
+                lines: vec![po_line(1, 100, 350)],
+            },
+        )
+        .unwrap()
+        .purchase_order
+        .id;
+        purchasing::send_purchase_order(&mut conn, second).unwrap();
+        purchasing::receive_purchase_order(&mut conn, second, receive_all()).unwrap();
+        assert_eq!(products::get_product(&conn, 1).unwrap().cost_price, Some(Money::from_minor(295)));
+
+        reports::start_day(&mut conn, 1).unwrap();
+        let order = orders::create_order(&mut conn, new_order(1, &[(1, 2), (3, 1)])).unwrap();
+        let heineken = order.items.iter().find(|i| i.product_id == 1).unwrap();
+        assert_eq!(heineken.cost_at_sale, Some(Money::from_minor(295)));
+        assert_eq!(order.items.iter().find(|i| i.product_id == 3).unwrap().cost_at_sale, None);
+
+        // A later delivery does not rewrite what the sale cost
+        conn.execute("UPDATE products SET cost_price = 400 WHERE id = 1", []).unwrap();
+        let session_id = reports::get_active_session(&conn).unwrap().unwrap().id;
+        let margins = reports::get_session_margins(&conn, session_id).unwrap();
+        assert_eq!(margins.revenue, Money::from_minor(2 * 500 + 700));
+        assert_eq!(margins.cost, Money::from_minor(2 * 295));
+        assert_eq!(margins.margin, Money::from_minor(2 * (500 - 295)));
+        let guinness = margins.products.iter().find(|p| p.product_id == 3).unwrap();
+        assert_eq!((guinness.uncosted_quantity, guinness.margin), (1, Money::zero()));
+    }
+
+    #[test]
+    fn test_margins_flag_sales_below_cost() {
+        let mut conn = setup_test_db();
+        seed_test_data(&conn);
+        conn.execute("INSERT INTO categories (name) VALUES ('Spirits')", []).unwrap();
+        conn.execute("UPDATE products SET cost_price = 200 WHERE id = 1", []).unwrap();
+        conn.execute("UPDATE products SET cost_price = 650 WHERE id = 2", []).unwrap();
+        conn.execute("UPDATE products SET cost_price = 300, category_id = 2 WHERE id = 3", []).unwrap();
+
+        reports::start_day(&mut conn, 1).unwrap();
+        orders::create_order(&mut conn, new_order(1, &[(1, 3), (2, 2), (3, 1)])).unwrap();
+
+        let today = chrono::Local::now().format("%Y-%m-%d").to_string();
+        let products = reports::get_product_margins(&conn, &today, &today).unwrap();
+        let corona = products.iter().find(|p| p.product_id == 2).unwrap();
+        assert_eq!(corona.below_cost_quantity, 2);
+        assert_eq!(corona.margin, Money::from_minor(-100));
+        assert_eq!(products.iter().find(|p| p.product_id == 1).unwrap().below_cost_quantity, 0);
+
+        let categories = reports::get_category_margins(&conn, &today, &today, None).unwrap();
+        assert_eq!(categories.len(), 2);
+        let beer = categories.iter().find(|c| c.category_id == Some(1)).unwrap();
+        assert_eq!((beer.quantity, beer.below_cost_quantity), (5, 2));
+        assert_eq!(beer.margin, Money::from_minor(3 * 300 - 2 * 50));
+
+        // Stock takes value variances at the average cost
+        let id = stock_takes::start_stock_take(&mut conn, 1, None, None).unwrap().stock_take.id;
+        stock_takes::record_stock_count(&mut conn, id, 2, 1, 40).unwrap();
+        let line = &stock_takes::get_stock_take(&conn, id).unwrap().lines[0];
+        assert_eq!(line.unit_cost, Some(Money::from_minor(650)));
+    }
+
+
+    // ===== PRICING RULE TESTS =====
+
+    fn pricing_rule(name: &str, kind: PricingRuleKind) -> CreatePricingRule {
+        CreatePricingRule {
+            name: name.to_string(),
+            kind,
+            percent: None,
+            amount: None,
+            buy_quantity: None,
+            free_quantity: None,
+            product_id: None,
+            category_id: None,
+            weekdays: Vec::new(),
+            start_time: None,
+            end_time: None,
+        }
+    }
+
+    fn local(datetime: &str) -> chrono::NaiveDateTime {
+        chrono::NaiveDateTime::parse_from_str(datetime, "%Y-%m-%d %H:%M").unwrap()
+    }
+
+    #[test]
+    fn test_pricing_rules_follow_their_schedule() {
+        let conn = setup_test_db();
+        seed_test_data(&conn);
+
+        // Weekday happy hour on beer, and a late Friday deal on Corona
+        let happy_hour = pricing::create_pricing_rule(
+            &conn,
+            CreatePricingRule {
+                percent: Some(50),
+                category_id: Some(1),
+                weekdays: vec![5, 1, 2, 3, 4],
+                start_time: Some("17:00".to_string()),
+                end_time: Some("19:00".to_string()),
+                ..pricing_rule("Happy hour", PricingRuleKind::PercentOff)
+            },
+        )
+        .unwrap();
+        assert_eq!(happy_hour.weekdays, vec![1, 2, 3, 4, 5]);
+        pricing::create_pricing_rule(
+            &conn,
+            CreatePricingRule {
+                amount: Some(Money::from_minor(100)),
+                product_id: Some(2),
+                weekdays: vec![5],
+                start_time: Some("22:00".to_string()),
+                end_time: Some("02:00".to_string()),
+                ..pricing_rule("Late Friday", PricingRuleKind::AmountOff)
+            },
+        )
+        .unwrap();
+
+        let price_at = |product_id, list, at| {
+            pricing::best_price(&conn, product_id, Money::from_minor(list), 1, local(at))
+                .unwrap()
+                .map(|rule| (rule.rule_name, rule.unit_price.minor_units))
+        };
+        // 2024-01-15 is a Monday, 2024-01-19 a Friday
+        assert_eq!(price_at(1, 500, "2024-01-15 17:30"), Some(("Happy hour".to_string(), 250)));
+        assert_eq!(price_at(1, 500, "2024-01-15 19:00"), None);
+        assert_eq!(price_at(1, 500, "2024-01-20 17:30"), None);
+        assert_eq!(price_at(2, 600, "2024-01-19 23:00"), Some(("Late Friday".to_string(), 500)));
+        assert_eq!(price_at(2, 600, "2024-01-20 01:30"), Some(("Late Friday".to_string(), 500)));
+        assert_eq!(price_at(2, 600, "2024-01-20 23:00"), None);
+        // Both apply to Corona at Friday happy hour; the cheaper wins
+        assert_eq!(price_at(2, 600, "2024-01-19 18:00"), Some(("Happy hour".to_string(), 300)));
+
+        let active = pricing::get_active_pricing_rules(&conn, local("2024-01-19 18:00")).unwrap();
+        assert_eq!(active.len(), 1);
+
+        let invalid = [
+            CreatePricingRule { percent: Some(0), ..pricing_rule("Nothing off", PricingRuleKind::PercentOff) },
+            CreatePricingRule {
+                percent: Some(10),
+                start_time: Some("17:00".to_string()),
+                ..pricing_rule("No end", PricingRuleKind::PercentOff)
+            },
+            CreatePricingRule {
+                amount: Some(Money::from_minor(50)),
+                product_id: Some(1),
+                category_id: Some(1),
+                ..pricing_rule("Both scopes", PricingRuleKind::AmountOff)
+            },
+            CreatePricingRule { buy_quantity: Some(2), ..pricing_rule("Nothing free", PricingRuleKind::BuyXGetY) },
+        ];
+        for rule in invalid {
+            assert_eq!(pricing::create_pricing_rule(&conn, rule).unwrap_err().code(), "VALIDATION");
+        }
+    }
+
+    #[test]
+    fn test_orders_record_the_rule_that_priced_each_line() {
+        let mut conn = setup_test_db();
+        seed_test_data(&conn);
+        let three_for_two = pricing::create_pricing_rule(
+            &conn,
+            CreatePricingRule {
+                buy_quantity: Some(2),
+                free_quantity: Some(1),
+                product_id: Some(1),
+                ..pricing_rule("3 for 2", PricingRuleKind::BuyXGetY)
+            },
+        )
+        .unwrap();
+        pricing::create_pricing_rule(
+            &conn,
+            CreatePricingRule {
+                amount: Some(Money::from_minor(100)),
+                category_id: Some(1),
+                ..pricing_rule("Beer night", PricingRuleKind::AmountOff)
+            },
+        )
+        .unwrap();
+        reports::start_day(&mut conn, 1).unwrap();
+
+        // Seven Heineken: two free (2500) beats 1.00 off each (2800)
+        let order = orders::create_order(&mut conn, new_order(1, &[(1, 7), (2, 1)])).unwrap();
+        assert_eq!(order.items.len(), 3);
+        assert_eq!(order.order.total, Money::from_minor(5 * 500 + 500));
+
+        let free = order.items.iter().find(|i| i.product_id == 1 && i.price_at_sale == Money::zero()).unwrap();
+        assert_eq!((free.quantity, free.pricing_rule_name.as_deref()), (2, Some("3 for 2")));
+        let paid = order.items.iter().find(|i| i.product_id == 1 && i.quantity == 5).unwrap();
+        assert_eq!((paid.price_at_sale, paid.pricing_rule_id), (Money::from_minor(500), None));
+        let corona = order.items.iter().find(|i| i.product_id == 2).unwrap();
+        assert_eq!(corona.price_at_sale, Money::from_minor(500));
+        assert_eq!(corona.pricing_rule_name.as_deref(), Some("Beer night"));
+        assert_eq!(stock_of(&conn, 1), 93);
+
+        let err = orders::increase_item_quantity(&mut conn, free.id).unwrap_err();
+        assert_eq!(err.code(), "VALIDATION");
+
+        // Applied rules are kept for the history and switched off instead
+        assert!(pricing::delete_pricing_rule(&conn, three_for_two.id).is_err());
+        pricing::update_pricing_rule(
+            &conn,
+            UpdatePricingRule {
+                id: three_for_two.id,
+                name: three_for_two.name.clone(),
+                kind: three_for_two.kind,
+                percent: None,
+                amount: None,
+                buy_quantity: Some(2),
+                free_quantity: Some(1),
+                product_id: Some(1),
+                category_id: None,
+                weekdays: Vec::new(),
+                start_time: None,
+                end_time: None,
+                active: false,
+            },
+        )
+        .unwrap();
+        let order = orders::add_items_to_order(&mut conn, order.order.id, new_order(1, &[(1, 3)]).items).unwrap();
+        let added = order.items.iter().find(|i| i.product_id == 1 && i.quantity == 3).unwrap();
+        assert_eq!(added.price_at_sale, Money::from_minor(400));
+    }
+
+    #[test]
+    fn test_free_items_follow_the_line_that_earned_them() {
+        let mut conn = setup_test_db();
+        seed_test_data(&conn);
+        pricing::create_pricing_rule(
+            &conn,
+            CreatePricingRule {
+                buy_quantity: Some(2),
+                free_quantity: Some(1),
+                product_id: Some(1),
+                ..pricing_rule("3 for 2", PricingRuleKind::BuyXGetY)
+            },
+        )
+        .unwrap();
+        reports::start_day(&mut conn, 1).unwrap();
+        let lines = |order: &OrderWithItems| -> Vec<(i32, i64)> {
+            order.items.iter().map(|i| (i.quantity, i.price_at_sale.minor_units)).collect()
+        };
+
+        let order = orders::create_order(&mut conn, new_order(1, &[(1, 3)])).unwrap();
+        assert_eq!(lines(&order), vec![(2, 500), (1, 0)]);
+        let paid_id = order.items[0].id;
+
+        // One Heineken back leaves two, and two do not earn a free one
+        let order = orders::decrease_item_quantity(&mut conn, paid_id).unwrap().unwrap();
+        assert_eq!(lines(&order), vec![(2, 500)]);
+        assert_eq!((order.order.total, stock_of(&conn, 1)), (Money::from_minor(1000), 98));
+
+        // Ringing the third up again earns it back
+        let order = orders::increase_item_quantity(&mut conn, paid_id).unwrap();
+        assert_eq!(lines(&order), vec![(2, 500), (1, 0)]);
+        assert_eq!((order.order.total, stock_of(&conn, 1)), (Money::from_minor(1000), 97));
+
+        let free_id = order.items[1].id;
+        let order = orders::decrease_item_quantity(&mut conn, free_id).unwrap().unwrap();
+        assert_eq!(lines(&order), vec![(2, 500)]);
+        let order = orders::decrease_item_quantity(&mut conn, paid_id).unwrap().unwrap();
+        assert_eq!(lines(&order), vec![(1, 500)]);
+        assert!(orders::decrease_item_quantity(&mut conn, paid_id).unwrap().is_none());
+        assert_eq!(stock_of(&conn, 1), 100);
+    }
+
+    #[test]
+    fn test_category_rules_cover_subcategories() {
+        let mut conn = setup_test_db();
+        seed_test_data(&conn);
+        let stout = categories::create_category(&conn, "Stout".to_string()).unwrap();
+        categories::update_category(&mut conn, move_category(stout.id, "Stout", Some(1))).unwrap();
+        conn.execute("UPDATE products SET category_id = ?1 WHERE id = 3", [stout.id]).unwrap();
+        pricing::create_pricing_rule(
+            &conn,
+            CreatePricingRule {
+                percent: Some(10),
+                category_id: Some(1),
+                ..pricing_rule("Beer week", PricingRuleKind::PercentOff)
+            },
+        )
+        .unwrap();
+
+        let rule = pricing::best_price(&conn, 3, Money::from_minor(700), 1, local("2024-01-15 12:00"))
+            .unwrap()
+            .unwrap();
+        assert_eq!((rule.rule_name.as_str(), rule.unit_price.minor_units), ("Beer week", 630));
+    }
+
+
+    // ===== PRICE HISTORY TESTS =====
+
+    #[test]
+    fn test_price_changes_are_recorded() {
+        let mut conn = setup_test_db();
+        seed_test_data(&conn);
+
+        let rakia = products::create_product(&mut conn, measured_product("Rakia", 0, Unit::Piece, None)).unwrap();
+        let update = |name: &str| UpdateProduct {
+            id: rakia.id,
+            name: name.to_string(),
+            price: Money::from_minor(350),
+            quantity: 0,
+            category_id: None,
+            low_stock_threshold: 1,
+            unit: None,
+            serving_size: None,
+            cost_price: None,
+            aliases: None,
+            staff_id: Some(2),
+        };
+        products::update_product(&mut conn, update("Rakia")).unwrap();
+        // Saving without a new price adds nothing
+        products::update_product(&mut conn, update("Rakia e Skraparit")).unwrap();
+
+        let history = prices::get_price_history(&conn, rakia.id).unwrap();
+        assert_eq!(history.len(), 2);
+        assert_eq!((history[0].old_price, history[0].new_price), (None, Money::from_minor(300)));
+        assert_eq!(history[1].old_price, Some(Money::from_minor(300)));
+        assert_eq!(history[1].new_price, Money::from_minor(350));
+        assert_eq!(history[1].staff_name.as_deref(), Some("Jane"));
+        assert!(history.iter().all(|change| change.applied_at.is_some()));
+
+        let change = SchedulePriceChange {
+            product_id: 1,
+            new_price: Money::from_minor(550),
+            effective_at: None,
+            staff_id: Some(1),
+        };
+        let applied = prices::schedule_price_change(&mut conn, change).unwrap();
+        assert_eq!(applied.old_price, Some(Money::from_minor(500)));
+        assert_eq!(products::get_product(&conn, 1).unwrap().price, Money::from_minor(550));
+    }
+
+    #[test]
+    fn test_scheduled_price_change_takes_effect_when_due() {
+        let mut conn = setup_test_db();
+        seed_test_data(&conn);
+
+        let schedule = |conn: &mut Connection, effective_at: &str| {
+            prices::schedule_price_change(
+                conn,
+                SchedulePriceChange {
+                    product_id: 1,
+                    new_price: Money::from_minor(600),
+                    effective_at: Some(effective_at.to_string()),
+                    staff_id: Some(1),
+                },
+            )
+        };
+        assert_eq!(schedule(&mut conn, "next friday").unwrap_err().code(), "VALIDATION");
+
+        let pending = schedule(&mut conn, "2999-01-01 12:00").unwrap();
+        assert!(pending.applied_at.is_none());
+        assert_eq!(prices::apply_due_price_changes(&mut conn).unwrap(), 0);
+        assert_eq!(products::get_product(&conn, 1).unwrap().price, Money::from_minor(500));
+
+        // Let it fall due; the next order picks it up
+        conn.execute(
+            "UPDATE product_price_history SET effective_at = datetime('now', '-1 minute') WHERE id = ?1",
+            [pending.id],
+        )
+        .unwrap();
+        reports::start_day(&mut conn, 1).unwrap();
+        let order = orders::create_order(&mut conn, new_order(1, &[(1, 1)])).unwrap();
+        assert_eq!(order.items[0].price_at_sale, Money::from_minor(600));
+
+        let history = prices::get_price_history(&conn, 1).unwrap();
+        let applied = history.iter().find(|change| change.id == pending.id).unwrap();
+        assert_eq!(applied.old_price, Some(Money::from_minor(500)));
+        assert!(applied.applied_at.is_some());
+        assert_eq!(prices::cancel_price_change(&conn, pending.id).unwrap_err().code(), "VALIDATION");
+
+        let later = schedule(&mut conn, "2999-06-01 09:00").unwrap();
+        prices::cancel_price_change(&conn, later.id).unwrap();
+        assert!(prices::get_price_history(&conn, 1).unwrap().iter().all(|change| change.id != later.id));
+    }
+
+
+    // ===== ARCHIVE TESTS =====
+
+    #[test]
+    fn test_archived_products_leave_sale_but_keep_history() {
+        let mut conn = setup_test_db();
+        seed_test_data(&conn);
+        reports::start_day(&mut conn, 1).unwrap();
+        let order = orders::create_order(&mut conn, new_order(1, &[(1, 2)])).unwrap();
+
+        products::archive_product(&conn, 1).unwrap();
+        assert_eq!(products::get_products(&conn, false).unwrap().len(), 2);
+        assert_eq!(products::get_products(&conn, true).unwrap().len(), 3);
+        let err = orders::create_order(&mut conn, new_order(1, &[(1, 1)])).unwrap_err();
+        assert_eq!(err.code(), "VALIDATION");
+        let err = orders::increase_item_quantity(&mut conn, order.items[0].id).unwrap_err();
+        assert_eq!(err.code(), "VALIDATION");
+        assert_eq!(orders::get_order(&conn, order.order.id).unwrap().items[0].product_name.as_deref(), Some("Heineken"));
+
+        // Sold, so it can only be archived
+        let err = products::purge_product(&mut conn, 1).unwrap_err();
+        assert!(err.to_string().contains("orders"));
+        products::restore_product(&conn, 1).unwrap();
+        orders::create_order(&mut conn, new_order(1, &[(1, 1)])).unwrap();
+
+        // An archived category takes its products off sale with it
+        let seasonal = categories::create_category(&conn, "Seasonal".to_string()).unwrap();
+        let mut mulled = measured_product("Mulled Wine", 0, Unit::Piece, None);
+        mulled.category_id = Some(seasonal.id);
+        let mulled = products::create_product(&mut conn, mulled).unwrap();
+        categories::archive_category(&conn, seasonal.id).unwrap();
+        assert!(categories::get_categories(&conn, false).unwrap().iter().all(|c| c.id != seasonal.id));
+        assert!(products::get_products(&conn, false).unwrap().iter().all(|p| p.id != mulled.id));
+
+        assert!(categories::purge_category(&mut conn, seasonal.id).is_err());
+        assert!(products::purge_product(&mut conn, mulled.id).is_err());
+        products::archive_product(&conn, mulled.id).unwrap();
+        products::purge_product(&mut conn, mulled.id).unwrap();
+        categories::purge_category(&mut conn, seasonal.id).unwrap();
+        assert_eq!(categories::get_categories(&conn, true).unwrap().len(), 1);
+
+        // Its lots keep their history too
+        lots::receive_stock_lot(&mut conn, stock_lot(3, 5, None, None)).unwrap();
+        products::archive_product(&conn, 3).unwrap();
+        let err = products::purge_product(&mut conn, 3).unwrap_err();
+        assert!(err.to_string().contains("stock lots"));
+        assert_eq!(lots::get_stock_lots(&conn, 3).unwrap().len(), 1);
+    }
+
+    #[test]
+    fn test_archived_staff_cannot_log_in() {
+        let mut conn = setup_test_db();
+        seed_test_data(&conn);
+
+        staff::archive_staff(&conn, 2).unwrap();
+        assert_eq!(staff::get_staff(&conn, false).unwrap().len(), 1);
+        assert_eq!(staff::get_staff(&conn, true).unwrap().len(), 2);
+        assert_eq!(staff::verify_staff_pin(&conn, 2, "").unwrap_err().code(), "VALIDATION");
+        assert!(reports::start_day(&mut conn, 2).is_err());
+
+        reports::start_day(&mut conn, 1).unwrap();
+        let by_jane = || CreateOrder { staff_id: 2, ..new_order(2, &[(1, 1)]) };
+        assert!(orders::create_order(&mut conn, by_jane()).is_err());
+        staff::restore_staff(&conn, 2).unwrap();
+        orders::create_order(&mut conn, by_jane()).unwrap();
+
+        // Open tabs block archiving; history blocks purging
+        let order = orders::create_order(&mut conn, new_order(1, &[(2, 1)])).unwrap();
+        assert!(staff::archive_staff(&conn, 1).is_err());
+        orders::mark_order_paid(&mut conn, order.order.id).unwrap();
+        staff::archive_staff(&conn, 1).unwrap();
+        assert!(staff::purge_staff(&conn, 1).unwrap_err().to_string().contains("orders"));
+
+        let temp = staff::create_staff(&conn, CreateStaff { name: "Temp".to_string(), pin: None }).unwrap();
+        assert!(staff::purge_staff(&conn, temp.id).is_err());
+        staff::archive_staff(&conn, temp.id).unwrap();
+        staff::purge_staff(&conn, temp.id).unwrap();
+        assert_eq!(staff::get_staff(&conn, true).unwrap().len(), 2);
+    }
+
+
+    // ===== IMPORT / EXPORT TESTS =====
+
+    #[test]
+    fn test_import_products_dry_run_and_rejections() {
+        let mut conn = setup_test_db();
+        seed_test_data(&conn);
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("products.csv");
+
+        std::fs::write(
+            &path,
+            "name,sku,category,price,quantity,unit,serving_size\n\
+             heineken,,,5.50,,,\n\
+             Rakia,RAK-1,Spirits,3,20,,\n\
+             \"Gin, London Dry\",,spirits,4.5,700,cl,40\n\
+             Raki,,Spirits,abc,,,\n\
+             Ouzo,,Spirits,,,,\n",
+        )
+        .unwrap();
+
+        let report = catalog::import_products(&mut conn, &path, true, Some(1)).unwrap();
+        assert!(!report.applied);
+        let lines = |rows: &[ImportedRow]| rows.iter().map(|row| row.line).collect::<Vec<_>>();
+        assert_eq!(lines(&report.updated), vec![2]);
+        assert_eq!(lines(&report.created), vec![3, 4]);
+        assert_eq!(report.rejected.iter().map(|row| row.line).collect::<Vec<_>>(), vec![5, 6]);
+        assert!(report.rejected[0].message.contains("not a price"));
+        assert_eq!(report.categories_created, vec!["Spirits".to_string()]);
+
+        // With rows rejected nothing is kept, dry run or not
+        assert!(!catalog::import_products(&mut conn, &path, false, Some(1)).unwrap().applied);
+        assert_eq!(products::get_products(&conn, true).unwrap().len(), 3);
+        assert_eq!(categories::get_categories(&conn, true).unwrap().len(), 1);
+
+        let text = std::fs::read_to_string(&path).unwrap();
+        std::fs::write(&path, text.lines().take(4).collect::<Vec<_>>().join("\n")).unwrap();
+        let report = catalog::import_products(&mut conn, &path, false, Some(1)).unwrap();
+        assert!(report.applied);
+        assert_eq!(products::get_product(&conn, 1).unwrap().price, Money::from_minor(550));
+        let rakia = codes::find_product_by_code(&conn, "RAK-1".to_string()).unwrap().product;
+        assert_eq!((rakia.quantity, rakia.category_name.as_deref()), (20, Some("Spirits")));
+        let gin = products::get_products(&conn, false).unwrap().into_iter().find(|p| p.name.starts_with("Gin,")).unwrap();
+        assert_eq!((gin.unit, gin.serving_size, gin.price), (Unit::Cl, 40, Money::from_minor(450)));
+    }
+
+    #[test]
+    fn test_export_then_import_round_trips() {
+        let mut conn = setup_test_db();
+        seed_test_data(&conn);
+        codes::add_product_code(&conn, 2, None, "CORONA-33".to_string(), None).unwrap();
+        let dir = tempfile::tempdir().unwrap();
+
+        let json = dir.path().join("products.json");
+        assert_eq!(catalog::export_products(&conn, &json).unwrap(), 3);
+        let csv = dir.path().join("products.csv");
+        catalog::export_products(&conn, &csv).unwrap();
+        let exported = std::fs::read_to_string(&csv).unwrap();
+        assert!(exported.starts_with("name,sku,category,price,cost_price,quantity"));
+        assert!(exported.contains("Corona,CORONA-33,Beer,6.00,,50,"));
+
+        conn.execute("UPDATE products SET price = 999, name = 'Corona Extra' WHERE id = 2", []).unwrap();
+        let report = catalog::import_products(&mut conn, &json, false, None).unwrap();
+        assert!(report.applied);
+        assert_eq!((report.created.len(), report.updated.len()), (0, 3));
+        // Matched on its SKU, so the rename is undone rather than a copy made
+        let corona = products::get_product(&conn, 2).unwrap();
+        assert_eq!((corona.name.as_str(), corona.price), ("Corona", Money::from_minor(600)));
+
+        let xlsx = dir.path().join("products.xlsx");
+        assert_eq!(catalog::import_products(&mut conn, &xlsx, true, None).unwrap_err().code(), "VALIDATION");
+    }
+
+
+    // ===== SEARCH TESTS =====
+
+    fn search_names(conn: &Connection, query: &str, category_id: Option<i64>) -> Vec<String> {
+        products::search_products(conn, query, category_id, 10)
+            .unwrap()
+            .into_iter()
+            .map(|p| p.name)
+            .collect()
+    }
+
+    #[test]
+    fn test_search_products_matches_prefixes_without_diacritics() {
+        let mut conn = setup_test_db();
+        seed_test_data(&conn);
+        let mut tea = measured_product("Çaj Mali", 0, Unit::Piece, None);
+        tea.aliases = vec!["mountain tea".to_string()];
+        products::create_product(&mut conn, tea).unwrap();
+        let mut korca = measured_product("Birrë Korça", 0, Unit::Piece, None);
+        korca.codes = vec!["KOR-500".to_string()];
+        products::create_product(&mut conn, korca).unwrap();
+
+        assert_eq!(search_names(&conn, "caj", None), vec!["Çaj Mali"]);
+        assert_eq!(search_names(&conn, "BIRRE korc", None), vec!["Birrë Korça"]);
+        assert_eq!(search_names(&conn, "kor-50", None), vec!["Birrë Korça"]);
+        assert_eq!(search_names(&conn, "mount", None), vec!["Çaj Mali"]);
+        assert_eq!(search_names(&conn, "\"hein", None), vec!["Heineken"]);
+        assert_eq!(search_names(&conn, "", Some(1)).len(), 3);
+
+        // The index follows renames, category names and archiving
+        conn.execute("UPDATE products SET name = 'Amstel' WHERE id = 1", []).unwrap();
+        assert!(search_names(&conn, "hein", None).is_empty());
+        conn.execute("UPDATE categories SET name = 'Lager' WHERE id = 1", []).unwrap();
+        assert_eq!(search_names(&conn, "lager", None).len(), 3);
+        products::archive_product(&conn, 1).unwrap();
+        assert!(search_names(&conn, "amstel", None).is_empty());
+    }
+
+    #[test]
+    fn test_search_ranks_best_sellers_first() {
+        let mut conn = setup_test_db();
+        seed_test_data(&conn);
+        reports::start_day(&mut conn, 1).unwrap();
+        orders::create_order(&mut conn, new_order(1, &[(3, 3), (2, 1)])).unwrap();
+        orders::create_order(&mut conn, new_order(2, &[(2, 1)])).unwrap();
+
+        assert_eq!(search_names(&conn, "beer", None), vec!["Guinness", "Corona", "Heineken"]);
+        let top = products::search_products(&conn, "", None, 2).unwrap();
+        assert_eq!(top.iter().map(|p| p.id).collect::<Vec<_>>(), vec![3, 2]);
+
+        let temp = products::create_product(&mut conn, measured_product("Temp", 0, Unit::Piece, None)).unwrap();
+        assert_eq!(search_names(&conn, "temp", None), vec!["Temp"]);
+        products::archive_product(&conn, temp.id).unwrap();
+        products::purge_product(&mut conn, temp.id).unwrap();
+        let indexed: i64 = conn
+            .query_row("SELECT COUNT(*) FROM product_search WHERE rowid = ?1", [temp.id], |row| row.get(0))
+            .unwrap();
+        assert_eq!(indexed, 0);
+    }
+
+
+    // ===== AVAILABILITY TESTS =====
+
+    #[test]
+    fn test_unavailable_product_cannot_be_sold() {
+        let mut conn = setup_test_db();
+        seed_test_data(&conn);
+        reports::start_day(&mut conn, 1).unwrap();
+        let order = orders::create_order(&mut conn, new_order(1, &[(1, 1)])).unwrap();
+
+        let reason = Some(" Line cleaning ".to_string());
+        let heineken = availability::mark_product_unavailable(&conn, 1, reason, None).unwrap();
+        assert!(!heineken.available);
+        assert_eq!(heineken.unavailable_reason.as_deref(), Some("Line cleaning"));
+        assert_eq!(heineken.quantity, 99);
+
+        let err = orders::create_order(&mut conn, new_order(2, &[(2, 1), (1, 1)])).unwrap_err();
+        assert_eq!(err.code(), "PRODUCT_UNAVAILABLE");
+        assert_eq!(err.to_string(), "Heineken is unavailable: Line cleaning");
+        let items = new_order(1, &[(1, 1)]).items;
+        let err = orders::add_items_to_order(&mut conn, order.order.id, items).unwrap_err();
+        assert_eq!(err.code(), "PRODUCT_UNAVAILABLE");
+        let err = orders::increase_item_quantity(&mut conn, order.items[0].id).unwrap_err();
+        assert_eq!(err.code(), "PRODUCT_UNAVAILABLE");
+        assert_eq!(products::get_product(&conn, 2).unwrap().quantity, 50);
+
+        assert!(availability::mark_product_available(&conn, 1).unwrap().available);
+        orders::increase_item_quantity(&mut conn, order.items[0].id).unwrap();
+
+        // An 86'd ingredient stops the drinks made from it
+        let gt = seed_gin_and_tonic(&mut conn);
+        availability::mark_product_unavailable(&conn, 5, None, None).unwrap();
+        assert!(products::get_product(&conn, gt).unwrap().available);
+        match orders::create_order(&mut conn, new_order(3, &[(gt, 1)])).unwrap_err() {
+            AppError::ProductUnavailable { product_id, reason, .. } => assert_eq!((product_id, reason), (5, None)),
+            other => panic!("unexpected error: {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_unavailable_product_comes_back_at_restore_time() {
+        let conn = setup_test_db();
+        seed_test_data(&conn);
+
+        let until = |when: &str| availability::mark_product_unavailable(&conn, 1, None, Some(when.to_string()));
+        assert_eq!(until("tonight").unwrap_err().code(), "VALIDATION");
+        assert_eq!(until("2000-01-01 12:00").unwrap_err().code(), "VALIDATION");
+        let heineken = until("2999-01-01 12:00").unwrap();
+        assert!(!heineken.available && heineken.unavailable_until.is_some());
+        assert!(availability::restore_due_availability(&conn).unwrap().is_empty());
+
+        conn.execute("UPDATE products SET unavailable_until = datetime('now', '-1 minute') WHERE id = 1", []).unwrap();
+        // Sellable as soon as the time passes, before the timer tidies up
+        assert!(products::get_product(&conn, 1).unwrap().available);
+        let restored = availability::restore_due_availability(&conn).unwrap();
+        assert_eq!(restored.iter().map(|p| p.id).collect::<Vec<_>>(), vec![1]);
+        assert!(restored[0].unavailable_until.is_none());
+        assert!(availability::restore_due_availability(&conn).unwrap().is_empty());
+    }
+
+    // ===== REORDER TESTS =====
+
+    /// Run a paid order through its own trading session.
+    fn trade_session(conn: &mut Connection, items: &[(i64, i32)]) {
+        reports::start_day(conn, 1).unwrap();
+        let order = orders::create_order(conn, new_order(1, items)).unwrap();
+        orders::mark_order_paid(conn, order.order.id).unwrap();
+        reports::close_day(conn).unwrap();
+    }
+
+    #[test]
+    fn test_reorder_suggestions_use_sales_velocity_per_supplier() {
+        let mut conn = setup_test_db();
+        seed_test_data(&conn);
+        let supplier_id = seed_supplier(&conn);
+        let po = draft_po(&mut conn, supplier_id);
+        purchasing::send_purchase_order(&mut conn, po.purchase_order.id).unwrap();
+
+        trade_session(&mut conn, &[(1, 10), (2, 2), (3, 1)]);
+        trade_session(&mut conn, &[(1, 6), (2, 2)]);
+
+        let groups = reorder::get_reorder_suggestions(&conn, 5, 10, 14).unwrap();
+        assert_eq!(groups.len(), 2);
+
+        let tirana = &groups[0];
+        assert_eq!(tirana.supplier_name.as_deref(), Some("Birra Tirana"));
+        let names: Vec<&str> = tirana.lines.iter().map(|l| l.product_name.as_str()).collect();
+        assert_eq!(names, ["Heineken", "Corona"]);
+
+        // 16 over two sessions, 84 left and 24 on order; 15 days need 120
+        let heineken = &tirana.lines[0];
+        assert_eq!(heineken.daily_usage, 8.0);
+        assert_eq!(heineken.days_of_cover, 10.5);
+        assert_eq!(heineken.on_order, 24);
+        assert_eq!(heineken.reorder_point, 40);
+        assert_eq!(heineken.suggested_quantity, 12);
+        assert_eq!(tirana.lines[1].suggested_quantity, 0);
+        assert_eq!(tirana.estimated_cost, Money::from_minor(3000));
+
+        // Never ordered, so there is no supplier or cost to go on
+        let unassigned = &groups[1];
+        assert_eq!(unassigned.supplier_id, None);
+        assert_eq!(unassigned.lines[0].product_name, "Guinness");
+        assert_eq!(unassigned.lines[0].suggested_quantity, 6);
+        assert_eq!(unassigned.estimated_cost, Money::zero());
+
+        // Only the latest session counted
+        let groups = reorder::get_reorder_suggestions(&conn, 5, 10, 1).unwrap();
+        assert_eq!(groups[0].lines[0].daily_usage, 6.0);
+        assert_eq!(groups.len(), 1);
+    }
+
+    #[test]
+    fn test_tune_low_stock_thresholds_to_reorder_point() {
+        let mut conn = setup_test_db();
+        seed_test_data(&conn);
+
+        assert!(reorder::tune_low_stock_thresholds(&mut conn, 2, 14).unwrap().is_empty());
+        assert_eq!(reorder::get_reorder_suggestions(&conn, 2, 7, 0).unwrap_err().code(), "VALIDATION");
+
+        trade_session(&mut conn, &[(1, 3)]);
+
+        let tuned = reorder::tune_low_stock_thresholds(&mut conn, 2, 14).unwrap();
+        assert_eq!(tuned.len(), 1);
+        assert_eq!(tuned[0].name, "Heineken");
+        assert_eq!(tuned[0].low_stock_threshold, 6);
+
+        // Products without recent sales keep their hand-entered threshold
+        let corona = products::get_product(&conn, 2).unwrap();
+        assert_eq!(corona.low_stock_threshold, 5);
+
+        // Already tuned
+        assert!(reorder::tune_low_stock_thresholds(&mut conn, 2, 14).unwrap().is_empty());
+    }
+
+    // ===== LOT TESTS =====
+
+    fn stock_lot(product_id: i64, quantity: i32, expires_on: Option<String>, keg_id: Option<&str>) -> CreateStockLot {
+        CreateStockLot {
+            product_id,
+            quantity,
+            expires_on,
+            keg_id: keg_id.map(str::to_string),
+            staff_id: Some(1),
+            reference: None,
+        }
+    }
+
+    fn days_from_today(days: i64) -> String {
+        (chrono::Local::now().date_naive() + chrono::Duration::days(days)).format("%Y-%m-%d").to_string()
+    }
+
+    #[test]
+    fn test_lots_are_sold_first_expired_first_out() {
+        let mut conn = setup_test_db();
+        seed_test_data(&conn);
+        reports::start_day(&mut conn, 1).unwrap();
+
+        let later = lots::receive_stock_lot(&mut conn, stock_lot(1, 10, Some(days_from_today(20)), None)).unwrap();
+        let sooner = lots::receive_stock_lot(&mut conn, stock_lot(1, 5, Some(days_from_today(3)), None)).unwrap();
+        assert_eq!(stock_of(&conn, 1), 115);
+
+        let order = orders::create_order(&mut conn, new_order(1, &[(1, 7)])).unwrap();
+        let quantities = |conn: &Connection| -> Vec<(i64, i32)> {
+            lots::get_stock_lots(conn, 1).unwrap().iter().map(|l| (l.id, l.quantity)).collect()
+        };
+        assert_eq!(quantities(&conn), [(later.id, 8)]);
+
+        // A return goes back to a lot the order drew from
+        orders::decrease_item_quantity(&mut conn, order.items[0].id).unwrap();
+        assert_eq!(quantities(&conn), [(sooner.id, 1), (later.id, 8)]);
+        assert_eq!(stock_of(&conn, 1), 109);
+
+        let expiring: Vec<i64> = lots::get_expiring_lots(&conn, 7).unwrap().iter().map(|l| l.id).collect();
+        assert_eq!(expiring, [sooner.id]);
+        assert_eq!(lots::get_expiring_lots(&conn, 30).unwrap().len(), 2);
+
+        let misdated = stock_lot(1, 5, Some("31/12/2026".to_string()), None);
+        assert_eq!(lots::receive_stock_lot(&mut conn, misdated).unwrap_err().code(), "VALIDATION");
+        assert_eq!(stock_of(&conn, 1), 109);
+    }
+
+    #[test]
+    fn test_keg_tap_and_retire_reports_yield() {
+        let mut conn = setup_test_db();
+        seed_test_data(&conn);
+        reports::start_day(&mut conn, 1).unwrap();
+
+        // 20 l kegs poured in 500 ml pints
+        let lager = products::create_product(&mut conn, measured_product("Lager", 0, Unit::L, Some(500))).unwrap();
+        let first = lots::receive_stock_lot(&mut conn, stock_lot(lager.id, 20000, None, Some("K-17"))).unwrap();
+        let second = lots::receive_stock_lot(&mut conn, stock_lot(lager.id, 20000, None, Some("K-18"))).unwrap();
+        let err = lots::receive_stock_lot(&mut conn, stock_lot(lager.id, 20000, None, Some("K-17"))).unwrap_err();
+        assert_eq!(err.code(), "VALIDATION");
+
+        let first = lots::tap_keg(&conn, first.id).unwrap();
+        assert!(first.tapped_at.is_some());
+        assert_eq!(lots::tap_keg(&conn, first.id).unwrap_err().code(), "VALIDATION");
+
+        orders::create_order(&mut conn, new_order(1, &[(lager.id, 36)])).unwrap();
+        assert_eq!(lots::get_lot(&conn, first.id).unwrap().quantity, 2000);
+        assert_eq!(lots::get_lot(&conn, second.id).unwrap().quantity, 20000);
+
+        // The last two litres went to foam
+        let keg_yield = lots::retire_keg(&mut conn, first.id, Some(1)).unwrap();
+        assert_eq!(keg_yield.poured, 18000);
+        assert_eq!(keg_yield.servings_poured, 36.0);
+        assert_eq!(keg_yield.theoretical_servings, 40.0);
+        assert_eq!(keg_yield.yield_percent, 90.0);
+        assert_eq!(keg_yield.loss, 2000);
+        assert!(keg_yield.lot.retired_at.is_some());
+        assert_eq!(stock_of(&conn, lager.id), 20000);
+        assert_eq!(lots::retire_keg(&mut conn, first.id, Some(1)).unwrap_err().code(), "VALIDATION");
+
+        // Pouring before anyone taps the next keg taps it
+        orders::create_order(&mut conn, new_order(2, &[(lager.id, 1)])).unwrap();
+        let second = lots::get_lot(&conn, second.id).unwrap();
+        assert!(second.tapped_at.is_some());
+        assert_eq!(second.quantity, 19500);
+    }
+
+    // ===== LOCATION TESTS =====
+
+    fn transfer(product_id: i64, from_location_id: i64, to_location_id: i64, quantity: i32) -> TransferStock {
+        TransferStock {
+            product_id,
+            from_location_id,
+            to_location_id,
+            quantity,
+            staff_id: Some(1),
+            note: None,
+        }
+    }
+
+    fn stock_by_location(conn: &Connection, product_id: i64) -> Vec<(String, i32)> {
+        locations::get_stock_by_location(conn, product_id)
+            .unwrap()
+            .into_iter()
+            .map(|q| (q.location_name, q.quantity))
+            .collect()
+    }
+
+    #[test]
+    fn test_transfers_and_orders_move_stock_per_location() {
+        let mut conn = setup_test_db();
+        seed_test_data(&conn);
+        reports::start_day(&mut conn, 1).unwrap();
+        let bar = locations::get_stock_locations(&conn).unwrap()[0].id;
+        let cellar = locations::create_stock_location(&conn, "Cellar".to_string()).unwrap().id;
+        let terrace = locations::create_stock_location(&conn, "Terrace".to_string()).unwrap().id;
+
+        locations::transfer_stock(&mut conn, transfer(1, bar, cellar, 24)).unwrap();
+        let moved = locations::transfer_stock(&mut conn, transfer(1, cellar, terrace, 6)).unwrap();
+        assert_eq!(
+            moved.into_iter().map(|q| q.quantity).collect::<Vec<_>>(),
+            [76, 18, 6],
+            "Bar, Cellar, Terrace"
+        );
+        assert_eq!(stock_of(&conn, 1), 100);
+
+        let history = stock::get_stock_movements(&conn, 1, None, None).unwrap();
+        assert_eq!(history[0].reason, StockReason::Transfer);
+        assert_eq!((history[0].location_name.as_str(), history[0].quantity_change), ("Terrace", 6));
+        assert_eq!((history[1].location_name.as_str(), history[1].quantity_change), ("Cellar", -6));
+
+        let err = locations::transfer_stock(&mut conn, transfer(1, cellar, bar, 30)).unwrap_err();
+        assert!(matches!(err, AppError::InsufficientStock { available: 18, .. }));
+
+        // The terrace sells from its own fridge, not the bar's
+        let on_terrace = CreateOrder { location_id: Some(terrace), ..new_order(5, &[(1, 2)]) };
+        let order = orders::create_order(&mut conn, on_terrace).unwrap();
+        orders::decrease_item_quantity(&mut conn, order.items[0].id).unwrap();
+        assert_eq!(
+            stock_by_location(&conn, 1),
+            [("Bar".to_string(), 76), ("Cellar".to_string(), 18), ("Terrace".to_string(), 5)]
+        );
+
+        let err = orders::create_order(&mut conn, new_order(1, &[(1, 80)])).unwrap_err();
+        assert!(matches!(err, AppError::InsufficientStock { available: 76, .. }));
+        assert_eq!(stock_of(&conn, 1), 99);
+    }
+
+    #[test]
+    fn test_low_stock_per_location_points_to_other_stock() {
+        let mut conn = setup_test_db();
+        seed_test_data(&conn);
+        let bar = locations::get_stock_locations(&conn).unwrap()[0].id;
+        let cellar = locations::create_stock_location(&conn, "Cellar".to_string()).unwrap().id;
+        let terrace = locations::create_stock_location(&conn, "Terrace".to_string()).unwrap().id;
+        locations::transfer_stock(&mut conn, transfer(1, bar, cellar, 96)).unwrap();
+
+        // "Cellar has 96, move them up"
+        let low = locations::get_location_low_stock(&conn, bar).unwrap();
+        let names: Vec<&str> = low.iter().map(|l| l.product_name.as_str()).collect();
+        assert_eq!(names, ["Guinness", "Heineken"]);
+        assert!(low[0].elsewhere.is_empty());
+        assert_eq!((low[1].quantity, low[1].low_stock_threshold), (4, 10));
+        assert_eq!(low[1].elsewhere[0].location_id, cellar);
+        assert_eq!(low[1].elsewhere[0].quantity, 96);
+
+        // Only products watched at a location are checked there
+        assert!(locations::get_location_low_stock(&conn, terrace).unwrap().is_empty());
+        locations::set_location_threshold(&conn, terrace, 2, Some(6)).unwrap();
+        let low = locations::get_location_low_stock(&conn, terrace).unwrap();
+        assert_eq!((low[0].product_name.as_str(), low[0].quantity), ("Corona", 0));
+        assert_eq!(low[0].elsewhere[0].location_id, bar);
+
+        locations::set_location_threshold(&conn, terrace, 2, None).unwrap();
+        assert!(locations::get_location_low_stock(&conn, terrace).unwrap().is_empty());
+        assert_eq!(locations::set_location_threshold(&conn, bar, 2, None).unwrap_err().code(), "VALIDATION");
+    }
+
+    #[test]
+    fn test_stock_take_counts_one_location() {
+        let mut conn = setup_test_db();
+        seed_test_data(&conn);
+        let bar = locations::get_stock_locations(&conn).unwrap()[0].id;
+        let cellar = locations::create_stock_location(&conn, "Cellar".to_string()).unwrap().id;
+        locations::transfer_stock(&mut conn, transfer(2, bar, cellar, 50)).unwrap();
+
+        // Both can be counted at once; each compares against its own shelf
+        let in_cellar = stock_takes::start_stock_take(&mut conn, 1, Some(cellar), None).unwrap().stock_take;
+        assert_eq!(in_cellar.location_name, "Cellar");
+        let at_bar = stock_takes::start_stock_take(&mut conn, 2, None, None).unwrap().stock_take;
+        assert!(stock_takes::start_stock_take(&mut conn, 2, Some(cellar), None).is_err());
+
+        let take = stock_takes::record_stock_count(&mut conn, in_cellar.id, 2, 1, 46).unwrap();
+        assert_eq!((take.lines[0].system_quantity, take.lines[0].variance), (50, -4));
+        stock_takes::commit_stock_take(&mut conn, in_cellar.id, 1).unwrap();
+        stock_takes::record_stock_count(&mut conn, at_bar.id, 2, 2, 0).unwrap();
+        stock_takes::commit_stock_take(&mut conn, at_bar.id, 2).unwrap();
+
+        assert_eq!(stock_by_location(&conn, 2), [("Bar".to_string(), 0), ("Cellar".to_string(), 46)]);
+        let movements = stock::get_stock_movements(&conn, 2, None, None).unwrap();
+        assert_eq!((movements[0].reason, movements[0].location_name.as_str()), (StockReason::StockTake, "Cellar"));
+    }
+
+    #[test]
+    fn test_retiring_a_keg_writes_off_where_it_was_poured() {
+        let mut conn = setup_test_db();
+        seed_test_data(&conn);
+        reports::start_day(&mut conn, 1).unwrap();
+        let bar = locations::get_stock_locations(&conn).unwrap()[0].id;
+        let cellar = locations::create_stock_location(&conn, "Cellar".to_string()).unwrap().id;
+        let lager = products::create_product(&mut conn, measured_product("Lager", 0, Unit::L, Some(500))).unwrap();
+        let keg = lots::receive_stock_lot(&mut conn, stock_lot(lager.id, 20000, None, Some("K-1"))).unwrap();
+        locations::transfer_stock(&mut conn, transfer(lager.id, bar, cellar, 20000)).unwrap();
+
+        let in_cellar = CreateOrder { location_id: Some(cellar), ..new_order(1, &[(lager.id, 36)]) };
+        orders::create_order(&mut conn, in_cellar).unwrap();
+        let keg_yield = lots::retire_keg(&mut conn, keg.id, Some(1)).unwrap();
+        assert_eq!(keg_yield.loss, 2000);
+
+        assert_eq!(stock_by_location(&conn, lager.id), [("Bar".to_string(), 0), ("Cellar".to_string(), 0)]);
+        let movements = stock::get_stock_movements(&conn, lager.id, None, None).unwrap();
+        assert_eq!((movements[0].reason, movements[0].location_name.as_str()), (StockReason::Waste, "Cellar"));
+    }
+
+    #[test]
+    fn test_kegs_are_drawn_and_retired_where_they_are() {
+        let mut conn = setup_test_db();
+        seed_test_data(&conn);
+        reports::start_day(&mut conn, 1).unwrap();
+        let bar = locations::get_stock_locations(&conn).unwrap()[0].id;
+        let cellar = locations::create_stock_location(&conn, "Cellar".to_string()).unwrap().id;
+        let lager = products::create_product(&mut conn, measured_product("Lager", 0, Unit::L, Some(500))).unwrap();
+
+        // K-1 goes down to the cellar untouched; K-2 stays at the bar
+        let k1 = lots::receive_stock_lot(&mut conn, stock_lot(lager.id, 20000, None, Some("K-1"))).unwrap();
+        locations::transfer_stock(&mut conn, transfer(lager.id, bar, cellar, 20000)).unwrap();
+        let k2 = lots::receive_stock_lot(&mut conn, stock_lot(lager.id, 10000, None, Some("K-2"))).unwrap();
+        assert_eq!(lots::get_lot(&conn, k1.id).unwrap().location_name, "Cellar");
+
+        // Pints at the bar come out of the keg at the bar
+        orders::create_order(&mut conn, new_order(1, &[(lager.id, 2)])).unwrap();
+        let (k1, k2) = (lots::get_lot(&conn, k1.id).unwrap(), lots::get_lot(&conn, k2.id).unwrap());
+        assert_eq!((k1.quantity, k1.tapped_at.is_some()), (20000, false));
+        assert_eq!((k2.quantity, k2.tapped_at.is_some()), (9000, true));
+
+        let keg_yield = lots::retire_keg(&mut conn, k1.id, Some(1)).unwrap();
+        assert_eq!((keg_yield.poured, keg_yield.loss), (0, 20000));
+        assert_eq!(stock_by_location(&conn, lager.id), [("Bar".to_string(), 9000), ("Cellar".to_string(), 0)]);
+        let movements = stock::get_stock_movements(&conn, lager.id, None, None).unwrap();
+        assert_eq!((movements[0].reason, movements[0].location_name.as_str()), (StockReason::Waste, "Cellar"));
+
+        // Part of a keg moves as a loose lot
+        locations::transfer_stock(&mut conn, transfer(lager.id, bar, cellar, 1000)).unwrap();
+        let lots = lots::get_stock_lots(&conn, lager.id).unwrap();
+        let moved: Vec<_> = lots.iter().map(|l| (l.keg_id.as_deref(), l.quantity, l.location_name.as_str())).collect();
+        assert_eq!(moved, vec![(Some("K-2"), 8000, "Bar"), (None, 1000, "Cellar")]);
+    }
+
+    #[test]
+    fn test_rebuild_stock_from_ledger_rebuilds_each_location() {
+        let mut conn = setup_test_db();
+        seed_test_data(&conn);
+        conn.execute(
+            "INSERT INTO stock_movements (product_id, quantity_change, reason) SELECT id, quantity, 'opening_balance' FROM products",
+            [],
+        )
+        .unwrap();
+        let bar = locations::get_stock_locations(&conn).unwrap()[0].id;
+        let cellar = locations::create_stock_location(&conn, "Cellar".to_string()).unwrap().id;
+        locations::transfer_stock(&mut conn, transfer(1, bar, cellar, 24)).unwrap();
+
+        // Both the total and the cellar drift; the bar's remainder follows them
+        conn.execute("UPDATE products SET quantity = 90 WHERE id = 1", []).unwrap();
+        conn.execute("UPDATE location_stock SET quantity = 30 WHERE product_id = 1", []).unwrap();
+        stock::rebuild_stock_from_ledger(&mut conn).unwrap();
+        assert_eq!(stock_by_location(&conn, 1), [("Bar".to_string(), 76), ("Cellar".to_string(), 24)]);
+
+        // A ledger that takes more out of the cellar than went in cannot be rebuilt
+        conn.execute(
+            "INSERT INTO stock_movements (product_id, quantity_change, reason, location_id)
+             VALUES (1, -30, 'waste', ?1)",
+            [cellar],
+        )
+        .unwrap();
+        let err = stock::rebuild_stock_from_ledger(&mut conn).unwrap_err();
+        assert_eq!(err.code(), "VALIDATION");
+        assert_eq!(stock_of(&conn, 1), 100);
+    }
+
+    // ===== CATEGORY TREE TESTS =====
+
+    fn move_category(id: i64, name: &str, parent_id: Option<i64>) -> UpdateCategory {
+        UpdateCategory {
+            id,
+            name: name.to_string(),
+            parent_id,
+            color: None,
+            icon: None,
+        }
+    }
+
+    fn category_names(conn: &Connection) -> Vec<String> {
+        categories::get_categories(conn, false).unwrap().into_iter().map(|c| c.name).collect()
+    }
+
+    #[test]
+    fn test_category_tree_ordering_and_cycle_protection() {
+        let mut conn = setup_test_db();
+        seed_test_data(&conn);
+        let draught = categories::create_category(&conn, "Draught".to_string()).unwrap().id;
+        let bottled = categories::create_category(&conn, "Bottled".to_string()).unwrap().id;
+        let wine = categories::create_category(&conn, "Wine".to_string()).unwrap().id;
+
+        let styled = UpdateCategory {
+            color: Some("#FFAA00".to_string()),
+            icon: Some("beer".to_string()),
+            ..move_category(draught, "Draught", Some(1))
+        };
+        let moved = categories::update_category(&mut conn, styled).unwrap();
+        assert_eq!((moved.parent_id, moved.color.as_deref()), (Some(1), Some("#ffaa00")));
+        categories::update_category(&mut conn, move_category(bottled, "Bottled", Some(1))).unwrap();
+        categories::update_category(&mut conn, move_category(wine, "Wines", None)).unwrap();
+        assert_eq!(category_names(&conn), ["Beer", "Draught", "Bottled", "Wines"]);
+
+        categories::reorder_categories(&mut conn, Some(1), vec![bottled]).unwrap();
+        let all = categories::reorder_categories(&mut conn, None, vec![wine]).unwrap();
+        let names: Vec<&str> = all.iter().map(|c| c.name.as_str()).collect();
+        assert_eq!(names, ["Wines", "Beer", "Bottled", "Draught"]);
+
+        // Beer cannot go under its own subcategory, or under itself
+        for parent in [draught, 1] {
+            let err = categories::update_category(&mut conn, move_category(1, "Beer", Some(parent))).unwrap_err();
+            assert_eq!(err.code(), "VALIDATION");
+        }
+        let err = categories::reorder_categories(&mut conn, None, vec![draught]).unwrap_err();
+        assert_eq!(err.code(), "VALIDATION");
+        let err = categories::update_category(
+            &mut conn,
+            UpdateCategory { color: Some("orange".to_string()), ..move_category(wine, "Wines", None) },
+        )
+        .unwrap_err();
+        assert_eq!(err.code(), "VALIDATION");
+
+        assert_eq!(categories::archive_category(&conn, 1).unwrap_err().code(), "VALIDATION");
+        assert_eq!(category_names(&conn), ["Wines", "Beer", "Bottled", "Draught"]);
+    }
+
+    #[test]
+    fn test_category_margins_roll_up_the_tree() {
+        let mut conn = setup_test_db();
+        seed_test_data(&conn);
+        let bottled = categories::create_category(&conn, "Bottled".to_string()).unwrap().id;
+        let imported = categories::create_category(&conn, "Imported".to_string()).unwrap().id;
+        categories::update_category(&mut conn, move_category(bottled, "Bottled", Some(1))).unwrap();
+        categories::update_category(&mut conn, move_category(imported, "Imported", Some(bottled))).unwrap();
+        conn.execute("UPDATE products SET category_id = ?1 WHERE id = 2", [bottled]).unwrap();
+        conn.execute("UPDATE products SET category_id = ?1 WHERE id = 3", [imported]).unwrap();
+
+        reports::start_day(&mut conn, 1).unwrap();
+        orders::create_order(&mut conn, new_order(1, &[(1, 2), (2, 1), (3, 1)])).unwrap();
+
+        let today = chrono::Local::now().format("%Y-%m-%d").to_string();
+        let revenue_by = |depth| -> Vec<(String, i64)> {
+            let mut rows: Vec<(String, i64)> = reports::get_category_margins(&conn, &today, &today, depth)
+                .unwrap()
+                .into_iter()
+                .map(|c| (c.category_name.unwrap(), c.revenue.minor_units))
+                .collect();
+            rows.sort();
+            rows
+        };
+
+        let leaves = [("Beer".to_string(), 1000), ("Bottled".to_string(), 600), ("Imported".to_string(), 700)];
+        assert_eq!(revenue_by(None), leaves);
+        assert_eq!(revenue_by(Some(0)), [("Beer".to_string(), 2300)]);
+        assert_eq!(revenue_by(Some(1)), [("Beer".to_string(), 1000), ("Bottled".to_string(), 1300)]);
+        assert_eq!(revenue_by(Some(5)), leaves);
+    }
+
+    #[test]
+    fn test_sales_roll_up_the_tree() {
+        let mut conn = setup_test_db();
+        seed_test_data(&conn);
+        let stout = categories::create_category(&conn, "Stout".to_string()).unwrap().id;
+        categories::update_category(&mut conn, move_category(stout, "Stout", Some(1))).unwrap();
+        conn.execute("UPDATE products SET category_id = ?1 WHERE id = 3", [stout]).unwrap();
+
+        reports::start_day(&mut conn, 1).unwrap();
+        orders::create_order(&mut conn, new_order(1, &[(1, 2), (2, 1), (3, 1)])).unwrap();
+
+        let rows = |sales: Vec<CategorySales>| -> Vec<(String, i32, i64)> {
+            sales.into_iter().map(|c| (c.category_name.unwrap(), c.quantity, c.revenue.minor_units)).collect()
+        };
+
+        let summary = reports::get_day_summary(&conn, None, None).unwrap();
+        let guinness = summary.product_sales.iter().find(|p| p.product_id == 3).unwrap();
+        assert_eq!((guinness.category_id, guinness.category_name.as_deref()), (Some(stout), Some("Stout")));
+        assert_eq!(
+            rows(summary.category_sales),
+            [("Beer".to_string(), 3, 1600), ("Stout".to_string(), 1, 700)]
+        );
+        let summary = reports::get_day_summary(&conn, None, Some(0)).unwrap();
+        assert_eq!(rows(summary.category_sales), [("Beer".to_string(), 4, 2300)]);
+
+        let today = chrono::Local::now().format("%Y-%m-%d").to_string();
+        assert_eq!(
+            rows(reports::get_category_sales(&conn, &today, &today, Some(0)).unwrap()),
+            [("Beer".to_string(), 4, 2300)]
+        );
+        assert_eq!(rows(reports::get_category_sales(&conn, &today, &today, Some(1)).unwrap()).len(), 2);
+    }
+
+
+    // ===== TAX TESTS =====
+
+    fn tax_rate(conn: &Connection, name: &str, basis_points: i32, included: bool) -> i64 {
+        taxes::create_tax_rate(conn, CreateTaxRate { name: name.to_string(), basis_points, included })
+            .unwrap()
+            .id
+    }
+
+    fn line_taxes(order: &OrderWithItems) -> Vec<(i64, Option<String>, i64)> {
+        let mut lines: Vec<_> = order
+            .items
+            .iter()
+            .map(|i| (i.product_id, i.tax_name.clone(), i.tax_amount.minor_units))
+            .collect();
+        lines.sort();
+        lines
+    }
+
+    #[test]
+    fn test_order_lines_snapshot_the_tax_rate_they_sold_under() {
+        let mut conn = setup_test_db();
+        seed_test_data(&conn);
+        let vat = tax_rate(&conn, "VAT", 2000, true);
+        let reduced = tax_rate(&conn, "Reduced", 600, false);
+        let imported = categories::create_category(&conn, "Imported".to_string()).unwrap().id;
+        categories::update_category(&mut conn, move_category(imported, "Imported", Some(1))).unwrap();
+        conn.execute("UPDATE products SET category_id = ?1 WHERE id = 3", [imported]).unwrap();
+
+        // Guinness takes VAT from Beer, two levels up; Corona has its own rate
+        taxes::set_category_tax_rate(&conn, 1, Some(vat)).unwrap();
+        let corona = taxes::set_product_tax_rate(&conn, 2, Some(reduced)).unwrap();
+        assert_eq!(corona.tax_rate_id, Some(reduced));
+
+        reports::start_day(&mut conn, 1).unwrap();
+        let order = orders::create_order(&mut conn, new_order(1, &[(1, 2), (2, 1), (3, 1)])).unwrap();
+        let vat_name = Some("VAT".to_string());
+        let reduced_name = Some("Reduced".to_string());
+        assert_eq!(
+            line_taxes(&order),
+            [(1, vat_name.clone(), 167), (2, reduced_name.clone(), 36), (3, vat_name.clone(), 117)]
+        );
+        // Only the excluded tax is added to the prices
+        assert_eq!(order.order.total.minor_units, 1000 + 600 + 36 + 700);
+
+        // A new rate applies to new lines; sold lines keep theirs as they grow
+        taxes::update_tax_rate(
+            &conn,
+            UpdateTaxRate { id: vat, name: "VAT".to_string(), basis_points: 1000, included: true },
+        )
+        .unwrap();
+        let heineken = order.items.iter().find(|i| i.product_id == 1).unwrap().id;
+        orders::increase_item_quantity(&mut conn, heineken).unwrap();
+        let corona_line = order.items.iter().find(|i| i.product_id == 2).unwrap().id;
+        let order = orders::increase_item_quantity(&mut conn, corona_line).unwrap();
+        assert_eq!(
+            line_taxes(&order),
+            [(1, vat_name.clone(), 250), (2, reduced_name, 72), (3, vat_name, 117)]
+        );
+        assert_eq!(order.order.total.minor_units, 1500 + 1200 + 72 + 700);
+
+        let err = taxes::delete_tax_rate(&conn, vat).unwrap_err();
+        assert_eq!(err.code(), "VALIDATION");
+        let negative = CreateTaxRate { name: "Bad".to_string(), basis_points: -1, included: true };
+        let err = taxes::create_tax_rate(&conn, negative).unwrap_err();
+        assert_eq!(err.code(), "VALIDATION");
+    }
+
+    #[test]
+    fn test_close_day_reports_net_tax_and_gross_per_rate() {
+        let mut conn = setup_test_db();
+        seed_test_data(&conn);
+        let vat = tax_rate(&conn, "VAT", 2000, true);
+        let reduced = tax_rate(&conn, "Reduced", 600, false);
+        taxes::set_category_tax_rate(&conn, 1, Some(vat)).unwrap();
+        taxes::set_product_tax_rate(&conn, 2, Some(reduced)).unwrap();
+        conn.execute("UPDATE products SET category_id = NULL WHERE id = 3", []).unwrap();
+
+        reports::start_day(&mut conn, 1).unwrap();
+        let order = orders::create_order(&mut conn, new_order(1, &[(1, 2), (2, 1), (3, 1)])).unwrap();
+        orders::mark_order_paid(&mut conn, order.order.id).unwrap();
+        let session = reports::close_day(&mut conn).unwrap();
+
+        let rows: Vec<(Option<&str>, i32, i64, i64, i64)> = session
+            .taxes
+            .iter()
+            .map(|t| (t.tax_name.as_deref(), t.basis_points, t.net.minor_units, t.tax.minor_units, t.gross.minor_units))
+            .collect();
+        assert_eq!(
+            rows,
+            [(Some("VAT"), 2000, 833, 167, 1000), (Some("Reduced"), 600, 600, 36, 636), (None, 0, 700, 0, 700)]
+        );
+        let gross: i64 = session.taxes.iter().map(|t| t.gross.minor_units).sum();
+        assert_eq!(Some(Money::from_minor(gross)), session.total_revenue);
+
+        let summary = reports::get_day_summary(&conn, Some(session.id), None).unwrap();
+        assert_eq!(summary.taxes.len(), 3);
+        assert_eq!(summary.taxes[1].tax.minor_units, 36);
+    }
 }
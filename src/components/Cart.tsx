@@ -1,4 +1,5 @@
 import { CartItem } from "../types";
+import { formatMoney, lineTotal, sumMoney } from "../utils/money";
 
 interface CartProps {
   items: CartItem[];
@@ -23,9 +24,8 @@ export function Cart({
   orderNotes,
   onOrderNotesChange,
 }: CartProps) {
-  const total = items.reduce(
-    (sum, item) => sum + item.product.price * item.quantity,
-    0
+  const total = sumMoney(
+    items.map((item) => lineTotal(item.product.price, item.quantity))
   );
 
   return (
@@ -41,7 +41,7 @@ export function Cart({
                 <div className="cart-item-info">
                   <span className="cart-item-name">{item.product.name}</span>
                   <span className="cart-item-price">
-                    {(item.product.price * item.quantity).toFixed(0)} ALL
+                    {formatMoney(lineTotal(item.product.price, item.quantity))}
                   </span>
                 </div>
                 <div className="cart-item-controls">
@@ -57,7 +57,7 @@ export function Cart({
                     onClick={() =>
                       onUpdateQuantity(item.product.id, item.quantity + 1)
                     }
-                    disabled={item.quantity >= item.product.quantity}
+                    disabled={item.quantity >= item.product.servings}
                   >
                     +
                   </button>
@@ -73,7 +73,7 @@ export function Cart({
           </div>
           <div className="cart-total">
             <span>Total:</span>
-            <span>{total.toFixed(0)} ALL</span>
+            <span>{formatMoney(total)}</span>
           </div>
           <div className="cart-order-info">
             <input
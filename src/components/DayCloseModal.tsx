@@ -1,10 +1,11 @@
-import { DaySummary } from "../types";
+import { DaySummary, Money } from "../types";
 import { exportToCSV, exportToText, exportToExcel } from "../utils/exportReport";
+import { formatMoney, lineTotal, sumMoney } from "../utils/money";
 
 interface ProductSummary {
   name: string;
   quantity: number;
-  revenue: number;
+  revenue: Money;
 }
 
 interface DayCloseModalProps {
@@ -32,19 +33,19 @@ export function DayCloseModal({
       const existing = productMap.get(name);
       if (existing) {
         existing.quantity += item.quantity;
-        existing.revenue += item.price_at_sale * item.quantity;
+        existing.revenue = sumMoney([existing.revenue, lineTotal(item.price_at_sale, item.quantity)]);
       } else {
         productMap.set(name, {
           name,
           quantity: item.quantity,
-          revenue: item.price_at_sale * item.quantity,
+          revenue: lineTotal(item.price_at_sale, item.quantity),
         });
       }
     });
   });
 
   productMap.forEach((value) => productSummary.push(value));
-  productSummary.sort((a, b) => b.revenue - a.revenue);
+  productSummary.sort((a, b) => b.revenue.minor_units - a.revenue.minor_units);
 
   return (
     <div className="modal-overlay">
@@ -60,7 +61,7 @@ export function DayCloseModal({
           <div className="stat">
             <span className="stat-label">Revenue</span>
             <span className="stat-value">
-              {summary.total_revenue.toFixed(0)} ALL
+              {formatMoney(summary.total_revenue)}
             </span>
           </div>
         </div>
@@ -81,7 +82,33 @@ export function DayCloseModal({
                   <tr key={p.name}>
                     <td>{p.name}</td>
                     <td>{p.quantity}</td>
-                    <td>{p.revenue.toFixed(0)} ALL</td>
+                    <td>{formatMoney(p.revenue)}</td>
+                  </tr>
+                ))}
+              </tbody>
+            </table>
+          </div>
+        )}
+
+        {summary.taxes.length > 0 && (
+          <div className="day-close-summary">
+            <h4>Taxes</h4>
+            <table className="product-summary-table">
+              <thead>
+                <tr>
+                  <th>Rate</th>
+                  <th>Net</th>
+                  <th>Tax</th>
+                  <th>Gross</th>
+                </tr>
+              </thead>
+              <tbody>
+                {summary.taxes.map((t) => (
+                  <tr key={t.tax_name ?? ""}>
+                    <td>{t.tax_name ? `${t.tax_name} ${t.basis_points / 100}%` : "Untaxed"}</td>
+                    <td>{formatMoney(t.net)}</td>
+                    <td>{formatMoney(t.tax)}</td>
+                    <td>{formatMoney(t.gross)}</td>
                   </tr>
                 ))}
               </tbody>
@@ -1,4 +1,5 @@
 import { DaySummary } from "../types";
+import { formatMoney } from "../utils/money";
 
 interface DayClosingProps {
   summary: DaySummary | null;
@@ -23,7 +24,7 @@ export function DayClosingComponent({
         </div>
         <div className="stat">
           <span className="stat-label">Total Revenue</span>
-          <span className="stat-value">{summary.total_revenue.toFixed(0)} ALL</span>
+          <span className="stat-value">{formatMoney(summary.total_revenue)}</span>
         </div>
       </div>
       {summary.orders.length > 0 && (
@@ -34,7 +35,7 @@ export function DayClosingComponent({
               <div className="order-header">
                 <span>#{o.order.id}</span>
                 <span>{o.order.staff_name}</span>
-                <span>{o.order.total.toFixed(0)} ALL</span>
+                <span>{formatMoney(o.order.total)}</span>
               </div>
               <div className="order-items">
                 {o.items.map((item) => (
@@ -1,4 +1,5 @@
 import { Product } from "../types";
+import { formatQuantity } from "../utils/units";
 
 interface LowStockAlertProps {
   products: Product[];
@@ -17,7 +18,7 @@ export function LowStockAlert({ products, onDismiss }: LowStockAlertProps) {
       <ul>
         {products.map((p) => (
           <li key={p.id}>
-            {p.name}: {p.quantity} left
+            {p.name}: {formatQuantity(p.quantity, p.unit)} left
           </li>
         ))}
       </ul>
@@ -1,4 +1,5 @@
 import { OrderWithItems } from "../types";
+import { formatMoney, lineTotal, toMajor } from "../utils/money";
 
 interface OrderCardProps {
   order: OrderWithItems;
@@ -29,7 +30,7 @@ export function OrderCard({ order }: OrderCardProps) {
               <span className="item-qty">{item.quantity}x</span>
               <span className="item-name">{item.product_name}</span>
               <span className="item-price">
-                {(item.price_at_sale * item.quantity).toFixed(0)}
+                {toMajor(lineTotal(item.price_at_sale, item.quantity)).toFixed(0)}
               </span>
             </div>
           ))}
@@ -46,7 +47,7 @@ export function OrderCard({ order }: OrderCardProps) {
         <div className="order-staff-info">
           <span className="staff-label">Staff:</span> {o.staff_name}
         </div>
-        <div className="order-total-amount">{o.total.toFixed(0)} ALL</div>
+        <div className="order-total-amount">{formatMoney(o.total)}</div>
       </div>
     </div>
   );
@@ -1,5 +1,7 @@
 import { useState, useEffect } from "react";
-import { Product, Category, CreateProduct, UpdateProduct } from "../types";
+import { Product, Category, CreateProduct, UpdateProduct, Unit } from "../types";
+import { fromMajor, toMajor } from "../utils/money";
+import { UNITS, fromUnit, toUnit } from "../utils/units";
 
 interface ProductFormProps {
   product?: Product;
@@ -15,22 +17,31 @@ export function ProductForm({
   onCancel,
 }: ProductFormProps) {
   const [name, setName] = useState(product?.name ?? "");
-  const [price, setPrice] = useState(product?.price?.toString() ?? "");
-  const [quantity, setQuantity] = useState(product?.quantity?.toString() ?? "0");
+  const [price, setPrice] = useState(product ? toMajor(product.price).toString() : "");
+  const [unit, setUnit] = useState<Unit>(product?.unit ?? "piece");
+  // Quantities are entered in the chosen unit and stored in base units
+  const [quantity, setQuantity] = useState(
+    product ? toUnit(product.quantity, product.unit).toString() : "0"
+  );
+  const [servingSize, setServingSize] = useState(
+    product ? toUnit(product.serving_size, product.unit).toString() : "1"
+  );
   const [categoryId, setCategoryId] = useState<number | null>(
     product?.category_id ?? null
   );
   const [threshold, setThreshold] = useState(
-    product?.low_stock_threshold?.toString() ?? "5"
+    product ? toUnit(product.low_stock_threshold, product.unit).toString() : "5"
   );
 
   useEffect(() => {
     if (product) {
       setName(product.name);
-      setPrice(product.price.toString());
-      setQuantity(product.quantity.toString());
+      setPrice(toMajor(product.price).toString());
+      setUnit(product.unit);
+      setQuantity(toUnit(product.quantity, product.unit).toString());
+      setServingSize(toUnit(product.serving_size, product.unit).toString());
       setCategoryId(product.category_id);
-      setThreshold(product.low_stock_threshold.toString());
+      setThreshold(toUnit(product.low_stock_threshold, product.unit).toString());
     }
   }, [product]);
 
@@ -38,10 +49,12 @@ export function ProductForm({
     e.preventDefault();
     const data = {
       name,
-      price: parseFloat(price),
-      quantity: parseInt(quantity),
+      price: fromMajor(parseFloat(price)),
+      quantity: fromUnit(parseFloat(quantity), unit),
       category_id: categoryId,
-      low_stock_threshold: parseInt(threshold),
+      low_stock_threshold: fromUnit(parseFloat(threshold), unit),
+      unit,
+      serving_size: fromUnit(parseFloat(servingSize), unit),
     };
 
     if (product) {
@@ -75,9 +88,31 @@ export function ProductForm({
         />
       </div>
       <div className="form-group">
-        <label>Quantity</label>
+        <label>Unit</label>
+        <select value={unit} onChange={(e) => setUnit(e.target.value as Unit)}>
+          {UNITS.map((u) => (
+            <option key={u} value={u}>
+              {u}
+            </option>
+          ))}
+        </select>
+      </div>
+      <div className="form-group">
+        <label>Serving Size ({unit})</label>
+        <input
+          type="number"
+          step="any"
+          min="0"
+          value={servingSize}
+          onChange={(e) => setServingSize(e.target.value)}
+          required
+        />
+      </div>
+      <div className="form-group">
+        <label>Quantity ({unit})</label>
         <input
           type="number"
+          step="any"
           min="0"
           value={quantity}
           onChange={(e) => setQuantity(e.target.value)}
@@ -101,9 +136,10 @@ export function ProductForm({
         </select>
       </div>
       <div className="form-group">
-        <label>Low Stock Threshold</label>
+        <label>Low Stock Threshold ({unit})</label>
         <input
           type="number"
+          step="any"
           min="0"
           value={threshold}
           onChange={(e) => setThreshold(e.target.value)}
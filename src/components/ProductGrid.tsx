@@ -1,4 +1,5 @@
 import { Product, Category } from "../types";
+import { formatMoney } from "../utils/money";
 
 interface ProductGridProps {
   products: Product[];
@@ -30,15 +31,16 @@ export function ProductGrid({
             {groupedProducts[category.id]?.map((product) => (
               <button
                 key={product.id}
-                className={`product-btn ${product.quantity === 0 ? "out-of-stock" : ""}`}
+                className={`product-btn ${product.servings === 0 || !product.available ? "out-of-stock" : ""}`}
                 onClick={() => onAddToCart(product)}
-                disabled={product.quantity === 0}
+                disabled={product.servings === 0 || !product.available}
+                title={product.available ? undefined : product.unavailable_reason ?? "Unavailable"}
               >
                 <span className="product-name">{product.name}</span>
                 <span className="product-price">
-                  {product.price.toFixed(0)} ALL
+                  {formatMoney(product.price)}
                 </span>
-                <span className="product-qty">({product.quantity})</span>
+                <span className="product-qty">({product.servings})</span>
               </button>
             ))}
           </div>
@@ -51,15 +53,16 @@ export function ProductGrid({
             {uncategorized.map((product) => (
               <button
                 key={product.id}
-                className={`product-btn ${product.quantity === 0 ? "out-of-stock" : ""}`}
+                className={`product-btn ${product.servings === 0 || !product.available ? "out-of-stock" : ""}`}
                 onClick={() => onAddToCart(product)}
-                disabled={product.quantity === 0}
+                disabled={product.servings === 0 || !product.available}
+                title={product.available ? undefined : product.unavailable_reason ?? "Unavailable"}
               >
                 <span className="product-name">{product.name}</span>
                 <span className="product-price">
-                  {product.price.toFixed(0)} ALL
+                  {formatMoney(product.price)}
                 </span>
-                <span className="product-qty">({product.quantity})</span>
+                <span className="product-qty">({product.servings})</span>
               </button>
             ))}
           </div>
@@ -1,4 +1,5 @@
 import { DaySession } from "../types";
+import { formatMoney, zeroMoney } from "../utils/money";
 
 interface SalesHistoryProps {
   history: DaySession[];
@@ -66,7 +67,7 @@ export function SalesHistory({ history, onSelectSession }: SalesHistoryProps) {
               >
                 <td>{formatSessionTimeRange(session)}</td>
                 <td>{session.total_orders ?? 0}</td>
-                <td>{(session.total_revenue ?? 0).toFixed(0)} ALL</td>
+                <td>{formatMoney(session.total_revenue ?? zeroMoney())}</td>
               </tr>
             ))}
           </tbody>
@@ -1,4 +1,5 @@
 import { invoke } from "@tauri-apps/api/core";
+import { listen } from "@tauri-apps/api/event";
 import {
   Category,
   Product,
@@ -10,30 +11,230 @@ import {
   OrderWithItems,
   DaySummary,
   DaySession,
+  StockMovement,
+  CreateStockMovement,
+  StockDiscrepancy,
+  Supplier,
+  CreateSupplier,
+  UpdateSupplier,
+  PurchaseOrder,
+  PurchaseOrderStatus,
+  PurchaseOrderWithLines,
+  CreatePurchaseOrder,
+  CreatePurchaseOrderLine,
+  ReceivePurchaseOrder,
+  SupplierBalance,
+  PurchaseReceipt,
+  StockTake,
+  StockTakeWithLines,
+  VarianceHistoryEntry,
+  RecipeComponent,
+  SetRecipeComponent,
+  ProductVariant,
+  CreateProductVariant,
+  UpdateProductVariant,
+  ModifierGroup,
+  CreateModifier,
+  CreateModifierGroup,
+  UpdateModifierGroup,
+  ProductCode,
+  CodeMatch,
+  ProductMargin,
+  CategoryMargin,
+  CategorySales,
+  SessionMargin,
+  PricingRule,
+  CreatePricingRule,
+  UpdatePricingRule,
+  PriceChange,
+  SchedulePriceChange,
+  ImportReport,
+  SupplierReorder,
+  StockLot,
+  CreateStockLot,
+  KegYield,
+  StockLocation,
+  LocationQuantity,
+  TransferStock,
+  LocationLowStock,
+  UpdateCategory,
+  TaxRate,
+  CreateTaxRate,
+  UpdateTaxRate,
 } from "../types";
 
 // Categories
-export const getCategories = () => invoke<Category[]>("get_categories");
+export const getCategories = (includeArchived?: boolean) =>
+  invoke<Category[]>("get_categories", { includeArchived });
 export const createCategory = (name: string) =>
   invoke<Category>("create_category", { name });
-export const deleteCategory = (id: number) =>
-  invoke<void>("delete_category", { id });
+export const updateCategory = (category: UpdateCategory) =>
+  invoke<Category>("update_category", { category });
+export const reorderCategories = (parentId: number | null, categoryIds: number[]) =>
+  invoke<Category[]>("reorder_categories", { parentId, categoryIds });
+export const archiveCategory = (id: number) =>
+  invoke<Category>("archive_category", { id });
+export const restoreCategory = (id: number) =>
+  invoke<Category>("restore_category", { id });
+export const purgeCategory = (id: number) =>
+  invoke<void>("purge_category", { id });
 
 // Products
-export const getProducts = () => invoke<Product[]>("get_products");
+export const getProducts = (includeArchived?: boolean) =>
+  invoke<Product[]>("get_products", { includeArchived });
+// Prefix and accent-insensitive search over name, category, codes and aliases
+export const searchProducts = (query: string, categoryId?: number, limit?: number) =>
+  invoke<Product[]>("search_products", { query, categoryId, limit });
 export const createProduct = (product: CreateProduct) =>
   invoke<Product>("create_product", { product });
 export const updateProduct = (product: UpdateProduct) =>
   invoke<Product>("update_product", { product });
-export const deleteProduct = (id: number) =>
-  invoke<void>("delete_product", { id });
+export const archiveProduct = (id: number) =>
+  invoke<Product>("archive_product", { id });
+export const restoreProduct = (id: number) =>
+  invoke<Product>("restore_product", { id });
+export const purgeProduct = (id: number) =>
+  invoke<void>("purge_product", { id });
 export const getLowStock = () => invoke<Product[]>("get_low_stock");
 
+// Availability ("86")
+export const markProductUnavailable = (productId: number, reason?: string, until?: string) =>
+  invoke<Product>("mark_product_unavailable", { productId, reason, until });
+export const markProductAvailable = (productId: number) =>
+  invoke<Product>("mark_product_available", { productId });
+// Fires in every window when a product is 86'd or comes back; returns an unsubscribe
+export const onProductAvailabilityChanged = (handler: (product: Product) => void) =>
+  listen<Product>("product-availability-changed", (event) => handler(event.payload));
+
+// Import / Export (.csv or .json, chosen by the file extension)
+export const importProducts = (path: string, dryRun = false, staffId?: number) =>
+  invoke<ImportReport>("import_products", { path, dryRun, staffId });
+export const exportProducts = (path: string) =>
+  invoke<number>("export_products", { path });
+
+// Prices
+export const getPriceHistory = (productId: number) =>
+  invoke<PriceChange[]>("get_price_history", { productId });
+export const schedulePriceChange = (change: SchedulePriceChange) =>
+  invoke<PriceChange>("schedule_price_change", { change });
+export const cancelPriceChange = (id: number) =>
+  invoke<void>("cancel_price_change", { id });
+
+// Variants
+export const getVariants = (productId?: number) =>
+  invoke<ProductVariant[]>("get_variants", { productId });
+export const createVariant = (variant: CreateProductVariant) =>
+  invoke<ProductVariant>("create_variant", { variant });
+export const updateVariant = (variant: UpdateProductVariant) =>
+  invoke<ProductVariant>("update_variant", { variant });
+export const deleteVariant = (id: number) =>
+  invoke<void>("delete_variant", { id });
+
+// Codes
+export const findProductByCode = (code: string) =>
+  invoke<CodeMatch>("find_product_by_code", { code });
+export const getProductCodes = (productId: number) =>
+  invoke<ProductCode[]>("get_product_codes", { productId });
+export const addProductCode = (
+  productId: number,
+  variantId: number | null,
+  code: string,
+  packQuantity: number | null = null
+) => invoke<ProductCode>("add_product_code", { productId, variantId, code, packQuantity });
+export const removeProductCode = (code: string) =>
+  invoke<void>("remove_product_code", { code });
+
+// Modifiers
+export const getModifierGroups = () => invoke<ModifierGroup[]>("get_modifier_groups");
+export const getProductModifierGroups = (productId: number) =>
+  invoke<ModifierGroup[]>("get_product_modifier_groups", { productId });
+export const createModifierGroup = (group: CreateModifierGroup) =>
+  invoke<ModifierGroup>("create_modifier_group", { group });
+export const updateModifierGroup = (group: UpdateModifierGroup) =>
+  invoke<ModifierGroup>("update_modifier_group", { group });
+export const deleteModifierGroup = (id: number) =>
+  invoke<void>("delete_modifier_group", { id });
+export const addModifier = (groupId: number, modifier: CreateModifier) =>
+  invoke<ModifierGroup>("add_modifier", { groupId, modifier });
+export const deleteModifier = (id: number) =>
+  invoke<void>("delete_modifier", { id });
+export const setModifierGroupLinks = (groupId: number, productIds: number[], categoryIds: number[]) =>
+  invoke<ModifierGroup>("set_modifier_group_links", { groupId, productIds, categoryIds });
+
+// Pricing Rules
+export const getPricingRules = () => invoke<PricingRule[]>("get_pricing_rules");
+export const getActivePricingRules = () => invoke<PricingRule[]>("get_active_pricing_rules");
+export const createPricingRule = (rule: CreatePricingRule) =>
+  invoke<PricingRule>("create_pricing_rule", { rule });
+export const updatePricingRule = (rule: UpdatePricingRule) =>
+  invoke<PricingRule>("update_pricing_rule", { rule });
+export const deletePricingRule = (id: number) =>
+  invoke<void>("delete_pricing_rule", { id });
+
+// Taxes
+export const getTaxRates = () => invoke<TaxRate[]>("get_tax_rates");
+export const createTaxRate = (rate: CreateTaxRate) =>
+  invoke<TaxRate>("create_tax_rate", { rate });
+export const updateTaxRate = (rate: UpdateTaxRate) =>
+  invoke<TaxRate>("update_tax_rate", { rate });
+export const deleteTaxRate = (id: number) =>
+  invoke<void>("delete_tax_rate", { id });
+export const setProductTaxRate = (productId: number, taxRateId: number | null) =>
+  invoke<Product>("set_product_tax_rate", { productId, taxRateId });
+export const setCategoryTaxRate = (categoryId: number, taxRateId: number | null) =>
+  invoke<Category>("set_category_tax_rate", { categoryId, taxRateId });
+
+// Recipes
+export const getRecipe = (productId: number) =>
+  invoke<RecipeComponent[]>("get_recipe", { productId });
+export const setRecipe = (productId: number, components: SetRecipeComponent[]) =>
+  invoke<RecipeComponent[]>("set_recipe", { productId, components });
+
+// Stock
+export const recordStockMovement = (movement: CreateStockMovement) =>
+  invoke<StockMovement>("record_stock_movement", { movement });
+export const getStockMovements = (productId: number, startDate?: string, endDate?: string) =>
+  invoke<StockMovement[]>("get_stock_movements", { productId, startDate, endDate });
+export const getStockDiscrepancies = () =>
+  invoke<StockDiscrepancy[]>("get_stock_discrepancies");
+export const rebuildStockFromLedger = () =>
+  invoke<StockDiscrepancy[]>("rebuild_stock_from_ledger");
+
+// Locations
+export const getStockLocations = () => invoke<StockLocation[]>("get_stock_locations");
+export const createStockLocation = (name: string) =>
+  invoke<StockLocation>("create_stock_location", { name });
+export const getStockByLocation = (productId: number) =>
+  invoke<LocationQuantity[]>("get_stock_by_location", { productId });
+export const transferStock = (transfer: TransferStock) =>
+  invoke<LocationQuantity[]>("transfer_stock", { transfer });
+export const setLocationThreshold = (locationId: number, productId: number, threshold: number | null) =>
+  invoke<void>("set_location_threshold", { locationId, productId, threshold });
+export const getLocationLowStock = (locationId: number) =>
+  invoke<LocationLowStock[]>("get_location_low_stock", { locationId });
+
+// Lots and Kegs
+export const getStockLots = (productId: number) =>
+  invoke<StockLot[]>("get_stock_lots", { productId });
+export const getExpiringLots = (days?: number) =>
+  invoke<StockLot[]>("get_expiring_lots", { days });
+export const receiveStockLot = (lot: CreateStockLot) =>
+  invoke<StockLot>("receive_stock_lot", { lot });
+export const tapKeg = (lotId: number) => invoke<StockLot>("tap_keg", { lotId });
+export const retireKeg = (lotId: number, staffId?: number) =>
+  invoke<KegYield>("retire_keg", { lotId, staffId });
+export const getKegYield = (lotId: number) => invoke<KegYield>("get_keg_yield", { lotId });
+
 // Staff
-export const getStaff = () => invoke<Staff[]>("get_staff");
+export const getStaff = (includeArchived?: boolean) =>
+  invoke<Staff[]>("get_staff", { includeArchived });
 export const createStaff = (staff: CreateStaff) =>
   invoke<Staff>("create_staff", { staff });
-export const deleteStaff = (id: number) => invoke<void>("delete_staff", { id });
+export const archiveStaff = (id: number) =>
+  invoke<Staff>("archive_staff", { id });
+export const restoreStaff = (id: number) =>
+  invoke<Staff>("restore_staff", { id });
+export const purgeStaff = (id: number) => invoke<void>("purge_staff", { id });
 export const verifyStaffPin = (id: number, pin: string) =>
   invoke<boolean>("verify_staff_pin", { id, pin });
 
@@ -46,6 +247,8 @@ export const getOrder = (id: number) =>
   invoke<OrderWithItems>("get_order", { id });
 export const addItemsToOrder = (orderId: number, items: { product_id: number; quantity: number }[]) =>
   invoke<OrderWithItems>("add_items_to_order", { orderId, items });
+export const addItemByCode = (orderId: number, code: string, quantity: number) =>
+  invoke<OrderWithItems>("add_item_by_code", { orderId, code, quantity });
 export const markOrderPaid = (orderId: number) =>
   invoke<OrderWithItems>("mark_order_paid", { orderId });
 export const decreaseItemQuantity = (orderItemId: number) =>
@@ -59,12 +262,20 @@ export const updateOrderNotes = (orderId: number, customerName: string | null, n
 export const closeDay = () => invoke<DaySession>("close_day");
 export const getSalesHistory = (limit?: number) =>
   invoke<DaySession[]>("get_sales_history", { limit });
-export const getDaySummary = (sessionId?: number) =>
-  invoke<DaySummary>("get_day_summary", { sessionId });
+export const getDaySummary = (sessionId?: number, depth?: number) =>
+  invoke<DaySummary>("get_day_summary", { sessionId, depth });
+export const getCategorySales = (startDate: string, endDate: string, depth?: number) =>
+  invoke<CategorySales[]>("get_category_sales", { startDate, endDate, depth });
 export const getOrdersByDateRange = (startDate: string, endDate: string) =>
   invoke<OrderWithItems[]>("get_orders_by_date_range", { startDate, endDate });
 export const createDayClosingForDate = (date: string) =>
   invoke<DaySession>("create_day_closing_for_date", { date });
+export const getProductMargins = (startDate: string, endDate: string) =>
+  invoke<ProductMargin[]>("get_product_margins", { startDate, endDate });
+export const getCategoryMargins = (startDate: string, endDate: string, depth?: number) =>
+  invoke<CategoryMargin[]>("get_category_margins", { startDate, endDate, depth });
+export const getSessionMargins = (sessionId: number) =>
+  invoke<SessionMargin>("get_session_margins", { sessionId });
 
 // Day Sessions
 export const getActiveSession = () =>
@@ -73,3 +284,54 @@ export const startDay = (staffId: number) =>
   invoke<DaySession>("start_day", { staffId });
 export const isDayActive = () =>
   invoke<boolean>("is_day_active");
+
+// Suppliers
+export const getSuppliers = () => invoke<Supplier[]>("get_suppliers");
+export const createSupplier = (supplier: CreateSupplier) =>
+  invoke<Supplier>("create_supplier", { supplier });
+export const updateSupplier = (supplier: UpdateSupplier) =>
+  invoke<Supplier>("update_supplier", { supplier });
+
+// Purchase Orders
+export const getPurchaseOrders = (supplierId?: number, status?: PurchaseOrderStatus) =>
+  invoke<PurchaseOrder[]>("get_purchase_orders", { supplierId, status });
+export const getPurchaseOrder = (id: number) =>
+  invoke<PurchaseOrderWithLines>("get_purchase_order", { id });
+export const createPurchaseOrder = (order: CreatePurchaseOrder) =>
+  invoke<PurchaseOrderWithLines>("create_purchase_order", { order });
+export const updatePurchaseOrderLines = (id: number, lines: CreatePurchaseOrderLine[]) =>
+  invoke<PurchaseOrderWithLines>("update_purchase_order_lines", { id, lines });
+export const sendPurchaseOrder = (id: number) =>
+  invoke<PurchaseOrderWithLines>("send_purchase_order", { id });
+export const cancelPurchaseOrder = (id: number) =>
+  invoke<PurchaseOrderWithLines>("cancel_purchase_order", { id });
+export const receivePurchaseOrder = (id: number, receipt: ReceivePurchaseOrder) =>
+  invoke<PurchaseOrderWithLines>("receive_purchase_order", { id, receipt });
+export const receiveByCode = (id: number, code: string, quantity: number, staffId?: number | null) =>
+  invoke<PurchaseOrderWithLines>("receive_by_code", { id, code, quantity, staffId });
+export const getSupplierBalances = () =>
+  invoke<SupplierBalance[]>("get_supplier_balances");
+export const getSupplierPurchaseHistory = (supplierId: number, startDate?: string, endDate?: string) =>
+  invoke<PurchaseReceipt[]>("get_supplier_purchase_history", { supplierId, startDate, endDate });
+
+// Reorder
+export const getReorderSuggestions = (leadTimeDays: number, coverDays?: number, sessions?: number) =>
+  invoke<SupplierReorder[]>("get_reorder_suggestions", { leadTimeDays, coverDays, sessions });
+export const tuneLowStockThresholds = (leadTimeDays: number, sessions?: number) =>
+  invoke<Product[]>("tune_low_stock_thresholds", { leadTimeDays, sessions });
+
+// Stock Takes
+export const getStockTakes = (limit?: number) =>
+  invoke<StockTake[]>("get_stock_takes", { limit });
+export const getStockTake = (id: number) =>
+  invoke<StockTakeWithLines>("get_stock_take", { id });
+export const startStockTake = (staffId: number, locationId: number | null, notes: string | null) =>
+  invoke<StockTakeWithLines>("start_stock_take", { staffId, locationId, notes });
+export const recordStockCount = (stockTakeId: number, productId: number, staffId: number, countedQuantity: number) =>
+  invoke<StockTakeWithLines>("record_stock_count", { stockTakeId, productId, staffId, countedQuantity });
+export const commitStockTake = (id: number, staffId: number) =>
+  invoke<StockTakeWithLines>("commit_stock_take", { id, staffId });
+export const cancelStockTake = (id: number) =>
+  invoke<StockTakeWithLines>("cancel_stock_take", { id });
+export const getVarianceHistory = (productId: number) =>
+  invoke<VarianceHistoryEntry[]>("get_variance_history", { productId });
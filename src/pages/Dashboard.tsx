@@ -14,14 +14,19 @@ import {
   verifyStaffPin,
   getActiveSession,
   startDay,
+  onProductAvailabilityChanged,
 } from "../hooks/useTauri";
 import { StaffSelector } from "../components/StaffSelector";
 import { ProductGrid } from "../components/ProductGrid";
 import { LowStockAlert } from "../components/LowStockAlert";
 import { PinModal } from "../components/PinModal";
+import { formatMoney, lineTotal, sumMoney, toMajor } from "../utils/money";
+import { errorMessage, isAppError } from "../utils/errors";
 
 const TOTAL_TABLES = 20;
 const STAFF_STORAGE_KEY = "menubar_selected_staff_id";
+// Stock location this terminal sells from; unset sells from the default location
+const LOCATION_STORAGE_KEY = "menubar_stock_location_id";
 
 export function Dashboard() {
   const [products, setProducts] = useState<Product[]>([]);
@@ -73,7 +78,7 @@ export function Dashboard() {
       }
     } catch (e) {
       console.error("loadData error:", e);
-      setError(String(e));
+      setError(errorMessage(e));
     } finally {
       setLoading(false);
     }
@@ -89,7 +94,7 @@ export function Dashboard() {
       const session = await startDay(selectedStaff.id);
       setDaySession(session);
     } catch (e) {
-      setError(String(e));
+      setError(errorMessage(e));
     }
   };
 
@@ -97,6 +102,16 @@ export function Dashboard() {
     loadData();
   }, []);
 
+  // Another window 86'd or restored a product
+  useEffect(() => {
+    const unlisten = onProductAvailabilityChanged((product) => {
+      setProducts((prev) => prev.map((p) => (p.id === product.id ? product : p)));
+    });
+    return () => {
+      unlisten.then((stop) => stop());
+    };
+  }, []);
+
   // Persist staff selection
   const handleStaffSelect = (staff: Staff) => {
     setSelectedStaff(staff);
@@ -107,7 +122,7 @@ export function Dashboard() {
     setCart((prev) => {
       const existing = prev.find((item) => item.product.id === product.id);
       if (existing) {
-        if (existing.quantity >= product.quantity) return prev;
+        if (existing.quantity >= product.servings) return prev;
         return prev.map((item) =>
           item.product.id === product.id
             ? { ...item, quantity: item.quantity + 1 }
@@ -162,11 +177,13 @@ export function Dashboard() {
         );
       } else {
         // Table is empty - create new order with items
+        const locationId = localStorage.getItem(LOCATION_STORAGE_KEY);
         await createOrder({
           staff_id: selectedStaff.id,
           table_number: selectedTableNumber,
           customer_name: null,
           notes: null,
+          location_id: locationId ? Number(locationId) : null,
           items: cart.map((item) => ({
             product_id: item.product.id,
             quantity: item.quantity,
@@ -176,7 +193,17 @@ export function Dashboard() {
       setCart([]);
       await loadData();
     } catch (e) {
-      setError(String(e));
+      if (isAppError(e, "INSUFFICIENT_STOCK") && e.details) {
+        // Trim the line to the servings left so the order can go through as-is.
+        // A short recipe ingredient is not in the cart; the message names it.
+        const productId = e.details.product_id as number;
+        const available = e.details.available as number;
+        const line = cart.find((item) => item.product.id === productId);
+        if (line) {
+          updateQuantity(productId, Math.floor(available / line.product.serving_size));
+        }
+      }
+      setError(errorMessage(e));
     }
   };
 
@@ -201,14 +228,13 @@ export function Dashboard() {
       }
       return false;
     } catch (e) {
-      setError(String(e));
+      setError(errorMessage(e));
       return false;
     }
   };
 
-  const cartTotal = cart.reduce(
-    (sum, item) => sum + item.product.price * item.quantity,
-    0
+  const cartTotal = sumMoney(
+    cart.map((item) => lineTotal(item.product.price, item.quantity))
   );
 
   if (loading) {
@@ -286,7 +312,7 @@ export function Dashboard() {
                   {hasItems ? (
                     <>
                       <div className="table-card-items">{order.items.length} items</div>
-                      <div className="table-card-total">{order.order.total.toFixed(0)} ALL</div>
+                      <div className="table-card-total">{formatMoney(order.order.total)}</div>
                     </>
                   ) : (
                     <div className="table-card-status">{isSelected ? "Selected" : "Available"}</div>
@@ -327,7 +353,7 @@ export function Dashboard() {
                           await decreaseItemQuantity(item.id);
                           await loadData();
                         } catch (e) {
-                          setError(String(e));
+                          setError(errorMessage(e));
                         }
                       }}
                       title="Decrease quantity"
@@ -342,20 +368,20 @@ export function Dashboard() {
                           await increaseItemQuantity(item.id);
                           await loadData();
                         } catch (e) {
-                          setError(String(e));
+                          setError(errorMessage(e));
                         }
                       }}
                       title="Increase quantity"
                     >
                       +
                     </button>
-                    <span className="item-total">{(item.price_at_sale * item.quantity).toFixed(0)}</span>
+                    <span className="item-total">{toMajor(lineTotal(item.price_at_sale, item.quantity)).toFixed(0)}</span>
                   </div>
                 </div>
               ))}
               <div className="existing-items-total">
                 <span>Subtotal</span>
-                <span>{selectedTableOrder.order.total.toFixed(0)} ALL</span>
+                <span>{formatMoney(selectedTableOrder.order.total)}</span>
               </div>
             </div>
           )}
@@ -371,7 +397,7 @@ export function Dashboard() {
                 <div className="cart-row-info">
                   <span className="cart-row-name">{item.product.name}</span>
                   <span className="cart-row-price">
-                    {(item.product.price * item.quantity).toFixed(0)}
+                    {toMajor(lineTotal(item.product.price, item.quantity)).toFixed(0)}
                   </span>
                 </div>
                 <div className="cart-row-controls">
@@ -379,7 +405,7 @@ export function Dashboard() {
                   <span>{item.quantity}</span>
                   <button
                     onClick={() => updateQuantity(item.product.id, item.quantity + 1)}
-                    disabled={item.quantity >= item.product.quantity}
+                    disabled={item.quantity >= item.product.servings}
                   >+</button>
                   <button className="remove" onClick={() => removeFromCart(item.product.id)}>×</button>
                 </div>
@@ -389,7 +415,7 @@ export function Dashboard() {
             {cart.length > 0 && (
               <div className="cart-subtotal">
                 <span>New items:</span>
-                <span>{cartTotal.toFixed(0)} ALL</span>
+                <span>{formatMoney(cartTotal)}</span>
               </div>
             )}
 
@@ -417,7 +443,7 @@ export function Dashboard() {
                 )}
                 {selectedTableOrder && selectedTableOrder.items.length > 0 && (
                   <button className="btn-close" onClick={handleCloseTable}>
-                    Close Table ({selectedTableOrder.order.total.toFixed(0)} ALL)
+                    Close Table ({formatMoney(selectedTableOrder.order.total)})
                   </button>
                 )}
                 <button className="btn-deselect" onClick={() => setSelectedTableNumber(null)}>
@@ -5,12 +5,15 @@ import {
   getCategories,
   createProduct,
   updateProduct,
-  deleteProduct,
+  archiveProduct,
   createCategory,
-  deleteCategory,
+  archiveCategory,
 } from "../hooks/useTauri";
 import { ProductForm } from "../components/ProductForm";
 import { ConfirmModal } from "../components/ConfirmModal";
+import { formatMoney } from "../utils/money";
+import { formatQuantity } from "../utils/units";
+import { errorMessage } from "../utils/errors";
 
 export function Products() {
   const [products, setProducts] = useState<Product[]>([]);
@@ -31,7 +34,7 @@ export function Products() {
       setProducts(prods);
       setCategories(cats);
     } catch (e) {
-      setError(String(e));
+      setError(errorMessage(e));
     }
   }, []);
 
@@ -50,7 +53,7 @@ export function Products() {
       setEditingProduct(null);
       await loadData();
     } catch (e) {
-      setError(String(e));
+      setError(errorMessage(e));
     }
   };
 
@@ -58,14 +61,14 @@ export function Products() {
     if (!deleteConfirm) return;
     try {
       if (deleteConfirm.type === "product") {
-        await deleteProduct(deleteConfirm.id);
+        await archiveProduct(deleteConfirm.id);
       } else {
-        await deleteCategory(deleteConfirm.id);
+        await archiveCategory(deleteConfirm.id);
       }
       setDeleteConfirm(null);
       await loadData();
     } catch (e) {
-      setError(String(e));
+      setError(errorMessage(e));
     }
   };
 
@@ -76,7 +79,7 @@ export function Products() {
       setNewCategory("");
       await loadData();
     } catch (e) {
-      setError(String(e));
+      setError(errorMessage(e));
     }
   };
 
@@ -105,15 +108,15 @@ export function Products() {
 
       {deleteConfirm && (
         <ConfirmModal
-          title={`Delete ${deleteConfirm.type === "product" ? "Product" : "Category"}`}
-          message={`Are you sure you want to delete "${deleteConfirm.name}"?${
+          title={`Archive ${deleteConfirm.type === "product" ? "Product" : "Category"}`}
+          message={`Are you sure you want to archive "${deleteConfirm.name}"?${
             deleteConfirm.type === "category"
-              ? " Products in this category will become uncategorized."
+              ? " Products in this category will be taken off sale."
               : ""
-          }`}
+          } Sales history is kept.`}
           onConfirm={handleDelete}
           onCancel={() => setDeleteConfirm(null)}
-          confirmText="Delete"
+          confirmText="Archive"
         />
       )}
 
@@ -173,8 +176,8 @@ export function Products() {
                 }
               >
                 <td>{product.name}</td>
-                <td>{product.price.toFixed(0)} ALL</td>
-                <td>{product.quantity}</td>
+                <td>{formatMoney(product.price)}</td>
+                <td>{formatQuantity(product.quantity, product.unit)}</td>
                 <td>{product.category_name ?? "-"}</td>
                 <td>
                   <button
@@ -194,7 +197,7 @@ export function Products() {
                       })
                     }
                   >
-                    Delete
+                    Archive
                   </button>
                 </td>
               </tr>
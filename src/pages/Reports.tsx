@@ -15,6 +15,8 @@ import { DayClosingComponent } from "../components/DayClosing";
 import { DayCloseModal } from "../components/DayCloseModal";
 import { PinModal } from "../components/PinModal";
 import { exportToExcel, exportToCSV, exportToText } from "../utils/exportReport";
+import { formatMoney, sumMoney } from "../utils/money";
+import { errorMessage } from "../utils/errors";
 
 export function Reports() {
   const [history, setHistory] = useState<DaySession[]>([]);
@@ -39,7 +41,7 @@ export function Reports() {
       const [hist, today, staffList, session] = await Promise.all([
         getSalesHistory(30),
         getDaySummary(),
-        getStaff(),
+        getStaff(true),
         getActiveSession(),
       ]);
       setHistory(hist);
@@ -49,7 +51,7 @@ export function Reports() {
       // Can close if there's an active session
       setCanCloseDay(session !== null);
     } catch (e) {
-      setError(String(e));
+      setError(errorMessage(e));
     }
   }, []);
 
@@ -86,7 +88,7 @@ export function Reports() {
       }
       return false;
     } catch (e) {
-      setError(String(e));
+      setError(errorMessage(e));
       return false;
     }
   };
@@ -98,7 +100,7 @@ export function Reports() {
       setSuccess("Day closed successfully!");
       await loadData();
     } catch (e) {
-      setError(String(e));
+      setError(errorMessage(e));
     } finally {
       setClosing(false);
       setSelectedStaff(null);
@@ -110,7 +112,7 @@ export function Reports() {
       const summary = await getDaySummary(sessionId);
       setSelectedDaySummary(summary);
     } catch (e) {
-      setError(String(e));
+      setError(errorMessage(e));
     }
   };
 
@@ -127,7 +129,7 @@ export function Reports() {
         setError(`No orders found for ${recoveryDate}`);
       }
     } catch (e) {
-      setError(String(e));
+      setError(errorMessage(e));
     }
   };
 
@@ -141,11 +143,11 @@ export function Reports() {
       setRecoveryOrders([]);
       await loadData();
     } catch (e) {
-      setError(String(e));
+      setError(errorMessage(e));
     }
   };
 
-  const recoveryTotal = recoveryOrders.reduce((sum, o) => sum + o.order.total, 0);
+  const recoveryTotal = sumMoney(recoveryOrders.map((o) => o.order.total));
 
   return (
     <div className="reports-page">
@@ -229,14 +231,14 @@ export function Reports() {
 
           {recoveryOrders.length > 0 && (
             <div className="recovery-results">
-              <h4>Found {recoveryOrders.length} orders - Total: {recoveryTotal.toFixed(0)} ALL</h4>
+              <h4>Found {recoveryOrders.length} orders - Total: {formatMoney(recoveryTotal)}</h4>
               <div className="recovery-orders">
                 {recoveryOrders.map((o) => (
                   <div key={o.order.id} className="recovery-order">
                     <span>#{o.order.id}</span>
                     <span>{o.order.staff_name}</span>
                     <span>Table {o.order.table_number}</span>
-                    <span>{o.order.total.toFixed(0)} ALL</span>
+                    <span>{formatMoney(o.order.total)}</span>
                     <span className={`status-${o.order.status}`}>{o.order.status}</span>
                   </div>
                 ))}
@@ -263,7 +265,7 @@ export function Reports() {
             <div className="selected-day">
               <h4>{selectedDaySummary.date}</h4>
               <p>Orders: {selectedDaySummary.total_orders}</p>
-              <p>Revenue: {selectedDaySummary.total_revenue.toFixed(0)} ALL</p>
+              <p>Revenue: {formatMoney(selectedDaySummary.total_revenue)}</p>
               <div className="export-buttons">
                 <button className="export-btn primary" onClick={() => exportToExcel(selectedDaySummary)}>
                   Export Excel
@@ -1,7 +1,8 @@
 import { useState, useEffect, useCallback } from "react";
 import { Staff as StaffType, CreateStaff } from "../types";
-import { getStaff, createStaff, deleteStaff } from "../hooks/useTauri";
+import { getStaff, createStaff, archiveStaff } from "../hooks/useTauri";
 import { ConfirmModal } from "../components/ConfirmModal";
+import { errorMessage } from "../utils/errors";
 
 export function Staff() {
   const [staffList, setStaffList] = useState<StaffType[]>([]);
@@ -18,7 +19,7 @@ export function Staff() {
       const staff = await getStaff();
       setStaffList(staff);
     } catch (e) {
-      setError(String(e));
+      setError(errorMessage(e));
     }
   }, []);
 
@@ -38,18 +39,18 @@ export function Staff() {
       setPin("");
       await loadData();
     } catch (e) {
-      setError(String(e));
+      setError(errorMessage(e));
     }
   };
 
   const handleDelete = async () => {
     if (!deleteConfirm) return;
     try {
-      await deleteStaff(deleteConfirm.id);
+      await archiveStaff(deleteConfirm.id);
       setDeleteConfirm(null);
       await loadData();
     } catch (e) {
-      setError(String(e));
+      setError(errorMessage(e));
     }
   };
 
@@ -64,11 +65,11 @@ export function Staff() {
 
       {deleteConfirm && (
         <ConfirmModal
-          title="Delete Staff Member"
-          message={`Are you sure you want to delete "${deleteConfirm.name}"?`}
+          title="Archive Staff Member"
+          message={`Are you sure you want to archive "${deleteConfirm.name}"? Their orders are kept.`}
           onConfirm={handleDelete}
           onCancel={() => setDeleteConfirm(null)}
-          confirmText="Delete"
+          confirmText="Archive"
         />
       )}
 
@@ -114,7 +115,7 @@ export function Staff() {
                       setDeleteConfirm({ id: staff.id, name: staff.name })
                     }
                   >
-                    Delete
+                    Archive
                   </button>
                 </td>
               </tr>
@@ -1,5 +1,6 @@
 import { describe, it, expect } from "vitest";
-import type { Product, OrderWithItems } from "../types";
+import type { Money, Product, OrderWithItems } from "../types";
+import { fromMajor, lineTotal, sumMoney } from "../utils/money";
 
 // Business logic helper functions that mirror dashboard behavior
 // These test the core business rules without Tauri dependencies
@@ -10,13 +11,13 @@ interface TestCartItem {
 }
 
 // Calculate cart total
-function calculateCartTotal(cart: TestCartItem[]): number {
-  return cart.reduce((sum, item) => sum + item.product.price * item.quantity, 0);
+function calculateCartTotal(cart: TestCartItem[]): Money {
+  return sumMoney(cart.map((item) => lineTotal(item.product.price, item.quantity)));
 }
 
 // Check if product can be added to cart (stock validation)
 function canAddToCart(product: Product, currentCartQty: number, requestedQty: number): boolean {
-  return product.quantity >= currentCartQty + requestedQty;
+  return product.servings >= currentCartQty + requestedQty;
 }
 
 // Check if order can be modified (status validation)
@@ -30,8 +31,8 @@ function canCloseDay(openOrders: OrderWithItems[]): boolean {
 }
 
 // Calculate order items total from order
-function calculateOrderTotal(order: OrderWithItems): number {
-  return order.items.reduce((sum, item) => sum + item.price_at_sale * item.quantity, 0);
+function calculateOrderTotal(order: OrderWithItems): Money {
+  return sumMoney(order.items.map((item) => lineTotal(item.price_at_sale, item.quantity)));
 }
 
 // Check if product is low stock
@@ -48,37 +49,48 @@ describe("Cart Operations", () => {
   const mockProduct: Product = {
     id: 1,
     name: "Heineken",
-    price: 5.0,
+    price: fromMajor(5.0),
     quantity: 10,
     category_id: 1,
     category_name: "Beer",
     low_stock_threshold: 5,
     created_at: "2024-01-15T10:00:00",
+    has_recipe: false,
+    unit: "piece",
+    serving_size: 1,
+    servings: 10,
+    codes: [],
+    aliases: [],
+    cost_price: null,
+    archived_at: null,
+    available: true,
+    unavailable_reason: null,
+    unavailable_until: null,
   };
 
   describe("calculateCartTotal", () => {
     it("calculates total for single item", () => {
       const cart: TestCartItem[] = [{ product: mockProduct, quantity: 2 }];
-      expect(calculateCartTotal(cart)).toBe(10.0);
+      expect(calculateCartTotal(cart)).toEqual(fromMajor(10.0));
     });
 
     it("calculates total for multiple items", () => {
-      const product2 = { ...mockProduct, id: 2, name: "Corona", price: 6.0 };
+      const product2 = { ...mockProduct, id: 2, name: "Corona", price: fromMajor(6.0) };
       const cart: TestCartItem[] = [
         { product: mockProduct, quantity: 2 },
         { product: product2, quantity: 3 },
       ];
-      expect(calculateCartTotal(cart)).toBe(28.0); // 10 + 18
+      expect(calculateCartTotal(cart)).toEqual(fromMajor(28.0)); // 10 + 18
     });
 
     it("returns 0 for empty cart", () => {
-      expect(calculateCartTotal([])).toBe(0);
+      expect(calculateCartTotal([])).toEqual(fromMajor(0));
     });
 
     it("handles decimal prices correctly", () => {
-      const product = { ...mockProduct, price: 5.99 };
+      const product = { ...mockProduct, price: fromMajor(5.99) };
       const cart: TestCartItem[] = [{ product, quantity: 3 }];
-      expect(calculateCartTotal(cart)).toBeCloseTo(17.97, 2);
+      expect(calculateCartTotal(cart)).toEqual(fromMajor(17.97));
     });
   });
 
@@ -114,14 +126,14 @@ describe("Order Operations", () => {
       staff_id: 1,
       staff_name: "John",
       table_number: 5,
-      total: 25.0,
+      total: fromMajor(25.0),
       customer_name: null,
       notes: null,
       status: "open",
       created_at: "2024-01-15T10:00:00",
     },
     items: [
-      { id: 1, order_id: 1, product_id: 1, product_name: "Heineken", quantity: 5, price_at_sale: 5.0 },
+      { id: 1, order_id: 1, product_id: 1, product_name: "Heineken", quantity: 5, price_at_sale: fromMajor(5.0), cost_at_sale: null, pricing_rule_id: null, pricing_rule_name: null, variant_id: null, variant_name: null, modifiers: [] },
     ],
   };
 
@@ -142,23 +154,23 @@ describe("Order Operations", () => {
 
   describe("calculateOrderTotal", () => {
     it("calculates correct total from items", () => {
-      expect(calculateOrderTotal(mockOpenOrder)).toBe(25.0);
+      expect(calculateOrderTotal(mockOpenOrder)).toEqual(fromMajor(25.0));
     });
 
     it("handles multiple items", () => {
       const multiItemOrder: OrderWithItems = {
         ...mockOpenOrder,
         items: [
-          { id: 1, order_id: 1, product_id: 1, product_name: "Heineken", quantity: 2, price_at_sale: 5.0 },
-          { id: 2, order_id: 1, product_id: 2, product_name: "Corona", quantity: 3, price_at_sale: 6.0 },
+          { id: 1, order_id: 1, product_id: 1, product_name: "Heineken", quantity: 2, price_at_sale: fromMajor(5.0), cost_at_sale: null, pricing_rule_id: null, pricing_rule_name: null, variant_id: null, variant_name: null, modifiers: [] },
+          { id: 2, order_id: 1, product_id: 2, product_name: "Corona", quantity: 3, price_at_sale: fromMajor(6.0), cost_at_sale: null, pricing_rule_id: null, pricing_rule_name: null, variant_id: null, variant_name: null, modifiers: [] },
         ],
       };
-      expect(calculateOrderTotal(multiItemOrder)).toBe(28.0);
+      expect(calculateOrderTotal(multiItemOrder)).toEqual(fromMajor(28.0));
     });
 
     it("returns 0 for order with no items", () => {
       const emptyOrder = { ...mockOpenOrder, items: [] };
-      expect(calculateOrderTotal(emptyOrder)).toBe(0);
+      expect(calculateOrderTotal(emptyOrder)).toEqual(fromMajor(0));
     });
   });
 });
@@ -171,7 +183,7 @@ describe("Day Session Operations", () => {
         staff_id: 1,
         staff_name: "John",
         table_number: 5,
-        total: 25.0,
+        total: fromMajor(25.0),
         customer_name: null,
         notes: null,
         status: "open",
@@ -201,12 +213,23 @@ describe("Stock Management", () => {
       const product: Product = {
         id: 1,
         name: "Test",
-        price: 5.0,
+        price: fromMajor(5.0),
         quantity: 5,
         category_id: 1,
         category_name: "Test",
         low_stock_threshold: 5,
         created_at: "",
+        has_recipe: false,
+        unit: "piece",
+        serving_size: 1,
+        servings: 5,
+        codes: [],
+        aliases: [],
+        cost_price: null,
+        archived_at: null,
+        available: true,
+        unavailable_reason: null,
+        unavailable_until: null,
       };
       expect(isLowStock(product)).toBe(true);
     });
@@ -215,12 +238,23 @@ describe("Stock Management", () => {
       const product: Product = {
         id: 1,
         name: "Test",
-        price: 5.0,
+        price: fromMajor(5.0),
         quantity: 3,
         category_id: 1,
         category_name: "Test",
         low_stock_threshold: 5,
         created_at: "",
+        has_recipe: false,
+        unit: "piece",
+        serving_size: 1,
+        servings: 3,
+        codes: [],
+        aliases: [],
+        cost_price: null,
+        archived_at: null,
+        available: true,
+        unavailable_reason: null,
+        unavailable_until: null,
       };
       expect(isLowStock(product)).toBe(true);
     });
@@ -229,12 +263,23 @@ describe("Stock Management", () => {
       const product: Product = {
         id: 1,
         name: "Test",
-        price: 5.0,
+        price: fromMajor(5.0),
         quantity: 10,
         category_id: 1,
         category_name: "Test",
         low_stock_threshold: 5,
         created_at: "",
+        has_recipe: false,
+        unit: "piece",
+        serving_size: 1,
+        servings: 10,
+        codes: [],
+        aliases: [],
+        cost_price: null,
+        archived_at: null,
+        available: true,
+        unavailable_reason: null,
+        unavailable_until: null,
       };
       expect(isLowStock(product)).toBe(false);
     });
@@ -243,12 +288,23 @@ describe("Stock Management", () => {
       const product: Product = {
         id: 1,
         name: "Test",
-        price: 5.0,
+        price: fromMajor(5.0),
         quantity: 0,
         category_id: 1,
         category_name: "Test",
         low_stock_threshold: 0,
         created_at: "",
+        has_recipe: false,
+        unit: "piece",
+        serving_size: 1,
+        servings: 0,
+        codes: [],
+        aliases: [],
+        cost_price: null,
+        archived_at: null,
+        available: true,
+        unavailable_reason: null,
+        unavailable_until: null,
       };
       expect(isLowStock(product)).toBe(true);
     });
@@ -285,38 +341,38 @@ describe("Revenue Calculations", () => {
   it("calculates daily revenue correctly", () => {
     const orders: OrderWithItems[] = [
       {
-        order: { id: 1, staff_id: 1, table_number: 1, total: 50.0, status: "paid", created_at: "", staff_name: null, customer_name: null, notes: null },
+        order: { id: 1, staff_id: 1, table_number: 1, total: fromMajor(50.0), status: "paid", created_at: "", staff_name: null, customer_name: null, notes: null },
         items: [],
       },
       {
-        order: { id: 2, staff_id: 1, table_number: 2, total: 75.0, status: "paid", created_at: "", staff_name: null, customer_name: null, notes: null },
+        order: { id: 2, staff_id: 1, table_number: 2, total: fromMajor(75.0), status: "paid", created_at: "", staff_name: null, customer_name: null, notes: null },
         items: [],
       },
       {
-        order: { id: 3, staff_id: 1, table_number: 3, total: 25.0, status: "paid", created_at: "", staff_name: null, customer_name: null, notes: null },
+        order: { id: 3, staff_id: 1, table_number: 3, total: fromMajor(25.0), status: "paid", created_at: "", staff_name: null, customer_name: null, notes: null },
         items: [],
       },
     ];
 
-    const totalRevenue = orders.reduce((sum, o) => sum + o.order.total, 0);
-    expect(totalRevenue).toBe(150.0);
+    const totalRevenue = sumMoney(orders.map((o) => o.order.total));
+    expect(totalRevenue).toEqual(fromMajor(150.0));
   });
 
   it("only counts paid orders for revenue", () => {
     const orders: OrderWithItems[] = [
       {
-        order: { id: 1, staff_id: 1, table_number: 1, total: 50.0, status: "paid", created_at: "", staff_name: null, customer_name: null, notes: null },
+        order: { id: 1, staff_id: 1, table_number: 1, total: fromMajor(50.0), status: "paid", created_at: "", staff_name: null, customer_name: null, notes: null },
         items: [],
       },
       {
-        order: { id: 2, staff_id: 1, table_number: 2, total: 75.0, status: "open", created_at: "", staff_name: null, customer_name: null, notes: null },
+        order: { id: 2, staff_id: 1, table_number: 2, total: fromMajor(75.0), status: "open", created_at: "", staff_name: null, customer_name: null, notes: null },
         items: [],
       },
     ];
 
     const paidRevenue = orders
       .filter(o => o.order.status === "paid")
-      .reduce((sum, o) => sum + o.order.total, 0);
-    expect(paidRevenue).toBe(50.0);
+      .map((o) => o.order.total);
+    expect(sumMoney(paidRevenue)).toEqual(fromMajor(50.0));
   });
 });
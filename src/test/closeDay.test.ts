@@ -1,7 +1,8 @@
 import { describe, it, expect, vi, beforeEach } from "vitest";
 import { invoke } from "@tauri-apps/api/core";
 import * as tauri from "../hooks/useTauri";
-import type { OrderWithItems, DaySession } from "../types";
+import type { Money, OrderWithItems, DaySession } from "../types";
+import { fromMajor, sumMoney } from "../utils/money";
 
 vi.mock("@tauri-apps/api/core", () => ({
   invoke: vi.fn(),
@@ -47,7 +48,7 @@ describe("Close Day Functionality", () => {
         started_at: "2024-01-15T09:00:00",
         closed_at: "2024-01-15T22:00:00",
         is_active: false,
-        total_revenue: 500.0,
+        total_revenue: fromMajor(500.0),
         total_orders: 20,
       };
 
@@ -69,14 +70,14 @@ describe("Close Day Functionality", () => {
         started_at: "2024-01-15T09:00:00",
         closed_at: "2024-01-15T22:00:00",
         is_active: false,
-        total_revenue: 1250.75,
+        total_revenue: fromMajor(1250.75),
         total_orders: 45,
       };
 
       mockInvoke.mockResolvedValueOnce(closedSession);
 
       const result = await tauri.closeDay();
-      expect(result.total_revenue).toBe(1250.75);
+      expect(result.total_revenue).toEqual(fromMajor(1250.75));
       expect(result.total_orders).toBe(45);
     });
 
@@ -89,7 +90,7 @@ describe("Close Day Functionality", () => {
         started_at: "2024-01-15T09:00:00",
         closed_at: "2024-01-15T22:30:45",
         is_active: false,
-        total_revenue: 100.0,
+        total_revenue: fromMajor(100.0),
         total_orders: 5,
       };
 
@@ -108,7 +109,7 @@ describe("Close Day Functionality", () => {
         started_at: "2024-01-15T09:00:00",
         closed_at: "2024-01-15T22:00:00",
         is_active: false,
-        total_revenue: 100.0,
+        total_revenue: fromMajor(100.0),
         total_orders: 5,
       };
 
@@ -200,7 +201,7 @@ describe("Close Day Functionality", () => {
         started_at: "2024-01-10T00:00:00",
         closed_at: "2024-01-15T10:00:00",
         is_active: false,
-        total_revenue: 350.0,
+        total_revenue: fromMajor(350.0),
         total_orders: 15,
       };
 
@@ -243,7 +244,7 @@ describe("Close Day Functionality", () => {
           started_at: "2024-01-14T09:00:00",
           closed_at: "2024-01-14T22:00:00",
           is_active: false,
-          total_revenue: 500.0,
+          total_revenue: fromMajor(500.0),
           total_orders: 20,
         },
         {
@@ -254,7 +255,7 @@ describe("Close Day Functionality", () => {
           started_at: "2024-01-13T09:00:00",
           closed_at: "2024-01-13T21:00:00",
           is_active: false,
-          total_revenue: 450.0,
+          total_revenue: fromMajor(450.0),
           total_orders: 18,
         },
       ];
@@ -278,7 +279,7 @@ describe("Close Day Functionality", () => {
     it("returns summary for active session", async () => {
       const summary = {
         date: "2024-01-15",
-        total_revenue: 250.0,
+        total_revenue: fromMajor(250.0),
         total_orders: 10,
         orders: [],
       };
@@ -286,14 +287,14 @@ describe("Close Day Functionality", () => {
       mockInvoke.mockResolvedValueOnce(summary);
 
       const result = await tauri.getDaySummary();
-      expect(result.total_revenue).toBe(250.0);
+      expect(result.total_revenue).toEqual(fromMajor(250.0));
       expect(result.total_orders).toBe(10);
     });
 
     it("returns summary for specific session by ID", async () => {
       const summary = {
         date: "2024-01-10",
-        total_revenue: 500.0,
+        total_revenue: fromMajor(500.0),
         total_orders: 25,
         orders: [],
       };
@@ -307,7 +308,7 @@ describe("Close Day Functionality", () => {
     it("returns empty summary when no active session", async () => {
       const summary = {
         date: "2024-01-15",
-        total_revenue: 0.0,
+        total_revenue: fromMajor(0.0),
         total_orders: 0,
         orders: [],
       };
@@ -316,7 +317,7 @@ describe("Close Day Functionality", () => {
 
       const result = await tauri.getDaySummary();
       expect(result.total_orders).toBe(0);
-      expect(result.total_revenue).toBe(0);
+      expect(result.total_revenue).toEqual(fromMajor(0));
     });
   });
 });
@@ -334,10 +335,10 @@ describe("Close Day Business Logic Validation", () => {
     return { canClose: true };
   }
 
-  function calculateSessionTotals(orders: OrderWithItems[]): { revenue: number; count: number } {
+  function calculateSessionTotals(orders: OrderWithItems[]): { revenue: Money; count: number } {
     const paidOrders = orders.filter((o) => o.order.status === "paid");
     return {
-      revenue: paidOrders.reduce((sum, o) => sum + o.order.total, 0),
+      revenue: sumMoney(paidOrders.map((o) => o.order.total)),
       count: paidOrders.length,
     };
   }
@@ -350,7 +351,7 @@ describe("Close Day Business Logic Validation", () => {
             id: 1,
             staff_id: 1,
             table_number: 1,
-            total: 50.0,
+            total: fromMajor(50.0),
             status: "open",
             created_at: "",
             staff_name: null,
@@ -373,7 +374,7 @@ describe("Close Day Business Logic Validation", () => {
             id: 1,
             staff_id: 1,
             table_number: 1,
-            total: 50.0,
+            total: fromMajor(50.0),
             status: "open",
             created_at: "",
             staff_name: null,
@@ -387,7 +388,7 @@ describe("Close Day Business Logic Validation", () => {
             id: 2,
             staff_id: 1,
             table_number: 5,
-            total: 75.0,
+            total: fromMajor(75.0),
             status: "open",
             created_at: "",
             staff_name: null,
@@ -401,7 +402,7 @@ describe("Close Day Business Logic Validation", () => {
             id: 3,
             staff_id: 1,
             table_number: 10,
-            total: 100.0,
+            total: fromMajor(100.0),
             status: "open",
             created_at: "",
             staff_name: null,
@@ -432,7 +433,7 @@ describe("Close Day Business Logic Validation", () => {
             id: 1,
             staff_id: 1,
             table_number: 1,
-            total: 100.0,
+            total: fromMajor(100.0),
             status: "paid",
             created_at: "",
             staff_name: null,
@@ -446,7 +447,7 @@ describe("Close Day Business Logic Validation", () => {
             id: 2,
             staff_id: 1,
             table_number: 2,
-            total: 50.0,
+            total: fromMajor(50.0),
             status: "open",
             created_at: "",
             staff_name: null,
@@ -460,7 +461,7 @@ describe("Close Day Business Logic Validation", () => {
             id: 3,
             staff_id: 1,
             table_number: 3,
-            total: 75.0,
+            total: fromMajor(75.0),
             status: "paid",
             created_at: "",
             staff_name: null,
@@ -472,13 +473,13 @@ describe("Close Day Business Logic Validation", () => {
       ];
 
       const result = calculateSessionTotals(orders);
-      expect(result.revenue).toBe(175.0); // 100 + 75, excluding open order
+      expect(result.revenue).toEqual(fromMajor(175.0)); // 100 + 75, excluding open order
       expect(result.count).toBe(2);
     });
 
     it("returns zero for no orders", () => {
       const result = calculateSessionTotals([]);
-      expect(result.revenue).toBe(0);
+      expect(result.revenue).toEqual(fromMajor(0));
       expect(result.count).toBe(0);
     });
 
@@ -489,7 +490,7 @@ describe("Close Day Business Logic Validation", () => {
             id: 1,
             staff_id: 1,
             table_number: 1,
-            total: 33.33,
+            total: fromMajor(33.33),
             status: "paid",
             created_at: "",
             staff_name: null,
@@ -503,7 +504,7 @@ describe("Close Day Business Logic Validation", () => {
             id: 2,
             staff_id: 1,
             table_number: 2,
-            total: 33.33,
+            total: fromMajor(33.33),
             status: "paid",
             created_at: "",
             staff_name: null,
@@ -517,7 +518,7 @@ describe("Close Day Business Logic Validation", () => {
             id: 3,
             staff_id: 1,
             table_number: 3,
-            total: 33.34,
+            total: fromMajor(33.34),
             status: "paid",
             created_at: "",
             staff_name: null,
@@ -529,7 +530,7 @@ describe("Close Day Business Logic Validation", () => {
       ];
 
       const result = calculateSessionTotals(orders);
-      expect(result.revenue).toBeCloseTo(100.0, 2);
+      expect(result.revenue).toEqual(fromMajor(100.0));
     });
   });
 });
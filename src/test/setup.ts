@@ -6,6 +6,10 @@ vi.mock("@tauri-apps/api/core", () => ({
   invoke: vi.fn(),
 }));
 
+vi.mock("@tauri-apps/api/event", () => ({
+  listen: vi.fn(() => Promise.resolve(() => {})),
+}));
+
 // Mock Tauri dialog plugin
 vi.mock("@tauri-apps/plugin-dialog", () => ({
   save: vi.fn(),
@@ -1,6 +1,7 @@
 import { describe, it, expect, vi, beforeEach } from "vitest";
 import { invoke } from "@tauri-apps/api/core";
 import * as tauri from "../hooks/useTauri";
+import { fromMajor } from "../utils/money";
 
 vi.mock("@tauri-apps/api/core", () => ({
   invoke: vi.fn(),
@@ -15,10 +16,10 @@ describe("useTauri hooks", () => {
 
   describe("Categories", () => {
     it("getCategories calls invoke with correct command", async () => {
-      mockInvoke.mockResolvedValueOnce([{ id: 1, name: "Beer" }]);
+      mockInvoke.mockResolvedValueOnce([{ id: 1, name: "Beer", archived_at: null }]);
       const result = await tauri.getCategories();
-      expect(mockInvoke).toHaveBeenCalledWith("get_categories");
-      expect(result).toEqual([{ id: 1, name: "Beer" }]);
+      expect(mockInvoke).toHaveBeenCalledWith("get_categories", { includeArchived: undefined });
+      expect(result).toEqual([{ id: 1, name: "Beer", archived_at: null }]);
     });
 
     it("createCategory calls invoke with name parameter", async () => {
@@ -27,17 +28,17 @@ describe("useTauri hooks", () => {
       expect(mockInvoke).toHaveBeenCalledWith("create_category", { name: "Wine" });
     });
 
-    it("deleteCategory calls invoke with id parameter", async () => {
-      mockInvoke.mockResolvedValueOnce(undefined);
-      await tauri.deleteCategory(1);
-      expect(mockInvoke).toHaveBeenCalledWith("delete_category", { id: 1 });
+    it("archiveCategory calls invoke with id parameter", async () => {
+      mockInvoke.mockResolvedValueOnce({ id: 1, name: "Beer", archived_at: "2024-01-15 10:00:00" });
+      await tauri.archiveCategory(1);
+      expect(mockInvoke).toHaveBeenCalledWith("archive_category", { id: 1 });
     });
   });
 
   describe("Products", () => {
     it("getProducts returns product list", async () => {
       const mockProducts = [
-        { id: 1, name: "Heineken", price: 5.0, quantity: 100, category_id: 1, low_stock_threshold: 10 },
+        { id: 1, name: "Heineken", price: fromMajor(5.0), quantity: 100, category_id: 1, low_stock_threshold: 10 },
       ];
       mockInvoke.mockResolvedValueOnce(mockProducts);
       const result = await tauri.getProducts();
@@ -47,7 +48,7 @@ describe("useTauri hooks", () => {
     it("createProduct sends correct product data", async () => {
       const newProduct = {
         name: "Corona",
-        price: 6.0,
+        price: fromMajor(6.0),
         quantity: 50,
         category_id: 1,
         low_stock_threshold: 5,
@@ -88,14 +89,14 @@ describe("useTauri hooks", () => {
         staff_id: 1,
         staff_name: "John",
         table_number: 5,
-        total: 15.0,
+        total: fromMajor(15.0),
         customer_name: null,
         notes: null,
         status: "open",
         created_at: "2024-01-15T10:00:00",
       },
       items: [
-        { id: 1, order_id: 1, product_id: 1, product_name: "Beer", quantity: 3, price_at_sale: 5.0 },
+        { id: 1, order_id: 1, product_id: 1, product_name: "Beer", quantity: 3, price_at_sale: fromMajor(5.0), cost_at_sale: null, pricing_rule_id: null, pricing_rule_name: null, variant_id: null, variant_name: null, modifiers: [] },
       ],
     };
 
@@ -174,14 +175,14 @@ describe("useTauri hooks", () => {
       const closedSession = {
         id: 1,
         is_active: false,
-        total_revenue: 500.0,
+        total_revenue: fromMajor(500.0),
         total_orders: 25,
       };
       mockInvoke.mockResolvedValueOnce(closedSession);
       const result = await tauri.closeDay();
       expect(mockInvoke).toHaveBeenCalledWith("close_day");
       expect(result.is_active).toBe(false);
-      expect(result.total_revenue).toBe(500.0);
+      expect(result.total_revenue).toEqual(fromMajor(500.0));
     });
 
     it("isDayActive returns boolean", async () => {
@@ -207,7 +208,7 @@ describe("useTauri hooks", () => {
     it("getDaySummary can query by session ID", async () => {
       const mockSummary = {
         date: "2024-01-15",
-        total_revenue: 300.0,
+        total_revenue: fromMajor(300.0),
         total_orders: 15,
         orders: [],
       };
@@ -1,34 +1,97 @@
+// Amount in integer minor units (1/100 of the currency), e.g. 1250 = 12.50 ALL
+export interface Money {
+  minor_units: number;
+  currency: string;
+}
+
 export interface Category {
   id: number;
   name: string;
+  archived_at: string | null;
+  parent_id: number | null;
+  sort_order: number;            // Position among its siblings
+  color: string | null;          // #rrggbb
+  icon: string | null;
+  tax_rate_id: number | null;    // Applies to products and subcategories that set none
+}
+
+export interface UpdateCategory {
+  id: number;
+  name: string;
+  parent_id: number | null;
+  color: string | null;
+  icon: string | null;
+}
+
+export interface TaxRate {
+  id: number;
+  name: string;
+  basis_points: number;          // Hundredths of a percent: 2000 is 20%
+  included: boolean;             // Prices already include it; otherwise it is added
+}
+
+export interface CreateTaxRate {
+  name: string;
+  basis_points: number;
+  included: boolean;
+}
+
+export interface UpdateTaxRate extends CreateTaxRate {
+  id: number;
 }
 
+export type Unit = "piece" | "ml" | "cl" | "l" | "g";
+
+// quantity, low_stock_threshold and serving_size are in base units (piece, ml or g)
 export interface Product {
   id: number;
   name: string;
-  price: number;
+  price: Money;
   quantity: number;
   category_id: number | null;
   category_name: string | null;
   low_stock_threshold: number;
   created_at: string;
+  has_recipe: boolean;          // Stock is held by its ingredients
+  unit: Unit;
+  serving_size: number;
+  servings: number;             // How many can be sold from what is on hand
+  codes: string[];              // SKUs/EANs of the product itself; variants carry their own
+  aliases: string[];            // Other names it is searched by
+  cost_price: Money | null;     // Weighted-average cost per base unit; null until costed
+  archived_at: string | null;   // Off sale; orders and stock history still refer to it
+  available: boolean;           // False while 86'd, whatever the stock
+  unavailable_reason: string | null;
+  unavailable_until: string | null; // UTC; comes back by itself then
+  tax_rate_id: number | null;   // Overrides the category's tax rate
 }
 
 export interface CreateProduct {
   name: string;
-  price: number;
+  price: Money;
   quantity: number;
   category_id: number | null;
   low_stock_threshold?: number;
+  staff_id?: number | null;
+  unit?: Unit;
+  serving_size?: number | null; // Required unless unit is "piece"
+  codes?: string[];
+  aliases?: string[];
+  cost_price?: Money | null;    // Per base unit, until deliveries average it
 }
 
 export interface UpdateProduct {
   id: number;
   name: string;
-  price: number;
+  price: Money;
   quantity: number;
   category_id: number | null;
   low_stock_threshold: number;
+  unit?: Unit;
+  serving_size?: number | null;
+  cost_price?: Money | null;    // Left unchanged when omitted
+  aliases?: string[] | null;    // Replaces the aliases; left unchanged when omitted
+  staff_id?: number | null;     // Recorded on the stock adjustment if quantity changed
 }
 
 export interface Staff {
@@ -36,6 +99,7 @@ export interface Staff {
   name: string;
   pin: string | null;
   created_at: string;
+  archived_at: string | null;
 }
 
 export interface CreateStaff {
@@ -48,7 +112,7 @@ export interface Order {
   staff_id: number;
   staff_name: string | null;
   table_number: number;
-  total: number;
+  total: Money;
   customer_name: string | null;
   notes: string | null;
   status: "open" | "paid";
@@ -61,12 +125,31 @@ export interface OrderItem {
   product_id: number;
   product_name: string | null;
   quantity: number;
-  price_at_sale: number;
+  price_at_sale: Money;
+  cost_at_sale: Money | null;     // Cost of one unit when sold; null if not yet costed
+  pricing_rule_id: number | null; // Rule that set price_at_sale, if any
+  pricing_rule_name: string | null;
+  variant_id: number | null;
+  variant_name: string | null;
+  modifiers: OrderItemModifier[]; // Already included in price_at_sale
+  tax_name: string | null;        // Rate the line sold under; null if untaxed
+  tax_basis_points: number;
+  tax_included: boolean;          // In price_at_sale, or charged on top
+  tax_amount: Money;              // On the whole line
+}
+
+// A modifier as sold on an order line
+export interface OrderItemModifier {
+  modifier_id: number | null;
+  name: string;
+  price_delta: Money;
 }
 
 export interface CreateOrderItem {
   product_id: number;
   quantity: number;
+  variant_id?: number | null;   // Sell this variant of product_id
+  modifier_ids?: number[];
 }
 
 export interface CreateOrder {
@@ -75,6 +158,7 @@ export interface CreateOrder {
   customer_name?: string | null;
   notes?: string | null;
   items: CreateOrderItem[];
+  location_id?: number | null;   // Where the order sells from; default location when omitted
 }
 
 export interface OrderWithItems {
@@ -84,9 +168,72 @@ export interface OrderWithItems {
 
 export interface DaySummary {
   date: string;
-  total_revenue: number;
+  total_revenue: Money;
   total_orders: number;
   orders: OrderWithItems[];
+  product_sales: ProductSales[];
+  category_sales: CategorySales[];
+  taxes: TaxBreakdown[];
+}
+
+// Sales per product with variants rolled up into their parent
+export interface ProductSales {
+  product_id: number;
+  product_name: string | null;
+  category_id: number | null;
+  category_name: string | null;
+  quantity: number;
+  revenue: Money;
+  variants: VariantSales[];
+}
+
+export interface CategorySales {
+  category_id: number | null;
+  category_name: string | null;
+  quantity: number;
+  revenue: Money;
+}
+
+export interface VariantSales {
+  variant_id: number;
+  variant_name: string | null;
+  quantity: number;
+  revenue: Money;
+}
+
+// Gross margin over costed units; uncosted_quantity were sold before the
+// product had a cost, below_cost_quantity were sold for less than they cost
+export interface ProductMargin {
+  product_id: number;
+  product_name: string | null;
+  category_id: number | null;
+  category_name: string | null;
+  quantity: number;
+  revenue: Money;
+  cost: Money;
+  margin: Money;
+  below_cost_quantity: number;
+  uncosted_quantity: number;
+}
+
+export interface CategoryMargin {
+  category_id: number | null;
+  category_name: string | null;
+  quantity: number;
+  revenue: Money;
+  cost: Money;
+  margin: Money;
+  below_cost_quantity: number;
+  uncosted_quantity: number;
+}
+
+export interface SessionMargin {
+  session_id: number;
+  date: string | null;
+  revenue: Money;
+  cost: Money;
+  margin: Money;
+  products: ProductMargin[];
 }
 
 export interface CartItem {
@@ -102,6 +249,498 @@ export interface DaySession {
   started_at: string;
   closed_at: string | null;      // When session was closed
   is_active: boolean;
-  total_revenue: number | null;  // Stored at close time
+  total_revenue: Money | null;   // Stored at close time
   total_orders: number | null;   // Stored at close time
+  taxes: TaxBreakdown[];
+}
+
+// Sales under one tax rate; untaxed sales have no tax_name
+export interface TaxBreakdown {
+  tax_name: string | null;
+  basis_points: number;
+  net: Money;
+  tax: Money;
+  gross: Money;
+}
+
+export type StockReason =
+  | "opening_balance"
+  | "sale"
+  | "return"
+  | "delivery"
+  | "adjustment"
+  | "waste"
+  | "comp"
+  | "stock_take"
+  | "transfer";
+
+export interface StockMovement {
+  id: number;
+  product_id: number;
+  product_name: string | null;
+  quantity_change: number;       // Signed: negative for sales, waste, comps
+  reason: StockReason;
+  staff_id: number | null;
+  staff_name: string | null;
+  order_id: number | null;
+  reference: string | null;      // Delivery note, invoice number, etc.
+  note: string | null;
+  created_at: string;
+  location_id: number;
+  location_name: string;
+}
+
+export interface CreateStockMovement {
+  product_id: number;
+  quantity_change: number;
+  reason: Exclude<StockReason, "opening_balance" | "sale" | "return" | "transfer">;
+  staff_id: number | null;
+  reference: string | null;
+  note: string | null;
+  location_id?: number | null;   // Default location when omitted
+}
+
+export interface StockLocation {
+  id: number;
+  name: string;
+  is_default: boolean;           // Holds whatever stock no other location does
+}
+
+export interface LocationQuantity {
+  location_id: number;
+  location_name: string;
+  quantity: number;
+}
+
+export interface TransferStock {
+  product_id: number;
+  from_location_id: number;
+  to_location_id: number;
+  quantity: number;
+  staff_id: number | null;
+  note: string | null;
+}
+
+export interface LocationLowStock {
+  location_id: number;
+  product_id: number;
+  product_name: string;
+  quantity: number;
+  low_stock_threshold: number;
+  elsewhere: LocationQuantity[]; // Where to restock from, fullest first
+}
+
+export interface StockDiscrepancy {
+  product_id: number;
+  product_name: string;
+  recorded_quantity: number;
+  ledger_quantity: number;
+}
+
+export interface StockLot {
+  id: number;
+  product_id: number;
+  product_name: string;
+  keg_id: string | null;
+  received_at: string;
+  expires_on: string | null;     // YYYY-MM-DD
+  initial_quantity: number;
+  quantity: number;
+  tapped_at: string | null;
+  retired_at: string | null;
+  location_id: number;           // where the lot is; moves with transfers
+  location_name: string;
+}
+
+export interface CreateStockLot {
+  product_id: number;
+  quantity: number;
+  expires_on: string | null;
+  keg_id: string | null;
+  staff_id: number | null;
+  reference: string | null;
+}
+
+export interface KegYield {
+  lot: StockLot;
+  poured: number;                // Base units sold from the keg
+  servings_poured: number;
+  theoretical_servings: number;
+  yield_percent: number;
+  loss: number;                  // Left in the keg when retired, written off
+}
+
+export interface Supplier {
+  id: number;
+  name: string;
+  contact_name: string | null;
+  phone: string | null;
+  email: string | null;
+  notes: string | null;
+  created_at: string;
+}
+
+export interface CreateSupplier {
+  name: string;
+  contact_name: string | null;
+  phone: string | null;
+  email: string | null;
+  notes: string | null;
+}
+
+export interface UpdateSupplier extends CreateSupplier {
+  id: number;
+}
+
+export type PurchaseOrderStatus = "draft" | "sent" | "partially_received" | "received" | "cancelled";
+
+export interface PurchaseOrder {
+  id: number;
+  supplier_id: number;
+  supplier_name: string | null;
+  status: PurchaseOrderStatus;
+  reference: string | null;
+  notes: string | null;
+  created_by: number | null;
+  total: Money;                  // Ordered quantity at ordered cost
+  created_at: string;
+  sent_at: string | null;
+  received_at: string | null;
+}
+
+export interface PurchaseOrderLine {
+  id: number;
+  purchase_order_id: number;
+  product_id: number;
+  product_name: string | null;
+  quantity_ordered: number;
+  quantity_received: number;
+  unit_cost: Money;
+}
+
+export interface PurchaseOrderWithLines {
+  purchase_order: PurchaseOrder;
+  lines: PurchaseOrderLine[];
+}
+
+export interface CreatePurchaseOrderLine {
+  product_id: number;
+  quantity: number;
+  unit_cost: Money;
+}
+
+export interface CreatePurchaseOrder {
+  supplier_id: number;
+  staff_id: number | null;
+  reference: string | null;
+  notes: string | null;
+  lines: CreatePurchaseOrderLine[];
+}
+
+export interface ReceiveLine {
+  line_id: number;
+  quantity: number;
+  unit_cost: Money | null;       // Invoiced cost if it differs from the order
+  expires_on?: string | null;    // YYYY-MM-DD; receives the delivery as a lot
+}
+
+export interface ReceivePurchaseOrder {
+  staff_id: number | null;
+  reference: string | null;      // Delivery note number
+  lines: ReceiveLine[] | null;   // null receives everything outstanding
+}
+
+export interface SupplierBalance {
+  supplier_id: number;
+  supplier_name: string;
+  open_orders: number;
+  outstanding_value: Money;
+  received_value: Money;
+}
+
+export interface PurchaseReceipt {
+  id: number;
+  purchase_order_id: number;
+  product_id: number;
+  product_name: string | null;
+  quantity: number;
+  unit_cost: Money;
+  line_total: Money;
+  received_by: number | null;
+  reference: string | null;
+  received_at: string;
+}
+
+export interface ReorderSuggestion {
+  product_id: number;
+  product_name: string;
+  unit: Unit;
+  quantity: number;
+  on_order: number;
+  daily_usage: number;
+  days_of_cover: number;
+  reorder_point: number;
+  suggested_quantity: number;
+  unit_cost: Money | null;
+}
+
+export interface SupplierReorder {
+  supplier_id: number | null;
+  supplier_name: string | null;
+  lines: ReorderSuggestion[];
+  estimated_cost: Money;
+}
+
+export type StockTakeStatus = "open" | "committed" | "cancelled";
+
+export interface StockTake {
+  id: number;
+  status: StockTakeStatus;
+  notes: string | null;
+  location_id: number;          // Where it was counted; variances are posted there
+  location_name: string;
+  started_by: number;
+  started_by_name: string | null;
+  started_at: string;
+  committed_by: number | null;
+  committed_at: string | null;
+}
+
+// One staff member's count; counts for the same product add up
+export interface StockCount {
+  id: number;
+  product_id: number;
+  product_name: string | null;
+  staff_id: number;
+  staff_name: string | null;
+  counted_quantity: number;
+  counted_at: string;
+}
+
+export interface StockTakeLine {
+  product_id: number;
+  product_name: string | null;
+  system_quantity: number;
+  counted_quantity: number;
+  variance: number;              // counted - system
+  unit_cost: Money | null;       // null if never received through a purchase order
+  unit_price: Money;             // per base unit, like unit_cost
+  variance_cost: Money | null;
+  variance_value: Money;
+}
+
+export interface StockTakeWithLines {
+  stock_take: StockTake;
+  counts: StockCount[];
+  lines: StockTakeLine[];
+  total_variance_cost: Money;
+  total_variance_value: Money;
+}
+
+export interface VarianceHistoryEntry {
+  stock_take_id: number;
+  committed_at: string;
+  system_quantity: number;
+  counted_quantity: number;
+  variance: number;
+  variance_cost: Money | null;
+  variance_value: Money;
+}
+
+// A way of selling a product from its stock, e.g. pint or half pint;
+// deduction is in base units of the parent product
+export interface ProductVariant {
+  id: number;
+  product_id: number;
+  product_name: string | null;
+  name: string;
+  price: Money;
+  codes: string[];
+  deduction: number;
+  created_at: string;
+}
+
+export interface CreateProductVariant {
+  product_id: number;
+  name: string;
+  price: Money;
+  codes?: string[];
+  deduction: number;
+}
+
+export interface UpdateProductVariant {
+  id: number;
+  name: string;
+  price: Money;
+  deduction: number;
+}
+
+// Choices offered with a product or category; min_select 0 makes the group optional
+export interface ModifierGroup {
+  id: number;
+  name: string;
+  min_select: number;
+  max_select: number | null;
+  modifiers: Modifier[];
+  product_ids: number[];
+  category_ids: number[];
+}
+
+export interface Modifier {
+  id: number;
+  group_id: number;
+  name: string;
+  price_delta: Money;
+}
+
+export interface CreateModifier {
+  name: string;
+  price_delta?: Money;
+}
+
+export interface CreateModifierGroup {
+  name: string;
+  min_select?: number;
+  max_select?: number | null;
+  modifiers?: CreateModifier[];
+}
+
+export interface UpdateModifierGroup {
+  id: number;
+  name: string;
+  min_select: number;
+  max_select: number | null;
+}
+
+// One change in a product's price; applied_at is null while it is scheduled
+// for effective_at, and old_price is filled in when it takes effect (UTC times)
+export interface PriceChange {
+  id: number;
+  product_id: number;
+  old_price: Money | null;
+  new_price: Money;
+  staff_id: number | null;
+  staff_name: string | null;
+  effective_at: string;
+  applied_at: string | null;
+  created_at: string;
+}
+
+export interface SchedulePriceChange {
+  product_id: number;
+  new_price: Money;
+  effective_at?: string | null; // Local "YYYY-MM-DD HH:MM"; now when omitted
+  staff_id?: number | null;
+}
+
+// Result of import_products. `line` is the CSV line a row starts on, or the
+// position of an entry in a JSON array; nothing is kept unless `applied`
+export interface ImportedRow {
+  line: number;
+  name: string;
+}
+
+export interface RejectedRow {
+  line: number;
+  message: string;
+}
+
+export interface ImportReport {
+  dry_run: boolean;
+  applied: boolean;
+  created: ImportedRow[];
+  updated: ImportedRow[];
+  rejected: RejectedRow[];
+  categories_created: string[];
+}
+
+export type PricingRuleKind = "percent_off" | "amount_off" | "buy_x_get_y";
+
+// A scheduled price change such as happy hour. Scoped to a product, a category,
+// or everything; weekdays are 1 (Monday) to 7, empty for every day; times are
+// local "HH:MM" and may run past midnight
+export interface PricingRule {
+  id: number;
+  name: string;
+  kind: PricingRuleKind;
+  percent: number | null;
+  amount: Money | null;
+  buy_quantity: number | null;
+  free_quantity: number | null;
+  product_id: number | null;
+  product_name: string | null;
+  category_id: number | null;
+  category_name: string | null;
+  weekdays: number[];
+  start_time: string | null;
+  end_time: string | null;
+  active: boolean;
+  created_at: string;
+}
+
+export interface CreatePricingRule {
+  name: string;
+  kind: PricingRuleKind;
+  percent?: number | null;
+  amount?: Money | null;
+  buy_quantity?: number | null;
+  free_quantity?: number | null;
+  product_id?: number | null;
+  category_id?: number | null;
+  weekdays?: number[];
+  start_time?: string | null;
+  end_time?: string | null;
+}
+
+export interface UpdatePricingRule extends CreatePricingRule {
+  id: number;
+  active: boolean;
+}
+
+// A scannable SKU or EAN; variant_id is set when the code sells a variant
+export interface ProductCode {
+  id: number;
+  code: string;
+  product_id: number;
+  variant_id: number | null;
+  created_at: string;
+  pack_quantity: number | null;  // base units one scan brings in at delivery
+}
+
+export interface CodeMatch {
+  code: string;
+  product: Product;
+  variant: ProductVariant | null;
+}
+
+// One ingredient of a recipe, per serving, in base units of the ingredient's unit
+export interface RecipeComponent {
+  component_id: number;
+  component_name: string | null;
+  quantity: number;
+  available: number;
+  unit: Unit;
+}
+
+export interface SetRecipeComponent {
+  component_id: number;
+  quantity: number;
+}
+
+// Error returned by every backend command; branch on `code`, show `message`
+export type AppErrorCode =
+  | "NOT_FOUND"
+  | "INSUFFICIENT_STOCK"
+  | "DAY_NOT_STARTED"
+  | "ORDER_NOT_OPEN"
+  | "OPEN_TABLES_REMAIN"
+  | "UNKNOWN_CODE"
+  | "PRODUCT_UNAVAILABLE"
+  | "DATABASE"
+  | "VALIDATION";
+
+export interface AppError {
+  code: AppErrorCode;
+  message: string;
+  details: Record<string, unknown> | null;
 }
@@ -0,0 +1,15 @@
+import type { AppError, AppErrorCode } from "../types";
+
+export function isAppError(e: unknown, code?: AppErrorCode): e is AppError {
+  if (typeof e !== "object" || e === null) return false;
+  const err = e as Partial<AppError>;
+  if (typeof err.code !== "string" || typeof err.message !== "string") return false;
+  return code === undefined || err.code === code;
+}
+
+/** Human-readable text for anything thrown by a command or the UI itself. */
+export function errorMessage(e: unknown): string {
+  if (isAppError(e)) return e.message;
+  if (e instanceof Error) return e.message;
+  return String(e);
+}
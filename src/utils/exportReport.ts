@@ -1,12 +1,13 @@
 import { save } from "@tauri-apps/plugin-dialog";
 import { writeTextFile, writeFile } from "@tauri-apps/plugin-fs";
-import { DaySummary } from "../types";
+import { DaySummary, Money } from "../types";
+import { formatMoney, lineTotal, sumMoney, toMajor } from "./money";
 import * as XLSX from "xlsx";
 
 interface ProductSummary {
   name: string;
   quantity: number;
-  revenue: number;
+  revenue: Money;
 }
 
 function aggregateProducts(summary: DaySummary): ProductSummary[] {
@@ -18,12 +19,12 @@ function aggregateProducts(summary: DaySummary): ProductSummary[] {
       const existing = productMap.get(name);
       if (existing) {
         existing.quantity += item.quantity;
-        existing.revenue += item.price_at_sale * item.quantity;
+        existing.revenue = sumMoney([existing.revenue, lineTotal(item.price_at_sale, item.quantity)]);
       } else {
         productMap.set(name, {
           name,
           quantity: item.quantity,
-          revenue: item.price_at_sale * item.quantity,
+          revenue: lineTotal(item.price_at_sale, item.quantity),
         });
       }
     });
@@ -31,7 +32,7 @@ function aggregateProducts(summary: DaySummary): ProductSummary[] {
 
   const productSummary: ProductSummary[] = [];
   productMap.forEach((value) => productSummary.push(value));
-  productSummary.sort((a, b) => b.revenue - a.revenue);
+  productSummary.sort((a, b) => b.revenue.minor_units - a.revenue.minor_units);
 
   return productSummary;
 }
@@ -42,14 +43,14 @@ export async function exportToCSV(summary: DaySummary): Promise<boolean> {
   const lines = [
     `Date;${summary.date}`,
     `Total Orders;${summary.total_orders}`,
-    `Total Revenue;${summary.total_revenue.toFixed(0)} ALL`,
+    `Total Revenue;${formatMoney(summary.total_revenue)}`,
     ``,
     `Product;Quantity;Revenue (ALL)`,
     ...productSummary.map((p) =>
-      `${p.name};${p.quantity};${p.revenue.toFixed(0)}`
+      `${p.name};${p.quantity};${toMajor(p.revenue).toFixed(0)}`
     ),
     ``,
-    `TOTAL;${productSummary.reduce((sum, p) => sum + p.quantity, 0)};${summary.total_revenue.toFixed(0)}`,
+    `TOTAL;${productSummary.reduce((sum, p) => sum + p.quantity, 0)};${toMajor(summary.total_revenue).toFixed(0)}`,
   ];
 
   const csvContent = lines.join("\n");
@@ -81,7 +82,7 @@ export async function exportToText(summary: DaySummary): Promise<boolean> {
     `═══════════════════════════════════════`,
     ``,
     `Total Orders: ${summary.total_orders}`,
-    `Total Revenue: ${summary.total_revenue.toFixed(0)} ALL`,
+    `Total Revenue: ${formatMoney(summary.total_revenue)}`,
     ``,
     `───────────────────────────────────────`,
     `PRODUCTS SOLD`,
@@ -89,11 +90,11 @@ export async function exportToText(summary: DaySummary): Promise<boolean> {
     ``,
     ...productSummary.map(
       (p) =>
-        `${p.name.padEnd(20)} x${p.quantity.toString().padStart(3)}  ${p.revenue.toFixed(0).padStart(8)} ALL`
+        `${p.name.padEnd(20)} x${p.quantity.toString().padStart(3)}  ${toMajor(p.revenue).toFixed(0).padStart(8)} ALL`
     ),
     ``,
     `───────────────────────────────────────`,
-    `TOTAL: ${summary.total_revenue.toFixed(0)} ALL`,
+    `TOTAL: ${formatMoney(summary.total_revenue)}`,
     `═══════════════════════════════════════`,
   ];
 
@@ -128,13 +129,13 @@ export async function exportToExcel(summary: DaySummary): Promise<boolean> {
       ["Day Report", summary.date],
       [],
       ["Total Orders", summary.total_orders],
-      ["Total Revenue (ALL)", summary.total_revenue],
+      ["Total Revenue (ALL)", toMajor(summary.total_revenue)],
       [],
       ["Products Sold"],
       ["Product", "Quantity", "Revenue (ALL)"],
-      ...productSummary.map((p) => [p.name, p.quantity, p.revenue]),
+      ...productSummary.map((p) => [p.name, p.quantity, toMajor(p.revenue)]),
       [],
-      ["TOTAL", productSummary.reduce((sum, p) => sum + p.quantity, 0), summary.total_revenue],
+      ["TOTAL", productSummary.reduce((sum, p) => sum + p.quantity, 0), toMajor(summary.total_revenue)],
     ];
 
     const summarySheet = XLSX.utils.aoa_to_sheet(summaryData);
@@ -155,7 +156,7 @@ export async function exportToExcel(summary: DaySummary): Promise<boolean> {
         o.order.table_number,
         o.order.staff_name || "-",
         o.order.customer_name || "-",
-        o.order.total,
+        toMajor(o.order.total),
         o.order.status,
         o.order.created_at,
       ]),
@@ -187,8 +188,8 @@ export async function exportToExcel(summary: DaySummary): Promise<boolean> {
           o.order.table_number,
           item.product_name || "Unknown",
           item.quantity,
-          item.price_at_sale,
-          item.quantity * item.price_at_sale,
+          toMajor(item.price_at_sale),
+          toMajor(lineTotal(item.price_at_sale, item.quantity)),
         ]);
       });
     });
@@ -0,0 +1,27 @@
+import { Money } from "../types";
+
+export const DEFAULT_CURRENCY = "ALL";
+export const MINOR_PER_MAJOR = 100;
+
+// Amounts travel as integer minor units; only convert to a float for display.
+export const toMajor = (amount: Money): number => amount.minor_units / MINOR_PER_MAJOR;
+
+export const fromMajor = (value: number, currency = DEFAULT_CURRENCY): Money => ({
+  minor_units: Math.round(value * MINOR_PER_MAJOR),
+  currency,
+});
+
+export const zeroMoney = (currency = DEFAULT_CURRENCY): Money => ({ minor_units: 0, currency });
+
+export const lineTotal = (price: Money, quantity: number): Money => ({
+  minor_units: price.minor_units * quantity,
+  currency: price.currency,
+});
+
+export const sumMoney = (amounts: Money[]): Money => ({
+  minor_units: amounts.reduce((sum, m) => sum + m.minor_units, 0),
+  currency: amounts[0]?.currency ?? DEFAULT_CURRENCY,
+});
+
+export const formatMoney = (amount: Money): string =>
+  `${toMajor(amount).toFixed(0)} ${amount.currency}`;
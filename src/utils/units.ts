@@ -0,0 +1,15 @@
+import { Unit } from "../types";
+
+// Stock travels as whole base units (pieces, ml or g); a 70 cl bottle is 700.
+export const BASE_FACTOR: Record<Unit, number> = { piece: 1, ml: 1, cl: 10, l: 1000, g: 1 };
+
+export const UNITS: Unit[] = ["piece", "ml", "cl", "l", "g"];
+
+export const toUnit = (quantity: number, unit: Unit): number => quantity / BASE_FACTOR[unit];
+
+export const fromUnit = (value: number, unit: Unit): number => Math.round(value * BASE_FACTOR[unit]);
+
+export const formatQuantity = (quantity: number, unit: Unit): string =>
+  unit === "piece"
+    ? String(quantity)
+    : `${Number(toUnit(quantity, unit).toFixed(2))} ${unit}`;